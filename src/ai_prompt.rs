@@ -1,6 +1,10 @@
 use crate::{
     command::{draft::DraftCommand, explain::ExplainCommand},
-    git_entity::{diff::Diff, GitEntity},
+    git_entity::{
+        commit::Commit,
+        diff::{strip_whitespace_changes, Diff},
+        GitEntity,
+    },
 };
 use indoc::{formatdoc, indoc};
 use thiserror::Error;
@@ -39,7 +43,11 @@ impl AIPrompt {
                     diff = commit.diff
                 }
             }
-            GitEntity::Diff(Diff::WorkingTree { diff, .. } | Diff::CommitsRange { diff, .. }) => {
+            GitEntity::Diff(
+                Diff::WorkingTree { diff, .. }
+                | Diff::CommitsRange { diff, .. }
+                | Diff::Patch { diff },
+            ) => {
                 formatdoc! {"
                     Context - Changes:
 
@@ -71,7 +79,7 @@ impl AIPrompt {
                     2. Direct impact
                     "
                 },
-                GitEntity::Diff(Diff::WorkingTree { .. }) => formatdoc! {"
+                GitEntity::Diff(Diff::WorkingTree { .. } | Diff::Patch { .. }) => formatdoc! {"
                     {base_content}
                     
                     Provide:
@@ -97,10 +105,28 @@ impl AIPrompt {
     }
 
     pub fn build_draft_prompt(command: &DraftCommand) -> Result<Self, AIPromptError> {
-        let GitEntity::Diff(Diff::WorkingTree { diff, .. }) = &command.git_entity else {
-            return Err(AIPromptError(
-                "`draft` is only supported for working tree diffs".into(),
-            ));
+        let diff = match &command.git_entity {
+            GitEntity::Diff(Diff::WorkingTree { diff, .. }) => diff,
+            GitEntity::Commit(Commit { diff, .. }) => diff,
+            _ => {
+                return Err(AIPromptError(
+                    "`draft` is only supported for working tree diffs and the last commit".into(),
+                ))
+            }
+        };
+
+        let stripped;
+        let diff = if command.ignore_whitespace {
+            stripped = strip_whitespace_changes(diff);
+            if stripped.trim().is_empty() {
+                return Err(AIPromptError(
+                    "staged changes are whitespace-only; nothing to draft with --ignore-whitespace"
+                        .into(),
+                ));
+            }
+            &stripped
+        } else {
+            diff
         };
 
         let system_prompt = String::from(indoc! {"
@@ -148,6 +174,93 @@ impl AIPrompt {
         })
     }
 
+    pub fn build_file_explain_prompt(filename: &str, diff: &str) -> Result<Self, AIPromptError> {
+        if diff.trim().is_empty() {
+            return Err(AIPromptError(format!("no changes to explain in {filename}")));
+        }
+
+        let system_prompt = String::from(indoc! {"
+            You are a helpful assistant that explains changes to a single file in a code review.
+            Focus on what changed in this file, why it likely changed, and anything a reviewer should double-check.
+            Keep explanations brief but informative and don't ask for further explanations.
+            Use markdown for clarity.
+        "});
+
+        let user_prompt = formatdoc! {"
+            Context - Changes to `{filename}`:
+
+            ```diff
+            {diff}
+            ```
+
+            Provide:
+            1. What changed in this file
+            2. Notable concerns (if any)
+            "
+        };
+
+        Ok(AIPrompt {
+            system_prompt,
+            user_prompt,
+        })
+    }
+
+    /// Reduce step of chunked explain: combine per-file explanations into one answer.
+    pub fn build_chunk_summary_prompt(
+        command: &ExplainCommand,
+        explanations: &[(String, String)],
+    ) -> Result<Self, AIPromptError> {
+        if explanations.is_empty() {
+            return Err(AIPromptError("no file explanations to summarize".into()));
+        }
+
+        let system_prompt = String::from(indoc! {"
+            You are a helpful assistant that explains Git changes in a concise way.
+            The change was too large to review at once, so each file was explained separately.
+            Combine those explanations into a single overview of the whole change.
+            Focus only on the most significant changes and their direct impact.
+            Use markdown for clarity.
+        "});
+
+        let message = match &command.git_entity {
+            GitEntity::Commit(commit) => format!("Commit message: {}\n\n", commit.message),
+            GitEntity::Diff(_) => String::new(),
+        };
+        let files = explanations
+            .iter()
+            .map(|(file, explanation)| format!("### {file}\n{explanation}"))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let task = match &command.query {
+            Some(query) => formatdoc! {"
+                Question: {query}
+
+                Provide a focused answer to the question based on the explanations above.
+                "
+            },
+            None => String::from(indoc! {"
+                Provide:
+                1. Core changes made
+                2. Direct impact
+                3. Notable concerns (if any)
+            "}),
+        };
+
+        let user_prompt = formatdoc! {"
+            {message}Context - Per-file explanations:
+
+            {files}
+
+            {task}"
+        };
+
+        Ok(AIPrompt {
+            system_prompt,
+            user_prompt,
+        })
+    }
+
     pub fn build_operate_prompt(query: &str) -> Result<Self, AIPromptError> {
         let system_prompt = String::from(indoc! {"
         You're a Git assistant that provides commands with clear explanations.
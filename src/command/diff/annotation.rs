@@ -7,7 +7,7 @@ use ratatui::{
 };
 use tui_textarea::TextArea;
 
-use super::state::HunkAnnotation;
+use super::state::{HunkAnnotation, FILE_ANNOTATION_HUNK};
 use super::theme;
 
 /// Result of handling input in the annotation editor
@@ -25,22 +25,18 @@ pub enum AnnotationEditorResult {
 /// A modal editor for creating/editing hunk annotations
 pub struct AnnotationEditor<'a> {
     textarea: TextArea<'a>,
-    pub file_index: usize,
     pub hunk_index: usize,
     pub filename: String,
     pub line_range: (usize, usize),
     is_edit: bool,
     /// Original creation time (preserved when editing)
     original_created_at: Option<SystemTime>,
+    /// Characters allowed before the counter warns; saving past it still works
+    soft_limit: Option<usize>,
 }
 
 impl<'a> AnnotationEditor<'a> {
-    pub fn new(
-        file_index: usize,
-        hunk_index: usize,
-        filename: String,
-        line_range: (usize, usize),
-    ) -> Self {
+    pub fn new(hunk_index: usize, filename: String, line_range: (usize, usize)) -> Self {
         let mut textarea = TextArea::default();
         let t = theme::get();
 
@@ -51,15 +47,27 @@ impl<'a> AnnotationEditor<'a> {
 
         Self {
             textarea,
-            file_index,
             hunk_index,
             filename,
             line_range,
             is_edit: false,
             original_created_at: None,
+            soft_limit: None,
         }
     }
 
+    /// Show a character counter that turns into a warning past `limit`.
+    pub fn with_soft_limit(mut self, limit: usize) -> Self {
+        self.soft_limit = Some(limit);
+        self
+    }
+
+    /// Characters typed so far, counting line breaks.
+    fn char_count(&self) -> usize {
+        let lines = self.textarea.lines();
+        lines.iter().map(|l| l.chars().count()).sum::<usize>() + lines.len().saturating_sub(1)
+    }
+
     pub fn with_content(mut self, content: &str, created_at: SystemTime) -> Self {
         self.textarea = TextArea::new(content.lines().map(String::from).collect());
         self.is_edit = true;
@@ -172,14 +180,16 @@ impl<'a> AnnotationEditor<'a> {
             .unwrap_or(&self.filename);
 
         // Compact title
-        let title = format!(
-            " {} · L{}-{} ",
-            short_filename,
-            self.line_range.0,
-            self.line_range.1
-        );
-
-        let block = Block::default()
+        let title = if self.hunk_index == FILE_ANNOTATION_HUNK {
+            format!(" {} · whole file ", short_filename)
+        } else {
+            format!(
+                " {} · L{}-{} ",
+                short_filename, self.line_range.0, self.line_range.1
+            )
+        };
+
+        let mut block = Block::default()
             .title(title)
             .title_style(Style::default().fg(t.ui.text_secondary))
             .borders(Borders::ALL)
@@ -187,6 +197,22 @@ impl<'a> AnnotationEditor<'a> {
             .border_style(Style::default().fg(t.ui.border_focused))
             .style(Style::default().bg(t.ui.bg));
 
+        if let Some(limit) = self.soft_limit {
+            let count = self.char_count();
+            let (counter, style) = if count > limit {
+                (
+                    format!(" {}/{} · over limit ", count, limit),
+                    Style::default().fg(t.ui.status_deleted),
+                )
+            } else {
+                (
+                    format!(" {}/{} ", count, limit),
+                    Style::default().fg(t.ui.text_muted),
+                )
+            };
+            block = block.title(Line::styled(counter, style).right_aligned());
+        }
+
         let inner = block.inner(modal_area);
         frame.render_widget(block, modal_area);
 
@@ -220,12 +246,13 @@ impl<'a> AnnotationEditor<'a> {
     /// Create a HunkAnnotation from the current editor state
     pub fn to_annotation(&self) -> HunkAnnotation {
         HunkAnnotation {
-            file_index: self.file_index,
             hunk_index: self.hunk_index,
             content: self.textarea.lines().join("\n"),
             line_range: self.line_range,
             filename: self.filename.clone(),
             created_at: self.original_created_at.unwrap_or_else(SystemTime::now),
+            pending_author: None,
+            stale: false,
         }
     }
 }
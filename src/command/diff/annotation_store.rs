@@ -0,0 +1,96 @@
+//! On-disk annotations in `lumen/annotations.json` under the repository's
+//! metadata directory, one list per diff reference, so notes survive
+//! restarting lumen on the same diff.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::state::HunkAnnotation;
+
+const STORE_FILE: &str = "annotations.json";
+
+type Store = BTreeMap<String, Vec<HunkAnnotation>>;
+
+/// Annotations saved in `dir` for `reference`; empty when nothing was saved or the file is unreadable.
+pub fn load(dir: &Path, reference: &str) -> Vec<HunkAnnotation> {
+    load_from(&dir.join(STORE_FILE), reference)
+}
+
+/// Replace the annotations saved in `dir` for `reference`, leaving other references untouched.
+pub fn save(dir: &Path, reference: &str, annotations: &[HunkAnnotation]) -> io::Result<()> {
+    save_to(&dir.join(STORE_FILE), reference, annotations)
+}
+
+fn read_store(path: &Path) -> Store {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn load_from(path: &Path, reference: &str) -> Vec<HunkAnnotation> {
+    read_store(path).remove(reference).unwrap_or_default()
+}
+
+fn save_to(path: &Path, reference: &str, annotations: &[HunkAnnotation]) -> io::Result<()> {
+    let mut store = read_store(path);
+    // Comments from an unsubmitted GitHub review live on GitHub, not here
+    let local: Vec<HunkAnnotation> = annotations
+        .iter()
+        .filter(|a| a.pending_author.is_none())
+        .cloned()
+        .collect();
+    if local.is_empty() {
+        if store.remove(reference).is_none() {
+            return Ok(());
+        }
+    } else {
+        store.insert(reference.to_string(), local);
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(&store).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn note(filename: &str, hunk_index: usize) -> HunkAnnotation {
+        HunkAnnotation {
+            hunk_index,
+            content: "look here".to_string(),
+            line_range: (3, 5),
+            filename: filename.to_string(),
+            created_at: SystemTime::now(),
+            pending_author: None,
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_per_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lumen").join(STORE_FILE);
+
+        save_to(&path, "main..HEAD", &[note("src/a.rs", 1)]).unwrap();
+        save_to(&path, "working tree", &[note("src/b.rs", 0)]).unwrap();
+
+        let loaded = load_from(&path, "main..HEAD");
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].filename, "src/a.rs");
+        assert_eq!(loaded[0].hunk_index, 1);
+        assert_eq!(loaded[0].line_range, (3, 5));
+        assert!(load_from(&path, "HEAD~1").is_empty());
+
+        save_to(&path, "main..HEAD", &[]).unwrap();
+        assert!(load_from(&path, "main..HEAD").is_empty());
+        assert_eq!(load_from(&path, "working tree").len(), 1);
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections::VecDeque;
-use std::io::{self, IsTerminal};
-use std::sync::mpsc::TryRecvError;
-use std::time::Duration;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
 
 #[cfg(unix)]
 use std::os::fd::AsRawFd;
@@ -16,31 +17,39 @@ use crossterm::{
 };
 use ratatui::prelude::*;
 
-use super::coordinates::{extract_selected_text, PanelLayout};
+use super::clipboard::copy;
+use super::coordinates::{extract_selected_text, wrapped_max_scroll, PanelLayout};
 use super::diff_algo::{compute_side_by_side, find_hunk_starts};
+use super::explain::spawn_file_explain;
 use super::git::{
-    get_current_branch, load_file_diffs, load_pr_file_diffs, load_single_commit_diffs,
+    fetch_pr_commits, get_current_branch, load_file_diffs, load_file_diffs_against,
+    load_file_revision_diffs, load_pr_commit_diffs, load_single_commit_diffs, old_side_ref,
+    panel_titles, pinned_base_titles, range_refs,
 };
 use super::highlight;
+use super::metadata::{format_commit_metadata, format_diff_stat, format_pr_metadata};
 use super::render::{
-    render_diff, render_empty_state, truncate_path, FilePickerItem, KeyBind, KeyBindSection, Modal,
-    ModalContent, ModalFileStatus, ModalResult,
+    compute_line_stats, render_diff, render_empty_state, truncate_path, FilePickerItem, KeyBind,
+    KeyBindSection, Modal, ModalContent, ModalFileStatus, ModalResult, OverviewItem,
 };
 use super::annotation::{AnnotationEditor, AnnotationEditorResult};
-use super::state::{adjust_scroll_for_hunk, adjust_scroll_to_line, AppState, PendingKey};
+use super::annotation_store;
+use super::review_store;
+use super::state::{
+    adjust_scroll_for_hunk, adjust_scroll_to_line, AppState, FooterPrompt, PendingKey,
+    PromptKind, FILE_ANNOTATION_HUNK,
+};
 use super::theme;
 use super::types::{
-    ChangeType, CursorPosition, DiffFullscreen, DiffPanelFocus, FileStatus, FocusedPanel,
-    SelectionMode, SidebarItem,
-};
-use super::watcher::{setup_watcher, WatchEvent};
-use super::{
-    fetch_viewed_files, mark_file_as_viewed_async, unmark_file_as_viewed_async, DiffOptions, PrInfo,
+    DiffFullscreen, DiffPanelFocus, DiffViewSettings, FileStatus, FocusedPanel, SelectionMode,
+    SidebarItem,
 };
+use super::watcher::{changed_files, setup_watcher, WatchEvent};
+use super::{old_side_blame_permalink, DiffOptions, Forge, PrInfo};
 use spinoff::{spinners, Color, Spinner};
 
 use crate::commit_reference::CommitReference;
-use crate::vcs::{StackedCommitInfo, VcsBackend};
+use crate::vcs::{BlameLine, LineOrigin, StackedCommitInfo, VcsBackend, EMPTY_TREE_SHA};
 
 /// Navigate to a different commit in stacked mode.
 /// Returns true if navigation was successful.
@@ -48,21 +57,108 @@ fn navigate_stacked_commit(
     state: &mut AppState,
     new_index: usize,
     options: &DiffOptions,
+    pr_info: Option<&PrInfo>,
     backend: &dyn VcsBackend,
 ) -> bool {
-    if new_index >= state.stacked_commits.len() {
+    let Some(commit) = state.stacked_commits.get(new_index) else {
         return false;
+    };
+    match load_stacked_commit_diffs(commit, options, pr_info, backend) {
+        Ok(file_diffs) => {
+            state.save_stacked_viewed_files();
+            state.current_commit_index = new_index;
+            state.reload(grep_files(file_diffs, &state.settings), None);
+            state.load_stacked_viewed_files();
+            true
+        }
+        Err(e) => {
+            state.set_status_message(e);
+            false
+        }
     }
-    state.save_stacked_viewed_files();
-    state.current_commit_index = new_index;
-    if let Some(commit) = state.stacked_commits.get(new_index) {
-        let file_diffs = load_single_commit_diffs(&commit.commit_id, &options.file, backend);
-        state.reload(file_diffs, None);
-        state.load_stacked_viewed_files();
-        true
-    } else {
-        false
+}
+
+/// Diffs for one commit of a stack: from GitHub when walking a PR, just the followed
+/// file on a file-history timeline, otherwise the whole commit from the local repo.
+fn load_stacked_commit_diffs(
+    commit: &StackedCommitInfo,
+    options: &DiffOptions,
+    pr_info: Option<&PrInfo>,
+    backend: &dyn VcsBackend,
+) -> Result<Vec<super::types::FileDiff>, String> {
+    let commit_id = &commit.commit_id;
+    match (pr_info, &commit.history) {
+        (Some(pr), _) => load_pr_commit_diffs(pr, commit_id, &options.file),
+        (None, Some(revision)) => Ok(load_file_revision_diffs(commit_id, revision, backend)),
+        (None, None) => Ok(load_single_commit_diffs(commit_id, &options.file, backend)),
+    }
+}
+
+/// Blame for the current file's old side: the pinned base, the parent of the commit
+/// shown in stacked mode, or otherwise the old ref of the diff itself.
+fn old_side_blame(
+    state: &AppState,
+    options: &DiffOptions,
+    pr_info: Option<&PrInfo>,
+    backend: &dyn VcsBackend,
+) -> Result<Vec<BlameLine>, String> {
+    if let Some(pr) = pr_info {
+        return Err(format!("not available for {}s", pr.forge.noun()));
     }
+    let diff = &state.file_diffs[state.current_file];
+    let mut path = diff.filename.as_str();
+    let old_ref = match (&state.pinned_base, state.current_commit()) {
+        (Some(base), _) => base.clone(),
+        (None, Some(commit)) if state.stacked_mode => {
+            // On a file-history timeline the old side may be under its pre-rename path
+            if let Some(old_path) = commit.history.as_ref().and_then(|h| h.old_path.as_deref()) {
+                path = old_path;
+            }
+            backend
+                .get_parent_ref_or_empty(&commit.commit_id)
+                .map_err(|e| e.to_string())?
+        }
+        _ => old_side_ref(options, backend),
+    };
+    if old_ref.is_empty() || old_ref == EMPTY_TREE_SHA {
+        return Err("root commit has no old side to blame".to_string());
+    }
+    backend
+        .blame(Path::new(path), &old_ref)
+        .map_err(|e| e.to_string())
+}
+
+/// Which commit of the diffed range last changed each line of the current file's
+/// new side. Only a range of commits has more than one commit to point at.
+fn range_line_origins(
+    state: &AppState,
+    options: &DiffOptions,
+    pr_info: Option<&PrInfo>,
+    backend: &dyn VcsBackend,
+) -> Result<Vec<Option<LineOrigin>>, String> {
+    if let Some(pr) = pr_info {
+        return Err(format!("not available for {}s", pr.forge.noun()));
+    }
+    let range =
+        range_refs(options, backend).filter(|_| !state.stacked_mode && state.pinned_base.is_none());
+    let Some((from, to)) = range else {
+        return Err("only available for a range of commits, e.g. main..HEAD".to_string());
+    };
+    let diff = &state.file_diffs[state.current_file];
+    backend
+        .line_origins(Path::new(&diff.filename), &from, &to)
+        .map_err(|e| e.to_string())
+}
+
+/// Rows of the diff panel's minimap: the panel height inside its borders, above any
+/// pinned hunk strip.
+fn minimap_rows(
+    term_height: u16,
+    header_height: u16,
+    footer_height: u16,
+    pinned_height: u16,
+) -> usize {
+    term_height.saturating_sub(header_height + footer_height + pinned_height + 2) as usize
 }
 
 /// Adjust sidebar scroll to ensure the selected item is visible.
@@ -74,6 +170,115 @@ fn ensure_sidebar_visible(state: &mut AppState, visible_height: usize) {
     }
 }
 
+/// Write the session's annotations to disk, reporting a failure in the footer.
+fn save_annotations(state: &mut AppState, dir: &Path, key: &str) {
+    if let Err(e) = annotation_store::save(dir, key, &state.annotations) {
+        state.set_status_message(format!("Could not save annotations: {}", e));
+    }
+}
+
+/// Select `file_index`, expanding its collapsed parent directories and scrolling the
+/// sidebar to it, so the sidebar shows where the diff view is however it got there.
+fn focus_file(state: &mut AppState, file_index: usize, visible_height: usize) {
+    state.reveal_file(file_index);
+    state.select_file(file_index);
+    ensure_sidebar_visible(state, visible_height);
+}
+
+/// Jump to a mark, or say why not in the footer.
+fn jump_to_mark(state: &mut AppState, mark: char, visible_height: usize) {
+    if state.jump_to_mark(mark) {
+        ensure_sidebar_visible(state, visible_height);
+    } else {
+        state.set_status_message(format!("mark {} not set", mark));
+    }
+}
+
+/// Furthest the diff view scrolls for the current file, leaving some padding below.
+fn diff_max_scroll(state: &AppState, visible_height: usize, width: u16) -> usize {
+    if state.file_diffs.is_empty() {
+        return 0;
+    }
+    let side_by_side = state.display_lines(state.current_file);
+    let height = visible_height.saturating_sub(5);
+    match state.row_layout(width) {
+        Some(layout) => wrapped_max_scroll(&side_by_side, height, layout),
+        None => side_by_side.len().saturating_sub(height),
+    }
+}
+
+/// Scroll to the next (or previous) search match. Matches in folded lines are
+/// unfolded, so the scroll limit is taken afterwards.
+fn step_search(state: &mut AppState, forward: bool, visible_height: usize, width: u16) {
+    match state.find_search_match(forward) {
+        Some(line) => {
+            let max_scroll = diff_max_scroll(state, visible_height, width);
+            state.scroll = adjust_scroll_to_line(line, state.scroll, visible_height, max_scroll);
+        }
+        None if state.search_state.match_count() > 0 => state.set_status_message(if forward {
+            "no more matches below"
+        } else {
+            "no more matches above"
+        }),
+        None => {}
+    }
+}
+
+/// List the marks, in letter order, for the `"` modal.
+fn marks_modal(state: &AppState) -> Modal {
+    let items = state
+        .sorted_marks()
+        .into_iter()
+        .map(|(mark, filename, scroll)| {
+            let filename = if state.file_index_of(filename).is_some() {
+                filename
+            } else {
+                "(file no longer in the diff)"
+            };
+            format!("{}  {}  row {}", mark, filename, scroll + 1)
+        })
+        .collect();
+    Modal::select("Marks", items)
+}
+
+/// Build the overview landing page: every file with its status and line stats.
+fn overview_modal(state: &AppState) -> Modal {
+    let items: Vec<OverviewItem> = state
+        .file_diffs
+        .iter()
+        .enumerate()
+        .filter(|(_, diff)| state.settings.show_deleted || diff.status != FileStatus::Deleted)
+        .map(|(i, diff)| {
+            let stats = if diff.is_binary {
+                None
+            } else {
+                let side_by_side = compute_side_by_side(
+                    &diff.old_content,
+                    &diff.new_content,
+                    state.settings.tab_width_for(&diff.filename),
+                    state.settings.ignore_whitespace,
+                );
+                Some(compute_line_stats(&side_by_side))
+            };
+            OverviewItem {
+                name: diff.filename.clone(),
+                icon: state.settings.indicators.file_icon(&diff.filename),
+                file_index: i,
+                status: match diff.status {
+                    FileStatus::Added => ModalFileStatus::Added,
+                    FileStatus::Modified => ModalFileStatus::Modified,
+                    FileStatus::Deleted => ModalFileStatus::Deleted,
+                },
+                viewed: state.viewed_files.contains(&i),
+                added: stats.as_ref().map_or(0, |s| s.added),
+                removed: stats.as_ref().map_or(0, |s| s.removed),
+                is_binary: diff.is_binary,
+            }
+        })
+        .collect();
+    Modal::overview("Overview", items, state.settings.indicators.clone())
+}
+
 /// Format an annotation for display in the annotations list.
 fn format_annotation_preview(annotation: &super::state::HunkAnnotation) -> String {
     let preview = annotation.content.lines().next().unwrap_or("");
@@ -83,14 +288,73 @@ fn format_annotation_preview(annotation: &super::state::HunkAnnotation) -> Strin
         preview.to_string()
     };
     let truncated_filename = truncate_path(&annotation.filename, 30);
-    format!(
-        "{}:{}-{} | {} | {}",
-        truncated_filename,
-        annotation.line_range.0,
-        annotation.line_range.1,
-        preview,
-        annotation.format_time()
-    )
+    let location = if annotation.is_file_level() {
+        format!("{} (file)", truncated_filename)
+    } else {
+        format!(
+            "{}:{}-{}",
+            truncated_filename, annotation.line_range.0, annotation.line_range.1
+        )
+    };
+    format!("{} | {} | {}", location, preview, annotation.format_time())
+}
+
+/// Raw mode and the alternate screen (with mouse capture for the viewer) for as long
+/// as it lives, so an early `?` return still hands the terminal back intact.
+struct TerminalGuard {
+    mouse: bool,
+}
+
+impl TerminalGuard {
+    fn enter(mouse: bool) -> io::Result<Self> {
+        enable_raw_mode()?;
+        // Constructed before the rest so a failure below still restores raw mode
+        let guard = Self { mouse };
+        io::stdout().execute(EnterAlternateScreen)?;
+        if mouse {
+            io::stdout().execute(EnableMouseCapture)?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if self.mouse {
+            let _ = io::stdout().execute(DisableMouseCapture);
+        }
+        let _ = disable_raw_mode();
+        let _ = io::stdout().execute(LeaveAlternateScreen);
+    }
+}
+
+/// Let the user pick one of `items` in the viewer's select modal, on a screen of its
+/// own since no diff is loaded yet. Returns the picked index, or None when dismissed.
+pub fn pick_in_modal(
+    title: &str,
+    items: Vec<String>,
+    theme_name: Option<&str>,
+) -> io::Result<Option<usize>> {
+    theme::init(theme_name);
+    let _guard = TerminalGuard::enter(false)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let mut modal = Modal::select(title, items);
+    let picked = loop {
+        terminal.draw(|frame| modal.render(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match modal.handle_input(key, terminal.size()?.height) {
+            Some(ModalResult::Selected(index, _)) => break Some(index),
+            Some(_) => break None,
+            None => {}
+        }
+    };
+    Ok(picked)
 }
 
 pub fn run_app_with_pr(
@@ -100,14 +364,30 @@ pub fn run_app_with_pr(
 ) -> io::Result<()> {
     let mut spinner = Spinner::new(
         spinners::Dots,
-        format!(
-            "Fetching diff for {}/{}#{}",
-            pr_info.repo_owner, pr_info.repo_name, pr_info.number
-        ),
+        format!("Fetching diff for {}", pr_info.full_ref()),
         Color::Cyan,
     );
-    match load_pr_file_diffs(&pr_info) {
-        Ok(file_diffs) => {
+    if options.stacked {
+        if pr_info.forge != Forge::GitHub {
+            spinner.fail("--stacked is only supported for GitHub pull requests");
+            std::process::exit(1);
+        }
+        return run_app_pr_stacked(options, pr_info, spinner, backend);
+    }
+    match pr_info
+        .forge
+        .backend()
+        .load_file_diffs(&pr_info, options.max_pr_files)
+    {
+        Ok((file_diffs, total)) if total > file_diffs.len() => {
+            spinner.warn(&format!(
+                "Fetched the first {} of {} files; raise max_pr_files in the config to see the rest",
+                file_diffs.len(),
+                total
+            ));
+            run_app_internal(options, Some(pr_info), file_diffs, None, backend)
+        }
+        Ok((file_diffs, _)) => {
             spinner.success(&format!("Fetched {} files", file_diffs.len()));
             run_app_internal(options, Some(pr_info), file_diffs, None, backend)
         }
@@ -118,6 +398,40 @@ pub fn run_app_with_pr(
     }
 }
 
+/// Walk a PR commit by commit, reusing stacked mode with diffs fetched from GitHub.
+fn run_app_pr_stacked(
+    options: DiffOptions,
+    pr_info: PrInfo,
+    mut spinner: Spinner,
+    backend: &dyn VcsBackend,
+) -> io::Result<()> {
+    let commits = match fetch_pr_commits(&pr_info) {
+        Ok(commits) if commits.is_empty() => {
+            spinner.fail("PR has no commits");
+            std::process::exit(1);
+        }
+        Ok(commits) => commits,
+        Err(e) => {
+            spinner.fail(&e);
+            std::process::exit(1);
+        }
+    };
+    match load_pr_commit_diffs(&pr_info, &commits[0].commit_id, &options.file) {
+        Ok(file_diffs) => {
+            spinner.success(&format!(
+                "Fetched {} commits ({} files in the first)",
+                commits.len(),
+                file_diffs.len()
+            ));
+            run_app_internal(options, Some(pr_info), file_diffs, Some(commits), backend)
+        }
+        Err(e) => {
+            spinner.fail(&e);
+            std::process::exit(1);
+        }
+    }
+}
+
 pub fn run_app(
     options: DiffOptions,
     pr_info: Option<PrInfo>,
@@ -133,14 +447,16 @@ pub fn run_app_stacked(
     backend: &dyn VcsBackend,
 ) -> io::Result<()> {
     // Load the first commit's diff
-    let first_commit = &commits[0];
-    let file_diffs = load_single_commit_diffs(&first_commit.commit_id, &options.file, backend);
+    let file_diffs =
+        load_stacked_commit_diffs(&commits[0], &options, None, backend).unwrap_or_default();
     run_app_internal(options, None, file_diffs, Some(commits), backend)
 }
 
-/// Sync viewed files from GitHub to local state
-fn sync_viewed_files_from_github(pr_info: &PrInfo, state: &mut AppState) {
-    if let Ok(viewed_paths) = fetch_viewed_files(pr_info) {
+/// Sync viewed files from the PR's host to local state, along with each file's
+/// current hash to spot files changed since you last saw them
+fn sync_viewed_files(pr_info: &PrInfo, max_files: usize, state: &mut AppState) {
+    let host = pr_info.forge.backend();
+    if let Ok(viewed_paths) = host.fetch_viewed_files(pr_info, max_files) {
         state.viewed_files.clear();
         for (idx, diff) in state.file_diffs.iter().enumerate() {
             if viewed_paths.contains(&diff.filename) {
@@ -148,6 +464,138 @@ fn sync_viewed_files_from_github(pr_info: &PrInfo, state: &mut AppState) {
             }
         }
     }
+    if let Ok(shas) = host.fetch_file_shas(pr_info) {
+        state.set_remote_shas(shas);
+    }
+}
+
+/// Write which file versions you have seen in `pr_info` to disk, reporting a failure
+/// in the footer.
+fn save_seen_files(state: &mut AppState, pr_info: &PrInfo) {
+    if let Err(e) = review_store::save(pr_info, &state.seen_shas) {
+        state.set_status_message(format!("Could not save review progress: {}", e));
+    }
+}
+
+/// Show your unsubmitted review comments next to the hunks they belong to
+fn sync_pending_review(pr_info: &PrInfo, state: &mut AppState) {
+    let host = pr_info.forge.backend();
+    if let Ok(comments) = host.fetch_pending_review_comments(pr_info) {
+        state.set_pending_review_comments(&comments);
+    }
+}
+
+/// Send a viewed toggle to the PR's host (in the background). GitLab keeps no
+/// per-file viewed state, so there the mark only lasts for this session.
+fn push_viewed_mark(pr_info: &PrInfo, filename: &str, viewed: bool, state: &mut AppState) {
+    let host = pr_info.forge.backend();
+    if !host.mark_viewed(pr_info, filename, viewed) {
+        state.set_status_message(format!(
+            "viewed marks stay local: {} has no per-file viewed state",
+            pr_info.host
+        ));
+    }
+}
+
+/// What the session shows, e.g. `main..feature` or `PR #12 (main...fix)`; None for the working tree.
+fn diff_reference_label(options: &DiffOptions, pr_info: Option<&PrInfo>) -> Option<String> {
+    if let Some(pr) = pr_info {
+        Some(format!(
+            "{} {} ({}...{})",
+            pr.forge.noun(),
+            pr.forge.number_ref(pr.number),
+            pr.base_ref,
+            pr.head_ref
+        ))
+    } else if let Some(op) = &options.in_progress {
+        let step = op
+            .step
+            .map(|(current, total)| format!(" {}/{}", current, total))
+            .unwrap_or_default();
+        Some(format!(
+            "{}{} {}",
+            op.kind,
+            step,
+            &op.commit_id[..7.min(op.commit_id.len())]
+        ))
+    } else if let Some(base) = &options.against {
+        Some(format!("{} vs working tree", base))
+    } else {
+        options.reference.as_ref().map(|r| match r {
+            CommitReference::Single(s) => s.clone(),
+            CommitReference::Range { from, to } => format!("{}..{}", from, to),
+            CommitReference::TripleDots { from, to } => format!("{}...{}", from, to),
+        })
+    }
+}
+
+/// `--export-html`: write every file of the diff to one page at `path` and skip the viewer.
+fn export_html(
+    path: &std::path::Path,
+    file_diffs: Vec<super::types::FileDiff>,
+    options: &DiffOptions,
+    title: &str,
+) -> io::Result<()> {
+    let mut state = AppState::new(file_diffs, None);
+    state.settings.hide_whitespace_emphasis = options.hide_whitespace_emphasis;
+    state.settings.tab_glyph = options.tab_glyph;
+    state.settings.tab_widths = options.tab_widths.clone();
+    let files = state.sidebar_file_order();
+    std::fs::write(path, state.html_snapshot(title, &files))?;
+    println!(
+        "Wrote {} file{} to {}",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        path.display()
+    );
+    Ok(())
+}
+
+/// `--grep`: keep only the files with an added or changed line matching the pattern.
+/// Lines are paired with the same tab width and whitespace mode the view uses.
+fn grep_files(
+    mut file_diffs: Vec<super::types::FileDiff>,
+    settings: &DiffViewSettings,
+) -> Vec<super::types::FileDiff> {
+    if let Some(filter) = &settings.grep {
+        file_diffs.retain(|diff| {
+            !diff.is_binary
+                && filter.matches_added(&compute_side_by_side(
+                    &diff.old_content,
+                    &diff.new_content,
+                    settings.tab_width_for(&diff.filename),
+                    settings.ignore_whitespace,
+                ))
+        });
+    }
+    file_diffs
+}
+
+/// The view settings `grep_files` needs before the app state exists.
+fn grep_settings(options: &DiffOptions) -> DiffViewSettings {
+    DiffViewSettings {
+        tab_widths: options.tab_widths.clone(),
+        grep: options.grep.clone(),
+        ..DiffViewSettings::default()
+    }
+}
+
+/// `--export-patch`: write the loaded diff to `path` and skip the viewer.
+fn export_patch(path: &std::path::Path, file_diffs: &[super::types::FileDiff]) -> io::Result<()> {
+    let export = super::patch::write_patch(path, file_diffs)?;
+    for filename in &export.skipped_binary {
+        eprintln!(
+            "\x1b[93mwarning:\x1b[0m left binary file {} out of the patch",
+            filename
+        );
+    }
+    println!(
+        "Wrote {} file{} to {}",
+        export.files,
+        if export.files == 1 { "" } else { "s" },
+        path.display()
+    );
+    Ok(())
 }
 
 fn run_app_internal(
@@ -157,8 +605,30 @@ fn run_app_internal(
     stacked_commits: Option<Vec<StackedCommitInfo>>,
     backend: &dyn VcsBackend,
 ) -> io::Result<()> {
+    if let Some(path) = &options.export_patch {
+        return export_patch(path, &file_diffs);
+    }
+    if options.stat {
+        let file_diffs = grep_files(file_diffs, &grep_settings(&options));
+        if !file_diffs.is_empty() {
+            print!("{}", format_diff_stat(&file_diffs, 4, true));
+        }
+        return Ok(());
+    }
+
     theme::init(options.theme.as_deref());
-    highlight::init();
+    highlight::init(
+        options.highlight,
+        options.languages.clone(),
+        options.no_highlight.clone(),
+        options.verbose,
+    );
+
+    if let Some(path) = &options.export_html {
+        let title = diff_reference_label(&options, pr_info.as_ref())
+            .unwrap_or_else(|| "working tree".to_string());
+        return export_html(path, file_diffs, &options, &title);
+    }
 
     // When stdout is not a TTY (e.g., in Helix :insert-output), redirect it to /dev/tty
     // so the TUI can render. crossterm's use-dev-tty feature handles stdin automatically.
@@ -184,54 +654,121 @@ fn run_app_internal(
     }
 
     // Initialize state before TUI so we can sync viewed files
-    let mut state = AppState::new(file_diffs, options.focus.as_deref());
+    let mut state = AppState::new(
+        grep_files(file_diffs, &grep_settings(&options)),
+        options.focus.as_deref(),
+    );
     state.set_vcs_name(backend.name());
+    state.settings.indicators = options.indicators.clone();
+    state.settings.hide_whitespace_emphasis = options.hide_whitespace_emphasis;
+    state.settings.tab_glyph = options.tab_glyph;
+    state.settings.tab_widths = options.tab_widths.clone();
+    state.settings.view_mode = options.view_mode;
+    state.settings.panel_borders = options.panel_borders;
+    state.settings.annotation_limit = options.annotation_limit;
+    state.export_template = options.annotation_export.clone();
+    state.search_state.wrap = options.search_wrap;
+    state.settings.fold_threshold = options.fold_threshold;
+    state.settings.grep = options.grep.clone();
 
     // Set diff reference for annotation export context
-    let diff_ref_str = if let Some(pr) = &pr_info {
-        Some(format!("PR #{} ({}...{})", pr.number, pr.base_ref, pr.head_ref))
-    } else {
-        options.reference.as_ref().map(|r| match r {
-            CommitReference::Single(s) => s.clone(),
-            CommitReference::Range { from, to } => format!("{}..{}", from, to),
-            CommitReference::TripleDots { from, to } => format!("{}...{}", from, to),
-        })
-    };
-    state.set_diff_reference(diff_ref_str);
+    let diff_ref_str = diff_reference_label(&options, pr_info.as_ref());
+    state.set_diff_reference(diff_ref_str.clone());
+    state.set_panel_titles(panel_titles(&options, pr_info.as_ref(), backend));
+
+    // Annotations from an earlier session on the same diff
+    let annotation_key = diff_ref_str
+        .clone()
+        .unwrap_or_else(|| "working tree".to_string());
+    let state_dir = backend.state_dir();
+    state.restore_annotations(annotation_store::load(&state_dir, &annotation_key));
+
+    // Viewed marks on the host cover the whole PR, so they don't apply when walking its commits
+    let viewed_sync_pr = pr_info.clone().filter(|_| stacked_commits.is_none());
 
     // Initialize stacked mode if commits were provided
     if let Some(commits) = stacked_commits {
         state.init_stacked_mode(commits);
     }
+    if options.tree_depth.is_some() {
+        state.set_tree_depth(options.tree_depth);
+    }
 
-    // Load viewed files from GitHub on startup in PR mode (before TUI starts)
-    if let Some(ref pr) = pr_info {
+    // Load viewed files from the host on startup in PR mode (before TUI starts)
+    if let Some(ref pr) = viewed_sync_pr {
         let mut spinner = Spinner::new(
             spinners::Dots,
             format!("Syncing viewed status for {} files", state.file_diffs.len()),
             Color::Cyan,
         );
-        sync_viewed_files_from_github(pr, &mut state);
+        state.seen_shas = review_store::load(pr);
+        sync_viewed_files(pr, options.max_pr_files, &mut state);
+        sync_pending_review(pr, &mut state);
+        save_seen_files(&mut state, pr);
         let viewed_count = state.viewed_files.len();
-        spinner.success(&format!("{} files marked as viewed", viewed_count));
+        let changed = match state.changed_since_review.len() {
+            0 => String::new(),
+            n => format!("; {} changed since your last review", n),
+        };
+        match state.pending_review.len() {
+            0 => spinner.success(&format!(
+                "{} files marked as viewed{}",
+                viewed_count, changed
+            )),
+            _ => spinner.success(&format!(
+                "{} files marked as viewed{}; showing your pending review comments",
+                viewed_count, changed
+            )),
+        }
     }
 
-    enable_raw_mode()?;
-    io::stdout().execute(EnterAlternateScreen)?;
-    io::stdout().execute(EnableMouseCapture)?;
-
+    let guard = TerminalGuard::enter(true)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
 
+    // `--focus` may name a file inside a directory the tree depth collapsed, and
+    // `--grep` should open on the first hunk it kept
+    if (options.focus.is_some() || options.grep.is_some()) && !state.file_diffs.is_empty() {
+        let sidebar_height = terminal.size()?.height.saturating_sub(5) as usize;
+        let current = state.current_file;
+        focus_file(&mut state, current, sidebar_height);
+    }
+
+    // A stopped rebase or cherry-pick, or `--conflicts`, opens on its first conflict
+    if options.in_progress.is_some() || options.conflicts {
+        if options.focus.is_none() && state.conflict_counts.get(state.current_file) == Some(&0) {
+            if let Some(file_index) = state.find_conflict_file(true) {
+                let sidebar_height = terminal.size()?.height.saturating_sub(5) as usize;
+                focus_file(&mut state, file_index, sidebar_height);
+            }
+        }
+        let visible_height = terminal.size()?.height.saturating_sub(2) as usize;
+        state.focus_conflict(None, true, visible_height, usize::MAX);
+    }
+    if let Some(op) = &options.in_progress {
+        state.set_status_message(format!("applying: {}", op.summary));
+    }
+
     let watch_rx = if options.watch && pr_info.is_none() {
         setup_watcher()
     } else {
         None
     };
+    let watch_interval = options.watch_interval.filter(|_| pr_info.is_none());
+    let mut last_poll = Instant::now();
 
-    let mut active_modal: Option<Modal> = None;
+    let mut active_modal: Option<Modal> = if options.overview && !state.file_diffs.is_empty() {
+        Some(overview_modal(&state))
+    } else {
+        None
+    };
     let mut annotation_editor: Option<AnnotationEditor> = None;
     let mut pending_watch_event: Option<WatchEvent> = None;
     let mut pending_events: VecDeque<Event> = VecDeque::new();
+    let mut explain_rx: Option<Receiver<Result<String, String>>> = None;
+    let mut theme_changed = false;
+    // Whether the screen is out of date, and whether the last frame showed a status message
+    let mut dirty = true;
+    let mut status_shown = false;
 
     'main: loop {
         if let Some(ref rx) = watch_rx {
@@ -245,97 +782,219 @@ fn run_app_internal(
             }
         }
 
+        // Timer reloads cover filesystems where the watcher sees no events
+        let mut timer_reload = false;
+        if let Some(interval) = watch_interval {
+            if last_poll.elapsed() >= interval {
+                last_poll = Instant::now();
+                timer_reload = !state.needs_reload;
+                state.needs_reload = true;
+            }
+        }
+
+        if let Some(ref rx) = explain_rx {
+            match rx.try_recv() {
+                Ok(result) => {
+                    if let Some(ref mut modal) = active_modal {
+                        modal.set_explanation(result);
+                    }
+                    explain_rx = None;
+                    dirty = true;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    explain_rx = None;
+                }
+            }
+        }
+
         if state.needs_reload {
-            let file_diffs = if let Some(ref pr) = pr_info {
-                // In PR mode, reload from GitHub
-                match load_pr_file_diffs(pr) {
-                    Ok(diffs) => diffs,
+            dirty = true;
+            let stacked_commit = state
+                .stacked_mode
+                .then(|| state.current_commit().cloned())
+                .flatten();
+            // A failed fetch keeps the diffs on screen and reports in the footer
+            let file_diffs = if let (Some(pr), Some(commit)) = (&pr_info, &stacked_commit) {
+                match load_pr_commit_diffs(pr, &commit.commit_id, &options.file) {
+                    Ok(diffs) => Some(diffs),
                     Err(e) => {
-                        eprintln!("Warning: failed to reload PR diffs: {}", e);
-                        Vec::new()
+                        state
+                            .set_status_message(format!("Could not reload PR commit diffs: {}", e));
+                        None
+                    }
+                }
+            } else if let Some((commit, revision)) = stacked_commit
+                .as_ref()
+                .and_then(|c| c.history.as_ref().map(|revision| (c, revision)))
+            {
+                Some(load_file_revision_diffs(
+                    &commit.commit_id,
+                    revision,
+                    backend,
+                ))
+            } else if let Some(ref pr) = pr_info {
+                // In PR mode, reload from the host
+                match pr.forge.backend().load_file_diffs(pr, options.max_pr_files) {
+                    Ok((diffs, total)) => {
+                        if total > diffs.len() {
+                            state.set_status_message(format!(
+                                "showing {} of {} files (max_pr_files)",
+                                diffs.len(),
+                                total
+                            ));
+                        }
+                        Some(diffs)
+                    }
+                    Err(e) => {
+                        state.set_status_message(format!("Could not reload PR diffs: {}", e));
+                        None
                     }
                 }
+            } else if let Some(base) = &state.pinned_base {
+                Some(load_file_diffs_against(base, &options, backend))
             } else {
-                load_file_diffs(&options, backend)
+                Some(load_file_diffs(&options, backend))
             };
 
-            // Pass changed files to reload so it can unmark them from viewed
-            let changed_files = pending_watch_event.take().map(|e| e.changed_files);
-            state.reload(file_diffs, changed_files.as_ref());
-
-            // Re-sync viewed files from GitHub in PR mode
-            if let Some(ref pr) = pr_info {
-                sync_viewed_files_from_github(pr, &mut state);
+            if let Some(file_diffs) = file_diffs {
+                // Pass changed files to reload so it can unmark them from viewed
+                let changed = match pending_watch_event.take() {
+                    Some(event) => Some(event.changed_files),
+                    None if timer_reload => Some(changed_files(&state.file_diffs, &file_diffs)),
+                    None => None,
+                };
+                state.reload(grep_files(file_diffs, &state.settings), changed.as_ref());
+
+                // Re-sync viewed files from the host in PR mode
+                if let Some(ref pr) = viewed_sync_pr {
+                    sync_viewed_files(pr, options.max_pr_files, &mut state);
+                    sync_pending_review(pr, &mut state);
+                }
+            } else {
+                state.needs_reload = false;
             }
         }
 
-        if state.file_diffs.is_empty() {
-            terminal.draw(|frame| {
-                render_empty_state(frame, options.watch);
-                if let Some(ref modal) = active_modal {
-                    modal.render(frame);
+        // Draw only when something may have changed; the explain spinner animates
+        // and an expired status message has to be cleared
+        let status_expired = status_shown && state.status_message().is_none();
+        if dirty || status_expired || explain_rx.is_some() || options.idle_redraw {
+            if state.file_diffs.is_empty() {
+                terminal.draw(|frame| {
+                    render_empty_state(frame, options.watch);
+                    if let Some(ref modal) = active_modal {
+                        modal.render(frame);
+                    }
+                })?;
+            } else {
+                if state.needs_blame() {
+                    let blame = if state.is_commit_message() {
+                        state.set_status_message("the commit message has no blame");
+                        Vec::new()
+                    } else {
+                        match old_side_blame(&state, &options, pr_info.as_ref(), backend) {
+                            Ok(blame) => blame,
+                            Err(e) => {
+                                state.set_status_message(format!("blame unavailable: {}", e));
+                                Vec::new()
+                            }
+                        }
+                    };
+                    state.set_blame(state.current_file, blame);
                 }
-            })?;
-        } else {
-            let diff = &state.file_diffs[state.current_file];
-            let side_by_side = compute_side_by_side(
-                &diff.old_content,
-                &diff.new_content,
-                state.settings.tab_width,
-            );
-            let hunks = find_hunk_starts(&side_by_side);
-            let hunk_count = hunks.len();
-            state
-                .search_state
-                .update_matches(&side_by_side, state.diff_fullscreen);
-            let branch_fallback = get_current_branch(backend);
-            let commit_ref = state
-                .diff_reference
-                .as_deref()
-                .unwrap_or(&branch_fallback);
-            terminal.draw(|frame| {
-                render_diff(
-                    frame,
-                    diff,
-                    &state.file_diffs,
-                    &state.sidebar_items,
-                    &state.sidebar_visible,
-                    &state.collapsed_dirs,
-                    state.current_file,
-                    state.scroll,
-                    state.h_scroll,
-                    options.watch,
-                    state.show_sidebar,
-                    state.focused_panel,
-                    state.sidebar_selected,
-                    state.sidebar_scroll,
-                    state.sidebar_h_scroll,
-                    &state.viewed_files,
-                    &state.settings,
-                    hunk_count,
-                    state.diff_fullscreen,
-                    &state.search_state,
-                    commit_ref,
-                    pr_info.as_ref(),
-                    state.focused_hunk,
-                    &hunks,
-                    state.stacked_mode,
-                    state.current_commit(),
-                    state.current_commit_index,
-                    state.stacked_commits.len(),
-                    &side_by_side,
-                    state.vcs_name,
-                    &state.annotations,
-                    &state.selection,
-                );
-                // Render annotation editor (on top of everything except modal)
-                if let Some(ref editor) = annotation_editor {
-                    editor.render(frame);
+                if state.needs_origins() {
+                    let origins = range_line_origins(&state, &options, pr_info.as_ref(), backend)
+                        .unwrap_or_else(|e| {
+                            state.set_status_message(format!("line origins unavailable: {}", e));
+                            Vec::new()
+                        });
+                    state.set_origins(state.current_file, origins);
                 }
-                if let Some(ref modal) = active_modal {
-                    modal.render(frame);
+                // Looking at a file catches you up on its latest version
+                if let Some(ref pr) = viewed_sync_pr {
+                    if state.mark_seen(state.current_file) {
+                        save_seen_files(&mut state, pr);
+                    }
                 }
-            })?;
+                let side_by_side = state.display_lines(state.current_file);
+                let diff = &state.file_diffs[state.current_file];
+                let hunks = find_hunk_starts(&side_by_side);
+                let hunk_count = hunks.len();
+                state
+                    .search_state
+                    .update_matches(&side_by_side, state.search_fullscreen());
+                // Only look up the branch when there is no explicit reference to show
+                let branch_fallback;
+                let commit_ref = match state.diff_reference.as_deref() {
+                    Some(reference) => reference,
+                    None => {
+                        branch_fallback = get_current_branch(backend);
+                        &branch_fallback
+                    }
+                };
+                terminal.draw(|frame| {
+                    render_diff(
+                        frame,
+                        diff,
+                        &state.file_diffs,
+                        &state.sidebar_items,
+                        &state.sidebar_visible,
+                        &state.collapsed_dirs,
+                        state.current_file,
+                        state.scroll,
+                        state.h_scroll,
+                        options.watch,
+                        state.show_sidebar,
+                        state.focused_panel,
+                        state.sidebar_selected,
+                        state.sidebar_scroll,
+                        state.sidebar_h_scroll,
+                        &state.viewed_files,
+                        &state.changed_since_review,
+                        state
+                            .sidebar_filter
+                            .as_deref()
+                            .map(|filter| (filter, state.sidebar_filter_count())),
+                        &state.conflict_counts,
+                        &state.settings,
+                        state.hidden_deleted_count(),
+                        hunk_count,
+                        state.effective_fullscreen(),
+                        &state.search_state,
+                        commit_ref,
+                        pr_info.as_ref(),
+                        state.focused_hunk,
+                        &hunks,
+                        &state.reviewed_hunks,
+                        state.skip_reviewed_hunks,
+                        state.stacked_mode,
+                        state.current_commit(),
+                        state.current_commit_index,
+                        state.stacked_commits.len(),
+                        &side_by_side,
+                        state.vcs_name,
+                        &state.annotations,
+                        &state.pending_review,
+                        &state.selection,
+                        state.status_message(),
+                        state.prompt.as_ref(),
+                        &state.panel_titles(),
+                        state.current_blame(),
+                        state.current_origins(),
+                        state.pinned_hunk_view(),
+                    );
+                    // Render annotation editor (on top of everything except modal)
+                    if let Some(ref editor) = annotation_editor {
+                        editor.render(frame);
+                    }
+                    if let Some(ref modal) = active_modal {
+                        modal.render(frame);
+                    }
+                })?;
+            }
+            dirty = false;
+            status_shown = state.status_message().is_some();
         }
 
         // Poll for new events if no pending events
@@ -345,45 +1004,87 @@ fn run_app_internal(
 
         // Process all pending events
         while let Some(current_event) = pending_events.pop_front() {
-            let visible_height = terminal.size()?.height.saturating_sub(2) as usize;
-            let bottom_padding = 5;
-            let max_scroll = if !state.file_diffs.is_empty() {
-                let diff = &state.file_diffs[state.current_file];
-                let total_lines = compute_side_by_side(
-                    &diff.old_content,
-                    &diff.new_content,
-                    state.settings.tab_width,
-                )
-                .len();
-                total_lines.saturating_sub(visible_height.saturating_sub(bottom_padding))
-            } else {
-                0
-            };
+            dirty = true;
+            let term_height = terminal.size()?.height;
+            let visible_height =
+                term_height.saturating_sub(2 + state.pinned_strip_height(term_height)) as usize;
+            let max_scroll = diff_max_scroll(&state, visible_height, terminal.size()?.width);
 
             match current_event {
+                Event::Key(key) if key.kind == KeyEventKind::Press && state.prompt.is_some() => {
+                    match key.code {
+                        KeyCode::Esc => state.prompt = None,
+                        KeyCode::Enter => {
+                            let Some(prompt) = state.prompt.take() else {
+                                continue;
+                            };
+                            let base = prompt.input.trim().to_string();
+                            if prompt.kind == PromptKind::SidebarFilter {
+                                state.set_sidebar_filter((!base.is_empty()).then_some(base));
+                                if state.sidebar_filter.is_some()
+                                    && state.sidebar_visible.is_empty()
+                                {
+                                    state.set_status_message("no files match the filter");
+                                }
+                                let sidebar_height =
+                                    terminal.size()?.height.saturating_sub(5) as usize;
+                                ensure_sidebar_visible(&mut state, sidebar_height);
+                            } else if base.is_empty() {
+                                // Empty input unpins and goes back to what the session opened on
+                                if state.pinned_base.is_some() {
+                                    state.unpin_base(
+                                        diff_ref_str.clone(),
+                                        panel_titles(&options, pr_info.as_ref(), backend),
+                                    );
+                                }
+                            } else {
+                                match backend.resolve_ref(&base) {
+                                    Ok(_) => {
+                                        let titles = pinned_base_titles(&base, backend);
+                                        state.pin_base(base, titles);
+                                    }
+                                    Err(e) => state
+                                        .set_status_message(format!("cannot pin {}: {}", base, e)),
+                                }
+                            }
+                        }
+                        KeyCode::Backspace => {
+                            if let Some(prompt) = state.prompt.as_mut() {
+                                prompt.input.pop();
+                            }
+                        }
+                        KeyCode::Char(c) => {
+                            if let Some(prompt) = state.prompt.as_mut() {
+                                prompt.input.push(c);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
                 Event::Key(key)
                     if key.kind == KeyEventKind::Press && state.search_state.is_active() =>
                 {
                     match key.code {
                         KeyCode::Esc => {
+                            // Back to where the view was before typing moved it
+                            state.scroll = state.search_state.origin_scroll as u16;
                             state.search_state.cancel();
                         }
                         KeyCode::Enter => {
+                            // Typing already jumped to the first match
                             state.search_state.confirm();
-                            if state.search_state.has_query() {
-                                if let Some(line) = state
-                                    .search_state
-                                    .jump_to_first_match(state.scroll as usize)
-                                {
-                                    state.scroll = line.saturating_sub(5) as u16;
-                                }
-                            }
                         }
                         KeyCode::Backspace => {
                             state.search_state.pop_char();
+                            state.update_incremental_search();
+                        }
+                        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            state.search_state.toggle_regex();
+                            state.update_incremental_search();
                         }
                         KeyCode::Char(c) => {
                             state.search_state.push_char(c);
+                            state.update_incremental_search();
                         }
                         _ => {}
                     }
@@ -398,10 +1099,12 @@ fn run_app_internal(
                             AnnotationEditorResult::Continue => {}
                             AnnotationEditorResult::Save => {
                                 state.set_annotation(editor.to_annotation());
+                                save_annotations(&mut state, &state_dir, &annotation_key);
                                 annotation_editor = None;
                             }
                             AnnotationEditorResult::Delete => {
-                                state.remove_annotation(editor.file_index, editor.hunk_index);
+                                state.remove_annotation(&editor.filename, editor.hunk_index);
+                                save_annotations(&mut state, &state_dir, &annotation_key);
                                 annotation_editor = None;
                             }
                             AnnotationEditorResult::Cancel => {
@@ -416,58 +1119,62 @@ fn run_app_internal(
                         if let Some(result) = modal.handle_input(key, term_height) {
                             match result {
                                 ModalResult::FileSelected(file_index) => {
-                                    state.reveal_file(file_index);
-                                    state.select_file(file_index);
-                                    if let Some(idx) =
-                                        state.sidebar_visible_index_for_file(state.current_file)
-                                    {
-                                        state.sidebar_selected = idx;
-                                        let visible_height =
-                                            terminal.size()?.height.saturating_sub(5) as usize;
-                                        ensure_sidebar_visible(&mut state, visible_height);
-                                    }
+                                    let sidebar_height =
+                                        terminal.size()?.height.saturating_sub(5) as usize;
+                                    focus_file(&mut state, file_index, sidebar_height);
                                     active_modal = None;
                                 }
-                                ModalResult::AnnotationJump { file_index, hunk_index } => {
-                                    // Jump to the file and hunk
-                                    state.select_file(file_index);
-                                    state.focused_hunk = Some(hunk_index);
-                                    // Scroll to the hunk
-                                    let diff = &state.file_diffs[file_index];
-                                    let side_by_side = compute_side_by_side(
-                                        &diff.old_content,
-                                        &diff.new_content,
-                                        state.settings.tab_width,
-                                    );
-                                    let hunks = find_hunk_starts(&side_by_side);
-                                    if let Some(&hunk_start) = hunks.get(hunk_index) {
-                                        state.scroll = adjust_scroll_for_hunk(
-                                            hunk_start,
-                                            state.scroll,
-                                            visible_height,
-                                            max_scroll,
-                                        );
+                                ModalResult::AnnotationJump { filename, hunk_index } => {
+                                    // Jump to the file and hunk, wherever the file sits now
+                                    if let Some(file_index) = state.file_index_of(&filename) {
+                                        let sidebar_height =
+                                            terminal.size()?.height.saturating_sub(5) as usize;
+                                        focus_file(&mut state, file_index, sidebar_height);
+                                        // File notes are pinned above the diff
+                                        if hunk_index != FILE_ANNOTATION_HUNK {
+                                            state.focused_hunk = Some(hunk_index);
+                                        }
+                                        // Scroll to the hunk
+                                        let side_by_side = state.display_lines(file_index);
+                                        let hunks = find_hunk_starts(&side_by_side);
+                                        if let Some(&hunk_start) = hunks.get(hunk_index) {
+                                            state.scroll = adjust_scroll_for_hunk(
+                                                hunk_start,
+                                                state.scroll,
+                                                visible_height,
+                                                max_scroll,
+                                            );
+                                        }
                                     }
                                     active_modal = None;
                                 }
-                                ModalResult::AnnotationEdit { file_index, hunk_index } => {
+                                ModalResult::AnnotationEdit { filename, hunk_index } => {
                                     // Close modal and open annotation editor for editing
-                                    if let Some(ann) = state.get_annotation(file_index, hunk_index) {
+                                    let file_index = state.file_index_of(&filename);
+                                    if let (Some(file_index), Some(ann)) =
+                                        (file_index, state.get_annotation(&filename, hunk_index))
+                                    {
                                         let editor = AnnotationEditor::new(
-                                            file_index,
                                             hunk_index,
                                             ann.filename.clone(),
                                             ann.line_range,
-                                        ).with_content(&ann.content, ann.created_at);
+                                        )
+                                        .with_content(&ann.content, ann.created_at)
+                                        .with_soft_limit(state.settings.annotation_limit);
                                         annotation_editor = Some(editor);
                                         // Also jump to the hunk
-                                        state.select_file(file_index);
-                                        state.focused_hunk = Some(hunk_index);
+                                        let sidebar_height =
+                                            terminal.size()?.height.saturating_sub(5) as usize;
+                                        focus_file(&mut state, file_index, sidebar_height);
+                                        if hunk_index != FILE_ANNOTATION_HUNK {
+                                            state.focused_hunk = Some(hunk_index);
+                                        }
                                     }
                                     active_modal = None;
                                 }
-                                ModalResult::AnnotationDelete { file_index, hunk_index } => {
-                                    state.remove_annotation(file_index, hunk_index);
+                                ModalResult::AnnotationDelete { filename, hunk_index } => {
+                                    state.remove_annotation(&filename, hunk_index);
+                                    save_annotations(&mut state, &state_dir, &annotation_key);
                                     // Refresh the modal if there are still annotations
                                     if !state.annotations.is_empty() {
                                         let mut sorted_annotations = state.annotations.clone();
@@ -476,7 +1183,12 @@ fn run_app_internal(
                                             .iter()
                                             .map(format_annotation_preview)
                                             .collect();
-                                        active_modal = Some(Modal::annotations("Annotations", items, sorted_annotations));
+                                        active_modal = Some(Modal::annotations(
+                                            "Annotations",
+                                            items,
+                                            sorted_annotations,
+                                            state.pending_review.len(),
+                                        ));
                                     } else {
                                         active_modal = None;
                                     }
@@ -484,9 +1196,11 @@ fn run_app_internal(
                                 ModalResult::AnnotationCopyAll => {
                                     // Copy all annotations to clipboard
                                     let formatted = state.format_annotations_for_export();
-                                    if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                                        let _ = clipboard.set_text(&formatted);
-                                    }
+                                    let message = match copy(&options.clipboard, &formatted) {
+                                        Ok(()) => "copied annotations".to_string(),
+                                        Err(e) => format!("clipboard unavailable: {}", e),
+                                    };
+                                    state.set_status_message(message);
                                     active_modal = None;
                                 }
                                 ModalResult::AnnotationExport(filename) => {
@@ -507,7 +1221,16 @@ fn run_app_internal(
                                         }
                                     }
                                 }
-                                ModalResult::Dismissed | ModalResult::Selected(_, _) => {
+                                ModalResult::Selected(index, _) => {
+                                    // The marks list is the only select modal
+                                    if let Some(&(mark, _, _)) = state.sorted_marks().get(index) {
+                                        let sidebar_height =
+                                            terminal.size()?.height.saturating_sub(5) as usize;
+                                        jump_to_mark(&mut state, mark, sidebar_height);
+                                    }
+                                    active_modal = None;
+                                }
+                                ModalResult::Dismissed => {
                                     active_modal = None;
                                 }
                             }
@@ -525,6 +1248,11 @@ fn run_app_internal(
                     let footer_height = 1u16;
                     let header_height = if state.stacked_mode { 1u16 } else { 0u16 };
                     let sidebar_width = if state.show_sidebar { 40u16 } else { 0u16 };
+                    // The pinned hunk strip sits between the diff rows and the footer
+                    let pinned_height = state.pinned_strip_height(term_size.height);
+                    let diff_bottom = term_size
+                        .height
+                        .saturating_sub(footer_height + pinned_height);
 
                     match mouse.kind {
                         MouseEventKind::Down(crossterm::event::MouseButton::Left) => {
@@ -533,7 +1261,13 @@ fn run_app_internal(
                                 // Left arrow click (first 4 columns to cover " < ")
                                 if mouse.column < 4 && state.current_commit_index > 0 {
                                     let new_index = state.current_commit_index - 1;
-                                    navigate_stacked_commit(&mut state, new_index, &options, backend);
+                                    navigate_stacked_commit(
+                                        &mut state,
+                                        new_index,
+                                        &options,
+                                        pr_info.as_ref(),
+                                        backend,
+                                    );
                                 }
                                 // Right arrow click (last 4 columns to cover " > ")
                                 else if mouse.column >= term_size.width.saturating_sub(4)
@@ -541,7 +1275,13 @@ fn run_app_internal(
                                         < state.stacked_commits.len().saturating_sub(1)
                                 {
                                     let new_index = state.current_commit_index + 1;
-                                    navigate_stacked_commit(&mut state, new_index, &options, backend);
+                                    navigate_stacked_commit(
+                                        &mut state,
+                                        new_index,
+                                        &options,
+                                        pr_info.as_ref(),
+                                        backend,
+                                    );
                                 }
                             } else if state.show_sidebar
                                 && mouse.column < sidebar_width
@@ -580,9 +1320,34 @@ fn run_app_internal(
                                         }
                                     }
                                 }
+                            } else if mouse.column == term_size.width.saturating_sub(1)
+                                && mouse.row > header_height
+                                && mouse.row < diff_bottom.saturating_sub(1)
+                                && !state.file_diffs.is_empty()
+                                && state.display_lines(state.current_file).len()
+                                    > minimap_rows(
+                                        term_size.height,
+                                        header_height,
+                                        footer_height,
+                                        pinned_height,
+                                    )
+                            {
+                                // Minimap on the right border: center the clicked spot
+                                state.focused_panel = FocusedPanel::DiffView;
+                                let rows = minimap_rows(
+                                    term_size.height,
+                                    header_height,
+                                    footer_height,
+                                    pinned_height,
+                                );
+                                let len = state.display_lines(state.current_file).len();
+                                let row = (mouse.row - header_height - 1) as usize;
+                                let target = row * len / rows;
+                                state.scroll =
+                                    target.saturating_sub(rows / 2).min(max_scroll) as u16;
                             } else if mouse.column >= sidebar_width
                                 && mouse.row >= header_height
-                                && mouse.row < term_size.height.saturating_sub(footer_height)
+                                && mouse.row < diff_bottom
                                 && !state.file_diffs.is_empty()
                             {
                                 state.focused_panel = FocusedPanel::DiffView;
@@ -592,8 +1357,12 @@ fn run_app_internal(
                                     term_size.width,
                                     sidebar_width,
                                     state.show_sidebar,
-                                    state.diff_fullscreen,
-                                );
+                                    state.effective_fullscreen(),
+                                    state.settings.panel_borders,
+                                    state.blame_width(),
+                                    state.origin_width(),
+                                )
+                                .with_gutter_width(state.gutter_width());
 
                                 if let Some(panel) = layout.panel_at_x(mouse.column) {
                                     let is_gutter = layout.is_in_gutter(mouse.column, panel);
@@ -602,7 +1371,6 @@ fn run_app_internal(
                                     // Fast coordinate calculation without side_by_side
                                     if mouse.row >= content_start_y {
                                         let rel_y = (mouse.row - content_start_y) as usize;
-                                        let line = state.scroll as usize + rel_y;
 
                                         let panel_x = match panel {
                                             DiffPanelFocus::Old => layout.old_panel_x,
@@ -612,18 +1380,19 @@ fn run_app_internal(
 
                                         let content_offset = layout.content_x_offset(panel);
                                         let rel_x = mouse.column.saturating_sub(panel_x);
-                                        let column = if rel_x >= content_offset {
-                                            (rel_x - content_offset + state.h_scroll) as usize
-                                        } else {
-                                            0
-                                        };
+                                        let text_x = rel_x.saturating_sub(content_offset);
 
                                         let mode = if is_gutter {
                                             SelectionMode::Line
                                         } else {
                                             SelectionMode::Character
                                         };
-                                        let pos = CursorPosition { line, column };
+                                        let pos = state.position_at(
+                                            rel_y,
+                                            text_x as usize,
+                                            panel,
+                                            term_size.width,
+                                        );
                                         state.start_selection(panel, pos, mode);
                                     }
                                 }
@@ -643,11 +1412,14 @@ fn run_app_internal(
                                             term_size.width,
                                             sidebar_width,
                                             state.show_sidebar,
-                                            state.diff_fullscreen,
-                                        );
+                                            state.effective_fullscreen(),
+                                            state.settings.panel_borders,
+                                            state.blame_width(),
+                                            state.origin_width(),
+                                        )
+                                        .with_gutter_width(state.gutter_width());
 
                                         let rel_y = (mouse.row - content_start_y) as usize;
-                                        let line = state.scroll as usize + rel_y;
 
                                         let panel_x = match panel {
                                             DiffPanelFocus::Old => layout.old_panel_x,
@@ -657,13 +1429,14 @@ fn run_app_internal(
 
                                         let content_offset = layout.content_x_offset(panel);
                                         let rel_x = mouse.column.saturating_sub(panel_x);
-                                        let column = if rel_x >= content_offset {
-                                            (rel_x - content_offset + state.h_scroll) as usize
-                                        } else {
-                                            0
-                                        };
+                                        let text_x = rel_x.saturating_sub(content_offset);
 
-                                        let pos = CursorPosition { line, column };
+                                        let pos = state.position_at(
+                                            rel_y,
+                                            text_x as usize,
+                                            panel,
+                                            term_size.width,
+                                        );
                                         state.extend_selection(pos);
                                     }
                                 }
@@ -706,8 +1479,7 @@ fn run_app_internal(
                             let in_sidebar = state.show_sidebar
                                 && mouse.column < sidebar_width
                                 && mouse.row < term_size.height.saturating_sub(footer_height);
-                            let in_diff = mouse.column >= sidebar_width
-                                && mouse.row < term_size.height.saturating_sub(footer_height);
+                            let in_diff = mouse.column >= sidebar_width && mouse.row < diff_bottom;
 
                             if in_sidebar {
                                 let max_sidebar_scroll =
@@ -762,8 +1534,7 @@ fn run_app_internal(
                             let in_sidebar = state.show_sidebar
                                 && mouse.column < sidebar_width
                                 && mouse.row < term_size.height.saturating_sub(footer_height);
-                            let in_diff = mouse.column >= sidebar_width
-                                && mouse.row < term_size.height.saturating_sub(footer_height);
+                            let in_diff = mouse.column >= sidebar_width && mouse.row < diff_bottom;
 
                             if in_sidebar {
                                 if h_scroll_delta > 0 {
@@ -789,6 +1560,7 @@ fn run_app_internal(
                     }
                 }
                 Event::Key(key) if key.kind == KeyEventKind::Press && active_modal.is_none() => {
+                    let pending_key = state.pending_key;
                     if key.code != KeyCode::Char('g') {
                         state.pending_key = PendingKey::None;
                     }
@@ -808,7 +1580,45 @@ fn run_app_internal(
                         {
                             state.search_state.clear();
                         }
-                        KeyCode::Char('q') | KeyCode::Esc => break 'main,
+                        KeyCode::Esc
+                            if matches!(
+                                pending_key,
+                                PendingKey::SetMark | PendingKey::JumpToMark
+                            ) => {}
+                        KeyCode::Char(c)
+                            if pending_key == PendingKey::SetMark && c.is_ascii_alphabetic() =>
+                        {
+                            state.set_mark(c);
+                        }
+                        KeyCode::Char(c)
+                            if pending_key == PendingKey::JumpToMark && c.is_ascii_alphabetic() =>
+                        {
+                            let sidebar_height = terminal.size()?.height.saturating_sub(5) as usize;
+                            jump_to_mark(&mut state, c, sidebar_height);
+                        }
+                        KeyCode::Char('m') => {
+                            state.pending_key = PendingKey::SetMark;
+                        }
+                        KeyCode::Char('\'' | '`') => {
+                            state.pending_key = PendingKey::JumpToMark;
+                        }
+                        KeyCode::Char('"') => {
+                            if state.marks.is_empty() {
+                                state.set_status_message("no marks set");
+                            } else {
+                                active_modal = Some(marks_modal(&state));
+                            }
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => {
+                            if state.quit_confirmed(options.confirm_quit, pending_key) {
+                                break 'main;
+                            }
+                        }
+                        KeyCode::Char('y') if matches!(pending_key, PendingKey::Quit(_)) => {
+                            if state.quit_confirmed(options.confirm_quit, pending_key) {
+                                break 'main;
+                            }
+                        }
                         KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                             break 'main
                         }
@@ -878,14 +1688,26 @@ fn run_app_internal(
                                 && state.current_commit_index < state.stacked_commits.len() - 1
                             {
                                 let new_index = state.current_commit_index + 1;
-                                navigate_stacked_commit(&mut state, new_index, &options, backend);
+                                navigate_stacked_commit(
+                                    &mut state,
+                                    new_index,
+                                    &options,
+                                    pr_info.as_ref(),
+                                    backend,
+                                );
                             }
                         }
                         // Stacked mode: navigate to previous commit
                         KeyCode::Char('h') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                             if state.stacked_mode && state.current_commit_index > 0 {
                                 let new_index = state.current_commit_index - 1;
-                                navigate_stacked_commit(&mut state, new_index, &options, backend);
+                                navigate_stacked_commit(
+                                    &mut state,
+                                    new_index,
+                                    &options,
+                                    pr_info.as_ref(),
+                                    backend,
+                                );
                             }
                         }
                         KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
@@ -902,6 +1724,10 @@ fn run_app_internal(
                                     .file_diffs
                                     .iter()
                                     .enumerate()
+                                    .filter(|(_, diff)| {
+                                        state.settings.show_deleted
+                                            || diff.status != FileStatus::Deleted
+                                    })
                                     .map(|(i, diff)| {
                                         let status = match diff.status {
                                             FileStatus::Added => ModalFileStatus::Added,
@@ -910,16 +1736,49 @@ fn run_app_internal(
                                         };
                                         FilePickerItem {
                                             name: diff.filename.clone(),
+                                            icon: state.settings.indicators.file_icon(&diff.filename),
                                             file_index: i,
                                             status,
                                             viewed: state.viewed_files.contains(&i),
                                         }
                                     })
                                     .collect();
-                                active_modal = Some(Modal::file_picker("Find File", items));
+                                active_modal = Some(Modal::file_picker(
+                                    "Find File",
+                                    items,
+                                    state.settings.indicators.clone(),
+                                ));
+                            }
+                        }
+                        KeyCode::Char('c')
+                            if matches!(
+                                pending_key,
+                                PendingKey::BracketClose(_) | PendingKey::BracketOpen(_)
+                            ) =>
+                        {
+                            // `]c` / `[c`: undo the fullscreen toggle, then jump to the next
+                            // conflict block, moving on to the next file with conflicts
+                            let forward = matches!(pending_key, PendingKey::BracketClose(_));
+                            if let PendingKey::BracketClose(prev) | PendingKey::BracketOpen(prev) =
+                                pending_key
+                            {
+                                state.diff_fullscreen = prev;
+                            }
+                            if !state.file_diffs.is_empty() {
+                                state.clear_selection();
+                                let row = state.find_conflict_in_file(forward);
+                                if row.is_none() {
+                                    if let Some(file_index) = state.find_conflict_file(forward) {
+                                        let sidebar_height =
+                                            terminal.size()?.height.saturating_sub(5) as usize;
+                                        focus_file(&mut state, file_index, sidebar_height);
+                                    }
+                                }
+                                state.focus_conflict(row, forward, visible_height, max_scroll);
                             }
                         }
                         KeyCode::Char(']') => {
+                            state.pending_key = PendingKey::BracketClose(state.diff_fullscreen);
                             if !state.file_diffs.is_empty() {
                                 let diff = &state.file_diffs[state.current_file];
                                 if !diff.new_content.is_empty() {
@@ -931,6 +1790,7 @@ fn run_app_internal(
                             }
                         }
                         KeyCode::Char('[') => {
+                            state.pending_key = PendingKey::BracketOpen(state.diff_fullscreen);
                             if !state.file_diffs.is_empty() {
                                 let diff = &state.file_diffs[state.current_file];
                                 if !diff.old_content.is_empty() {
@@ -944,31 +1804,82 @@ fn run_app_internal(
                         KeyCode::Char('=') => {
                             state.diff_fullscreen = DiffFullscreen::None;
                         }
-                        KeyCode::Down
-                            if state.search_state.has_query()
-                                && state.focused_panel == FocusedPanel::DiffView =>
-                        {
-                            if let Some(line) = state.search_state.find_next() {
-                                state.scroll = adjust_scroll_to_line(
-                                    line,
-                                    state.scroll,
-                                    visible_height,
-                                    max_scroll,
+                        KeyCode::Char('b') => {
+                            if pr_info.is_some() || state.stacked_mode {
+                                state.set_status_message(
+                                    "pinning a base needs a working-tree or commit diff",
                                 );
+                            } else {
+                                state.prompt = Some(FooterPrompt {
+                                    kind: PromptKind::PinBase,
+                                    label: "base (empty to unpin): ",
+                                    input: String::new(),
+                                });
                             }
                         }
-                        KeyCode::Up
+                        KeyCode::Char('w') => {
+                            state.toggle_wrap();
+                            state.set_status_message(if state.settings.wrap {
+                                "wrap: on"
+                            } else {
+                                "wrap: off"
+                            });
+                        }
+                        KeyCode::Char('W') => {
+                            state.toggle_ignore_whitespace();
+                            state.set_status_message(if state.settings.ignore_whitespace {
+                                "ignore whitespace: on"
+                            } else {
+                                "ignore whitespace: off"
+                            });
+                        }
+                        KeyCode::Char('s') => {
+                            state.toggle_compact();
+                            state.set_status_message(if state.settings.compact {
+                                "compact: on"
+                            } else {
+                                "compact: off"
+                            });
+                        }
+                        KeyCode::Char('Z') => {
+                            if !state.settings.compact && state.settings.grep.is_none() {
+                                state.set_status_message("nothing is folded outside compact view");
+                            } else {
+                                let full = state.toggle_full_context();
+                                state.set_status_message(if full {
+                                    "full file: on"
+                                } else {
+                                    "full file: off"
+                                });
+                            }
+                        }
+                        KeyCode::Char('v') => {
+                            state.settings.view_mode = state.settings.view_mode.next();
+                            state.clear_selection();
+                            state.set_status_message(format!(
+                                "view: {}",
+                                state.settings.view_mode.name()
+                            ));
+                        }
+                        KeyCode::Char('u') => {
+                            state.settings.view_mode = state.settings.view_mode.toggle_unified();
+                            state.clear_selection();
+                            state.set_status_message(format!(
+                                "view: {}",
+                                state.settings.view_mode.name()
+                            ));
+                        }
+                        KeyCode::Down | KeyCode::Up
                             if state.search_state.has_query()
                                 && state.focused_panel == FocusedPanel::DiffView =>
                         {
-                            if let Some(line) = state.search_state.find_prev() {
-                                state.scroll = adjust_scroll_to_line(
-                                    line,
-                                    state.scroll,
-                                    visible_height,
-                                    max_scroll,
-                                );
-                            }
+                            let width = terminal.size()?.width;
+                            step_search(
+                                &mut state,
+                                key.code == KeyCode::Down,
+                                visible_height,
+                                width,
+                            );
                         }
                         KeyCode::Down | KeyCode::Char('j') => {
                             if state.focused_panel == FocusedPanel::Sidebar {
@@ -1007,6 +1918,13 @@ fn run_app_internal(
                                 state.sidebar_h_scroll = state.sidebar_h_scroll.saturating_add(4);
                             }
                         }
+                        KeyCode::Char('0') if state.focused_panel == FocusedPanel::DiffView => {
+                            state.h_scroll = 0;
+                        }
+                        KeyCode::Char('$') if state.focused_panel == FocusedPanel::DiffView => {
+                            let term_width = terminal.size()?.width;
+                            state.h_scroll = state.h_scroll_to_line_end(visible_height, term_width);
+                        }
                         KeyCode::Enter => {
                             if state.focused_panel == FocusedPanel::Sidebar
                                 && state.sidebar_selected < state.sidebar_visible_len()
@@ -1037,6 +1955,16 @@ fn run_app_internal(
                                         }
                                     }
                                 }
+                            } else if state.focused_panel == FocusedPanel::DiffView
+                                && state.settings.compact
+                            {
+                                let term_height = terminal.size()?.height;
+                                let visible_height = term_height
+                                    .saturating_sub(5 + state.pinned_strip_height(term_height))
+                                    as usize;
+                                if !state.expand_visible_gap(visible_height) {
+                                    state.set_status_message("no folded lines on screen");
+                                }
                             }
                         }
                         KeyCode::Char(' ') => {
@@ -1062,12 +1990,13 @@ fn run_app_internal(
                                             }
 
                                             // Fire off async API call if in PR mode
-                                            if let Some(ref pr) = pr_info {
-                                                if was_viewed {
-                                                    unmark_file_as_viewed_async(pr, &filename);
-                                                } else {
-                                                    mark_file_as_viewed_async(pr, &filename);
-                                                }
+                                            if let Some(ref pr) = viewed_sync_pr {
+                                                push_viewed_mark(
+                                                    pr,
+                                                    &filename,
+                                                    !was_viewed,
+                                                    &mut state,
+                                                );
                                             }
                                         }
                                         SidebarItem::Directory { path, .. } => {
@@ -1106,14 +2035,16 @@ fn run_app_internal(
                                             }
 
                                             // Fire off async API calls if in PR mode
-                                            if let Some(ref pr) = pr_info {
+                                            if let Some(ref pr) = viewed_sync_pr {
                                                 for &idx in &child_indices {
-                                                    let filename = &state.file_diffs[idx].filename;
-                                                    if all_viewed {
-                                                        unmark_file_as_viewed_async(pr, filename);
-                                                    } else {
-                                                        mark_file_as_viewed_async(pr, filename);
-                                                    }
+                                                    let filename =
+                                                        state.file_diffs[idx].filename.clone();
+                                                    push_viewed_mark(
+                                                        pr,
+                                                        &filename,
+                                                        !all_viewed,
+                                                        &mut state,
+                                                    );
                                                 }
                                             }
                                         }
@@ -1173,12 +2104,8 @@ fn run_app_internal(
                                 }
 
                                 // Fire off async API call if in PR mode
-                                if let Some(ref pr) = pr_info {
-                                    if was_viewed {
-                                        unmark_file_as_viewed_async(pr, &filename);
-                                    } else {
-                                        mark_file_as_viewed_async(pr, &filename);
-                                    }
+                                if let Some(ref pr) = viewed_sync_pr {
+                                    push_viewed_mark(pr, &filename, !was_viewed, &mut state);
                                 }
                             }
                         }
@@ -1191,21 +2118,22 @@ fn run_app_internal(
                         KeyCode::Char('}') => {
                             if !state.file_diffs.is_empty() {
                                 state.clear_selection(); // Clear selection on hunk navigation
-                                let diff = &state.file_diffs[state.current_file];
-                                let side_by_side = compute_side_by_side(
-                                    &diff.old_content,
-                                    &diff.new_content,
-                                    state.settings.tab_width,
-                                );
+                                let side_by_side = state.display_lines(state.current_file);
                                 let hunks = find_hunk_starts(&side_by_side);
                                 let current_hunk = state.focused_hunk.unwrap_or(0);
                                 let next_hunk = if state.focused_hunk.is_none() {
-                                    hunks
+                                    let start = hunks
                                         .iter()
                                         .position(|&h| h > state.scroll as usize + 5)
-                                        .unwrap_or(0)
+                                        .unwrap_or(0);
+                                    state
+                                        .next_navigable_hunk(start, hunks.len())
+                                        .unwrap_or(start)
                                 } else {
-                                    (current_hunk + 1).min(hunks.len().saturating_sub(1))
+                                    state
+                                        .next_navigable_hunk(current_hunk + 1, hunks.len())
+                                        .unwrap_or(current_hunk)
+                                        .min(hunks.len().saturating_sub(1))
                                 };
                                 if !hunks.is_empty() {
                                     state.focused_hunk = Some(next_hunk);
@@ -1221,21 +2149,21 @@ fn run_app_internal(
                         KeyCode::Char('{') => {
                             if !state.file_diffs.is_empty() {
                                 state.clear_selection(); // Clear selection on hunk navigation
-                                let diff = &state.file_diffs[state.current_file];
-                                let side_by_side = compute_side_by_side(
-                                    &diff.old_content,
-                                    &diff.new_content,
-                                    state.settings.tab_width,
-                                );
+                                let side_by_side = state.display_lines(state.current_file);
                                 let hunks = find_hunk_starts(&side_by_side);
                                 let current_hunk = state.focused_hunk.unwrap_or(hunks.len());
                                 let prev_hunk = if state.focused_hunk.is_none() {
-                                    hunks
+                                    let start = hunks
                                         .iter()
                                         .rposition(|&h| (h as u16) < state.scroll.saturating_sub(5))
-                                        .unwrap_or(hunks.len().saturating_sub(1))
+                                        .unwrap_or(hunks.len().saturating_sub(1));
+                                    state.prev_navigable_hunk(start).unwrap_or(start)
+                                } else if current_hunk == 0 {
+                                    0
                                 } else {
-                                    current_hunk.saturating_sub(1)
+                                    state
+                                        .prev_navigable_hunk(current_hunk - 1)
+                                        .unwrap_or(current_hunk)
                                 };
                                 if !hunks.is_empty() {
                                     state.focused_hunk = Some(prev_hunk);
@@ -1248,71 +2176,85 @@ fn run_app_internal(
                                 }
                             }
                         }
-                        KeyCode::Char('i') => {
-                            // Add annotation to focused hunk
+                        KeyCode::Char('.') => {
                             if let Some(hunk_index) = state.focused_hunk {
                                 let file_index = state.current_file;
-                                let diff = &state.file_diffs[file_index];
-
-                                // Calculate line range for this hunk
-                                let side_by_side = compute_side_by_side(
-                                    &diff.old_content,
-                                    &diff.new_content,
-                                    state.settings.tab_width,
-                                );
-                                let hunks = find_hunk_starts(&side_by_side);
-                                let hunk_start = hunks.get(hunk_index).copied().unwrap_or(0);
-                                let next_hunk_start = hunks
-                                    .get(hunk_index + 1)
-                                    .copied()
-                                    .unwrap_or(side_by_side.len());
-
-                                // Find the actual end of the hunk (last changed line, not start of next hunk)
-                                let mut actual_hunk_end = hunk_start;
-                                for i in hunk_start..next_hunk_start {
-                                    if let Some(dl) = side_by_side.get(i) {
-                                        if !matches!(dl.change_type, ChangeType::Equal) {
-                                            actual_hunk_end = i;
-                                        }
-                                    }
+                                state.toggle_hunk_reviewed(file_index, hunk_index);
+                            }
+                        }
+                        KeyCode::Char(c @ ('a' | 'R' | 'M')) if pending_key == PendingKey::Z => {
+                            // Vim-style folds over the sidebar tree
+                            match c {
+                                'a' => state.toggle_fold_at_selection(),
+                                'R' => state.expand_all_dirs(),
+                                _ => state.collapse_all_dirs(),
+                            }
+                            let visible_height = terminal.size()?.height.saturating_sub(5) as usize;
+                            ensure_sidebar_visible(&mut state, visible_height);
+                        }
+                        KeyCode::Char('z') => {
+                            state.pending_key = PendingKey::Z;
+                        }
+                        KeyCode::Char('a') => {
+                            state.show_blame = !state.show_blame;
+                            state.set_status_message(if state.show_blame {
+                                "blame: on"
+                            } else {
+                                "blame: off"
+                            });
+                        }
+                        KeyCode::Char('t') => {
+                            state.show_origins = !state.show_origins;
+                            state.set_status_message(if state.show_origins {
+                                "line origins: on"
+                            } else {
+                                "line origins: off"
+                            });
+                        }
+                        KeyCode::Char('x') if viewed_sync_pr.is_some() => {
+                            match state.next_changed_since_review() {
+                                Some(file_index) => {
+                                    state.clear_selection();
+                                    let sidebar_height =
+                                        terminal.size()?.height.saturating_sub(5) as usize;
+                                    focus_file(&mut state, file_index, sidebar_height);
                                 }
-
-                                let start_line = side_by_side
-                                    .get(hunk_start)
-                                    .and_then(|dl| {
-                                        dl.new_line
-                                            .as_ref()
-                                            .map(|(n, _)| *n)
-                                            .or(dl.old_line.as_ref().map(|(n, _)| *n))
-                                    })
-                                    .unwrap_or(1);
-                                let end_line = side_by_side
-                                    .get(actual_hunk_end)
-                                    .and_then(|dl| {
-                                        dl.new_line
-                                            .as_ref()
-                                            .map(|(n, _)| *n)
-                                            .or(dl.old_line.as_ref().map(|(n, _)| *n))
-                                    })
-                                    .unwrap_or(start_line);
-
-                                let editor = AnnotationEditor::new(
-                                    file_index,
-                                    hunk_index,
-                                    diff.filename.clone(),
-                                    (start_line, end_line),
-                                );
-
-                                // If editing existing, pre-fill content
-                                let editor = if let Some(ann) = state.get_annotation(file_index, hunk_index) {
-                                    editor.with_content(&ann.content, ann.created_at)
-                                } else {
-                                    editor
-                                };
-
-                                annotation_editor = Some(editor);
+                                None => state
+                                    .set_status_message("no files changed since your last review"),
                             }
                         }
+                        KeyCode::Char('>') => {
+                            state.skip_reviewed_hunks = !state.skip_reviewed_hunks;
+                        }
+                        KeyCode::Char('i') | KeyCode::Char('A') if !state.file_diffs.is_empty() => {
+                            // Annotate the focused hunk; `A`, or a file without hunks,
+                            // gets a note on the whole file
+                            let hunk_index = match state.focused_hunk {
+                                Some(hunk_index) if key.code == KeyCode::Char('i') => hunk_index,
+                                _ => FILE_ANNOTATION_HUNK,
+                            };
+                            let file_index = state.current_file;
+                            let line_range = state
+                                .hunk_line_range(file_index, hunk_index)
+                                .unwrap_or((1, 1));
+                            let diff = &state.file_diffs[file_index];
+
+                            let editor = AnnotationEditor::new(
+                                hunk_index,
+                                diff.filename.clone(),
+                                line_range,
+                            )
+                            .with_soft_limit(state.settings.annotation_limit);
+
+                            // If editing existing, pre-fill content
+                            let editor = if let Some(ann) = state.get_annotation(&diff.filename, hunk_index) {
+                                editor.with_content(&ann.content, ann.created_at)
+                            } else {
+                                editor
+                            };
+
+                            annotation_editor = Some(editor);
+                        }
                         KeyCode::Char('I') => {
                             // Open annotations menu
                             if !state.annotations.is_empty() {
@@ -1322,7 +2264,18 @@ fn run_app_internal(
                                     .iter()
                                     .map(format_annotation_preview)
                                     .collect();
-                                active_modal = Some(Modal::annotations("Annotations", items, sorted_annotations));
+                                active_modal = Some(Modal::annotations(
+                                    "Annotations",
+                                    items,
+                                    sorted_annotations,
+                                    state.pending_review.len(),
+                                ));
+                            } else if !state.pending_review.is_empty() {
+                                state.set_status_message(format!(
+                                    "no annotations; {} pending review comment{} shown under their hunks",
+                                    state.pending_review.len(),
+                                    if state.pending_review.len() == 1 { "" } else { "s" }
+                                ));
                             }
                         }
                         KeyCode::Char('r') => {
@@ -1331,30 +2284,149 @@ fn run_app_internal(
                         KeyCode::Char('y') => {
                             if !state.file_diffs.is_empty() {
                                 // If selection is active, copy selected text
-                                if state.selection.is_active() {
-                                    let diff = &state.file_diffs[state.current_file];
-                                    let side_by_side = compute_side_by_side(
-                                        &diff.old_content,
-                                        &diff.new_content,
-                                        state.settings.tab_width,
-                                    );
-                                    if let Some(text) = extract_selected_text(&state.selection, &side_by_side) {
-                                        if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                                            let _ = clipboard.set_text(&text);
-                                        }
-                                    }
+                                let copied = if state.selection.is_active() {
+                                    let side_by_side = state.display_lines(state.current_file);
+                                    let text =
+                                        extract_selected_text(&state.selection, &side_by_side);
                                     state.clear_selection();
+                                    text.map(|text| (copy(&options.clipboard, &text), "selection"))
                                 } else {
                                     // Otherwise copy filename
-                                    if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                                        let _ = clipboard
-                                            .set_text(&state.file_diffs[state.current_file].filename);
+                                    let filename = &state.file_diffs[state.current_file].filename;
+                                    Some((copy(&options.clipboard, filename), "filename"))
+                                };
+                                match copied {
+                                    Some((Ok(()), what)) => {
+                                        state.set_status_message(format!("copied {}", what))
+                                    }
+                                    Some((Err(e), _)) => state.set_status_message(format!(
+                                        "clipboard unavailable: {}",
+                                        e
+                                    )),
+                                    None => {}
+                                }
+                            }
+                        }
+                        KeyCode::Char('P') => {
+                            if state.pin_focused_hunk() {
+                                state.set_status_message("pinned hunk (U to unpin)");
+                            } else {
+                                state.set_status_message("focus a hunk to pin it");
+                            }
+                        }
+                        KeyCode::Char('U') => {
+                            if state.unpin_hunk() {
+                                state.set_status_message("unpinned hunk");
+                            }
+                        }
+                        KeyCode::Char('R') => {
+                            let message = match state.hunk_comment_block() {
+                                Some(block) => match copy(&options.clipboard, &block) {
+                                    Ok(()) => "copied hunk as a review comment".to_string(),
+                                    Err(e) => format!("clipboard unavailable: {}", e),
+                                },
+                                None => "focus a hunk to copy it as a comment".to_string(),
+                            };
+                            state.set_status_message(message);
+                        }
+                        KeyCode::Char('c') => {
+                            let message = match state.focused_hunk_patch() {
+                                Some(patch) => match copy(&options.clipboard, &patch) {
+                                    Ok(()) => "copied hunk as a unified diff".to_string(),
+                                    Err(e) => format!("clipboard unavailable: {}", e),
+                                },
+                                None => "focus a hunk to copy it as a diff".to_string(),
+                            };
+                            state.set_status_message(message);
+                        }
+                        KeyCode::Char('S') => {
+                            let header = match (state.current_commit(), &options.reference) {
+                                (Some(commit), _) => {
+                                    Some(format!("{} {}", commit.short_id, commit.summary))
+                                }
+                                (None, Some(CommitReference::Single(reference))) => {
+                                    Some(reference.clone())
+                                }
+                                _ => None,
+                            };
+                            let message = match header {
+                                Some(header) => {
+                                    let stat = format_diff_stat(
+                                        &state.file_diffs,
+                                        state.settings.tab_width,
+                                        false,
+                                    );
+                                    let text = format!("{}\n\n{}", header, stat);
+                                    match copy(&options.clipboard, &text) {
+                                        Ok(()) => "copied diff stat".to_string(),
+                                        Err(e) => format!("clipboard unavailable: {}", e),
                                     }
                                 }
+                                None => "diff stat is only available for a commit".to_string(),
+                            };
+                            state.set_status_message(message);
+                        }
+                        KeyCode::Char('B') => {
+                            if state.is_commit_message() {
+                                state.set_status_message("the commit message has no blame");
+                            } else if !state.file_diffs.is_empty() {
+                                let message = match state.old_line_for_blame() {
+                                    Some(line) => {
+                                        let path = &state.file_diffs[state.current_file].filename;
+                                        match old_side_blame_permalink(
+                                            &options,
+                                            pr_info.as_ref(),
+                                            backend,
+                                            path,
+                                            line,
+                                        ) {
+                                            Ok(url) => match copy(&options.clipboard, &url) {
+                                                Ok(()) => {
+                                                    format!("copied blame link for line {}", line)
+                                                }
+                                                Err(e) => format!("clipboard unavailable: {}", e),
+                                            },
+                                            Err(e) => e,
+                                        }
+                                    }
+                                    None => "select or focus an old-side line to blame".to_string(),
+                                };
+                                state.clear_selection();
+                                state.set_status_message(message);
                             }
                         }
+                        KeyCode::Char('H') if state.is_commit_message() => {
+                            state.set_status_message("the commit message is not a file to export");
+                        }
+                        KeyCode::Char('H') if !state.file_diffs.is_empty() => {
+                            let filename = &state.file_diffs[state.current_file].filename;
+                            let title = format!(
+                                "{} — {}",
+                                filename,
+                                state.diff_reference.as_deref().unwrap_or("working tree")
+                            );
+                            let html = state.html_snapshot(&title, &[state.current_file]);
+                            // Kept out of the working tree so it can't end up in a commit;
+                            // a fresh file with a random name, so nothing can be planted there
+                            let written = tempfile::Builder::new()
+                                .prefix(&format!("{}.", filename.replace('/', "_")))
+                                .suffix(".diff.html")
+                                .tempfile()
+                                .and_then(|file| file.keep().map_err(|e| e.error))
+                                .and_then(|(mut file, path)| {
+                                    file.write_all(html.as_bytes())?;
+                                    Ok(path)
+                                });
+                            let message = match written {
+                                Ok(path) => format!("wrote {}", path.display()),
+                                Err(e) => format!("cannot write the HTML snapshot: {}", e),
+                            };
+                            state.set_status_message(message);
+                        }
                         KeyCode::Char('e') => {
-                            if !state.file_diffs.is_empty() {
+                            if state.is_commit_message() {
+                                state.set_status_message("the commit message is not a file to edit");
+                            } else if !state.file_diffs.is_empty() {
                                 io::stdout().execute(DisableMouseCapture)?;
                                 io::stdout().execute(LeaveAlternateScreen)?;
                                 disable_raw_mode()?;
@@ -1364,14 +2436,13 @@ fn run_app_internal(
                                 let filename = &state.file_diffs[state.current_file].filename;
 
                                 let line_arg = if let Some(hunk_idx) = state.focused_hunk {
-                                    let diff = &state.file_diffs[state.current_file];
-                                    let side_by_side = compute_side_by_side(
-                                        &diff.old_content,
-                                        &diff.new_content,
-                                        state.settings.tab_width,
-                                    );
+                                    let side_by_side = state.display_lines(state.current_file);
                                     let hunks = find_hunk_starts(&side_by_side);
-                                    if let Some(&hunk_start) = hunks.get(hunk_idx) {
+                                    // A hunk holding a conflict opens at its `<<<<<<<` line
+                                    let start = state
+                                        .focused_conflict_row()
+                                        .or(hunks.get(hunk_idx).copied());
+                                    if let Some(hunk_start) = start {
                                         side_by_side.get(hunk_start).and_then(|dl| {
                                             dl.new_line
                                                 .as_ref()
@@ -1385,15 +2456,11 @@ fn run_app_internal(
                                     None
                                 };
 
-                                let status = if let Some(line) = line_arg {
-                                    std::process::Command::new(&editor)
-                                        .arg(format!("+{}", line))
-                                        .arg(filename)
-                                        .status()
-                                } else {
-                                    std::process::Command::new(&editor).arg(filename).status()
-                                };
-                                let _ = status;
+                                let mut command = super::child_command(&editor);
+                                if let Some(line) = line_arg {
+                                    command.arg(format!("+{}", line));
+                                }
+                                let _ = command.arg(filename).status();
 
                                 enable_raw_mode()?;
                                 io::stdout().execute(EnterAlternateScreen)?;
@@ -1401,18 +2468,131 @@ fn run_app_internal(
                                 terminal.clear()?;
                             }
                         }
+                        KeyCode::Char('T') => {
+                            let preset = theme::cycle();
+                            theme_changed = true;
+                            state.set_status_message(format!("theme: {}", preset.name()));
+                        }
+                        KeyCode::Char('D') => {
+                            state.toggle_show_deleted();
+                            let visible_height = terminal.size()?.height.saturating_sub(5) as usize;
+                            ensure_sidebar_visible(&mut state, visible_height);
+                        }
+                        KeyCode::Char('&') => {
+                            state.prompt = Some(FooterPrompt {
+                                kind: PromptKind::SidebarFilter,
+                                label: "filter files, e.g. *.rs or src/** (empty to clear): ",
+                                input: state.sidebar_filter.clone().unwrap_or_default(),
+                            });
+                        }
+                        KeyCode::Char('F') => {
+                            state.toggle_flat_sidebar();
+                            let visible_height = terminal.size()?.height.saturating_sub(5) as usize;
+                            ensure_sidebar_visible(&mut state, visible_height);
+                        }
+                        KeyCode::Char('L') => {
+                            // Cycle the depth limit: 1, 2, 3, then the full tree
+                            let depth = match state.tree_depth {
+                                None => Some(1),
+                                Some(depth) if depth < 3 => Some(depth + 1),
+                                Some(_) => None,
+                            };
+                            state.set_tree_depth(depth);
+                            let visible_height = terminal.size()?.height.saturating_sub(5) as usize;
+                            ensure_sidebar_visible(&mut state, visible_height);
+                            state.set_status_message(match depth {
+                                Some(depth) => format!("tree depth: {}", depth),
+                                None => "tree depth: unlimited".to_string(),
+                            });
+                        }
+                        KeyCode::Char('X') => {
+                            if !state.file_diffs.is_empty() {
+                                let filename = state.file_diffs[state.current_file].filename.clone();
+                                let mut modal = Modal::explanation(format!("Explain {}", filename));
+                                match state.file_diff_text(state.current_file) {
+                                    Some(diff_text) => {
+                                        explain_rx =
+                                            Some(spawn_file_explain(&options.ai, filename, diff_text));
+                                    }
+                                    None => {
+                                        modal.set_explanation(Err(
+                                            "Binary files cannot be explained".to_string(),
+                                        ));
+                                        explain_rx = None;
+                                    }
+                                }
+                                active_modal = Some(modal);
+                            }
+                        }
+                        KeyCode::Char('C') => {
+                            let commit_ref = match (state.current_commit(), &options.reference) {
+                                (Some(commit), _) => Some(commit.commit_id.clone()),
+                                (None, Some(CommitReference::Single(reference))) => {
+                                    Some(reference.clone())
+                                }
+                                _ => None,
+                            };
+                            let details = if let Some(ref pr) = pr_info {
+                                let title = format!(
+                                    "{} {}",
+                                    pr.forge.noun(),
+                                    pr.forge.number_ref(pr.number)
+                                );
+                                Some((title, Ok(format_pr_metadata(pr))))
+                            } else {
+                                commit_ref.map(|reference| {
+                                    let details = backend
+                                        .get_commit_metadata(&reference)
+                                        .map(|m| format_commit_metadata(&m))
+                                        .map_err(|e| e.to_string());
+                                    (format!("Commit {}", reference), details)
+                                })
+                            };
+                            match details {
+                                Some((title, details)) => {
+                                    let mut modal = Modal::explanation(title);
+                                    modal.set_explanation(details);
+                                    // A pending explanation must not land in this modal
+                                    explain_rx = None;
+                                    active_modal = Some(modal);
+                                }
+                                None => state.set_status_message(
+                                    "no single commit in view (use a commit or --stacked)",
+                                ),
+                            }
+                        }
+                        KeyCode::Char('O') => {
+                            if !state.file_diffs.is_empty() {
+                                active_modal = Some(overview_modal(&state));
+                            }
+                        }
+                        KeyCode::Char('Y') => {
+                            if pr_info.is_some() && state.is_commit_message() {
+                                state.set_status_message("the commit message has no permalink");
+                            } else if let Some(ref pr) = pr_info {
+                                let message = match state.selected_new_lines() {
+                                    Some((start, end)) => {
+                                        let path = &state.file_diffs[state.current_file].filename;
+                                        let url =
+                                            pr.forge.backend().lines_url(pr, path, start, end);
+                                        match copy(&options.clipboard, &url) {
+                                            Ok(()) => {
+                                                "copied permalink to the selected lines".to_string()
+                                            }
+                                            Err(e) => format!("clipboard unavailable: {}", e),
+                                        }
+                                    }
+                                    None => "select lines in the new panel to link to".to_string(),
+                                };
+                                state.clear_selection();
+                                state.set_status_message(message);
+                            }
+                        }
                         KeyCode::Char('o') => {
                             if let Some(ref pr) = pr_info {
                                 if !state.file_diffs.is_empty() {
                                     let filename = &state.file_diffs[state.current_file].filename;
-                                    let file_url = format!(
-                                        "https://github.com/{}/{}/pull/{}/files#diff-{}",
-                                        pr.repo_owner,
-                                        pr.repo_name,
-                                        pr.number,
-                                        generate_file_anchor(filename)
-                                    );
-                                    let _ = open_url(&file_url);
+                                    let _ = open_url(&pr.forge.backend().file_url(pr, filename));
                                 }
                             }
                         }
@@ -1431,27 +2611,11 @@ fn run_app_internal(
                             if key.code == KeyCode::Char('/')
                                 || key.modifiers.contains(KeyModifiers::CONTROL) =>
                         {
-                            state.search_state.start_forward();
-                        }
-                        KeyCode::Char('n') if state.search_state.has_query() => {
-                            if let Some(line) = state.search_state.find_next() {
-                                state.scroll = adjust_scroll_to_line(
-                                    line,
-                                    state.scroll,
-                                    visible_height,
-                                    max_scroll,
-                                );
-                            }
+                            state.search_state.start_forward(state.scroll as usize);
                         }
-                        KeyCode::Char('N') if state.search_state.has_query() => {
-                            if let Some(line) = state.search_state.find_prev() {
-                                state.scroll = adjust_scroll_to_line(
-                                    line,
-                                    state.scroll,
-                                    visible_height,
-                                    max_scroll,
-                                );
-                            }
+                        KeyCode::Char(c @ ('n' | 'N')) if state.search_state.has_query() => {
+                            let width = terminal.size()?.width;
+                            step_search(&mut state, c == 'n', visible_height, width);
                         }
                         KeyCode::Char('?') => {
                             active_modal = Some(Modal::keybindings(
@@ -1484,6 +2648,10 @@ fn run_app_internal(
                                                 key: "ctrl+p",
                                                 description: "Open file picker",
                                             },
+                                            KeyBind {
+                                                key: "O",
+                                                description: "Open files overview",
+                                            },
                                             KeyBind {
                                                 key: "r",
                                                 description: "Refresh diff / PR",
@@ -1492,6 +2660,34 @@ fn run_app_internal(
                                                 key: "y",
                                                 description: "Copy current filename",
                                             },
+                                            KeyBind {
+                                                key: "R",
+                                                description: "Copy focused hunk as a Markdown block to comment on",
+                                            },
+                                            KeyBind {
+                                                key: "c",
+                                                description: "Copy focused hunk as a unified diff patch",
+                                            },
+                                            KeyBind {
+                                                key: "B",
+                                                description: "Copy GitHub blame link for old-side line",
+                                            },
+                                            KeyBind {
+                                                key: "a",
+                                                description: "Toggle blame column in the old panel",
+                                            },
+                                            KeyBind {
+                                                key: "t",
+                                                description: "Toggle which commit in the range added each new-side line",
+                                            },
+                                            KeyBind {
+                                                key: "H",
+                                                description: "Save this file's diff as an HTML page",
+                                            },
+                                            KeyBind {
+                                                key: "S",
+                                                description: "Copy commit diff stat summary",
+                                            },
                                             KeyBind {
                                                 key: "e",
                                                 description: "Edit file (at hunk line if focused)",
@@ -1500,6 +2696,54 @@ fn run_app_internal(
                                                 key: "o",
                                                 description: "Open file in browser (PR mode)",
                                             },
+                                            KeyBind {
+                                                key: "Y",
+                                                description: "Copy permalink to the selected lines (PR mode)",
+                                            },
+                                            KeyBind {
+                                                key: "T",
+                                                description: "Cycle color theme",
+                                            },
+                                            KeyBind {
+                                                key: "D",
+                                                description: "Show / hide deleted files",
+                                            },
+                                            KeyBind {
+                                                key: "F",
+                                                description: "Toggle tree / flat file list",
+                                            },
+                                            KeyBind {
+                                                key: "&",
+                                                description: "Filter files by glob (*.rs, src/**)",
+                                            },
+                                            KeyBind {
+                                                key: "L",
+                                                description: "Cycle sidebar tree depth limit",
+                                            },
+                                            KeyBind {
+                                                key: "za",
+                                                description: "Fold/unfold the selected directory",
+                                            },
+                                            KeyBind {
+                                                key: "zR / zM",
+                                                description: "Expand / collapse all directories",
+                                            },
+                                            KeyBind {
+                                                key: "ma / 'a",
+                                                description: "Set mark a / jump back to mark a",
+                                            },
+                                            KeyBind {
+                                                key: "\"",
+                                                description: "List marks",
+                                            },
+                                            KeyBind {
+                                                key: "C",
+                                                description: "Show commit / PR metadata",
+                                            },
+                                            KeyBind {
+                                                key: "X",
+                                                description: "Explain current file with AI",
+                                            },
                                             KeyBind {
                                                 key: "ctrl+l / ctrl+h",
                                                 description: "Next / prev commit (stacked)",
@@ -1529,6 +2773,10 @@ fn run_app_internal(
                                                 key: "space",
                                                 description: "Toggle file as viewed",
                                             },
+                                            KeyBind {
+                                                key: "x",
+                                                description: "Next file changed since your last review (PR, marked *)",
+                                            },
                                         ],
                                     },
                                     KeyBindSection {
@@ -1542,6 +2790,10 @@ fn run_app_internal(
                                                 key: "h/l or left/right",
                                                 description: "Scroll horizontally",
                                             },
+                                            KeyBind {
+                                                key: "0 / $",
+                                                description: "Scroll to line start / end",
+                                            },
                                             KeyBind {
                                                 key: "gg / G",
                                                 description: "Scroll to top / bottom",
@@ -1550,6 +2802,14 @@ fn run_app_internal(
                                                 key: "{ / }",
                                                 description: "Focus prev / next hunk",
                                             },
+                                            KeyBind {
+                                                key: ". / >",
+                                                description: "Toggle focused hunk as reviewed / skip reviewed",
+                                            },
+                                            KeyBind {
+                                                key: "P / U",
+                                                description: "Pin focused hunk below the diff / unpin",
+                                            },
                                             KeyBind {
                                                 key: "pageup / pagedown",
                                                 description: "Scroll by page",
@@ -1570,6 +2830,38 @@ fn run_app_internal(
                                                 key: "=",
                                                 description: "Reset fullscreen to side-by-side",
                                             },
+                                            KeyBind {
+                                                key: "v",
+                                                description: "Toggle side-by-side / change-bar view",
+                                            },
+                                            KeyBind {
+                                                key: "u",
+                                                description: "Toggle side-by-side / unified view",
+                                            },
+                                            KeyBind {
+                                                key: "w",
+                                                description: "Toggle wrapping long lines (remembered per file)",
+                                            },
+                                            KeyBind {
+                                                key: "W",
+                                                description: "Toggle ignoring whitespace-only changes",
+                                            },
+                                            KeyBind {
+                                                key: "s",
+                                                description: "Toggle compact view (Enter unfolds)",
+                                            },
+                                            KeyBind {
+                                                key: "Z",
+                                                description: "Show this file in full in compact view",
+                                            },
+                                            KeyBind {
+                                                key: "b",
+                                                description: "Pin a base to diff the working tree against",
+                                            },
+                                            KeyBind {
+                                                key: "]c / [c",
+                                                description: "Next / prev conflict (across files)",
+                                            },
                                         ],
                                     },
                                     KeyBindSection {
@@ -1579,6 +2871,10 @@ fn run_app_internal(
                                                 key: "/ or ctrl+f",
                                                 description: "Start search",
                                             },
+                                            KeyBind {
+                                                key: "ctrl+r",
+                                                description: "Toggle regex while typing",
+                                            },
                                             KeyBind {
                                                 key: "n or down",
                                                 description: "Next match",
@@ -1600,6 +2896,10 @@ fn run_app_internal(
                                                 key: "i",
                                                 description: "Add annotation to focused hunk",
                                             },
+                                            KeyBind {
+                                                key: "A",
+                                                description: "Add annotation to whole file",
+                                            },
                                             KeyBind {
                                                 key: "I",
                                                 description: "View all annotations",
@@ -1617,9 +2917,15 @@ fn run_app_internal(
         }
     }
 
-    io::stdout().execute(DisableMouseCapture)?;
-    disable_raw_mode()?;
-    io::stdout().execute(LeaveAlternateScreen)?;
+    drop(guard);
+
+    if theme_changed {
+        let name = theme::current_preset().name();
+        println!(
+            "Theme: {} (keep it with --theme {} or \"theme\": \"{}\" in lumen.config.json)",
+            name, name, name
+        );
+    }
 
     Ok(())
 }
@@ -1627,25 +2933,17 @@ fn run_app_internal(
 fn open_url(url: &str) -> io::Result<()> {
     #[cfg(target_os = "macos")]
     {
-        std::process::Command::new("open").arg(url).spawn()?;
+        super::child_command("open").arg(url).spawn()?;
     }
     #[cfg(target_os = "linux")]
     {
-        std::process::Command::new("xdg-open").arg(url).spawn()?;
+        super::child_command("xdg-open").arg(url).spawn()?;
     }
     #[cfg(target_os = "windows")]
     {
-        std::process::Command::new("cmd")
+        super::child_command("cmd")
             .args(["/C", "start", url])
             .spawn()?;
     }
     Ok(())
 }
-
-fn generate_file_anchor(filename: &str) -> String {
-    use sha2::{Digest, Sha256};
-
-    let mut hasher = Sha256::new();
-    hasher.update(filename.as_bytes());
-    format!("{:x}", hasher.finalize())
-}
@@ -0,0 +1,158 @@
+use std::io::{self, Write};
+use std::process::Stdio;
+
+/// How copy actions in the diff viewer reach the clipboard, from the `clipboard`
+/// config value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ClipboardMethod {
+    /// OSC 52 over SSH, otherwise the system clipboard with OSC 52 as the fallback
+    #[default]
+    Auto,
+    /// The system clipboard (X11, Wayland, macOS, Windows) through arboard
+    Arboard,
+    /// An OSC 52 escape sequence, which the terminal turns into a clipboard write
+    Osc52,
+    /// A program that reads the text on stdin, e.g. `pbcopy` or `xclip -selection clipboard`
+    Command(String),
+}
+
+impl ClipboardMethod {
+    /// Parse the `clipboard` config value; "command" takes its program from `command`.
+    pub fn parse(value: &str, command: Option<&str>) -> Result<Self, String> {
+        match value {
+            "auto" => Ok(Self::Auto),
+            "arboard" => Ok(Self::Arboard),
+            "osc52" => Ok(Self::Osc52),
+            "command" => match command.map(str::trim).filter(|c| !c.is_empty()) {
+                Some(command) => Ok(Self::Command(command.to_string())),
+                None => Err(
+                    "clipboard is \"command\" but no clipboard_command is set (e.g. \"pbcopy\")"
+                        .to_string(),
+                ),
+            },
+            _ => Err(format!(
+                "invalid clipboard: {} (expected auto, arboard, osc52 or command)",
+                value
+            )),
+        }
+    }
+}
+
+/// Copy `text` with `method`. The error explains why nothing was copied.
+pub fn copy(method: &ClipboardMethod, text: &str) -> Result<(), String> {
+    match method {
+        // A remote session's system clipboard is not the one in front of the user
+        ClipboardMethod::Auto if is_ssh_session() => copy_osc52(text),
+        ClipboardMethod::Auto => copy_arboard(text).or_else(|_| copy_osc52(text)),
+        ClipboardMethod::Arboard => copy_arboard(text),
+        ClipboardMethod::Osc52 => copy_osc52(text),
+        ClipboardMethod::Command(command) => copy_command(command, text),
+    }
+}
+
+fn is_ssh_session() -> bool {
+    ["SSH_TTY", "SSH_CONNECTION"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some())
+}
+
+fn copy_arboard(text: &str) -> Result<(), String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|e| e.to_string())
+}
+
+fn copy_osc52(text: &str) -> Result<(), String> {
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(osc52_sequence(text, std::env::var_os("TMUX").is_some()).as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| format!("could not write to the terminal: {}", e))
+}
+
+/// The OSC 52 "set clipboard" sequence for `text`. Inside tmux it is wrapped in a
+/// passthrough so it reaches the outer terminal.
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+fn copy_command(command: &str, text: &str) -> Result<(), String> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or("clipboard_command is empty")?;
+    let mut child = super::child_command(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("could not run {}: {}", program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("could not write to {}: {}", program, e))?;
+    }
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{} exited with {}", program, status)),
+        Err(e) => Err(format!("{} failed: {}", program, e)),
+    }
+}
+
+/// Standard, padded base64, as OSC 52 expects.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clipboard_method() {
+        assert_eq!(
+            ClipboardMethod::parse("osc52", None),
+            Ok(ClipboardMethod::Osc52)
+        );
+        assert_eq!(
+            ClipboardMethod::parse("command", Some("xclip -selection clipboard")),
+            Ok(ClipboardMethod::Command(
+                "xclip -selection clipboard".to_string()
+            ))
+        );
+        assert!(ClipboardMethod::parse("command", Some(" ")).is_err());
+        assert!(ClipboardMethod::parse("xclip", None).is_err());
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(
+            osc52_sequence("hi", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+    }
+}
@@ -1,4 +1,26 @@
-use crate::command::diff::types::{DiffFullscreen, DiffLine, DiffPanelFocus};
+use unicode_width::UnicodeWidthChar;
+
+use crate::command::diff::types::{
+    ChangeType, DiffFullscreen, DiffLine, DiffPanelFocus, PanelBorders,
+};
+
+/// Columns the blame margin takes in the old panel: "1a2b3c4 author__ 2024-01-31 "
+pub const BLAME_WIDTH: u16 = 28;
+
+/// Columns the line-origin margin takes in the new panel: "1a2b3c4 subject_______ "
+pub const ORIGIN_WIDTH: u16 = 24;
+
+/// Column at the left edge of a panel that marks the focused hunk
+pub const FOCUS_INDICATOR_WIDTH: u16 = 1;
+
+/// Line numbers and the space after them: "1234 "
+pub const GUTTER_WIDTH: u16 = 5;
+
+/// The unified view's gutter, numbering each row on both sides: "1234 1234+"
+pub const DUAL_GUTTER_WIDTH: u16 = 10;
+
+/// One side of a panel's border
+pub const BORDER_WIDTH: u16 = 1;
 
 /// Layout information for the diff panels
 #[derive(Debug, Clone)]
@@ -14,8 +36,13 @@ pub struct PanelLayout {
     pub new_panel_width: u16,
     /// Width of the focus indicator (1 char)
     pub focus_indicator_width: u16,
-    /// Width of the gutter (line numbers + space, typically 5 chars)
+    /// Width of the gutter (line numbers + space, typically 5 chars; both sides' numbers
+    /// in the unified view)
     pub gutter_width: u16,
+    /// Width of the blame margin before the old panel's gutter (0 when hidden)
+    pub blame_width: u16,
+    /// Width of the line-origin margin before the new panel's gutter (0 when hidden)
+    pub origin_width: u16,
     /// Width of the border (1 char)
     pub border_width: u16,
     /// Whether sidebar is shown
@@ -33,10 +60,13 @@ impl PanelLayout {
         sidebar_width: u16,
         show_sidebar: bool,
         diff_fullscreen: DiffFullscreen,
+        panel_borders: PanelBorders,
+        blame_width: u16,
+        origin_width: u16,
     ) -> Self {
-        let focus_indicator_width = 1u16;
-        let gutter_width = 5u16; // "1234 " format
-        let border_width = 1u16;
+        let focus_indicator_width = FOCUS_INDICATOR_WIDTH;
+        let gutter_width = GUTTER_WIDTH;
+        let border_width = BORDER_WIDTH;
 
         let diff_area_start = if show_sidebar { sidebar_width } else { 0 };
         let diff_area_width = term_width.saturating_sub(diff_area_start);
@@ -59,9 +89,15 @@ impl PanelLayout {
                 let half_width = diff_area_width / 2;
                 let old_x = diff_area_start + border_width;
                 let old_width = half_width.saturating_sub(border_width);
-                // New panel shares border with old panel
-                let new_x = diff_area_start + half_width;
-                let new_width = diff_area_width.saturating_sub(half_width).saturating_sub(border_width);
+                // New panel shares border with old panel, unless it draws its own
+                let new_left_border = match panel_borders {
+                    PanelBorders::Separate => border_width,
+                    PanelBorders::Shared | PanelBorders::None => 0,
+                };
+                let new_x = diff_area_start + half_width + new_left_border;
+                let new_width = diff_area_width
+                    .saturating_sub(half_width)
+                    .saturating_sub(border_width + new_left_border);
                 (old_x, old_width, new_x, new_width)
             }
         };
@@ -73,6 +109,8 @@ impl PanelLayout {
             new_panel_width,
             focus_indicator_width,
             gutter_width,
+            blame_width,
+            origin_width,
             border_width,
             show_sidebar,
             sidebar_width,
@@ -80,6 +118,14 @@ impl PanelLayout {
         }
     }
 
+    /// The same layout with a gutter `gutter_width` columns wide
+    pub fn with_gutter_width(self, gutter_width: u16) -> Self {
+        Self {
+            gutter_width,
+            ..self
+        }
+    }
+
     /// Determine which panel (if any) is at a given x coordinate
     pub fn panel_at_x(&self, x: u16) -> Option<DiffPanelFocus> {
         match self.diff_fullscreen {
@@ -126,12 +172,12 @@ impl PanelLayout {
         // Layout within panel: [focus_indicator 1][line_num 4][space 1][content...]
         // Old panel always has focus indicator, new panel only in fullscreen mode
         let gutter_start = match panel {
-            DiffPanelFocus::Old => self.focus_indicator_width,
+            DiffPanelFocus::Old => self.focus_indicator_width + self.blame_width,
             DiffPanelFocus::New => {
                 if self.diff_fullscreen == DiffFullscreen::NewOnly {
-                    self.focus_indicator_width
+                    self.focus_indicator_width + self.origin_width
                 } else {
-                    0
+                    self.origin_width
                 }
             }
             DiffPanelFocus::None => return false,
@@ -145,23 +191,33 @@ impl PanelLayout {
     pub fn content_x_offset(&self, panel: DiffPanelFocus) -> u16 {
         match panel {
             DiffPanelFocus::Old => {
-                // Old panel: [border 1][focus 1][gutter 5][content...]
-                self.focus_indicator_width + self.gutter_width
+                // Old panel: [border 1][focus 1][blame][gutter 5][content...]
+                self.focus_indicator_width + self.blame_width + self.gutter_width
             }
             DiffPanelFocus::New => {
                 if self.diff_fullscreen == DiffFullscreen::NewOnly {
-                    // Fullscreen new: [border 1][focus 1][gutter 5][content...]
-                    self.focus_indicator_width + self.gutter_width
+                    // Fullscreen new: [border 1][focus 1][origin][gutter 5][content...]
+                    self.focus_indicator_width + self.origin_width + self.gutter_width
                 } else {
                     // Side-by-side new: no focus indicator, no left border
-                    // [gutter 5][content...]
-                    self.gutter_width
+                    // [origin][gutter 5][content...]
+                    self.origin_width + self.gutter_width
                 }
             }
             DiffPanelFocus::None => 0,
         }
     }
 
+    /// Columns of line text a panel shows per row, after the focus bar and gutter
+    pub fn text_width(&self, panel: DiffPanelFocus) -> usize {
+        let panel_width = match panel {
+            DiffPanelFocus::Old => self.old_panel_width,
+            DiffPanelFocus::New => self.new_panel_width,
+            DiffPanelFocus::None => 0,
+        };
+        panel_width.saturating_sub(self.content_x_offset(panel)) as usize
+    }
+
     /// Convert screen coordinates to content position
     #[allow(dead_code)]
     /// Returns None if the position is not valid (e.g., empty placeholder line)
@@ -223,6 +279,123 @@ impl PanelLayout {
     }
 }
 
+/// How diff lines map to screen rows: the text columns per row of each panel while
+/// lines wrap (0 when they don't, or the panel is hidden), and whether the unified
+/// view stacks both sides of a changed line in one column.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RowLayout {
+    pub old_width: usize,
+    pub new_width: usize,
+    pub unified: bool,
+}
+
+impl RowLayout {
+    /// Rows a diff line takes on screen.
+    pub fn rows(&self, line: &DiffLine) -> usize {
+        let rows = |side: &Option<(usize, String)>, width: usize| match side {
+            Some((_, text)) if width > 0 => wrapped_row_count(text, width),
+            _ => 1,
+        };
+        if !self.unified {
+            return rows(&line.old_line, self.old_width).max(rows(&line.new_line, self.new_width));
+        }
+        // One column as wide as the new panel; unchanged lines show their new side only
+        let old_rows = match line.old_line {
+            Some(_) if !matches!(line.change_type, ChangeType::Equal) => {
+                rows(&line.old_line, self.new_width)
+            }
+            _ => 0,
+        };
+        let new_rows = match line.new_line {
+            Some(_) => rows(&line.new_line, self.new_width),
+            None => 0,
+        };
+        (old_rows + new_rows).max(1)
+    }
+}
+
+/// Whether a character `char_width` columns wide starts a new row when the row
+/// already holds `col` of its `width` columns. A wide character that would straddle
+/// the edge moves down whole; a row always takes at least one character.
+pub fn wraps_before(col: usize, char_width: usize, width: usize) -> bool {
+    col > 0 && col + char_width > width
+}
+
+/// Rows `text` takes when it soft-wraps every `width` display columns
+pub fn wrapped_row_count(text: &str, width: usize) -> usize {
+    let mut rows = 1;
+    let mut col = 0;
+    for ch in text.chars() {
+        let char_width = ch.width().unwrap_or(0);
+        if wraps_before(col, char_width, width) {
+            rows += 1;
+            col = 0;
+        }
+        col += char_width;
+    }
+    rows
+}
+
+/// Character index under display column `x` of wrapped row `row` of `text`, or the
+/// start of the next row (the end of the text) past the end of that row
+pub fn wrapped_char_index(text: &str, width: usize, row: usize, x: usize) -> usize {
+    let mut current = 0;
+    let mut col = 0;
+    for (idx, ch) in text.chars().enumerate() {
+        let char_width = ch.width().unwrap_or(0);
+        if wraps_before(col, char_width, width) {
+            current += 1;
+            col = 0;
+        }
+        if current > row || (current == row && col + char_width > x) {
+            return idx;
+        }
+        col += char_width;
+    }
+    text.chars().count()
+}
+
+/// Line index and wrapped row within it for the row `rel_y` rows below `scroll`
+pub fn wrapped_line_at(
+    side_by_side: &[DiffLine],
+    scroll: usize,
+    rel_y: usize,
+    layout: RowLayout,
+) -> (usize, usize) {
+    let mut remaining = rel_y;
+    for (line_idx, line) in side_by_side.iter().enumerate().skip(scroll) {
+        let rows = layout.rows(line);
+        if remaining < rows {
+            return (line_idx, remaining);
+        }
+        remaining -= rows;
+    }
+    (side_by_side.len().max(scroll) + remaining, 0)
+}
+
+/// Furthest scroll at which the wrapped lines below still fill `height` rows
+pub fn wrapped_max_scroll(side_by_side: &[DiffLine], height: usize, layout: RowLayout) -> usize {
+    let mut rows = 0;
+    for (line_idx, line) in side_by_side.iter().enumerate().rev() {
+        rows += layout.rows(line);
+        if rows > height {
+            return line_idx + 1;
+        }
+    }
+    0
+}
+
+/// Height of the strip showing pinned hunk `lines` at the bottom of a diff panel
+/// `panel_height` rows tall, borders included: a row per removed or added line,
+/// at most a third of the panel.
+pub fn pinned_strip_height(lines: &[DiffLine], panel_height: u16) -> u16 {
+    let rows = lines
+        .iter()
+        .map(|line| 1 + matches!(line.change_type, ChangeType::Modified) as u16)
+        .sum::<u16>();
+    (rows + 2).min(panel_height / 3).max(3)
+}
+
 /// Check if a cursor position is valid for selection
 /// Returns false for empty placeholder lines (no content)
 #[allow(dead_code)]
@@ -1,6 +1,10 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
 use similar::{ChangeTag, TextDiff};
 
-use super::types::{expand_tabs, ChangeType, DiffLine, InlineSegment};
+use super::search::GrepFilter;
+use super::types::{expand_tabs, is_conflict_marker, ChangeType, DiffLine, InlineSegment};
 
 /// Check if a string contains meaningful (non-whitespace) content.
 fn has_meaningful_content(s: &str) -> bool {
@@ -81,8 +85,28 @@ fn compute_word_diff(
 ///
 /// This algorithm pairs consecutive deletions with consecutive insertions,
 /// showing them on the same row. This avoids the visual offset where a modified
-pub fn compute_side_by_side(old: &str, new: &str, tab_width: usize) -> Vec<DiffLine> {
-    let diff = TextDiff::from_lines(old, new);
+pub fn compute_side_by_side(
+    old: &str,
+    new: &str,
+    tab_width: usize,
+    ignore_whitespace: bool,
+) -> Vec<DiffLine> {
+    // Lines are split on '\n' only, like git, and diffed as slices so the change
+    // indices always point into these vectors. With `ignore_whitespace` they are
+    // matched on their normalized form, but each row still shows the original text.
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let old_keys = match_keys(&old_lines, ignore_whitespace);
+    let new_keys = match_keys(&new_lines, ignore_whitespace);
+    let old_refs: Vec<&str> = old_keys.iter().map(AsRef::as_ref).collect();
+    let new_refs: Vec<&str> = new_keys.iter().map(AsRef::as_ref).collect();
+    let diff = TextDiff::from_slices(&old_refs, &new_refs);
+    let old_text = |index: Option<usize>| {
+        expand_tabs(index.map_or("", |i| old_lines[i]).trim_end(), tab_width)
+    };
+    let new_text = |index: Option<usize>| {
+        expand_tabs(index.map_or("", |i| new_lines[i]).trim_end(), tab_width)
+    };
     let mut lines = Vec::new();
     let mut old_num = 1usize;
     let mut new_num = 1usize;
@@ -96,10 +120,9 @@ pub fn compute_side_by_side(old: &str, new: &str, tab_width: usize) -> Vec<DiffL
 
         match change.tag() {
             ChangeTag::Equal => {
-                let text = expand_tabs(change.value().trim_end(), tab_width);
                 lines.push(DiffLine {
-                    old_line: Some((old_num, text.clone())),
-                    new_line: Some((new_num, text)),
+                    old_line: Some((old_num, old_text(change.old_index()))),
+                    new_line: Some((new_num, new_text(change.new_index()))),
                     change_type: ChangeType::Equal,
                     old_segments: None,
                     new_segments: None,
@@ -112,10 +135,7 @@ pub fn compute_side_by_side(old: &str, new: &str, tab_width: usize) -> Vec<DiffL
                 // Collect consecutive deletions
                 let mut deletions = Vec::new();
                 while i < changes.len() && changes[i].tag() == ChangeTag::Delete {
-                    deletions.push((
-                        old_num,
-                        expand_tabs(changes[i].value().trim_end(), tab_width),
-                    ));
+                    deletions.push((old_num, old_text(changes[i].old_index())));
                     old_num += 1;
                     i += 1;
                 }
@@ -123,10 +143,7 @@ pub fn compute_side_by_side(old: &str, new: &str, tab_width: usize) -> Vec<DiffL
                 // Collect consecutive insertions that follow
                 let mut insertions = Vec::new();
                 while i < changes.len() && changes[i].tag() == ChangeTag::Insert {
-                    insertions.push((
-                        new_num,
-                        expand_tabs(changes[i].value().trim_end(), tab_width),
-                    ));
+                    insertions.push((new_num, new_text(changes[i].new_index())));
                     new_num += 1;
                     i += 1;
                 }
@@ -174,7 +191,7 @@ pub fn compute_side_by_side(old: &str, new: &str, tab_width: usize) -> Vec<DiffL
                 // Handle insertions that aren't preceded by deletions
                 lines.push(DiffLine {
                     old_line: None,
-                    new_line: Some((new_num, expand_tabs(change.value().trim_end(), tab_width))),
+                    new_line: Some((new_num, new_text(change.new_index()))),
                     change_type: ChangeType::Insert,
                     old_segments: None,
                     new_segments: None,
@@ -187,12 +204,35 @@ pub fn compute_side_by_side(old: &str, new: &str, tab_width: usize) -> Vec<DiffL
     lines
 }
 
+/// The form each line is matched on: itself, or its normalized text with `ignore_whitespace`.
+fn match_keys<'a>(lines: &[&'a str], ignore_whitespace: bool) -> Vec<Cow<'a, str>> {
+    lines
+        .iter()
+        .map(|&line| {
+            if ignore_whitespace {
+                Cow::Owned(normalize_whitespace(line))
+            } else {
+                Cow::Borrowed(line)
+            }
+        })
+        .collect()
+}
+
+/// Trims a line and collapses runs of inner whitespace, keeping its line break.
+fn normalize_whitespace(line: &str) -> String {
+    let mut normalized = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.ends_with('\n') {
+        normalized.push('\n');
+    }
+    normalized
+}
+
 pub fn find_hunk_starts(lines: &[DiffLine]) -> Vec<usize> {
     let mut hunks = Vec::new();
     let mut in_hunk = false;
 
     for (i, line) in lines.iter().enumerate() {
-        let is_change = !matches!(line.change_type, ChangeType::Equal);
+        let is_change = line.change_type.is_change();
         if is_change && !in_hunk {
             hunks.push(i);
             in_hunk = true;
@@ -202,3 +242,209 @@ pub fn find_hunk_starts(lines: &[DiffLine]) -> Vec<usize> {
     }
     hunks
 }
+
+/// Rows whose new side opens a merge conflict block (`<<<<<<<`).
+pub fn find_conflict_starts(lines: &[DiffLine]) -> Vec<usize> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            line.new_line
+                .as_ref()
+                .is_some_and(|(_, text)| text.starts_with('<') && is_conflict_marker(text))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The hunk that row `row` falls in, if the row is part of a change.
+pub fn hunk_at_row(lines: &[DiffLine], hunks: &[usize], row: usize) -> Option<usize> {
+    let is_change = lines
+        .get(row)
+        .is_some_and(|line| line.change_type.is_change());
+    let index = hunks.partition_point(|&start| start <= row);
+    (is_change && index > 0).then(|| index - 1)
+}
+
+/// Unchanged lines left visible next to a change when the run around them is folded.
+const FOLD_CONTEXT: usize = 3;
+
+/// The compact view: every run of more than `threshold` unchanged lines becomes one
+/// `ChangeType::Folded` row, keeping `FOLD_CONTEXT` lines beside the changes it
+/// separates. Gaps whose first hidden line is in `expanded` are left as they are.
+/// Hunks keep their order, so hunk indices match the unfolded diff.
+pub fn fold_unchanged(
+    lines: Vec<DiffLine>,
+    threshold: usize,
+    expanded: &HashSet<usize>,
+) -> Vec<DiffLine> {
+    // Where each row starts in the full diff, past any hunks hidden by `--grep`
+    let mut full_index = Vec::with_capacity(lines.len());
+    let mut at = 0;
+    for line in &lines {
+        full_index.push(at);
+        at += match line.change_type {
+            ChangeType::Hidden { count, .. } => count,
+            _ => 1,
+        };
+    }
+
+    let mut gaps: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        if lines[start].change_type.is_change() {
+            start += 1;
+            continue;
+        }
+        let end = (start..lines.len())
+            .find(|&i| lines[i].change_type.is_change())
+            .unwrap_or(lines.len());
+        let lead = if start == 0 { 0 } else { FOLD_CONTEXT };
+        let trail = if end == lines.len() { 0 } else { FOLD_CONTEXT };
+        let run = end - start;
+        if run > threshold && run > lead + trail && !expanded.contains(&full_index[start + lead]) {
+            gaps.push((start + lead, run - lead - trail));
+        }
+        start = end;
+    }
+    if gaps.is_empty() {
+        return lines;
+    }
+
+    let mut folded = Vec::with_capacity(lines.len());
+    let mut gaps = gaps.into_iter().peekable();
+    for (i, line) in lines.into_iter().enumerate() {
+        match gaps.peek() {
+            Some(&(from, count)) if i >= from => {
+                if i == from {
+                    folded.push(DiffLine {
+                        old_line: None,
+                        new_line: None,
+                        change_type: ChangeType::Folded {
+                            from: full_index[from],
+                            count,
+                        },
+                        old_segments: None,
+                        new_segments: None,
+                    });
+                }
+                if i + 1 == from + count {
+                    gaps.next();
+                }
+            }
+            _ => folded.push(line),
+        }
+    }
+    folded
+}
+
+/// The `--grep` view: every hunk without an added or changed line matching `filter`
+/// becomes one `ChangeType::Hidden` row, unless its first row is in `expanded`.
+/// The row still counts as a hunk, so hunk indices match the full diff.
+pub fn hide_unmatched_hunks(
+    lines: Vec<DiffLine>,
+    filter: &GrepFilter,
+    expanded: &HashSet<usize>,
+) -> Vec<DiffLine> {
+    let mut hidden: Vec<(usize, usize)> = Vec::new();
+    for start in find_hunk_starts(&lines) {
+        let end = (start..lines.len())
+            .find(|&i| !lines[i].change_type.is_change())
+            .unwrap_or(lines.len());
+        if !expanded.contains(&start) && !filter.matches_added(&lines[start..end]) {
+            hidden.push((start, end - start));
+        }
+    }
+    if hidden.is_empty() {
+        return lines;
+    }
+
+    let mut kept = Vec::with_capacity(lines.len());
+    let mut hidden = hidden.into_iter().peekable();
+    for (i, line) in lines.into_iter().enumerate() {
+        match hidden.peek() {
+            Some(&(from, count)) if i >= from => {
+                if i == from {
+                    kept.push(DiffLine {
+                        old_line: None,
+                        new_line: None,
+                        change_type: ChangeType::Hidden { from, count },
+                        old_segments: None,
+                        new_segments: None,
+                    });
+                }
+                if i + 1 == from + count {
+                    hidden.next();
+                }
+            }
+            _ => kept.push(line),
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_whitespace_keeps_original_text() {
+        let old = "fn main() {\nfoo(a,  b);\n}\n";
+        let new = "fn main() {\n    foo(a, b);\n    bar();\n}\n";
+
+        let lines = compute_side_by_side(old, new, 4, true);
+        assert!(matches!(lines[1].change_type, ChangeType::Equal));
+        assert_eq!(lines[1].old_line, Some((2, "foo(a,  b);".to_string())));
+        assert_eq!(lines[1].new_line, Some((2, "    foo(a, b);".to_string())));
+        assert!(matches!(lines[2].change_type, ChangeType::Insert));
+
+        let lines = compute_side_by_side(old, new, 4, false);
+        assert!(matches!(lines[1].change_type, ChangeType::Modified));
+    }
+
+    #[test]
+    fn test_lone_carriage_return_stays_inside_its_line() {
+        let old = "one\rtwo\nthree\nfour\n";
+        let new = "one\rtwo\nthree\n4\n";
+
+        for ignore_whitespace in [false, true] {
+            let lines = compute_side_by_side(old, new, 4, ignore_whitespace);
+            assert_eq!(lines.len(), 3);
+            assert_eq!(lines[0].old_line, Some((1, "one\rtwo".to_string())));
+            assert!(matches!(lines[2].change_type, ChangeType::Modified));
+            assert_eq!(lines[2].new_line, Some((3, "4".to_string())));
+        }
+    }
+
+    #[test]
+    fn test_grep_hides_unmatched_hunks_but_keeps_their_index() {
+        let middle = "same\n".repeat(20);
+        let old = format!("a\n{}b\n{}c\n", middle, middle);
+        let new = format!("A\n{}b // TODO\n{}C\n", middle, middle);
+        let lines = || compute_side_by_side(&old, &new, 4, false);
+        let filter = GrepFilter::new("todo").unwrap();
+
+        let shown = hide_unmatched_hunks(lines(), &filter, &HashSet::new());
+        let hunks = find_hunk_starts(&shown);
+        assert_eq!(hunks.len(), 3);
+        assert!(matches!(
+            shown[hunks[0]].change_type,
+            ChangeType::Hidden { from: 0, count: 1 }
+        ));
+        assert!(matches!(shown[hunks[1]].change_type, ChangeType::Modified));
+
+        // Gaps after a hidden hunk are keyed by their row in the full diff
+        let folded = fold_unchanged(shown, 10, &HashSet::new());
+        let gap = folded
+            .iter()
+            .find_map(|line| match line.change_type {
+                ChangeType::Folded { from, .. } => Some(from),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(lines()[gap].new_line, Some((5, "same".to_string())));
+
+        let shown = hide_unmatched_hunks(lines(), &filter, &HashSet::from([0]));
+        assert!(matches!(shown[0].change_type, ChangeType::Modified));
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use super::AiSettings;
+use crate::provider::{AiProvider, LumenProvider};
+
+/// Ask the configured provider to explain a single file's diff (non-blocking, spawns a thread).
+/// The receiver yields exactly one message: the explanation or a displayable error.
+pub fn spawn_file_explain(
+    settings: &AiSettings,
+    filename: String,
+    diff: String,
+) -> Receiver<Result<String, String>> {
+    let (tx, rx) = mpsc::channel();
+    let settings = settings.clone();
+
+    thread::spawn(move || {
+        let result = explain_file_sync(settings, &filename, &diff);
+        // The modal may have been dismissed already; nothing to do then
+        let _ = tx.send(result);
+    });
+
+    rx
+}
+
+fn explain_file_sync(settings: AiSettings, filename: &str, diff: &str) -> Result<String, String> {
+    let provider: Box<dyn AiProvider> = Box::new(
+        LumenProvider::new(settings.provider, settings.api_key, settings.model)
+            .map_err(|e| e.to_string())?,
+    );
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start async runtime: {}", e))?;
+
+    runtime
+        .block_on(provider.explain_file(filename, diff))
+        .map_err(|e| e.to_string())
+}
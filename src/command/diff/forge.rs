@@ -0,0 +1,243 @@
+//! Hosts that serve code review requests: GitHub pull requests through `gh` and
+//! GitLab merge requests through `glab`.
+
+use std::collections::{HashMap, HashSet};
+
+use super::gitlab::GitLabMrs;
+use super::types::FileDiff;
+use super::{git, PendingReviewComment, PrInfo};
+
+/// Where a pull or merge request lives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+}
+
+impl Forge {
+    /// `explicit` when given (`--mr`), otherwise read from the URL; bare numbers are GitHub PRs.
+    pub fn detect(input: &str, explicit: Option<Forge>) -> Forge {
+        explicit.unwrap_or(if input.contains("/-/merge_requests/") {
+            Forge::GitLab
+        } else {
+            Forge::GitHub
+        })
+    }
+
+    pub fn backend(self) -> &'static dyn PrBackend {
+        match self {
+            Forge::GitHub => &GitHubPrs,
+            Forge::GitLab => &GitLabMrs,
+        }
+    }
+
+    /// What the host calls a review request: "PR" or "MR".
+    pub fn noun(self) -> &'static str {
+        match self {
+            Forge::GitHub => "PR",
+            Forge::GitLab => "MR",
+        }
+    }
+
+    /// Short reference in the host's own notation, e.g. `#12` or `!12`.
+    pub fn number_ref(self, number: u64) -> String {
+        match self {
+            Forge::GitHub => format!("#{}", number),
+            Forge::GitLab => format!("!{}", number),
+        }
+    }
+}
+
+/// Everything the diff viewer needs from a review host.
+pub trait PrBackend {
+    /// Metadata for the request `input` names. `default_host` is the configured
+    /// `github_host`, for GitHub requests given by number.
+    fn fetch_info(&self, input: &str, default_host: Option<&str>) -> Result<PrInfo, String>;
+
+    /// Up to `max_files` of the request's files, along with how many it changes in total.
+    fn load_file_diffs(
+        &self,
+        pr_info: &PrInfo,
+        max_files: usize,
+    ) -> Result<(Vec<FileDiff>, usize), String>;
+
+    /// Paths the current user has marked as viewed on the host, or an error when the
+    /// host has no such state.
+    fn fetch_viewed_files(
+        &self,
+        pr_info: &PrInfo,
+        max_files: usize,
+    ) -> Result<HashSet<String>, String>;
+
+    /// Content hash of each file at the head of the request, by path, or an error
+    /// when the host doesn't report one.
+    fn fetch_file_shas(&self, _pr_info: &PrInfo) -> Result<HashMap<String, String>, String> {
+        Err("file hashes are not available from this host".to_string())
+    }
+
+    /// Records `path` as viewed or not without blocking. Returns false when the host
+    /// keeps no viewed state, so the mark only lasts for this session.
+    fn mark_viewed(&self, pr_info: &PrInfo, path: &str, viewed: bool) -> bool;
+
+    /// Comments in the current user's unsubmitted review, where the host has drafts.
+    fn fetch_pending_review_comments(
+        &self,
+        _pr_info: &PrInfo,
+    ) -> Result<Vec<PendingReviewComment>, String> {
+        Ok(Vec::new())
+    }
+
+    /// How many commits the base branch has that the head branch lacks.
+    fn fetch_behind_by(&self, pr_info: &PrInfo) -> Result<usize, String>;
+
+    /// Web page showing the request's changes to `path`.
+    fn file_url(&self, pr_info: &PrInfo, path: &str) -> String;
+
+    /// Blame of an old-side line of `path`, on the request's base branch.
+    fn blame_url(&self, pr_info: &PrInfo, path: &str, line: usize) -> String;
+
+    /// Permalink to new-side lines `start..=end` of `path` at the head commit.
+    fn lines_url(&self, pr_info: &PrInfo, path: &str, start: usize, end: usize) -> String;
+}
+
+struct GitHubPrs;
+
+impl PrBackend for GitHubPrs {
+    fn fetch_info(&self, input: &str, default_host: Option<&str>) -> Result<PrInfo, String> {
+        super::fetch_pr_info(input, default_host)
+    }
+
+    fn load_file_diffs(
+        &self,
+        pr_info: &PrInfo,
+        max_files: usize,
+    ) -> Result<(Vec<FileDiff>, usize), String> {
+        git::load_pr_file_diffs(pr_info, max_files)
+    }
+
+    fn fetch_viewed_files(
+        &self,
+        pr_info: &PrInfo,
+        max_files: usize,
+    ) -> Result<HashSet<String>, String> {
+        super::fetch_viewed_files(pr_info, max_files)
+    }
+
+    fn fetch_file_shas(&self, pr_info: &PrInfo) -> Result<HashMap<String, String>, String> {
+        git::fetch_pr_file_shas(pr_info)
+    }
+
+    fn mark_viewed(&self, pr_info: &PrInfo, path: &str, viewed: bool) -> bool {
+        if viewed {
+            super::mark_file_as_viewed_async(pr_info, path);
+        } else {
+            super::unmark_file_as_viewed_async(pr_info, path);
+        }
+        true
+    }
+
+    fn fetch_pending_review_comments(
+        &self,
+        pr_info: &PrInfo,
+    ) -> Result<Vec<PendingReviewComment>, String> {
+        super::fetch_pending_review_comments(pr_info)
+    }
+
+    fn fetch_behind_by(&self, pr_info: &PrInfo) -> Result<usize, String> {
+        super::fetch_pr_behind_by(pr_info)
+    }
+
+    fn file_url(&self, pr_info: &PrInfo, path: &str) -> String {
+        format!(
+            "https://{}/{}/{}/pull/{}/files#diff-{}",
+            pr_info.host,
+            pr_info.repo_owner,
+            pr_info.repo_name,
+            pr_info.number,
+            generate_file_anchor(path)
+        )
+    }
+
+    fn blame_url(&self, pr_info: &PrInfo, path: &str, line: usize) -> String {
+        let repo_full = format!("{}/{}", pr_info.base_repo_owner, pr_info.repo_name);
+        super::blame_permalink(&pr_info.host, &repo_full, pr_info.base_commit(), path, line)
+    }
+
+    fn lines_url(&self, pr_info: &PrInfo, path: &str, start: usize, end: usize) -> String {
+        // Fork commits are only guaranteed to be browsable in the fork
+        let owner = pr_info
+            .head_repo_owner
+            .as_ref()
+            .unwrap_or(&pr_info.base_repo_owner);
+        let anchor = if start == end {
+            format!("L{}", start)
+        } else {
+            format!("L{}-L{}", start, end)
+        };
+        format!(
+            "https://{}/{}/{}/blob/{}/{}#{}",
+            pr_info.host, owner, pr_info.repo_name, pr_info.head_sha, path, anchor
+        )
+    }
+}
+
+/// GitHub's per-file anchor in the "Files changed" tab: the SHA-256 of the path.
+fn generate_file_anchor(filename: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(filename.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_forge() {
+        let mr = "https://gitlab.com/group/sub/project/-/merge_requests/12";
+        assert_eq!(Forge::detect(mr, None), Forge::GitLab);
+        assert_eq!(
+            Forge::detect("https://github.com/o/r/pull/3", None),
+            Forge::GitHub
+        );
+        assert_eq!(Forge::detect("12", None), Forge::GitHub);
+        assert_eq!(Forge::detect("12", Some(Forge::GitLab)), Forge::GitLab);
+    }
+
+    #[test]
+    fn test_github_permalinks() {
+        let pr = PrInfo {
+            forge: Forge::GitHub,
+            host: "github.com".to_string(),
+            number: 7,
+            node_id: String::new(),
+            repo_owner: "acme".to_string(),
+            repo_name: "app".to_string(),
+            base_ref: "main".to_string(),
+            head_ref: "fix".to_string(),
+            head_sha: "abc123".to_string(),
+            base_sha: "def456".to_string(),
+            base_repo_owner: "acme".to_string(),
+            head_repo_owner: Some("ann".to_string()),
+            title: String::new(),
+            author: None,
+            state: "OPEN".to_string(),
+            mergeable: "UNKNOWN".to_string(),
+            behind_by: None,
+        };
+        assert_eq!(
+            GitHubPrs.lines_url(&pr, "src/a.rs", 3, 8),
+            "https://github.com/ann/app/blob/abc123/src/a.rs#L3-L8"
+        );
+        assert_eq!(
+            GitHubPrs.lines_url(&pr, "src/a.rs", 5, 5),
+            "https://github.com/ann/app/blob/abc123/src/a.rs#L5"
+        );
+        assert_eq!(
+            GitHubPrs.blame_url(&pr, "src/a.rs", 4),
+            "https://github.com/acme/app/blame/def456/src/a.rs#L4"
+        );
+    }
+}
@@ -1,24 +1,35 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use std::process::Command;
 
-use super::types::{is_binary_content, FileDiff, FileStatus};
-use super::{DiffOptions, PrInfo};
+use super::types::{is_binary_content, FileDiff, FileStatus, PanelTitles};
+use super::{gh_command, DiffOptions, PrInfo};
 use crate::commit_reference::CommitReference;
-use crate::vcs::VcsBackend;
+use crate::vcs::{FileRevision, StackedCommitInfo, VcsBackend, EMPTY_TREE_SHA};
 
+/// Label for what the working copy is on: the branch, the commit when detached,
+/// or a note that the repository has no commits yet.
 pub fn get_current_branch(backend: &dyn VcsBackend) -> String {
-    backend
-        .get_current_branch()
-        .ok()
-        .flatten()
-        .unwrap_or_else(|| "unknown".to_string())
+    let head = match backend.name() {
+        "jj" => "@",
+        "hg" => ".",
+        _ => "HEAD",
+    };
+    let commit = backend.resolve_ref(head).ok();
+    match (backend.get_current_branch().ok().flatten(), commit) {
+        (Some(branch), Some(_)) => branch,
+        (Some(branch), None) => format!("{} (no commits yet)", branch),
+        (None, Some(sha)) => format!("(detached at {})", abbreviate_ref(&sha)),
+        (None, None) => "(no commits yet)".to_string(),
+    }
 }
 
 /// Resolved references for diff comparison
 pub enum DiffRefs {
     /// Uncommitted changes (working tree vs HEAD)
     WorkingTree,
+    /// Working tree vs a named commit (`git diff <sha>`)
+    WorkingTreeAgainst(String),
     /// Single commit (SHA vs SHA^)
     Single(String),
     /// Range between two refs
@@ -27,6 +38,9 @@ pub enum DiffRefs {
 
 impl DiffRefs {
     pub fn from_options(options: &DiffOptions, backend: &dyn VcsBackend) -> Self {
+        if let Some(base) = &options.against {
+            return DiffRefs::WorkingTreeAgainst(base.clone());
+        }
         match &options.reference {
             None => DiffRefs::WorkingTree,
             Some(CommitReference::Single(sha)) => DiffRefs::Single(sha.clone()),
@@ -53,15 +67,20 @@ impl DiffRefs {
 }
 
 /// Get the list of files changed
-pub fn get_changed_files(options: &DiffOptions, backend: &dyn VcsBackend) -> Vec<String> {
-    let refs = DiffRefs::from_options(options, backend);
-
+fn get_changed_files(
+    refs: &DiffRefs,
+    options: &DiffOptions,
+    backend: &dyn VcsBackend,
+) -> Vec<String> {
     let files: Vec<String> = match refs {
-        DiffRefs::Single(sha) => backend.get_changed_files(&sha).unwrap_or_default(),
+        DiffRefs::Single(sha) => backend.get_changed_files(sha).unwrap_or_default(),
         DiffRefs::Range { from, to } => backend
-            .get_range_changed_files(&from, &to)
+            .get_range_changed_files(from, to)
             .unwrap_or_default(),
         DiffRefs::WorkingTree => backend.get_working_tree_changed_files().unwrap_or_default(),
+        DiffRefs::WorkingTreeAgainst(base) => backend
+            .get_working_tree_changed_files_against(base)
+            .unwrap_or_default(),
     };
 
     if let Some(ref filter) = options.file {
@@ -71,16 +90,93 @@ pub fn get_changed_files(options: &DiffOptions, backend: &dyn VcsBackend) -> Vec
     }
 }
 
-/// Get content of a file at the "old" side of the diff
-pub fn get_old_content(filename: &str, refs: &DiffRefs, backend: &dyn VcsBackend) -> String {
-    let ref_str = match refs {
+/// Get the ref for the "old" side of the diff (empty for a root commit's parent)
+fn get_old_ref(refs: &DiffRefs, backend: &dyn VcsBackend) -> String {
+    match refs {
         DiffRefs::Single(sha) => {
             // Use get_parent_ref_or_empty to handle root commits gracefully
             backend.get_parent_ref_or_empty(sha).unwrap_or_default()
         }
         DiffRefs::Range { from, .. } => from.clone(),
         DiffRefs::WorkingTree => backend.working_copy_parent_ref().to_string(),
+        DiffRefs::WorkingTreeAgainst(base) => base.clone(),
+    }
+}
+
+/// Panel titles for the working tree compared with a base pinned from the diff view.
+pub fn pinned_base_titles(base: &str, backend: &dyn VcsBackend) -> PanelTitles {
+    titles_for_refs(&DiffRefs::WorkingTreeAgainst(base.to_string()), backend)
+}
+
+/// The "old" side ref for the diff described by `options` (empty for a root commit)
+pub fn old_side_ref(options: &DiffOptions, backend: &dyn VcsBackend) -> String {
+    get_old_ref(&DiffRefs::from_options(options, backend), backend)
+}
+
+/// The `from` and `to` refs when `options` describe a range of commits
+pub fn range_refs(options: &DiffOptions, backend: &dyn VcsBackend) -> Option<(String, String)> {
+    match DiffRefs::from_options(options, backend) {
+        DiffRefs::Range { from, to } => Some((from, to)),
+        _ => None,
+    }
+}
+
+/// Get the ref for the "new" side of the diff (None for the working tree)
+fn get_new_ref(refs: &DiffRefs) -> Option<&str> {
+    match refs {
+        DiffRefs::Single(sha) => Some(sha),
+        DiffRefs::Range { to, .. } => Some(to),
+        DiffRefs::WorkingTree | DiffRefs::WorkingTreeAgainst(_) => None,
+    }
+}
+
+/// Panel titles for the diff described by `options`: base/head branches for a PR,
+/// otherwise the refs (with full SHAs abbreviated) on each side.
+pub fn panel_titles(
+    options: &DiffOptions,
+    pr_info: Option<&PrInfo>,
+    backend: &dyn VcsBackend,
+) -> PanelTitles {
+    match pr_info {
+        Some(pr) => PanelTitles {
+            old: pr.base_ref.clone(),
+            new: pr.head_ref.clone(),
+        },
+        None => {
+            let mut titles = titles_for_refs(&DiffRefs::from_options(options, backend), backend);
+            // The working tree holds the commit being applied, conflicts and all
+            if let Some(op) = &options.in_progress {
+                titles.new = format!("{} {}", op.kind, abbreviate_ref(&op.commit_id));
+            }
+            titles
+        }
+    }
+}
+
+fn titles_for_refs(refs: &DiffRefs, backend: &dyn VcsBackend) -> PanelTitles {
+    let old = match get_old_ref(refs, backend) {
+        old if old.is_empty() || old == EMPTY_TREE_SHA => "(empty)".to_string(),
+        old => abbreviate_ref(&old),
     };
+    let new = match get_new_ref(refs) {
+        Some(new) => abbreviate_ref(new),
+        None => "Working tree".to_string(),
+    };
+    PanelTitles { old, new }
+}
+
+/// Shorten full commit hashes to 7 characters; names and short refs are kept as is.
+fn abbreviate_ref(reference: &str) -> String {
+    if reference.len() >= 12 && reference.chars().all(|c| c.is_ascii_hexdigit()) {
+        reference[..7].to_string()
+    } else {
+        reference.to_string()
+    }
+}
+
+/// Get content of a file at the "old" side of the diff
+pub fn get_old_content(filename: &str, refs: &DiffRefs, backend: &dyn VcsBackend) -> String {
+    let ref_str = get_old_ref(refs, backend);
 
     // Empty ref means root commit with no parent - return empty content
     if ref_str.is_empty() {
@@ -101,20 +197,121 @@ pub fn get_new_content(filename: &str, refs: &DiffRefs, backend: &dyn VcsBackend
         DiffRefs::Range { to, .. } => backend
             .get_file_content_at_ref(to, Path::new(filename))
             .unwrap_or_default(),
-        DiffRefs::WorkingTree => {
+        DiffRefs::WorkingTree | DiffRefs::WorkingTreeAgainst(_) => {
             // Read from working tree (actual filesystem)
             fs::read_to_string(filename).unwrap_or_default()
         }
     }
 }
 
+/// Describe a submodule pointer bump as file contents: each side shows the commit the
+/// submodule points to, and the new side also lists the submodule commits in between.
+/// Returns None if `filename` is not a submodule on either side.
+fn get_submodule_contents(
+    filename: &str,
+    old_ref: &str,
+    new_ref: Option<&str>,
+    backend: &dyn VcsBackend,
+) -> Option<(String, String)> {
+    let path = Path::new(filename);
+    let old_sha = if old_ref.is_empty() {
+        None
+    } else {
+        backend.get_submodule_commit(Some(old_ref), path).ok().flatten()
+    };
+    let new_sha = backend.get_submodule_commit(new_ref, path).ok().flatten();
+    if old_sha.is_none() && new_sha.is_none() {
+        return None;
+    }
+
+    let describe = |sha: &Option<String>| {
+        sha.as_ref()
+            .map(|sha| format!("Subproject commit {}\n", sha))
+            .unwrap_or_default()
+    };
+    let old_content = describe(&old_sha);
+    let mut new_content = describe(&new_sha);
+
+    if let (Some(old), Some(new)) = (&old_sha, &new_sha) {
+        if old != new {
+            new_content.push('\n');
+            match backend.get_submodule_commits(path, old, new) {
+                Ok(commits) if !commits.is_empty() => {
+                    for commit in commits {
+                        new_content.push_str(&format!("  > {} {}\n", commit.short_id, commit.summary));
+                    }
+                }
+                // Nothing ahead means the pointer moved backwards
+                Ok(_) => {
+                    let rewound = backend.get_submodule_commits(path, new, old).unwrap_or_default();
+                    for commit in rewound.iter().rev() {
+                        new_content.push_str(&format!("  < {} {}\n", commit.short_id, commit.summary));
+                    }
+                }
+                Err(e) => {
+                    new_content.push_str(&format!("  (commit list unavailable: {})\n", e));
+                }
+            }
+        }
+    }
+
+    Some((old_content, new_content))
+}
+
+/// Pseudo-file listing the commit message change when reviewing an amend
+/// (named like Gerrit's, with a leading slash so it cannot clash with a real path)
+pub const COMMIT_MESSAGE_FILE: &str = "/COMMIT_MSG";
+
+/// The old and new commit messages as a file diff, or `None` when the message is unchanged.
+pub fn commit_message_diff(from: &str, to: &str, backend: &dyn VcsBackend) -> Option<FileDiff> {
+    let old_content = backend.get_commit_metadata(from).ok()?.message;
+    let new_content = backend.get_commit_metadata(to).ok()?.message;
+    (old_content != new_content).then(|| FileDiff {
+        filename: COMMIT_MESSAGE_FILE.to_string(),
+        old_content,
+        new_content,
+        status: FileStatus::Modified,
+        is_binary: false,
+        old_mode: None,
+        new_mode: None,
+    })
+}
+
 pub fn load_file_diffs(options: &DiffOptions, backend: &dyn VcsBackend) -> Vec<FileDiff> {
-    let refs = DiffRefs::from_options(options, backend);
-    get_changed_files(options, backend)
+    load_file_diffs_for_refs(DiffRefs::from_options(options, backend), options, backend)
+}
+
+/// The working tree compared with `base`, whatever the session was opened on.
+/// The `options` file filter and submodule setting still apply.
+pub fn load_file_diffs_against(
+    base: &str,
+    options: &DiffOptions,
+    backend: &dyn VcsBackend,
+) -> Vec<FileDiff> {
+    let refs = DiffRefs::WorkingTreeAgainst(base.to_string());
+    load_file_diffs_for_refs(refs, options, backend)
+}
+
+fn load_file_diffs_for_refs(
+    refs: DiffRefs,
+    options: &DiffOptions,
+    backend: &dyn VcsBackend,
+) -> Vec<FileDiff> {
+    let old_ref = get_old_ref(&refs, backend);
+    let mut diffs: Vec<FileDiff> = get_changed_files(&refs, options, backend)
         .into_iter()
         .map(|filename| {
-            let old_content = get_old_content(&filename, &refs, backend);
-            let new_content = get_new_content(&filename, &refs, backend);
+            let submodule = if options.recurse_submodules {
+                get_submodule_contents(&filename, &old_ref, get_new_ref(&refs), backend)
+            } else {
+                None
+            };
+            let (old_content, new_content) = submodule.unwrap_or_else(|| {
+                (
+                    get_old_content(&filename, &refs, backend),
+                    get_new_content(&filename, &refs, backend),
+                )
+            });
             let status = if old_content.is_empty() && !new_content.is_empty() {
                 FileStatus::Added
             } else if !old_content.is_empty() && new_content.is_empty() {
@@ -124,22 +321,38 @@ pub fn load_file_diffs(options: &DiffOptions, backend: &dyn VcsBackend) -> Vec<F
             };
             let is_binary =
                 is_binary_content(&old_content) || is_binary_content(&new_content);
+            let path = Path::new(&filename);
+            let old_mode = file_mode_at(&old_ref, path, backend);
+            let new_mode = backend.get_file_mode(get_new_ref(&refs), path);
             FileDiff {
                 filename,
                 old_content,
                 new_content,
                 status,
                 is_binary,
+                old_mode,
+                new_mode,
             }
         })
-        .collect()
+        .collect();
+
+    if let (true, DiffRefs::Range { from, to }) = (options.amend, &refs) {
+        if let Some(message) = commit_message_diff(from, to, backend) {
+            diffs.insert(0, message);
+        }
+    }
+    diffs
 }
 
-pub fn load_pr_file_diffs(pr_info: &PrInfo) -> Result<Vec<FileDiff>, String> {
+/// Load up to `max_files` of a PR's files, along with how many files the PR changes in total.
+pub fn load_pr_file_diffs(
+    pr_info: &PrInfo,
+    max_files: usize,
+) -> Result<(Vec<FileDiff>, usize), String> {
     let repo_arg = format!("{}/{}", pr_info.repo_owner, pr_info.repo_name);
 
     // Get PR diff to find changed files
-    let output = Command::new("gh")
+    let output = gh_command(&pr_info.host)
         .args([
             "pr",
             "diff",
@@ -156,7 +369,9 @@ pub fn load_pr_file_diffs(pr_info: &PrInfo) -> Result<Vec<FileDiff>, String> {
     }
 
     let diff_output = String::from_utf8_lossy(&output.stdout);
-    let changed_files = parse_changed_files_from_diff(&diff_output);
+    let mut changed_files = parse_changed_files_from_diff(&diff_output);
+    let total = changed_files.len();
+    changed_files.truncate(max_files);
 
     // Fetch full file contents for each changed file
     let base_repo = format!("{}/{}", pr_info.base_repo_owner, pr_info.repo_name);
@@ -169,10 +384,183 @@ pub fn load_pr_file_diffs(pr_info: &PrInfo) -> Result<Vec<FileDiff>, String> {
     let file_diffs: Vec<FileDiff> = changed_files
         .into_iter()
         .map(|filename| {
-            let old_content =
-                fetch_file_content_from_github(&base_repo, &pr_info.base_ref, &filename);
+            let old_content = fetch_file_content_from_github(
+                &pr_info.host,
+                &base_repo,
+                &pr_info.base_ref,
+                &filename,
+            );
+            let new_content = fetch_file_content_from_github(
+                &pr_info.host,
+                &head_repo,
+                &pr_info.head_ref,
+                &filename,
+            );
+
+            let status = if old_content.is_empty() && !new_content.is_empty() {
+                FileStatus::Added
+            } else if !old_content.is_empty() && new_content.is_empty() {
+                FileStatus::Deleted
+            } else {
+                FileStatus::Modified
+            };
+
+            let is_binary =
+                is_binary_content(&old_content) || is_binary_content(&new_content);
+            FileDiff {
+                filename,
+                old_content,
+                new_content,
+                status,
+                is_binary,
+                old_mode: None,
+                new_mode: None,
+            }
+        })
+        .collect();
+
+    Ok((file_diffs, total))
+}
+
+/// List a PR's commits, oldest first, for walking the PR commit by commit.
+pub fn fetch_pr_commits(pr_info: &PrInfo) -> Result<Vec<StackedCommitInfo>, String> {
+    let api_path = format!(
+        "repos/{}/{}/pulls/{}/commits",
+        pr_info.repo_owner, pr_info.repo_name, pr_info.number
+    );
+    let output = gh_command(&pr_info.host)
+        .args([
+            "api",
+            &api_path,
+            "--paginate",
+            "--jq",
+            r#".[] | [.sha, (.commit.message | split("\n")[0])] | @tsv"#,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run gh api: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list PR commits: {}", stderr.trim()));
+    }
+
+    Ok(parse_pr_commits(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Each PR file's blob sha on the head branch, by path, so a later session can
+/// tell which files were pushed to since.
+pub fn fetch_pr_file_shas(pr_info: &PrInfo) -> Result<HashMap<String, String>, String> {
+    let api_path = format!(
+        "repos/{}/{}/pulls/{}/files",
+        pr_info.repo_owner, pr_info.repo_name, pr_info.number
+    );
+    let output = gh_command(&pr_info.host)
+        .args([
+            "api",
+            &api_path,
+            "--paginate",
+            "--jq",
+            r#".[] | [.sha // "", .filename] | @tsv"#,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run gh api: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list PR files: {}", stderr.trim()));
+    }
+
+    Ok(parse_pr_file_shas(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `sha<TAB>path` lines from `fetch_pr_file_shas`; files without a sha are left out.
+fn parse_pr_file_shas(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (sha, path) = line.split_once('\t')?;
+            (!sha.is_empty()).then(|| (path.to_string(), sha.to_string()))
+        })
+        .collect()
+}
+
+/// Parse `sha<TAB>summary` lines from `fetch_pr_commits`.
+fn parse_pr_commits(output: &str) -> Vec<StackedCommitInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (sha, summary) = line.split_once('\t').unwrap_or((line, ""));
+            let sha = sha.trim();
+            if sha.is_empty() {
+                return None;
+            }
+            Some(StackedCommitInfo {
+                commit_id: sha.to_string(),
+                short_id: sha.chars().take(7).collect(),
+                change_id: None,
+                summary: summary.to_string(),
+                history: None,
+            })
+        })
+        .collect()
+}
+
+/// The first parent and changed files from the tagged `parent <sha>` / `file <path>`
+/// lines `load_pr_commit_diffs` asks `gh api --paginate` for.
+fn parse_commit_files(output: &str) -> (String, Vec<String>) {
+    let mut parent = None;
+    let mut files = Vec::new();
+    for line in output.lines() {
+        if let Some(sha) = line.strip_prefix("parent ") {
+            parent.get_or_insert_with(|| sha.trim().to_string());
+        } else if let Some(file) = line.strip_prefix("file ") {
+            files.push(file.to_string());
+        }
+    }
+    (parent.unwrap_or_default(), files)
+}
+
+/// Load the diff of a single PR commit against its first parent, from GitHub.
+pub fn load_pr_commit_diffs(
+    pr_info: &PrInfo,
+    commit_sha: &str,
+    file_filter: &Option<Vec<String>>,
+) -> Result<Vec<FileDiff>, String> {
+    let repo = format!("{}/{}", pr_info.repo_owner, pr_info.repo_name);
+    let api_path = format!("repos/{}/commits/{}", repo, commit_sha);
+    // The file list is paged (300 a page) and every page repeats the parents, so the
+    // lines are tagged and the parent taken from the first page
+    let output = gh_command(&pr_info.host)
+        .args([
+            "api",
+            "--paginate",
+            &api_path,
+            "--jq",
+            r#"("parent " + (.parents[0].sha // "")), (.files[] | "file " + .filename)"#,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run gh api: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to fetch commit {}: {}", commit_sha, stderr.trim()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (parent, files) = parse_commit_files(&stdout);
+    let files = files
+        .into_iter()
+        .filter(|f| file_filter.as_ref().is_none_or(|filter| filter.contains(f)));
+
+    let file_diffs = files
+        .map(|filename| {
+            let old_content = if parent.is_empty() {
+                String::new()
+            } else {
+                fetch_file_content_from_github(&pr_info.host, &repo, &parent, &filename)
+            };
             let new_content =
-                fetch_file_content_from_github(&head_repo, &pr_info.head_ref, &filename);
+                fetch_file_content_from_github(&pr_info.host, &repo, commit_sha, &filename);
 
             let status = if old_content.is_empty() && !new_content.is_empty() {
                 FileStatus::Added
@@ -190,6 +578,8 @@ pub fn load_pr_file_diffs(pr_info: &PrInfo) -> Result<Vec<FileDiff>, String> {
                 new_content,
                 status,
                 is_binary,
+                old_mode: None,
+                new_mode: None,
             }
         })
         .collect();
@@ -197,9 +587,9 @@ pub fn load_pr_file_diffs(pr_info: &PrInfo) -> Result<Vec<FileDiff>, String> {
     Ok(file_diffs)
 }
 
-fn fetch_file_content_from_github(repo: &str, git_ref: &str, path: &str) -> String {
+fn fetch_file_content_from_github(host: &str, repo: &str, git_ref: &str, path: &str) -> String {
     let api_path = format!("repos/{}/contents/{}?ref={}", repo, path, git_ref);
-    let output = Command::new("gh")
+    let output = gh_command(host)
         .args([
             "api",
             &api_path,
@@ -214,8 +604,54 @@ fn fetch_file_content_from_github(repo: &str, git_ref: &str, path: &str) -> Stri
     }
 }
 
+/// Removes ANSI escape sequences (SGR colors, cursor control, OSC hyperlinks) from text.
+/// Colored diff output from pagers or `color.ui=always` would otherwise corrupt parsing.
+pub fn strip_ansi_codes(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek() {
+            // CSI: ESC [ params... final byte in 0x40..=0x7e
+            Some('[') => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ESC ] ... terminated by BEL or ESC \
+            Some(']') => {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Two-byte escape sequence
+            Some(_) => {
+                chars.next();
+            }
+            None => {}
+        }
+    }
+
+    result
+}
+
 fn parse_changed_files_from_diff(diff: &str) -> Vec<String> {
     let mut files = Vec::new();
+    let diff = strip_ansi_codes(diff);
 
     for line in diff.lines() {
         if line.starts_with("diff --git") {
@@ -284,17 +720,71 @@ pub fn load_single_commit_diffs(
 
             let is_binary =
                 is_binary_content(&old_content) || is_binary_content(&new_content);
+            let old_mode = file_mode_at(&parent_ref, path, backend);
+            let new_mode = file_mode_at(commit_id, path, backend);
             FileDiff {
                 filename,
                 old_content,
                 new_content,
                 status,
                 is_binary,
+                old_mode,
+                new_mode,
             }
         })
         .collect()
 }
 
+/// The followed file's diff in one commit of a file-history timeline. A rename
+/// compares against the file's old path in the parent.
+pub fn load_file_revision_diffs(
+    commit_id: &str,
+    revision: &FileRevision,
+    backend: &dyn VcsBackend,
+) -> Vec<FileDiff> {
+    let parent_ref = backend
+        .get_parent_ref_or_empty(commit_id)
+        .unwrap_or_default();
+    let old_path = revision.old_path.as_deref().unwrap_or(&revision.path);
+
+    let old_content = if parent_ref.is_empty() {
+        String::new()
+    } else {
+        backend
+            .get_file_content_at_ref(&parent_ref, Path::new(old_path))
+            .unwrap_or_default()
+    };
+    let new_content = backend
+        .get_file_content_at_ref(commit_id, Path::new(&revision.path))
+        .unwrap_or_default();
+
+    let status = if old_content.is_empty() && !new_content.is_empty() {
+        FileStatus::Added
+    } else if !old_content.is_empty() && new_content.is_empty() {
+        FileStatus::Deleted
+    } else {
+        FileStatus::Modified
+    };
+    let is_binary = is_binary_content(&old_content) || is_binary_content(&new_content);
+    vec![FileDiff {
+        filename: revision.path.clone(),
+        old_content,
+        new_content,
+        status,
+        is_binary,
+        old_mode: file_mode_at(&parent_ref, Path::new(old_path), backend),
+        new_mode: file_mode_at(commit_id, Path::new(&revision.path), backend),
+    }]
+}
+
+/// The mode of `path` at `reference`; an empty ref (a root commit's parent) has none.
+fn file_mode_at(reference: &str, path: &Path, backend: &dyn VcsBackend) -> Option<u32> {
+    if reference.is_empty() {
+        return None;
+    }
+    backend.get_file_mode(Some(reference), path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -302,6 +792,135 @@ mod tests {
     use crate::vcs::GitBackend;
     use std::fs;
 
+    #[test]
+    fn test_parse_pr_file_shas() {
+        let shas = parse_pr_file_shas("abc123\tsrc/a.rs\n\tsrc/removed.rs\n");
+        assert_eq!(shas.len(), 1);
+        assert_eq!(shas["src/a.rs"], "abc123");
+    }
+
+    #[test]
+    fn test_parse_pr_commits() {
+        let commits = parse_pr_commits(
+            "0123456789abcdef\tAdd parser\nfedcba9876543210\tFix tests\n\n",
+        );
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].commit_id, "0123456789abcdef");
+        assert_eq!(commits[0].short_id, "0123456");
+        assert_eq!(commits[0].summary, "Add parser");
+        assert_eq!(commits[1].summary, "Fix tests");
+        assert!(commits[1].change_id.is_none());
+    }
+
+    #[test]
+    fn test_current_branch_label() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let branch = backend.get_current_branch().unwrap().unwrap();
+        assert_eq!(get_current_branch(&backend), branch);
+
+        let head = backend.resolve_ref("HEAD").unwrap();
+        let repo = git2::Repository::open(".").expect("open repo");
+        repo.set_head_detached(git2::Oid::from_str(&head).unwrap())
+            .expect("detach HEAD");
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            get_current_branch(&backend),
+            format!("(detached at {})", &head[..7])
+        );
+
+        let dir = make_temp_dir("git-no-commits");
+        git(&dir, &["init"]);
+        let backend = GitBackend::new(&dir).expect("should open repo");
+        let unborn = backend.get_current_branch().unwrap().unwrap();
+        assert_eq!(
+            get_current_branch(&backend),
+            format!("{} (no commits yet)", unborn)
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_commit_message_diff_after_amend() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let before = backend.resolve_ref("HEAD").unwrap();
+
+        let repo = git2::Repository::open(".").expect("open repo");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        head.amend(Some("HEAD"), None, None, None, Some("Reworded\n"), None)
+            .expect("amend");
+        let after = backend.resolve_ref("HEAD").unwrap();
+
+        let diff = commit_message_diff(&before, &after, &backend).expect("message changed");
+        assert_eq!(diff.filename, COMMIT_MESSAGE_FILE);
+        assert_eq!(diff.new_content, "Reworded");
+        assert_ne!(diff.old_content, diff.new_content);
+        assert!(commit_message_diff(&after, &after, &backend).is_none());
+    }
+
+    #[test]
+    fn test_titles_for_refs() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let titles = |old: &str, new: &str| PanelTitles {
+            old: old.into(),
+            new: new.into(),
+        };
+
+        assert_eq!(
+            titles_for_refs(&DiffRefs::WorkingTree, &backend),
+            titles("HEAD", "Working tree")
+        );
+        // HEAD is the root commit, so there is no parent to name
+        assert_eq!(
+            titles_for_refs(&DiffRefs::Single("HEAD".into()), &backend),
+            titles("(empty)", "HEAD")
+        );
+
+        let refs = DiffRefs::Range {
+            from: "0123456789abcdef0123456789abcdef01234567".into(),
+            to: "feature".into(),
+        };
+        assert_eq!(titles_for_refs(&refs, &backend), titles("0123456", "feature"));
+        assert_eq!(
+            titles_for_refs(&DiffRefs::WorkingTreeAgainst("main".into()), &backend),
+            titles("main", "Working tree")
+        );
+    }
+
+    #[test]
+    fn test_parse_commit_files_across_pages() {
+        let output = "parent abc\nfile a.rs\nfile dir/b c.rs\nparent abc\nfile z.rs\n";
+        let (parent, files) = parse_commit_files(output);
+        assert_eq!(parent, "abc");
+        assert_eq!(files, vec!["a.rs", "dir/b c.rs", "z.rs"]);
+
+        // A root commit has no parent
+        assert_eq!(parse_commit_files("parent \nfile a.rs\n").0, "");
+    }
+
+    #[test]
+    fn test_strip_ansi_codes() {
+        let colored = "\x1b[1mdiff --git a/src/main.rs b/src/main.rs\x1b[m\n\x1b[32m+added\x1b[0m\n";
+        assert_eq!(
+            strip_ansi_codes(colored),
+            "diff --git a/src/main.rs b/src/main.rs\n+added\n"
+        );
+        let hyperlink = "\x1b]8;;file:///a.rs\x1b\\a.rs\x1b]8;;\x1b\\";
+        assert_eq!(strip_ansi_codes(hyperlink), "a.rs");
+        assert_eq!(strip_ansi_codes("plain text"), "plain text");
+    }
+
+    #[test]
+    fn test_parse_changed_files_from_colored_diff() {
+        let diff = "\x1b[1mdiff --git a/src/lib.rs b/src/lib.rs\x1b[m\n\x1b[1mindex 1234..5678\x1b[m\n\x1b[1mdiff --git a/README.md b/README.md\x1b[m\n";
+        assert_eq!(
+            parse_changed_files_from_diff(diff),
+            vec!["src/lib.rs".to_string(), "README.md".to_string()]
+        );
+    }
+
     #[test]
     fn test_load_single_commit_diffs_added_file() {
         let _repo = RepoGuard::new();
@@ -422,6 +1041,60 @@ mod tests {
         let _ = fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn test_get_submodule_contents_lists_bumped_commits() {
+        let sub_dir = make_temp_dir("git-submodule-dep");
+        git(&sub_dir, &["init"]);
+        let mut shas = Vec::new();
+        for msg in ["one", "two", "three"] {
+            fs::write(sub_dir.join("lib.txt"), format!("{}\n", msg)).expect("write lib");
+            git(&sub_dir, &["add", "."]);
+            git(&sub_dir, &["commit", "-m", msg]);
+            let repo = git2::Repository::open(&sub_dir).expect("open dep");
+            shas.push(repo.head().unwrap().target().unwrap());
+        }
+
+        let dir = make_temp_dir("git-submodule-super");
+        git(&dir, &["init"]);
+        fs::write(dir.join("README.md"), "hello\n").expect("write readme");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "init"]);
+
+        let repo = git2::Repository::open(&dir).expect("open super");
+        let url = format!("file://{}", sub_dir.display());
+        let mut submodule = repo
+            .submodule(&url, Path::new("dep"), true)
+            .expect("add submodule");
+        let checkout = |sha: git2::Oid| {
+            let dep = git2::Repository::open(dir.join("dep")).expect("open checkout");
+            dep.set_head_detached(sha).expect("detach");
+            dep.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
+                .expect("checkout");
+        };
+        submodule.clone(None).expect("clone submodule");
+        checkout(shas[0]);
+        submodule.add_finalize().expect("finalize submodule");
+        git(&dir, &["commit", "-m", "add dep"]);
+
+        checkout(shas[2]);
+        git(&dir, &["add", "dep"]);
+        git(&dir, &["commit", "-m", "bump dep"]);
+
+        let backend = GitBackend::new(&dir).expect("should open repo");
+        let (old, new) = get_submodule_contents("dep", "HEAD~1", Some("HEAD"), &backend)
+            .expect("dep is a submodule");
+
+        assert_eq!(old, format!("Subproject commit {}\n", shas[0]));
+        assert!(new.starts_with(&format!("Subproject commit {}\n", shas[2])));
+        assert!(new.contains(" two\n") && new.contains(" three\n"));
+        assert!(!new.contains(" one\n"), "old side commit is excluded");
+
+        assert!(get_submodule_contents("README.md", "HEAD~1", Some("HEAD"), &backend).is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(&sub_dir);
+    }
+
     #[test]
     fn test_stacked_diff_integration_git() {
         let _lock = crate::vcs::test_utils::cwd_lock()
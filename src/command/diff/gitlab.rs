@@ -0,0 +1,323 @@
+//! GitLab merge requests through `glab api`, which takes care of authentication
+//! and self-hosted instances.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use super::forge::{Forge, PrBackend};
+use super::types::{is_binary_content, FileDiff, FileStatus};
+use super::PrInfo;
+
+pub struct GitLabMrs;
+
+/// A file changed by a merge request, from its `changes` endpoint.
+struct MrChange {
+    old_path: String,
+    new_path: String,
+    status: FileStatus,
+}
+
+/// `(host, project path, iid)` from a merge request URL such as
+/// `https://gitlab.example.com/group/sub/project/-/merge_requests/12`;
+/// a bare number leaves `glab` to find the project from the current repository.
+fn parse_mr_input(input: &str) -> Option<(Option<String>, Option<String>, u64)> {
+    if !(input.starts_with("http://") || input.starts_with("https://")) {
+        return input.parse::<u64>().ok().map(|iid| (None, None, iid));
+    }
+    let (_, rest) = input.split_once("://")?;
+    let (project_url, tail) = rest.split_once("/-/merge_requests/")?;
+    let iid = tail.split(['/', '#', '?']).next()?.parse::<u64>().ok()?;
+    let (host, project) = project_url.split_once('/')?;
+    Some((Some(host.to_string()), Some(project.to_string()), iid))
+}
+
+/// Percent-encodes everything but unreserved characters, as GitLab expects for
+/// project paths, file paths and refs inside API paths.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn glab_api(host: Option<&str>, endpoint: &str) -> Result<Vec<u8>, String> {
+    let mut command = super::child_command("glab");
+    command.arg("api");
+    if let Some(host) = host {
+        command.args(["--hostname", host]);
+    }
+    let output = command
+        .arg(endpoint)
+        .output()
+        .map_err(|e| format!("Failed to run glab api: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("glab api failed: {}", stderr.trim()));
+    }
+    Ok(output.stdout)
+}
+
+fn glab_json(host: Option<&str>, endpoint: &str) -> Result<Value, String> {
+    let body = glab_api(host, endpoint)?;
+    serde_json::from_slice(&body).map_err(|e| e.to_string())
+}
+
+fn project_endpoint(pr_info: &PrInfo) -> String {
+    format!(
+        "projects/{}",
+        encode(&format!("{}/{}", pr_info.repo_owner, pr_info.repo_name))
+    )
+}
+
+/// Builds `PrInfo` from a merge request object. The project and host come from its
+/// `web_url`, so a bare number resolved by `glab` ends up with the same fields as a URL.
+fn parse_mr_info(json: &Value) -> Result<PrInfo, String> {
+    let web_url = json["web_url"]
+        .as_str()
+        .ok_or_else(|| "Could not parse merge request from GitLab response".to_string())?;
+    let (host, project, number) = parse_mr_input(web_url)
+        .and_then(|(host, project, iid)| Some((host?, project?, iid)))
+        .ok_or_else(|| format!("Unexpected merge request URL: {}", web_url))?;
+    let (repo_owner, repo_name) = project
+        .rsplit_once('/')
+        .map(|(owner, name)| (owner.to_string(), name.to_string()))
+        .ok_or_else(|| format!("Invalid project path: {}", project))?;
+
+    let state = json["state"].as_str().unwrap_or("unknown");
+    let mergeable = json["detailed_merge_status"]
+        .as_str()
+        .or_else(|| json["merge_status"].as_str())
+        .unwrap_or("unknown");
+    Ok(PrInfo {
+        forge: Forge::GitLab,
+        host,
+        number,
+        node_id: json["id"].as_u64().unwrap_or_default().to_string(),
+        base_repo_owner: repo_owner.clone(),
+        // Fork MRs are read through the target project's merge request refs
+        head_repo_owner: Some(repo_owner.clone()),
+        repo_owner,
+        repo_name,
+        base_ref: json["target_branch"].as_str().unwrap_or("base").to_string(),
+        head_ref: json["source_branch"].as_str().unwrap_or("head").to_string(),
+        head_sha: json["sha"].as_str().unwrap_or_default().to_string(),
+        base_sha: json["diff_refs"]["base_sha"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        title: json["title"].as_str().unwrap_or_default().to_string(),
+        author: json["author"]["username"].as_str().map(str::to_string),
+        state: state.to_uppercase(),
+        mergeable: mergeable.to_uppercase(),
+        behind_by: None,
+    })
+}
+
+fn parse_changes(json: &Value) -> Vec<MrChange> {
+    let changes = json["changes"].as_array();
+    changes
+        .into_iter()
+        .flatten()
+        .filter_map(|change| {
+            let new_path = change["new_path"].as_str()?.to_string();
+            let old_path = change["old_path"].as_str().unwrap_or(&new_path).to_string();
+            let status = if change["new_file"].as_bool() == Some(true) {
+                FileStatus::Added
+            } else if change["deleted_file"].as_bool() == Some(true) {
+                FileStatus::Deleted
+            } else {
+                FileStatus::Modified
+            };
+            Some(MrChange {
+                old_path,
+                new_path,
+                status,
+            })
+        })
+        .collect()
+}
+
+fn fetch_file_content(pr_info: &PrInfo, git_ref: &str, path: &str) -> Result<String, String> {
+    let endpoint = format!(
+        "{}/repository/files/{}/raw?ref={}",
+        project_endpoint(pr_info),
+        encode(path),
+        encode(git_ref)
+    );
+    glab_api(Some(&pr_info.host), &endpoint)
+        .map(|body| String::from_utf8_lossy(&body).to_string())
+        .map_err(|e| format!("Failed to fetch {} at {}: {}", path, git_ref, e))
+}
+
+impl PrBackend for GitLabMrs {
+    fn fetch_info(&self, input: &str, _default_host: Option<&str>) -> Result<PrInfo, String> {
+        // A bare number leaves `glab` to find the host from the current repository
+        let (host, project, iid) = parse_mr_input(input)
+            .ok_or_else(|| format!("Invalid MR reference: {}. Use an MR number or URL.", input))?;
+        let project = project
+            .map(|p| encode(&p))
+            .unwrap_or_else(|| ":id".to_string());
+        let endpoint = format!("projects/{}/merge_requests/{}", project, iid);
+        parse_mr_info(&glab_json(host.as_deref(), &endpoint)?)
+    }
+
+    fn load_file_diffs(
+        &self,
+        pr_info: &PrInfo,
+        max_files: usize,
+    ) -> Result<(Vec<FileDiff>, usize), String> {
+        let endpoint = format!(
+            "{}/merge_requests/{}/changes",
+            project_endpoint(pr_info),
+            pr_info.number
+        );
+        let mut changes = parse_changes(&glab_json(Some(&pr_info.host), &endpoint)?);
+        let total = changes.len();
+        changes.truncate(max_files);
+
+        // Present even when the source branch lives in a fork or was deleted
+        let head_ref = format!("refs/merge-requests/{}/head", pr_info.number);
+        let file_diffs = changes
+            .into_iter()
+            .map(|change| {
+                let old_content = match change.status {
+                    FileStatus::Added => String::new(),
+                    _ => fetch_file_content(pr_info, &pr_info.base_ref, &change.old_path)?,
+                };
+                let new_content = match change.status {
+                    FileStatus::Deleted => String::new(),
+                    _ => fetch_file_content(pr_info, &head_ref, &change.new_path)?,
+                };
+                let is_binary = is_binary_content(&old_content) || is_binary_content(&new_content);
+                Ok(FileDiff {
+                    filename: change.new_path,
+                    old_content,
+                    new_content,
+                    status: change.status,
+                    is_binary,
+                    old_mode: None,
+                    new_mode: None,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok((file_diffs, total))
+    }
+
+    fn fetch_behind_by(&self, pr_info: &PrInfo) -> Result<usize, String> {
+        let endpoint = format!(
+            "{}/merge_requests/{}?include_diverged_commits_count=true",
+            project_endpoint(pr_info),
+            pr_info.number
+        );
+        glab_json(Some(&pr_info.host), &endpoint)?["diverged_commits_count"]
+            .as_u64()
+            .map(|count| count as usize)
+            .ok_or_else(|| "GitLab did not report diverged commits".to_string())
+    }
+
+    fn fetch_viewed_files(
+        &self,
+        _pr_info: &PrInfo,
+        _max_files: usize,
+    ) -> Result<HashSet<String>, String> {
+        // An error keeps the marks made this session when the view reloads
+        Err("GitLab has no per-file viewed state".to_string())
+    }
+
+    fn mark_viewed(&self, _pr_info: &PrInfo, _path: &str, _viewed: bool) -> bool {
+        // GitLab only remembers viewed files in the browser
+        false
+    }
+
+    fn file_url(&self, pr_info: &PrInfo, _path: &str) -> String {
+        format!(
+            "https://{}/{}/{}/-/merge_requests/{}/diffs",
+            pr_info.host, pr_info.repo_owner, pr_info.repo_name, pr_info.number
+        )
+    }
+
+    fn blame_url(&self, pr_info: &PrInfo, path: &str, line: usize) -> String {
+        format!(
+            "https://{}/{}/{}/-/blame/{}/{}#L{}",
+            pr_info.host,
+            pr_info.repo_owner,
+            pr_info.repo_name,
+            pr_info.base_commit(),
+            path,
+            line
+        )
+    }
+
+    fn lines_url(&self, pr_info: &PrInfo, path: &str, start: usize, end: usize) -> String {
+        let anchor = if start == end {
+            format!("L{}", start)
+        } else {
+            format!("L{}-{}", start, end)
+        };
+        format!(
+            "https://{}/{}/{}/-/blob/{}/{}#{}",
+            pr_info.host, pr_info.repo_owner, pr_info.repo_name, pr_info.head_sha, path, anchor
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_mr_input() {
+        assert_eq!(
+            parse_mr_input("https://gitlab.example.com/group/sub/app/-/merge_requests/12/diffs"),
+            Some((
+                Some("gitlab.example.com".to_string()),
+                Some("group/sub/app".to_string()),
+                12
+            ))
+        );
+        assert_eq!(parse_mr_input("7"), Some((None, None, 7)));
+        assert_eq!(
+            parse_mr_input("https://gitlab.com/group/app/-/issues/3"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_mr_info_and_changes() {
+        let mr = json!({
+            "id": 991, "iid": 12, "title": "Add cache", "state": "opened",
+            "detailed_merge_status": "mergeable", "target_branch": "main",
+            "source_branch": "cache", "author": { "username": "ann" },
+            "web_url": "https://gitlab.com/group/sub/app/-/merge_requests/12"
+        });
+        let info = parse_mr_info(&mr).unwrap();
+        assert_eq!((info.host.as_str(), info.number), ("gitlab.com", 12));
+        assert_eq!(
+            (info.repo_owner.as_str(), info.repo_name.as_str()),
+            ("group/sub", "app")
+        );
+        assert_eq!(
+            (info.state.as_str(), info.mergeable.as_str()),
+            ("OPENED", "MERGEABLE")
+        );
+        assert_eq!(project_endpoint(&info), "projects/group%2Fsub%2Fapp");
+
+        let changes = parse_changes(&json!({ "changes": [
+            { "old_path": "a.rs", "new_path": "a.rs", "new_file": true, "deleted_file": false },
+            { "old_path": "old.rs", "new_path": "new.rs", "new_file": false, "deleted_file": false },
+            { "old_path": "gone.rs", "new_path": "gone.rs", "new_file": false, "deleted_file": true }
+        ] }));
+        assert!(matches!(changes[0].status, FileStatus::Added));
+        assert_eq!(
+            (changes[1].old_path.as_str(), changes[1].new_path.as_str()),
+            ("old.rs", "new.rs")
+        );
+        assert!(matches!(changes[2].status, FileStatus::Deleted));
+    }
+}
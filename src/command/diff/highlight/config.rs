@@ -1,3 +1,5 @@
+use std::sync::Mutex;
+
 use once_cell::sync::Lazy;
 use tree_sitter_highlight::HighlightConfiguration;
 
@@ -37,24 +39,55 @@ pub const HIGHLIGHT_NAMES: &[&str] = &[
 ];
 
 pub struct LanguageConfig {
+    /// Tree-sitter language name, e.g. `rust`; configs and modelines may use it
+    pub name: &'static str,
     pub config: HighlightConfiguration,
 }
 
+/// `"<language> (.<ext>): <reason>"` for every config that failed to build or
+/// validate while `CONFIGS` was initialized; files of those types render plain.
+pub static LOAD_FAILURES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Why a freshly built config would highlight nothing, if it would: a query
+/// with no patterns, or captures that map to none of `HIGHLIGHT_NAMES`.
+fn validate(config: &HighlightConfiguration) -> Result<(), String> {
+    if config.query.pattern_count() == 0 {
+        return Err("highlight query has no patterns".to_string());
+    }
+    // Same rule as `HighlightConfiguration::configure`: every part of a recognized
+    // name must appear in the capture name
+    let recognized = config.query.capture_names().iter().any(|capture| {
+        let parts: Vec<&str> = capture.split('.').collect();
+        HIGHLIGHT_NAMES
+            .iter()
+            .any(|name| name.split('.').all(|part| parts.contains(&part)))
+    });
+    if !recognized {
+        return Err("no query capture matches a known highlight name".to_string());
+    }
+    Ok(())
+}
+
 fn load_config(
     language: tree_sitter::Language,
-    name: &str,
+    name: &'static str,
     highlights: &str,
     ext: &'static str,
     configs: &mut Vec<(&'static str, LanguageConfig)>,
 ) {
-    match HighlightConfiguration::new(language, name, highlights, "", "") {
-        Ok(mut config) => {
+    let loaded = HighlightConfiguration::new(language, name, highlights, "", "")
+        .map_err(|e| e.to_string())
+        .and_then(|mut config| {
+            validate(&config)?;
             config.configure(HIGHLIGHT_NAMES);
-            configs.push((ext, LanguageConfig { config }));
-        }
-        Err(_e) => {
-            #[cfg(debug_assertions)]
-            eprintln!("[WARN] Failed to load {} highlight config: {:?}", name, _e);
+            Ok(config)
+        });
+    match loaded {
+        Ok(config) => configs.push((ext, LanguageConfig { name, config })),
+        Err(reason) => {
+            if let Ok(mut failures) = LOAD_FAILURES.lock() {
+                failures.push(format!("{} (.{}): {}", name, ext, reason));
+            }
         }
     }
 }
@@ -224,3 +257,19 @@ pub static CONFIGS: Lazy<Vec<(&'static str, LanguageConfig)>> = Lazy::new(|| {
 
     configs
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rejects_unrecognized_captures() {
+        let rust = || tree_sitter_rust::LANGUAGE.into();
+        let config = HighlightConfiguration::new(rust(), "rust", "(identifier) @zzz", "", "");
+        assert!(validate(&config.unwrap()).is_err());
+
+        let config =
+            HighlightConfiguration::new(rust(), "rust", "(identifier) @variable.other", "", "");
+        assert!(validate(&config.unwrap()).is_ok());
+    }
+}
@@ -1,14 +1,25 @@
 mod config;
+mod overrides;
 mod queries;
 
+pub(crate) use overrides::pattern_matches;
+
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
 use ratatui::prelude::*;
 use tree_sitter_highlight::{HighlightEvent, Highlighter};
 
 use super::theme;
-use config::{LanguageConfig, CONFIGS, HIGHLIGHT_NAMES};
+use config::{LanguageConfig, CONFIGS, HIGHLIGHT_NAMES, LOAD_FAILURES};
+
+/// Cleared by `--no-highlight` / `"syntax": false`; every lookup then yields plain text.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// File extensions from the `no_highlight` config, always shown as plain text.
+static PLAIN_EXTENSIONS: OnceLock<Vec<String>> = OnceLock::new();
 
 pub fn highlight_color(index: usize) -> Color {
     let t = theme::get();
@@ -35,13 +46,26 @@ pub fn highlight_color(index: usize) -> Color {
     }
 }
 
-fn get_config_for_file(filename: &str) -> Option<&'static LanguageConfig> {
-    let ext = Path::new(filename).extension().and_then(|e| e.to_str())?;
+/// Highlight config for a file: a configured or modeline language first, then the extension.
+fn get_config_for_file(filename: &str, content: &str) -> Option<&'static LanguageConfig> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let extension = Path::new(filename).extension().and_then(|e| e.to_str());
+    if let (Some(extension), Some(plain)) = (extension, PLAIN_EXTENSIONS.get()) {
+        if plain.iter().any(|p| p == extension) {
+            return None;
+        }
+    }
+    let ext = match overrides::language_for(filename, content) {
+        Some(ext) => ext,
+        None => extension?,
+    };
     CONFIGS.iter().find(|(e, _)| *e == ext).map(|(_, c)| c)
 }
 
 fn highlight_code(code: &str, filename: &str) -> Vec<(String, Option<usize>)> {
-    let Some(lang_config) = get_config_for_file(filename) else {
+    let Some(lang_config) = get_config_for_file(filename, code) else {
         return code.lines().map(|l| (l.to_string(), None)).collect();
     };
 
@@ -84,7 +108,7 @@ pub struct FileHighlighter {
 impl FileHighlighter {
     /// Create a new FileHighlighter by analyzing the entire file content.
     pub fn new(content: &str, filename: &str) -> Self {
-        let Some(lang_config) = get_config_for_file(filename) else {
+        let Some(lang_config) = get_config_for_file(filename, content) else {
             return Self::default();
         };
 
@@ -169,17 +193,26 @@ impl FileHighlighter {
         let bg_color = bg.unwrap_or(Color::Reset);
         let default_fg = theme::get().syntax.default_text;
 
+        let styled = |text: &str, highlight_idx: Option<usize>| {
+            let fg = highlight_idx.map(highlight_color).unwrap_or(default_fg);
+            Span::styled(text.to_string(), Style::default().fg(fg).bg(bg_color))
+        };
+
         self.line_highlights
             .get(&line_number)
             .map(|spans| {
-                spans
+                let mut line: Vec<Span> = spans
                     .iter()
                     .filter(|(text, _)| *text != "\n") // Skip newline markers
-                    .map(|(text, highlight_idx)| {
-                        let fg = highlight_idx.map(highlight_color).unwrap_or(default_fg);
-                        Span::styled(text.clone(), Style::default().fg(fg).bg(bg_color))
-                    })
-                    .collect()
+                    .map(|(text, highlight_idx)| styled(text, *highlight_idx))
+                    .collect();
+                // A blank line inside a multi-line construct (a blank line in a block
+                // comment) has only its newline marker: keep the construct's style on an
+                // empty span rather than let callers re-highlight the line on its own
+                if let (true, Some((_, highlight_idx))) = (line.is_empty(), spans.first()) {
+                    line.push(styled("", *highlight_idx));
+                }
+                line
             })
             .unwrap_or_default()
     }
@@ -207,8 +240,40 @@ pub fn highlight_line_spans<'a>(line: &str, filename: &str, bg: Option<Color>) -
         .collect()
 }
 
-pub fn init() {
+/// `languages` maps filename globs to language names, overriding the extension (names
+/// no config knows are warned about here, once); files with an extension in
+/// `plain_extensions` are never highlighted.
+/// Builds and validates every highlight config up front; with `verbose` (always in
+/// debug builds) each language that failed is named on stderr, once.
+pub fn init(
+    enabled: bool,
+    languages: Vec<(String, String)>,
+    plain_extensions: Vec<String>,
+    verbose: bool,
+) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    overrides::set(languages);
+    let _ = PLAIN_EXTENSIONS.set(plain_extensions);
+    if !enabled {
+        // Skip building the tree-sitter configs altogether
+        return;
+    }
     let _ = &*CONFIGS;
+    for language in overrides::unknown_languages() {
+        eprintln!(
+            "\x1b[93mwarning:\x1b[0m unknown language \"{}\" in the languages config; \
+             using modelines and extensions instead",
+            language
+        );
+    }
+    if verbose || cfg!(debug_assertions) {
+        for failure in load_failures() {
+            eprintln!(
+                "\x1b[93mwarning:\x1b[0m syntax highlighting disabled for {}",
+                failure
+            );
+        }
+    }
     #[cfg(debug_assertions)]
     {
         let extensions: Vec<&str> = CONFIGS.iter().map(|(ext, _)| *ext).collect();
@@ -216,6 +281,15 @@ pub fn init() {
     }
 }
 
+/// Languages whose highlight config failed to build or validate, with the reason.
+pub fn load_failures() -> Vec<String> {
+    let _ = &*CONFIGS;
+    LOAD_FAILURES
+        .lock()
+        .map(|failures| failures.clone())
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -242,6 +316,7 @@ mod tests {
             "Elixir script config should be loaded"
         );
         assert!(extensions.contains(&"java"), "Java config should be loaded");
+        assert_eq!(load_failures(), Vec::<String>::new());
     }
 
     #[test]
@@ -428,4 +503,24 @@ function foo() {}"#;
         let has_function = line12_spans.iter().any(|s| s.content.contains("function"));
         assert!(has_function, "Line 12 should contain 'function'");
     }
+
+    #[test]
+    fn test_blank_lines_in_block_comment_keep_comment_style() {
+        use config::HIGHLIGHT_NAMES;
+        let code = "/**\n * Summary.\n\n * Details.\n\n */\nfunction foo() {}\n";
+        let highlighter = FileHighlighter::new(code, "test.ts");
+        let comment_idx = HIGHLIGHT_NAMES
+            .iter()
+            .position(|&n| n == "comment")
+            .unwrap();
+        let bg = Color::Rgb(1, 2, 3);
+
+        for line_num in [3, 5] {
+            let spans = highlighter.get_line_spans(line_num, Some(bg));
+            assert_eq!(spans.len(), 1, "line {} should keep its style", line_num);
+            assert_eq!(spans[0].content, "");
+            assert_eq!(spans[0].style.fg, Some(highlight_color(comment_idx)));
+            assert_eq!(spans[0].style.bg, Some(bg));
+        }
+    }
 }
@@ -0,0 +1,227 @@
+//! Explicit language choices that win over the file extension: the `languages`
+//! config map and vim/emacs modelines.
+
+use std::sync::OnceLock;
+
+use super::config::CONFIGS;
+
+/// `(pattern, language)` pairs from the config, most specific pattern first.
+static OVERRIDES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// How many lines at each end of a file are searched for a modeline (vim's default).
+const MODELINE_LINES: usize = 5;
+
+/// Names editors use that are neither a highlight config's language name nor its
+/// extension, with the extension they mean.
+const ALIASES: &[(&str, &str)] = &[
+    ("typescriptreact", "tsx"),
+    ("javascriptreact", "jsx"),
+    ("golang", "go"),
+    ("conf-toml", "toml"),
+    ("shell", "sh"),
+    ("shell-script", "sh"),
+    ("zsh", "sh"),
+    ("csharp", "cs"),
+];
+
+pub fn set(overrides: Vec<(String, String)>) {
+    let mut overrides = overrides;
+    overrides.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    let _ = OVERRIDES.set(overrides);
+}
+
+fn configured() -> &'static [(String, String)] {
+    OVERRIDES.get().map_or(&[], Vec::as_slice)
+}
+
+/// Extension key of the language to use for `filename`, if the config or a modeline names one.
+pub fn language_for(filename: &str, content: &str) -> Option<&'static str> {
+    resolve_language(configured(), filename, content)
+}
+
+/// A configured language that no highlight config knows falls through to the
+/// modeline, and then (in the caller) to the extension.
+fn resolve_language(
+    overrides: &[(String, String)],
+    filename: &str,
+    content: &str,
+) -> Option<&'static str> {
+    overrides
+        .iter()
+        .find(|(pattern, _)| pattern_matches(pattern, filename))
+        .and_then(|(_, language)| language_ext(language))
+        .or_else(|| modeline_language(content).and_then(language_ext))
+}
+
+/// Configured language names that match no highlight config, each once.
+pub fn unknown_languages() -> Vec<&'static str> {
+    unknown_in(configured())
+}
+
+fn unknown_in(overrides: &[(String, String)]) -> Vec<&str> {
+    let mut unknown: Vec<&str> = overrides
+        .iter()
+        .map(|(_, language)| language.as_str())
+        .filter(|language| language_ext(language).is_none())
+        .collect();
+    unknown.sort_unstable();
+    unknown.dedup();
+    unknown
+}
+
+/// Maps a language name as written in configs and modelines to the extension
+/// its highlight config is registered under: an extension, a config's language
+/// name, or one of `ALIASES`.
+fn language_ext(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_ascii_lowercase();
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name.as_str(), |(_, ext)| ext);
+    CONFIGS
+        .iter()
+        .find(|(ext, _)| *ext == name)
+        .or_else(|| CONFIGS.iter().find(|(_, config)| config.name == name))
+        .map(|(ext, _)| *ext)
+}
+
+/// Glob match where `*` and `?` stay within one path segment and `**/` spans
+/// directories. Patterns without a `/` match the file name alone.
+pub(crate) fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let path = if pattern.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+    glob_match(pattern.as_bytes(), path.as_bytes())
+}
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        // A trailing `**` takes everything below, `src/**` included
+        [b'*', b'*'] => true,
+        [b'*', b'*', b'/', rest @ ..] => {
+            // Zero or more whole directories
+            glob_match(rest, text)
+                || text
+                    .iter()
+                    .position(|&c| c == b'/')
+                    .is_some_and(|slash| glob_match(pattern, &text[slash + 1..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => matches!(text, [c, ..] if *c != b'/') && glob_match(rest, &text[1..]),
+        [p, rest @ ..] => matches!(text, [c, ..] if c == p) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Language named by a vim (`vim: set ft=toml:`) or emacs (`-*- mode: toml -*-`)
+/// modeline in the first or last few lines.
+fn modeline_language(content: &str) -> Option<&str> {
+    let lines: Vec<&str> = content.lines().collect();
+    let tail_start = lines
+        .len()
+        .saturating_sub(MODELINE_LINES)
+        .max(MODELINE_LINES);
+    lines
+        .iter()
+        .take(MODELINE_LINES)
+        .chain(lines.iter().skip(tail_start))
+        .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+}
+
+fn vim_modeline(line: &str) -> Option<&str> {
+    let (_, options) = ["vim:", "vi:", "ex:"]
+        .iter()
+        .find_map(|marker| line.split_once(marker))?;
+    options
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syntax" | "syn").then_some(value)
+        })
+        .filter(|value| !value.is_empty())
+}
+
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (vars, _) = rest.split_once("-*-")?;
+    if !vars.contains(':') {
+        // `-*- toml -*-` names the mode directly
+        return Some(vars.trim()).filter(|mode| !mode.is_empty());
+    }
+    vars.split(';').find_map(|var| {
+        let (key, value) = var.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case("mode")).then(|| value.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("Dockerfile*", "deploy/Dockerfile.prod"));
+        assert!(pattern_matches("*.conf", "etc/app.conf"));
+        assert!(!pattern_matches("etc/*.conf", "etc/nested/app.conf"));
+        assert!(pattern_matches("etc/**/*.conf", "etc/nested/app.conf"));
+        assert!(pattern_matches("etc/**/*.conf", "etc/app.conf"));
+        assert!(!pattern_matches("*.conf", "app.config"));
+        assert!(pattern_matches("src/**", "src/command/diff/app.rs"));
+        assert!(!pattern_matches("src/**", "tests/app.rs"));
+    }
+
+    #[test]
+    fn test_modeline_language() {
+        assert_eq!(
+            modeline_language("# vim: set ft=toml:\nkey = 1\n"),
+            Some("toml")
+        );
+        assert_eq!(
+            modeline_language("#!/bin/sh\n# -*- mode: sh; -*-\n"),
+            Some("sh")
+        );
+        assert_eq!(modeline_language("; -*- toml -*-\n"), Some("toml"));
+
+        let tail = format!("{}# vi: filetype=python\n", "x = 1\n".repeat(20));
+        assert_eq!(modeline_language(&tail), Some("python"));
+        assert_eq!(modeline_language("plain text\n"), None);
+    }
+
+    #[test]
+    fn test_unknown_configured_language_falls_through() {
+        let overrides = vec![
+            ("*.conf".to_string(), "cobol".to_string()),
+            ("*.tmpl".to_string(), "toml".to_string()),
+            ("*.cfg".to_string(), "cobol".to_string()),
+        ];
+        assert_eq!(
+            resolve_language(&overrides, "app.conf", "# vim: set ft=python:\n"),
+            Some("py")
+        );
+        assert_eq!(resolve_language(&overrides, "app.conf", "x = 1\n"), None);
+        assert_eq!(
+            resolve_language(&overrides, "app.tmpl", "# vim: set ft=python:\n"),
+            Some("toml")
+        );
+        assert_eq!(unknown_in(&overrides), vec!["cobol"]);
+    }
+
+    #[test]
+    fn test_language_ext_accepts_names_and_extensions() {
+        assert_eq!(language_ext("Python"), Some("py"));
+        assert_eq!(language_ext("toml"), Some("toml"));
+        assert_eq!(language_ext("cobol"), None);
+        assert_eq!(language_ext("javascript"), Some("js"));
+        assert_eq!(language_ext("c_sharp"), Some("cs"));
+        assert_eq!(language_ext("zsh"), Some("sh"));
+        // Every registered config can be named by its extension or language name
+        for (ext, config) in CONFIGS.iter() {
+            assert_eq!(language_ext(ext), Some(*ext));
+            assert!(language_ext(config.name).is_some());
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use ratatui::style::Color;
+
+use super::theme::Theme;
+use super::types::FileStatus;
+use crate::config::configuration::FileStatusConfig;
+
+/// How file statuses are drawn in the sidebar and the file picker.
+/// Built once from the `file_status` config section; unset fields keep the
+/// `A`/`M`/`D` letters and theme colors.
+#[derive(Clone, Debug)]
+pub struct StatusIndicators {
+    added: String,
+    modified: String,
+    deleted: String,
+    added_color: Option<Color>,
+    modified_color: Option<Color>,
+    deleted_color: Option<Color>,
+    /// Show nerd-font file-type icons next to filenames
+    pub icons: bool,
+}
+
+impl Default for StatusIndicators {
+    fn default() -> Self {
+        Self {
+            added: FileStatus::Added.symbol().to_string(),
+            modified: FileStatus::Modified.symbol().to_string(),
+            deleted: FileStatus::Deleted.symbol().to_string(),
+            added_color: None,
+            modified_color: None,
+            deleted_color: None,
+            icons: false,
+        }
+    }
+}
+
+impl StatusIndicators {
+    pub fn from_config(config: &FileStatusConfig) -> Result<Self, String> {
+        let defaults = Self::default();
+        let symbol = |value: &Option<String>, default: String| match value {
+            Some(s) if !s.is_empty() => s.clone(),
+            _ => default,
+        };
+
+        Ok(Self {
+            added: symbol(&config.added, defaults.added),
+            modified: symbol(&config.modified, defaults.modified),
+            deleted: symbol(&config.deleted, defaults.deleted),
+            added_color: parse_color(config.added_color.as_deref())?,
+            modified_color: parse_color(config.modified_color.as_deref())?,
+            deleted_color: parse_color(config.deleted_color.as_deref())?,
+            icons: config.icons,
+        })
+    }
+
+    pub fn symbol(&self, status: FileStatus) -> &str {
+        match status {
+            FileStatus::Added => &self.added,
+            FileStatus::Modified => &self.modified,
+            FileStatus::Deleted => &self.deleted,
+        }
+    }
+
+    pub fn color(&self, status: FileStatus, theme: &Theme) -> Color {
+        match status {
+            FileStatus::Added => self.added_color.unwrap_or(theme.ui.status_added),
+            FileStatus::Modified => self.modified_color.unwrap_or(theme.ui.status_modified),
+            FileStatus::Deleted => self.deleted_color.unwrap_or(theme.ui.status_deleted),
+        }
+    }
+
+    /// Icon prefix (glyph plus a space) for a file, or an empty string when icons are off.
+    pub fn file_icon(&self, filename: &str) -> &'static str {
+        if !self.icons {
+            return "";
+        }
+        let name = filename.rsplit('/').next().unwrap_or(filename);
+        let ext = name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+        match ext {
+            "rs" => "\u{e7a8} ",
+            "py" => "\u{e606} ",
+            "js" | "mjs" | "cjs" | "jsx" => "\u{e74e} ",
+            "ts" | "tsx" => "\u{e628} ",
+            "go" => "\u{e626} ",
+            "md" => "\u{e609} ",
+            "json" => "\u{e60b} ",
+            "toml" | "yaml" | "yml" | "ini" => "\u{e615} ",
+            "html" => "\u{e736} ",
+            "css" | "scss" => "\u{e749} ",
+            "sh" | "bash" | "zsh" | "fish" => "\u{f489} ",
+            _ => "\u{f15b} ",
+        }
+    }
+
+    /// Icon prefix for a directory, or an empty string when icons are off.
+    pub fn dir_icon(&self, collapsed: bool) -> &'static str {
+        match (self.icons, collapsed) {
+            (false, _) => "",
+            (true, true) => "\u{f07b} ",
+            (true, false) => "\u{f07c} ",
+        }
+    }
+}
+
+fn parse_color(value: Option<&str>) -> Result<Option<Color>, String> {
+    value
+        .map(|s| Color::from_str(s).map_err(|_| format!("invalid file_status color: {}", s)))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config_overrides_symbols_and_colors() {
+        let config = FileStatusConfig {
+            added: Some("+".into()),
+            modified: Some("~".into()),
+            deleted: None,
+            added_color: Some("#00ff00".into()),
+            modified_color: None,
+            deleted_color: Some("red".into()),
+            icons: false,
+        };
+        let indicators = StatusIndicators::from_config(&config).unwrap();
+
+        assert_eq!(indicators.symbol(FileStatus::Added), "+");
+        assert_eq!(indicators.symbol(FileStatus::Modified), "~");
+        assert_eq!(indicators.symbol(FileStatus::Deleted), "D");
+        assert_eq!(indicators.added_color, Some(Color::Rgb(0, 255, 0)));
+        assert_eq!(indicators.deleted_color, Some(Color::Red));
+        assert_eq!(indicators.file_icon("src/main.rs"), "");
+    }
+
+    #[test]
+    fn test_from_config_rejects_bad_color() {
+        let config = FileStatusConfig {
+            added_color: Some("not-a-color".into()),
+            ..Default::default()
+        };
+        assert!(StatusIndicators::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use super::diff_algo::compute_side_by_side;
+use super::render::compute_line_stats;
+use super::types::FileDiff;
+use super::PrInfo;
+use crate::vcs::CommitMetadata;
+
+/// Widest `+++---` bar in a diff stat, like `git show --stat` on a narrow terminal.
+const STAT_BAR_WIDTH: usize = 40;
+
+/// Plain-text commit details for the metadata modal.
+pub fn format_commit_metadata(metadata: &CommitMetadata) -> String {
+    let mut out = format!("Commit:     {}\n", metadata.commit_id);
+    if let Some(change_id) = &metadata.change_id {
+        out.push_str(&format!("Change:     {}\n", change_id));
+    }
+    out.push_str(&format!(
+        "Author:     {}\nAuthorDate: {}\n",
+        metadata.author, metadata.author_date
+    ));
+    if let Some(committer) = &metadata.committer {
+        out.push_str(&format!(
+            "Committer:  {}\nCommitDate: {}\n",
+            committer,
+            metadata.committer_date.as_deref().unwrap_or_default()
+        ));
+    }
+    if !metadata.parents.is_empty() {
+        out.push_str(&format!("Parents:    {}\n", metadata.parents.join(" ")));
+    }
+    if let Some(signature) = &metadata.signature {
+        out.push_str(&format!("Signature:  {}\n", signature));
+    }
+    out.push('\n');
+    out.push_str(&metadata.message);
+    out
+}
+
+/// Plain-text pull or merge request details for the metadata modal.
+pub fn format_pr_metadata(pr: &PrInfo) -> String {
+    let head_owner = pr.head_repo_owner.as_deref().unwrap_or("(deleted fork)");
+    let mut out = format!(
+        "{}:         {}\nTitle:      {}\nAuthor:     {}\nState:      {}\nMergeable:  {}\nBase:       {}:{}\nHead:       {}:{}",
+        pr.forge.noun(),
+        pr.full_ref(),
+        pr.title,
+        pr.author.as_deref().unwrap_or("(deleted user)"),
+        pr.state,
+        pr.mergeable,
+        pr.base_repo_owner,
+        pr.base_ref,
+        head_owner,
+        pr.head_ref,
+    );
+    if let Some(behind) = pr.behind_by {
+        out.push_str(&format!(
+            "\nBehind:     {} commit{} on {}",
+            behind,
+            if behind == 1 { "" } else { "s" },
+            pr.base_ref
+        ));
+    }
+    out
+}
+
+/// `git show --stat`-style summary of the loaded file diffs. `show_status` puts each
+/// file's A/M/D status in front of its name.
+pub fn format_diff_stat(file_diffs: &[FileDiff], tab_width: usize, show_status: bool) -> String {
+    let stats: Vec<Option<(usize, usize)>> = file_diffs
+        .iter()
+        .map(|diff| {
+            if diff.is_binary {
+                return None;
+            }
+            let side_by_side =
+                compute_side_by_side(&diff.old_content, &diff.new_content, tab_width, false);
+            let stats = compute_line_stats(&side_by_side);
+            Some((stats.added, stats.removed))
+        })
+        .collect();
+
+    let name_width = file_diffs
+        .iter()
+        .map(|d| d.filename.chars().count())
+        .max()
+        .unwrap_or(0);
+    let max_changes = stats
+        .iter()
+        .flatten()
+        .map(|(a, r)| a + r)
+        .max()
+        .unwrap_or(0);
+    let count_width = max_changes.to_string().len();
+
+    let mut out = String::new();
+    let (mut insertions, mut deletions) = (0, 0);
+    for (diff, stat) in file_diffs.iter().zip(&stats) {
+        if show_status {
+            out.push_str(&format!(" {}", diff.status.symbol()));
+        }
+        match stat {
+            Some((added, removed)) => {
+                insertions += added;
+                deletions += removed;
+                let total = added + removed;
+                let (plus, minus) = if max_changes > STAT_BAR_WIDTH {
+                    // Scale down but keep at least one mark for any nonzero side
+                    let scale = |n: usize| {
+                        if n == 0 {
+                            0
+                        } else {
+                            (n * STAT_BAR_WIDTH / max_changes).max(1)
+                        }
+                    };
+                    (scale(*added), scale(*removed))
+                } else {
+                    (*added, *removed)
+                };
+                out.push_str(&format!(
+                    " {:name_width$} | {:>count_width$} {}{}\n",
+                    diff.filename,
+                    total,
+                    "+".repeat(plus),
+                    "-".repeat(minus),
+                ));
+            }
+            None => out.push_str(&format!(" {:name_width$} | Bin\n", diff.filename)),
+        }
+    }
+
+    let plural = |n: usize, one: &str, many: &str| if n == 1 { one } else { many }.to_string();
+    out.push_str(&format!(
+        " {} {} changed",
+        file_diffs.len(),
+        plural(file_diffs.len(), "file", "files")
+    ));
+    if insertions > 0 {
+        out.push_str(&format!(
+            ", {} {}(+)",
+            insertions,
+            plural(insertions, "insertion", "insertions")
+        ));
+    }
+    if deletions > 0 {
+        out.push_str(&format!(
+            ", {} {}(-)",
+            deletions,
+            plural(deletions, "deletion", "deletions")
+        ));
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::diff::types::FileStatus;
+    use crate::command::diff::Forge;
+
+    #[test]
+    fn test_format_diff_stat() {
+        let file = |name: &str, old: &str, new: &str, is_binary: bool| FileDiff {
+            filename: name.into(),
+            old_content: old.into(),
+            new_content: new.into(),
+            status: FileStatus::Modified,
+            is_binary,
+            old_mode: None,
+            new_mode: None,
+        };
+        let diffs = vec![
+            file("src/lib.rs", "a\nb\n", "a\nc\nd\n", false),
+            file("logo.png", "", "", true),
+        ];
+        let text = format_diff_stat(&diffs, 4, false);
+
+        assert_eq!(
+            text,
+            " src/lib.rs | 3 ++-\n logo.png   | Bin\n 2 files changed, 2 insertions(+), 1 deletion(-)\n"
+        );
+
+        let text = format_diff_stat(&diffs[..1], 4, true);
+        assert_eq!(
+            text,
+            " M src/lib.rs | 3 ++-\n 1 file changed, 2 insertions(+), 1 deletion(-)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_commit_metadata() {
+        let metadata = CommitMetadata {
+            commit_id: "abc123".into(),
+            change_id: None,
+            author: "Ann <ann@example.com>".into(),
+            author_date: "2024-01-02 03:04:05".into(),
+            committer: Some("Bob <bob@example.com>".into()),
+            committer_date: Some("2024-01-03 03:04:05".into()),
+            parents: vec!["p1".into(), "p2".into()],
+            message: "Subject\n\nBody".into(),
+            signature: Some("unsigned".into()),
+        };
+        let text = format_commit_metadata(&metadata);
+
+        assert!(text.starts_with("Commit:     abc123\n"));
+        assert!(text.contains("Committer:  Bob <bob@example.com>\n"));
+        assert!(text.contains("Parents:    p1 p2\n"));
+        assert!(text.contains("Signature:  unsigned\n"));
+        assert!(text.ends_with("\n\nSubject\n\nBody"));
+
+        // Backends without a separate committer leave the lines out
+        let text = format_commit_metadata(&CommitMetadata {
+            committer: None,
+            committer_date: None,
+            ..metadata
+        });
+        assert!(!text.contains("Committer:"));
+    }
+
+    #[test]
+    fn test_format_pr_metadata_behind() {
+        let pr = PrInfo {
+            forge: Forge::GitHub,
+            host: "github.com".to_string(),
+            number: 7,
+            node_id: String::new(),
+            repo_owner: "acme".to_string(),
+            repo_name: "app".to_string(),
+            base_ref: "main".to_string(),
+            head_ref: "fix".to_string(),
+            head_sha: "abc123".to_string(),
+            base_sha: "def456".to_string(),
+            base_repo_owner: "acme".to_string(),
+            head_repo_owner: None,
+            title: "Fix".to_string(),
+            author: None,
+            state: "OPEN".to_string(),
+            mergeable: "UNKNOWN".to_string(),
+            behind_by: Some(1),
+        };
+        assert!(format_pr_metadata(&pr).ends_with("\nBehind:     1 commit on main"));
+
+        let pr = PrInfo {
+            behind_by: Some(3),
+            ..pr
+        };
+        assert!(format_pr_metadata(&pr).ends_with("\nBehind:     3 commits on main"));
+
+        let pr = PrInfo {
+            behind_by: None,
+            ..pr
+        };
+        assert!(!format_pr_metadata(&pr).contains("Behind:"));
+    }
+}
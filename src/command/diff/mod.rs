@@ -1,11 +1,20 @@
 mod annotation;
+mod annotation_store;
 mod app;
+mod clipboard;
 mod context;
 mod coordinates;
 mod diff_algo;
+mod explain;
+mod forge;
 pub mod git;
+mod gitlab;
 pub mod highlight;
+pub mod indicators;
+mod metadata;
+mod patch;
 mod render;
+mod review_store;
 mod search;
 mod state;
 mod sticky_lines;
@@ -13,73 +22,242 @@ pub mod theme;
 mod types;
 mod watcher;
 
-use std::collections::HashSet;
-use std::io;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::{self, Command};
 use std::thread;
+use std::time::Duration;
 
 use spinoff::{spinners, Color, Spinner};
 
 use crate::commit_reference::CommitReference;
-use crate::vcs::VcsBackend;
+use crate::config::cli::ProviderType;
+use crate::vcs::{InProgressOperation, VcsBackend};
+
+pub use clipboard::ClipboardMethod;
+pub use forge::Forge;
+pub use search::GrepFilter;
+pub use types::{DiffViewMode, ExportTemplate, PanelBorders};
+
+/// Set on every program lumen launches (the editor, the clipboard command, gh, a
+/// shell running a suggested command) so nested invocations that would start lumen
+/// again print plain output instead of taking over the terminal. Passed per child:
+/// changing our own environment while other threads run is unsound.
+pub const ACTIVE_ENV: &str = "LUMEN_ACTIVE";
+
+/// A `Command` for `program` with [`ACTIVE_ENV`] set. Use it for every child lumen spawns.
+pub fn child_command(program: impl AsRef<OsStr>) -> Command {
+    let mut command = Command::new(program);
+    command.env(ACTIVE_ENV, "1");
+    command
+}
 
 pub struct DiffOptions {
     pub reference: Option<CommitReference>,
+    /// Compare this commit with the working tree instead of showing a commit
+    pub against: Option<String>,
+    /// Step through the commits that changed this file, one at a time
+    pub history: Option<String>,
     pub pr: Option<String>,
+    /// Host of `pr` when a flag names it (`--mr`) rather than its URL
+    pub pr_forge: Option<Forge>,
     pub file: Option<Vec<String>>,
     pub watch: bool,
+    /// Reload on this timer as well as on file-system events
+    pub watch_interval: Option<Duration>,
     pub theme: Option<String>,
     pub stacked: bool,
     pub focus: Option<String>,
+    /// Open on the files overview instead of the first file's diff
+    pub overview: bool,
+    /// Only files and hunks with an added or changed line matching this
+    pub grep: Option<GrepFilter>,
+    /// Write the loaded diff to this file as a patch instead of opening the viewer
+    pub export_patch: Option<PathBuf>,
+    /// Print a diffstat of the loaded diff instead of opening the viewer
+    pub stat: bool,
+    /// Write every file to this HTML page instead of opening the viewer
+    pub export_html: Option<PathBuf>,
+    /// Start with sidebar directories at this depth or deeper collapsed
+    pub tree_depth: Option<usize>,
+    pub recurse_submodules: bool,
+    /// Syntax highlighting; off skips tree-sitter entirely
+    pub highlight: bool,
+    /// Print startup diagnostics, such as highlight grammars that failed to load
+    pub verbose: bool,
+    /// Filename glob to language overrides for highlighting
+    pub languages: Vec<(String, String)>,
+    /// Tab width by file extension, overriding the default of 4
+    pub tab_widths: HashMap<String, usize>,
+    /// File extensions left unhighlighted
+    pub no_highlight: Vec<String>,
+    pub indicators: indicators::StatusIndicators,
+    /// Leave whitespace-only changes out of word-level emphasis
+    pub hide_whitespace_emphasis: bool,
+    /// Show tabs as this glyph followed by padding instead of plain spaces
+    pub tab_glyph: Option<char>,
+    /// Reviewing an amend: prepend the commit message change to the file list
+    pub amend: bool,
+    /// The stopped rebase or cherry-pick whose working tree is being reviewed
+    pub in_progress: Option<InProgressOperation>,
+    /// Reviewing the files left with conflict markers: open on the first conflict
+    pub conflicts: bool,
+    /// Where copy actions put their text
+    pub clipboard: ClipboardMethod,
+    /// Most files fetched for a PR, for both contents and viewed state
+    pub max_pr_files: usize,
+    /// Annotations longer than this are flagged in the editor and cut short inline
+    pub annotation_limit: usize,
+    /// Replaces the built-in annotation export format when set
+    pub annotation_export: Option<ExportTemplate>,
+    pub view_mode: DiffViewMode,
+    pub panel_borders: PanelBorders,
+    /// Search stops at the last/first match instead of wrapping around
+    pub search_wrap: bool,
+    /// Compact view folds unchanged runs longer than this
+    pub fold_threshold: usize,
+    /// Redraw on every tick instead of only after something changed
+    pub idle_redraw: bool,
+    /// `q` only quits when pressed again (or followed by `y`) while the hint shows
+    pub confirm_quit: bool,
+    /// GitHub host for PRs given by number, from the `github_host` setting
+    pub github_host: Option<String>,
+    pub ai: AiSettings,
+}
+
+/// Provider settings used to construct an AI provider on demand from the diff view,
+/// so the viewer itself never requires an API key.
+#[derive(Clone)]
+pub struct AiSettings {
+    pub provider: ProviderType,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct PrInfo {
+    pub forge: Forge,
+    pub host: String,
     pub number: u64,
     pub node_id: String,
     pub repo_owner: String,
     pub repo_name: String,
     pub base_ref: String,
     pub head_ref: String,
+    /// Commit at the tip of the head branch, for permalinks
+    pub head_sha: String,
+    /// Commit at the tip of the base branch, for old-side permalinks
+    pub base_sha: String,
     pub base_repo_owner: String,
     pub head_repo_owner: Option<String>, // None if head repo was deleted (fork deleted)
+    pub title: String,
+    pub author: Option<String>, // None for deleted (ghost) accounts
+    pub state: String,
+    pub mergeable: String,
+    /// Commits on the base branch the head has not caught up with, when known
+    pub behind_by: Option<usize>,
+}
+
+impl PrInfo {
+    /// `owner/repo#123` for a PR, `group/project!123` for an MR.
+    pub fn full_ref(&self) -> String {
+        format!(
+            "{}/{}{}",
+            self.repo_owner,
+            self.repo_name,
+            self.forge.number_ref(self.number)
+        )
+    }
+
+    /// The base commit for old-side links, or the base branch if the host didn't say.
+    pub fn base_commit(&self) -> &str {
+        if self.base_sha.is_empty() {
+            &self.base_ref
+        } else {
+            &self.base_sha
+        }
+    }
+}
+
+/// A pull request as given on the command line; a bare number leaves the host and
+/// repository to the configuration and the current directory.
+#[derive(Debug, PartialEq)]
+struct PrInput {
+    host: Option<String>,
+    owner: Option<String>,
+    repo: Option<String>,
+    number: u64,
 }
 
-fn parse_pr_input(input: &str) -> Option<(Option<String>, Option<String>, u64)> {
+fn parse_pr_input(input: &str) -> Option<PrInput> {
     // Try to parse as a URL first
-    if input.starts_with("http://") || input.starts_with("https://") {
+    if let Some(rest) = input
+        .strip_prefix("http://")
+        .or_else(|| input.strip_prefix("https://"))
+    {
         // Extract PR number and repo info from URL
-        // Format: https://github.com/owner/repo/pull/123
-        let parts: Vec<&str> = input.trim_end_matches('/').split('/').collect();
-        if parts.len() >= 2 {
-            if let Some(pos) = parts.iter().position(|&p| p == "pull") {
-                if pos + 1 < parts.len() {
-                    if let Ok(num) = parts[pos + 1].parse::<u64>() {
-                        // Extract owner and repo
-                        if pos >= 2 {
-                            let owner = parts[pos - 2].to_string();
-                            let repo = parts[pos - 1].to_string();
-                            return Some((Some(owner), Some(repo), num));
-                        }
-                        return Some((None, None, num));
-                    }
-                }
-            }
-        }
-        None
+        // Format: https://<host>/owner/repo/pull/123, on github.com or an Enterprise server
+        let parts: Vec<&str> = rest.trim_end_matches('/').split('/').collect();
+        let pos = parts.iter().position(|&p| p == "pull")?;
+        let number = parts.get(pos + 1)?.parse::<u64>().ok()?;
+        let host = Some(parts[0].to_string()).filter(|h| !h.is_empty());
+        // Extract owner and repo
+        let (owner, repo) = if pos >= 3 {
+            (
+                Some(parts[pos - 2].to_string()),
+                Some(parts[pos - 1].to_string()),
+            )
+        } else {
+            (None, None)
+        };
+        Some(PrInput {
+            host,
+            owner,
+            repo,
+            number,
+        })
     } else {
         // Try to parse as a PR number
-        input.parse::<u64>().ok().map(|num| (None, None, num))
+        input.parse::<u64>().ok().map(|number| PrInput {
+            host: None,
+            owner: None,
+            repo: None,
+            number,
+        })
     }
 }
 
-fn fetch_pr_info(pr_input: &str) -> Result<PrInfo, String> {
-    let (owner, repo, number) = parse_pr_input(pr_input).ok_or_else(|| {
+/// GitHub host for requests that don't name one: the `github_host` setting, then
+/// `GH_HOST` as `gh` itself reads it, then github.com.
+fn default_github_host(configured: Option<&str>) -> String {
+    configured
+        .map(str::to_string)
+        .or_else(|| std::env::var("GH_HOST").ok().filter(|h| !h.is_empty()))
+        .unwrap_or_else(|| "github.com".to_string())
+}
+
+/// `gh` pointed at `host`, so Enterprise requests don't go to github.com.
+pub(crate) fn gh_command(host: &str) -> Command {
+    let mut command = child_command("gh");
+    command.env("GH_HOST", host);
+    command
+}
+
+fn fetch_pr_info(pr_input: &str, default_host: Option<&str>) -> Result<PrInfo, String> {
+    let PrInput {
+        host,
+        owner,
+        repo,
+        number,
+    } = parse_pr_input(pr_input).ok_or_else(|| {
         format!(
             "Invalid PR reference: {}. Use a PR number or URL.",
             pr_input
         )
     })?;
+    let host = host.unwrap_or_else(|| default_github_host(default_host));
 
     // Build gh command with repo if available
     let repo_arg = match (&owner, &repo) {
@@ -90,27 +268,7 @@ fn fetch_pr_info(pr_input: &str) -> Result<PrInfo, String> {
     // Get PR URL, repo info, and node ID via GraphQL (more reliable for node_id)
     let repo_full = match &repo_arg {
         Some(r) => r.clone(),
-        None => {
-            // Get repo from current directory
-            let output = Command::new("gh")
-                .args([
-                    "repo",
-                    "view",
-                    "--json",
-                    "nameWithOwner",
-                    "-q",
-                    ".nameWithOwner",
-                ])
-                .output()
-                .map_err(|e| format!("Failed to get current repo: {}", e))?;
-            if !output.status.success() {
-                return Err(
-                    "Could not determine repository. Run from a git repo or specify --repo"
-                        .to_string(),
-                );
-            }
-            String::from_utf8_lossy(&output.stdout).trim().to_string()
-        }
+        None => current_github_repo(&host)?,
     };
 
     let (repo_owner, repo_name) = {
@@ -126,11 +284,11 @@ fn fetch_pr_info(pr_input: &str) -> Result<PrInfo, String> {
 
     // Use GraphQL to get the PR node ID, branch refs, and repo owners
     let query = format!(
-        r#"query {{ repository(owner: "{}", name: "{}") {{ pullRequest(number: {}) {{ id url baseRefName headRefName baseRepository {{ owner {{ login }} }} headRepository {{ owner {{ login }} }} }} }} }}"#,
+        r#"query {{ repository(owner: "{}", name: "{}") {{ pullRequest(number: {}) {{ id url baseRefName headRefName headRefOid baseRefOid baseRepository {{ owner {{ login }} }} headRepository {{ owner {{ login }} }} title state mergeable author {{ login }} }} }} }}"#,
         repo_owner, repo_name, number
     );
 
-    let output = Command::new("gh")
+    let output = gh_command(&host)
         .args(["api", "graphql", "-f", &format!("query={}", query)])
         .output()
         .map_err(|e| format!("Failed to run gh api graphql: {}", e))?;
@@ -149,24 +307,264 @@ fn fetch_pr_info(pr_input: &str) -> Result<PrInfo, String> {
         extract_json_string(&json_str, "baseRefName").unwrap_or_else(|| "base".to_string());
     let head_ref =
         extract_json_string(&json_str, "headRefName").unwrap_or_else(|| "head".to_string());
+    let head_sha = extract_json_string(&json_str, "headRefOid").unwrap_or_default();
+    let base_sha = extract_json_string(&json_str, "baseRefOid").unwrap_or_default();
 
     // Extract repo owners from nested structure
     let base_repo_owner =
         extract_nested_login(&json_str, "baseRepository").unwrap_or_else(|| repo_owner.clone());
     let head_repo_owner = extract_nested_login(&json_str, "headRepository");
 
+    let title = extract_json_string(&json_str, "title").unwrap_or_default();
+    let author = extract_nested_login(&json_str, "author");
+    let state = extract_json_string(&json_str, "state").unwrap_or_else(|| "UNKNOWN".to_string());
+    let mergeable =
+        extract_json_string(&json_str, "mergeable").unwrap_or_else(|| "UNKNOWN".to_string());
+
     Ok(PrInfo {
+        forge: Forge::GitHub,
+        host,
         number,
         node_id,
         repo_owner,
         repo_name,
         base_ref,
         head_ref,
+        head_sha,
+        base_sha,
         base_repo_owner,
         head_repo_owner,
+        title,
+        author,
+        state,
+        mergeable,
+        behind_by: None,
     })
 }
 
+/// Commits on the PR's base branch that its head lacks, from GitHub's compare API.
+fn fetch_pr_behind_by(pr_info: &PrInfo) -> Result<usize, String> {
+    let head_owner = pr_info
+        .head_repo_owner
+        .as_ref()
+        .ok_or_else(|| "head repository was deleted".to_string())?;
+    let api_path = format!(
+        "repos/{}/{}/compare/{}...{}:{}",
+        pr_info.base_repo_owner, pr_info.repo_name, pr_info.base_ref, head_owner, pr_info.head_ref
+    );
+    let output = gh_command(&pr_info.host)
+        .args(["api", &api_path, "--jq", ".behind_by"])
+        .output()
+        .map_err(|e| format!("Failed to run gh api: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("gh api compare failed: {}", stderr.trim()));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| "Could not parse behind_by from compare response".to_string())
+}
+
+/// An open pull request authored by the current `gh` user.
+struct OwnPr {
+    number: u64,
+    head_ref: String,
+    title: String,
+}
+
+impl std::fmt::Display for OwnPr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} {} ({})", self.number, self.title, self.head_ref)
+    }
+}
+
+/// Parses `number<TAB>head<TAB>title` lines from `gh pr list`.
+fn parse_own_prs(output: &str) -> Vec<OwnPr> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let number = fields.next()?.trim().parse().ok()?;
+            let head_ref = fields.next()?.to_string();
+            let title = fields.next().unwrap_or_default().to_string();
+            Some(OwnPr {
+                number,
+                head_ref,
+                title,
+            })
+        })
+        .collect()
+}
+
+/// Open pull requests you authored in the current repository, newest first.
+fn list_own_open_prs(host: &str) -> Result<Vec<OwnPr>, String> {
+    let output = gh_command(host)
+        .args([
+            "pr",
+            "list",
+            "--author",
+            "@me",
+            "--state",
+            "open",
+            "--json",
+            "number,headRefName,title",
+            "-q",
+            r#".[] | "\(.number)\t\(.headRefName)\t\(.title)""#,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run gh pr list: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("gh pr list failed: {}", stderr.trim()));
+    }
+    Ok(parse_own_prs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// PR number to review for `--my-pr`: the only open PR you authored, or the one picked
+/// from a list in the viewer's select modal when there are several. `github_host` is
+/// the configured host, as for PRs given by number; `theme` styles the list.
+pub fn pick_own_pr(github_host: Option<&str>, theme: Option<&str>) -> Result<String, String> {
+    let prs = list_own_open_prs(&default_github_host(github_host))?;
+    let index = match prs.len() {
+        0 => return Err("You have no open pull requests in this repository".to_string()),
+        1 => 0,
+        _ => {
+            let items = prs.iter().map(ToString::to_string).collect();
+            app::pick_in_modal("Which of your pull requests?", items, theme)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "No pull request selected".to_string())?
+        }
+    };
+    Ok(prs[index].number.to_string())
+}
+
+/// A comment in your own not-yet-submitted review of a pull request.
+#[derive(Clone)]
+pub struct PendingReviewComment {
+    pub path: String,
+    /// Line number on the side the comment is attached to
+    pub line: usize,
+    /// Attached to the old (LEFT) side rather than the new one
+    pub old_side: bool,
+    pub body: String,
+    pub author: String,
+}
+
+/// Comments in the current user's pending review of `pr_info`, so drafts made with
+/// `gh` or the web UI show up in the viewer.
+pub fn fetch_pending_review_comments(
+    pr_info: &PrInfo,
+) -> Result<Vec<PendingReviewComment>, String> {
+    let query = format!(
+        r#"query {{ repository(owner: "{}", name: "{}") {{ pullRequest(number: {}) {{ reviews(states: PENDING, first: 10) {{ nodes {{ viewerDidAuthor author {{ login }} comments(first: 100) {{ nodes {{ path line originalLine diffSide body }} }} }} }} }} }} }}"#,
+        pr_info.repo_owner, pr_info.repo_name, pr_info.number
+    );
+
+    let output = gh_command(&pr_info.host)
+        .args(["api", "graphql", "-f", &format!("query={}", query)])
+        .output()
+        .map_err(|e| format!("Failed to run gh api graphql: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("gh api graphql failed: {}", stderr.trim()));
+    }
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+    Ok(parse_pending_review_comments(&json))
+}
+
+fn parse_pending_review_comments(json: &serde_json::Value) -> Vec<PendingReviewComment> {
+    let reviews = json["data"]["repository"]["pullRequest"]["reviews"]["nodes"].as_array();
+    reviews
+        .into_iter()
+        .flatten()
+        .filter(|review| review["viewerDidAuthor"].as_bool() == Some(true))
+        .flat_map(|review| {
+            let author = review["author"]["login"].as_str().unwrap_or("you").to_string();
+            let comments = review["comments"]["nodes"].as_array().cloned().unwrap_or_default();
+            comments.into_iter().filter_map(move |comment| {
+                // Outdated comments lose `line` but keep where they were written
+                let line = comment["line"]
+                    .as_u64()
+                    .or_else(|| comment["originalLine"].as_u64())?;
+                Some(PendingReviewComment {
+                    path: comment["path"].as_str()?.to_string(),
+                    line: line as usize,
+                    old_side: comment["diffSide"].as_str() == Some("LEFT"),
+                    body: comment["body"].as_str().unwrap_or_default().to_string(),
+                    author: author.clone(),
+                })
+            })
+        })
+        .collect()
+}
+
+/// `owner/name` of the GitHub repository for the current directory, via `gh`.
+fn current_github_repo(host: &str) -> Result<String, String> {
+    let output = gh_command(host)
+        .args([
+            "repo",
+            "view",
+            "--json",
+            "nameWithOwner",
+            "-q",
+            ".nameWithOwner",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to get current repo: {}", e))?;
+    if !output.status.success() {
+        return Err(
+            "Could not determine repository. Run from a git repo or specify --repo".to_string(),
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// GitHub blame URL for `path` at `reference`, anchored at `line`.
+fn blame_permalink(
+    host: &str,
+    repo_full: &str,
+    reference: &str,
+    path: &str,
+    line: usize,
+) -> String {
+    format!(
+        "https://{}/{}/blame/{}/{}#L{}",
+        host, repo_full, reference, path, line
+    )
+}
+
+/// Blame permalink for an old-side line of `path`.
+/// PRs link against the base repository at the base commit; local diffs against the
+/// resolved old-side commit, which must exist on GitHub for the link to work.
+pub fn old_side_blame_permalink(
+    options: &DiffOptions,
+    pr_info: Option<&PrInfo>,
+    backend: &dyn VcsBackend,
+    path: &str,
+    line: usize,
+) -> Result<String, String> {
+    if let Some(pr) = pr_info {
+        return Ok(pr.forge.backend().blame_url(pr, path, line));
+    }
+
+    let old_ref = git::old_side_ref(options, backend);
+    if old_ref.is_empty() {
+        return Err("root commit has no old side to blame".to_string());
+    }
+    let sha = backend
+        .resolve_ref(&old_ref)
+        .map_err(|e| format!("cannot resolve {}: {}", old_ref, e))?;
+    let host = default_github_host(options.github_host.as_deref());
+    let repo_full = current_github_repo(&host)?;
+    Ok(blame_permalink(&host, &repo_full, &sha, path, line))
+}
+
 fn extract_json_string(json: &str, key: &str) -> Option<String> {
     let pattern = format!("\"{}\":\"", key);
     if let Some(start) = json.find(&pattern) {
@@ -200,31 +598,60 @@ fn extract_nested_login(json: &str, parent_key: &str) -> Option<String> {
     None
 }
 
-/// Fetch the list of files that are marked as viewed on GitHub
-pub fn fetch_viewed_files(pr_info: &PrInfo) -> Result<HashSet<String>, String> {
-    let query = format!(
-        r#"query {{ repository(owner: "{}", name: "{}") {{ pullRequest(number: {}) {{ files(first: 100) {{ nodes {{ path viewerViewedState }} }} }} }} }}"#,
-        pr_info.repo_owner, pr_info.repo_name, pr_info.number
-    );
+/// Fetch the list of files that are marked as viewed on GitHub,
+/// paging through the PR's files up to `max_files`.
+pub fn fetch_viewed_files(pr_info: &PrInfo, max_files: usize) -> Result<HashSet<String>, String> {
+    let mut viewed_files = HashSet::new();
+    let mut cursor: Option<String> = None;
+    let mut fetched = 0;
+
+    while fetched < max_files {
+        // GitHub caps a page at 100 nodes
+        let page_size = (max_files - fetched).min(100);
+        let after = cursor
+            .as_ref()
+            .map(|c| format!(r#", after: "{}""#, c))
+            .unwrap_or_default();
+        let query = format!(
+            r#"query {{ repository(owner: "{}", name: "{}") {{ pullRequest(number: {}) {{ files(first: {}{}) {{ pageInfo {{ hasNextPage endCursor }} nodes {{ path viewerViewedState }} }} }} }} }}"#,
+            pr_info.repo_owner, pr_info.repo_name, pr_info.number, page_size, after
+        );
+
+        let output = gh_command(&pr_info.host)
+            .args(["api", "graphql", "-f", &format!("query={}", query)])
+            .output()
+            .map_err(|e| format!("Failed to run gh api graphql: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("gh api graphql failed: {}", stderr.trim()));
+        }
 
-    let output = Command::new("gh")
-        .args(["api", "graphql", "-f", &format!("query={}", query)])
-        .output()
-        .map_err(|e| format!("Failed to run gh api graphql: {}", e))?;
+        let json_str = String::from_utf8_lossy(&output.stdout);
+        collect_viewed_paths(&json_str, &mut viewed_files);
+        fetched += page_size;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("gh api graphql failed: {}", stderr.trim()));
+        match next_page_cursor(&json_str) {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
     }
 
-    let json_str = String::from_utf8_lossy(&output.stdout);
+    Ok(viewed_files)
+}
 
-    // Parse the response to find viewed files
-    // Look for patterns like: "path":"filename","viewerViewedState":"VIEWED"
-    let mut viewed_files = HashSet::new();
+/// The `endCursor` to continue from, or `None` on the last page.
+fn next_page_cursor(json: &str) -> Option<String> {
+    if !json.contains("\"hasNextPage\":true") {
+        return None;
+    }
+    extract_json_string(json, "endCursor")
+}
 
-    // Simple parsing: find all path/viewerViewedState pairs
-    let mut remaining = json_str.as_ref();
+/// Add the paths a files page reports as viewed to `viewed_files`.
+fn collect_viewed_paths(json_str: &str, viewed_files: &mut HashSet<String>) {
+    // Look for patterns like: "path":"filename","viewerViewedState":"VIEWED"
+    let mut remaining = json_str;
     while let Some(path_start) = remaining.find("\"path\":\"") {
         let path_value_start = path_start + 8;
         let after_path = &remaining[path_value_start..];
@@ -249,38 +676,38 @@ pub fn fetch_viewed_files(pr_info: &PrInfo) -> Result<HashSet<String>, String> {
             break;
         }
     }
-
-    Ok(viewed_files)
 }
 
 /// Mark a file as viewed on GitHub PR (non-blocking, spawns a thread)
 pub fn mark_file_as_viewed_async(pr_info: &PrInfo, file_path: &str) {
+    let host = pr_info.host.clone();
     let node_id = pr_info.node_id.clone();
     let path = file_path.to_string();
 
     thread::spawn(move || {
-        let _ = mark_file_as_viewed_sync(&node_id, &path);
+        let _ = mark_file_as_viewed_sync(&host, &node_id, &path);
     });
 }
 
 /// Unmark a file as viewed on GitHub PR (non-blocking, spawns a thread)
 pub fn unmark_file_as_viewed_async(pr_info: &PrInfo, file_path: &str) {
+    let host = pr_info.host.clone();
     let node_id = pr_info.node_id.clone();
     let path = file_path.to_string();
 
     thread::spawn(move || {
-        let _ = unmark_file_as_viewed_sync(&node_id, &path);
+        let _ = unmark_file_as_viewed_sync(&host, &node_id, &path);
     });
 }
 
 /// Mark a file as viewed on GitHub PR (blocking)
-fn mark_file_as_viewed_sync(node_id: &str, file_path: &str) -> Result<(), String> {
+fn mark_file_as_viewed_sync(host: &str, node_id: &str, file_path: &str) -> Result<(), String> {
     let mutation = format!(
         r#"mutation {{ markFileAsViewed(input: {{ pullRequestId: "{}", path: "{}" }}) {{ clientMutationId }} }}"#,
         node_id, file_path
     );
 
-    let output = Command::new("gh")
+    let output = gh_command(host)
         .args(["api", "graphql", "-f", &format!("query={}", mutation)])
         .output()
         .map_err(|e| format!("Failed to run gh api graphql: {}", e))?;
@@ -294,13 +721,13 @@ fn mark_file_as_viewed_sync(node_id: &str, file_path: &str) -> Result<(), String
 }
 
 /// Unmark a file as viewed on GitHub PR (blocking)
-fn unmark_file_as_viewed_sync(node_id: &str, file_path: &str) -> Result<(), String> {
+fn unmark_file_as_viewed_sync(host: &str, node_id: &str, file_path: &str) -> Result<(), String> {
     let mutation = format!(
         r#"mutation {{ unmarkFileAsViewed(input: {{ pullRequestId: "{}", path: "{}" }}) {{ clientMutationId }} }}"#,
         node_id, file_path
     );
 
-    let output = Command::new("gh")
+    let output = gh_command(host)
         .args(["api", "graphql", "-f", &format!("query={}", mutation)])
         .output()
         .map_err(|e| format!("Failed to run gh api graphql: {}", e))?;
@@ -313,57 +740,122 @@ fn unmark_file_as_viewed_sync(node_id: &str, file_path: &str) -> Result<(), Stri
     Ok(())
 }
 
+/// Write the requested diff as plain text, for nested invocations inside a running TUI.
+fn print_plain_diff(options: &DiffOptions, backend: &dyn VcsBackend) -> io::Result<()> {
+    if options.pr.is_some() {
+        eprintln!("lumen is already running; skipping nested PR view");
+        return Ok(());
+    }
+
+    if options.against.is_some() {
+        eprintln!("lumen is already running; skipping nested --against view");
+        return Ok(());
+    }
+
+    if options.history.is_some() {
+        eprintln!("lumen is already running; skipping nested --history view");
+        return Ok(());
+    }
+
+    let diff = match &options.reference {
+        None => backend.get_working_tree_diff(false),
+        Some(CommitReference::Single(reference)) => {
+            backend.get_commit(reference).map(|info| info.diff)
+        }
+        Some(CommitReference::Range { from, to }) => backend.get_range_diff(from, to, false),
+        Some(CommitReference::TripleDots { from, to }) => backend.get_range_diff(from, to, true),
+    }
+    .map_err(io::Error::other)?;
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(diff.as_bytes())?;
+    stdout.flush()
+}
+
+/// Fetch the request's metadata from its host, then open it in the viewer.
+fn run_pr(
+    options: DiffOptions,
+    pr_input: &str,
+    forge: Forge,
+    backend: &dyn VcsBackend,
+) -> io::Result<()> {
+    let spinner_msg = match (forge, parse_pr_input(pr_input)) {
+        (
+            Forge::GitHub,
+            Some(PrInput {
+                owner: Some(owner),
+                repo: Some(repo),
+                number,
+                ..
+            }),
+        ) => {
+            format!("Fetching PR {}/{}#{}", owner, repo, number)
+        }
+        (Forge::GitHub, Some(PrInput { number, .. })) => {
+            format!("Fetching PR #{}", number)
+        }
+        (forge, _) => format!("Fetching {} {}", forge.noun(), pr_input),
+    };
+    let mut spinner = Spinner::new(spinners::Dots, spinner_msg, Color::Cyan);
+    match forge
+        .backend()
+        .fetch_info(pr_input, options.github_host.as_deref())
+    {
+        Ok(mut pr_info) => {
+            // Only informational, so a failed comparison leaves it unknown
+            pr_info.behind_by = forge.backend().fetch_behind_by(&pr_info).ok();
+            spinner.success(&format!("Fetched {} metadata", forge.noun()));
+            app::run_app_with_pr(options, pr_info, backend)
+        }
+        Err(e) => {
+            spinner.fail(&e);
+            process::exit(1);
+        }
+    }
+}
+
 pub fn run_diff_ui(options: DiffOptions, backend: &dyn VcsBackend) -> io::Result<()> {
+    if std::env::var_os(ACTIVE_ENV).is_some() {
+        return print_plain_diff(&options, backend);
+    }
+
     // Handle PR mode
-    if let Some(ref pr_input) = options.pr {
-        let spinner_msg = match parse_pr_input(pr_input) {
-            Some((Some(owner), Some(repo), number)) => {
-                format!("Fetching PR {}/{}#{}", owner, repo, number)
-            }
-            Some((_, _, number)) => {
-                format!("Fetching PR #{}", number)
-            }
-            None => "Fetching PR".to_string(),
-        };
-        let mut spinner = Spinner::new(spinners::Dots, spinner_msg, Color::Cyan);
-        match fetch_pr_info(pr_input) {
-            Ok(pr_info) => {
-                spinner.success("Fetched PR metadata");
-                return app::run_app_with_pr(options, pr_info, backend);
-            }
-            Err(e) => {
-                spinner.fail(&e);
-                process::exit(1);
-            }
-        }
+    if let Some(pr_input) = options.pr.clone() {
+        let forge = Forge::detect(&pr_input, options.pr_forge);
+        return run_pr(options, &pr_input, forge, backend);
     }
 
-    // Also check if the reference looks like a PR (number or URL)
+    // Also check if the reference looks like a PR or MR (number or URL)
     if let Some(CommitReference::Single(ref input)) = options.reference {
-        if input.contains("/pull/") || input.parse::<u64>().is_ok() {
-            let spinner_msg = match parse_pr_input(input) {
-                Some((Some(owner), Some(repo), number)) => {
-                    format!("Fetching PR {}/{}#{}", owner, repo, number)
-                }
-                Some((_, _, number)) => {
-                    format!("Fetching PR #{}", number)
-                }
-                None => "Fetching PR".to_string(),
-            };
-            let mut spinner = Spinner::new(spinners::Dots, spinner_msg, Color::Cyan);
-            match fetch_pr_info(input) {
-                Ok(pr_info) => {
-                    spinner.success("Fetched PR metadata");
-                    return app::run_app_with_pr(options, pr_info, backend);
-                }
-                Err(e) => {
-                    spinner.fail(&e);
-                    process::exit(1);
-                }
-            }
+        if input.contains("/pull/")
+            || input.contains("/-/merge_requests/")
+            || input.parse::<u64>().is_ok()
+        {
+            let input = input.clone();
+            let forge = Forge::detect(&input, None);
+            return run_pr(options, &input, forge, backend);
         }
     }
 
+    // A file's history walks like a stack, one commit at a time
+    if let Some(ref path) = options.history {
+        let commits = match backend.get_file_history(Path::new(path)) {
+            Ok(c) if c.is_empty() => {
+                eprintln!(
+                    "\x1b[91merror:\x1b[0m No history found for {} (paths are relative to the repository root)",
+                    path
+                );
+                process::exit(1);
+            }
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("\x1b[91merror:\x1b[0m {}", e);
+                process::exit(1);
+            }
+        };
+        return app::run_app_stacked(options, commits, backend);
+    }
+
     // Handle stacked mode for range references
     if options.stacked {
         if let Some(ref reference) = options.reference {
@@ -408,3 +900,83 @@ pub fn run_diff_ui(options: DiffOptions, backend: &dyn VcsBackend) -> io::Result
 
     app::run_app(options, None, backend)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_commands_carry_the_active_marker() {
+        let command = gh_command("github.com");
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&(OsStr::new(ACTIVE_ENV), Some(OsStr::new("1")))));
+        assert!(envs.contains(&(OsStr::new("GH_HOST"), Some(OsStr::new("github.com")))));
+    }
+
+    #[test]
+    fn test_parse_pending_review_comments() {
+        let json = serde_json::json!({ "data": { "repository": { "pullRequest": { "reviews": { "nodes": [
+            {
+                "viewerDidAuthor": true,
+                "author": { "login": "ann" },
+                "comments": { "nodes": [
+                    { "path": "src/a.rs", "line": 12, "originalLine": 10, "diffSide": "RIGHT", "body": "typo" },
+                    { "path": "src/b.rs", "line": null, "originalLine": 4, "diffSide": "LEFT", "body": "why?" }
+                ] }
+            },
+            {
+                "viewerDidAuthor": false,
+                "author": { "login": "bob" },
+                "comments": { "nodes": [{ "path": "src/a.rs", "line": 1, "diffSide": "RIGHT", "body": "x" }] }
+            }
+        ] } } } } });
+        let comments = parse_pending_review_comments(&json);
+
+        assert_eq!(comments.len(), 2);
+        assert_eq!((comments[0].line, comments[0].old_side), (12, false));
+        assert_eq!(comments[0].author, "ann");
+        assert_eq!((comments[1].line, comments[1].old_side), (4, true));
+    }
+
+    #[test]
+    fn test_parse_pr_input_keeps_enterprise_host() {
+        let pr = parse_pr_input("https://github.example.com/acme/app/pull/42/files").unwrap();
+        assert_eq!(pr.host.as_deref(), Some("github.example.com"));
+        assert_eq!(
+            (pr.owner.as_deref(), pr.repo.as_deref(), pr.number),
+            (Some("acme"), Some("app"), 42)
+        );
+
+        let bare = parse_pr_input("42").unwrap();
+        assert_eq!((bare.host, bare.number), (None, 42));
+        assert_eq!(
+            parse_pr_input("https://github.com/acme/app/issues/42"),
+            None
+        );
+        assert_eq!(
+            blame_permalink("github.example.com", "acme/app", "main", "a.rs", 3),
+            "https://github.example.com/acme/app/blame/main/a.rs#L3"
+        );
+    }
+
+    #[test]
+    fn test_parse_own_prs() {
+        let prs = parse_own_prs("42\tfix-login\tFix login\tredirect\n7\tdocs\t\nnot-a-pr\n");
+        assert_eq!(prs.len(), 2);
+        assert_eq!(prs[0].number, 42);
+        assert_eq!(prs[0].title, "Fix login\tredirect");
+        assert_eq!(prs[1].to_string(), "#7  (docs)");
+    }
+
+    #[test]
+    fn test_viewed_files_page_parsing() {
+        let page = r#"{"data":{"repository":{"pullRequest":{"files":{"pageInfo":{"hasNextPage":true,"endCursor":"Y3Vyc29yOjEwMA=="},"nodes":[{"path":"a.rs","viewerViewedState":"VIEWED"},{"path":"b.rs","viewerViewedState":"UNVIEWED"}]}}}}}"#;
+        let mut viewed = HashSet::new();
+        collect_viewed_paths(page, &mut viewed);
+        assert_eq!(viewed, HashSet::from(["a.rs".to_string()]));
+        assert_eq!(next_page_cursor(page), Some("Y3Vyc29yOjEwMA==".to_string()));
+
+        let last = page.replace("\"hasNextPage\":true", "\"hasNextPage\":false");
+        assert_eq!(next_page_cursor(&last), None);
+    }
+}
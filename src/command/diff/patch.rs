@@ -0,0 +1,299 @@
+//! The loaded diff as a patch that `git apply` accepts, for `lumen diff --export-patch`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use similar::TextDiff;
+
+use super::git::COMMIT_MESSAGE_FILE;
+use super::types::{ChangeType, DiffLine, FileDiff, FileStatus};
+
+/// Lines of unchanged context around each hunk, as `git diff` writes by default.
+const CONTEXT: usize = 3;
+
+/// Mode assumed for files whose source doesn't record one: a regular file.
+const REGULAR_FILE_MODE: u32 = 0o100644;
+
+/// Binary files are left out: without blob ids `git apply` cannot rebuild them.
+pub struct PatchExport {
+    pub files: usize,
+    pub skipped_binary: Vec<String>,
+}
+
+/// One file's `diff --git` section. None when there is no change to apply:
+/// binary files, identical contents and modes, and the amend commit message.
+pub fn file_patch(diff: &FileDiff) -> Option<String> {
+    if diff.is_binary
+        || diff.filename == COMMIT_MESSAGE_FILE
+        || (diff.old_content == diff.new_content && mode_change(diff).is_none())
+    {
+        return None;
+    }
+
+    let (mode_line, old_path, new_path) = patch_paths(diff);
+    let hunks = TextDiff::from_lines(&diff.old_content, &diff.new_content)
+        .unified_diff()
+        .context_radius(CONTEXT)
+        .header(&old_path, &new_path)
+        .to_string();
+    Some(format!(
+        "diff --git a/{} b/{}\n{}{}",
+        diff.filename, diff.filename, mode_line, hunks
+    ))
+}
+
+/// The mode lines and the `---`/`+++` paths for a file's patch header.
+fn patch_paths(diff: &FileDiff) -> (String, String, String) {
+    let name = &diff.filename;
+    let mode = |mode: Option<u32>| mode.unwrap_or(REGULAR_FILE_MODE);
+    match diff.status {
+        FileStatus::Added => (
+            format!("new file mode {:o}\n", mode(diff.new_mode)),
+            "/dev/null".to_string(),
+            format!("b/{}", name),
+        ),
+        FileStatus::Deleted => (
+            format!("deleted file mode {:o}\n", mode(diff.old_mode)),
+            format!("a/{}", name),
+            "/dev/null".to_string(),
+        ),
+        FileStatus::Modified => (
+            mode_change(diff)
+                .map(|(old, new)| format!("old mode {:o}\nnew mode {:o}\n", old, new))
+                .unwrap_or_default(),
+            format!("a/{}", name),
+            format!("b/{}", name),
+        ),
+    }
+}
+
+/// The old and new modes of a file whose mode changed, e.g. on `chmod +x`.
+fn mode_change(diff: &FileDiff) -> Option<(u32, u32)> {
+    match (diff.old_mode, diff.new_mode) {
+        (Some(old), Some(new)) if old != new => Some((old, new)),
+        _ => None,
+    }
+}
+
+/// The hunk of `rows` (the file's side-by-side diff) starting at row `start`, as a
+/// patch of its own: file header, `@@` line, then the changed lines with up to three
+/// lines of context either side. Lines come from the file contents, so tabs survive.
+pub fn hunk_patch(diff: &FileDiff, rows: &[DiffLine], start: usize) -> Option<String> {
+    if diff.is_binary || !rows.get(start)?.change_type.is_change() {
+        return None;
+    }
+    let end = start
+        + rows[start..]
+            .iter()
+            .take_while(|row| row.change_type.is_change())
+            .count();
+    let is_equal = |row: &&DiffLine| matches!(row.change_type, ChangeType::Equal);
+    let before: Vec<&DiffLine> = rows[..start]
+        .iter()
+        .rev()
+        .take_while(is_equal)
+        .take(CONTEXT)
+        .collect();
+    let after = rows[end..].iter().take_while(is_equal).take(CONTEXT);
+
+    // (sign, line number) per patch line; context lines are numbered on the old side
+    let old_num = |row: &DiffLine| row.old_line.as_ref().map(|(num, _)| *num);
+    let new_num = |row: &DiffLine| row.new_line.as_ref().map(|(num, _)| *num);
+    let changes = &rows[start..end];
+    // Like git, a run of changes lists its removals before its additions
+    let removed = changes
+        .iter()
+        .filter_map(|row| old_num(row).map(|num| ('-', num)));
+    let added = changes
+        .iter()
+        .filter_map(|row| new_num(row).map(|num| ('+', num)));
+    let entries: Vec<(char, usize)> = before
+        .iter()
+        .rev()
+        .copied()
+        .filter_map(|row| old_num(row).map(|num| (' ', num)))
+        .chain(removed)
+        .chain(added)
+        .chain(after.filter_map(|row| old_num(row).map(|num| (' ', num))))
+        .collect();
+
+    let old_lines = patch_lines(&diff.old_content);
+    let new_lines = patch_lines(&diff.new_content);
+    let mut body = String::new();
+    for &(sign, num) in &entries {
+        let (lines, content) = if sign == '+' {
+            (&new_lines, &diff.new_content)
+        } else {
+            (&old_lines, &diff.old_content)
+        };
+        body.push(sign);
+        body.push_str(lines.get(num - 1).copied().unwrap_or(""));
+        body.push('\n');
+        if num == lines.len() && !content.ends_with('\n') {
+            body.push_str("\\ No newline at end of file\n");
+        }
+    }
+
+    // A side's range starts at its first line in the hunk; an empty side names the
+    // line just before the hunk (0 at the top of the file)
+    let range = |count: usize, num: &dyn Fn(&DiffLine) -> Option<usize>| {
+        let hunk_rows = &rows[start - before.len()..end];
+        let first = hunk_rows.iter().find_map(num).filter(|_| count > 0);
+        let start = first.or_else(|| rows[..start].iter().rev().find_map(num));
+        format!("{},{}", start.unwrap_or(0), count)
+    };
+    let old_count = entries.iter().filter(|(sign, _)| *sign != '+').count();
+    let new_count = entries.iter().filter(|(sign, _)| *sign != '-').count();
+    let old_range = range(old_count, &old_num);
+    let new_range = range(new_count, &new_num);
+
+    let (mode_line, old_path, new_path) = patch_paths(diff);
+    Some(format!(
+        "diff --git a/{name} b/{name}\n{mode_line}--- {old_path}\n+++ {new_path}\n@@ -{old_range} +{new_range} @@\n{body}",
+        name = diff.filename,
+    ))
+}
+
+/// The lines of `text` without their '\n'. Unlike `str::lines` a CRLF file keeps
+/// its '\r', which the patch needs to apply.
+fn patch_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n')
+        .map(|line| line.strip_suffix('\n').unwrap_or(line))
+        .collect()
+}
+
+/// Every file of the diff, in order, as one patch.
+pub fn patch_text(file_diffs: &[FileDiff]) -> (String, PatchExport) {
+    let mut text = String::new();
+    let mut export = PatchExport {
+        files: 0,
+        skipped_binary: Vec::new(),
+    };
+    for diff in file_diffs {
+        if diff.is_binary {
+            export.skipped_binary.push(diff.filename.clone());
+        } else if let Some(patch) = file_patch(diff) {
+            text.push_str(&patch);
+            export.files += 1;
+        }
+    }
+    (text, export)
+}
+
+pub fn write_patch(path: &Path, file_diffs: &[FileDiff]) -> io::Result<PatchExport> {
+    let (text, export) = patch_text(file_diffs);
+    fs::write(path, text)?;
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(filename: &str, old: &str, new: &str, status: FileStatus) -> FileDiff {
+        FileDiff {
+            filename: filename.to_string(),
+            old_content: old.to_string(),
+            new_content: new.to_string(),
+            status,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        }
+    }
+
+    #[test]
+    fn test_patch_text_covers_every_file() {
+        let diffs = vec![
+            file("src/a.rs", "one\ntwo\n", "one\n2\n", FileStatus::Modified),
+            file("new.txt", "", "hello", FileStatus::Added),
+            file("old.txt", "bye\n", "", FileStatus::Deleted),
+            file("same.txt", "x\n", "x\n", FileStatus::Modified),
+        ];
+        let (text, export) = patch_text(&diffs);
+
+        assert_eq!(export.files, 3);
+        assert!(text.starts_with(
+            "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,2 +1,2 @@\n one\n-two\n+2\n"
+        ));
+        assert!(text.contains(
+            "diff --git a/new.txt b/new.txt\nnew file mode 100644\n--- /dev/null\n+++ b/new.txt\n"
+        ));
+        assert!(text.contains("+hello\n\\ No newline at end of file\n"));
+        assert!(text.contains("deleted file mode 100644\n--- a/old.txt\n+++ /dev/null\n"));
+        assert!(!text.contains("same.txt"));
+    }
+
+    #[test]
+    fn test_patch_keeps_file_modes() {
+        let mut script = file("run.sh", "", "echo hi\n", FileStatus::Added);
+        script.new_mode = Some(0o100755);
+        let mut link = file("latest", "", "releases/v2", FileStatus::Added);
+        link.new_mode = Some(0o120000);
+        let mut chmod = file("build.sh", "make\n", "make\n", FileStatus::Modified);
+        chmod.old_mode = Some(0o100644);
+        chmod.new_mode = Some(0o100755);
+        let (text, export) = patch_text(&[script, link, chmod]);
+
+        assert_eq!(export.files, 3);
+        assert!(text.contains("diff --git a/run.sh b/run.sh\nnew file mode 100755\n"));
+        assert!(text.contains("diff --git a/latest b/latest\nnew file mode 120000\n"));
+        assert!(
+            text.ends_with("diff --git a/build.sh b/build.sh\nold mode 100644\nnew mode 100755\n")
+        );
+    }
+
+    #[test]
+    fn test_hunk_patch_keeps_context_and_raw_text() {
+        use super::super::diff_algo::{compute_side_by_side, find_hunk_starts};
+
+        let old = "a\nb\nc\nd\n\tx\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nd\n\ty\n\tz\ne\nf\ng\nh\n";
+        let diff = file("src/a.rs", old, new, FileStatus::Modified);
+        let rows = compute_side_by_side(old, new, 4, false);
+        let start = find_hunk_starts(&rows)[0];
+
+        assert_eq!(
+            hunk_patch(&diff, &rows, start).unwrap(),
+            "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n\
+             @@ -2,7 +2,8 @@\n b\n c\n d\n-\tx\n+\ty\n+\tz\n e\n f\n g\n"
+        );
+        assert!(hunk_patch(&diff, &rows, 0).is_none());
+
+        // CRLF line endings are kept, so the patch still matches the file
+        let old = "a\r\nb\r\nc\r\n";
+        let new = "a\r\nB\r\nc\r\n";
+        let diff = file("crlf.txt", old, new, FileStatus::Modified);
+        let rows = compute_side_by_side(old, new, 4, false);
+        let start = find_hunk_starts(&rows)[0];
+        assert_eq!(
+            hunk_patch(&diff, &rows, start).unwrap(),
+            "diff --git a/crlf.txt b/crlf.txt\n--- a/crlf.txt\n+++ b/crlf.txt\n\
+             @@ -1,3 +1,3 @@\n a\r\n-b\r\n+B\r\n c\r\n"
+        );
+    }
+
+    #[test]
+    fn test_hunk_patch_of_new_file() {
+        use super::super::diff_algo::compute_side_by_side;
+
+        let diff = file("new.txt", "", "one\ntwo", FileStatus::Added);
+        let rows = compute_side_by_side("", "one\ntwo", 4, false);
+
+        assert_eq!(
+            hunk_patch(&diff, &rows, 0).unwrap(),
+            "diff --git a/new.txt b/new.txt\nnew file mode 100644\n--- /dev/null\n+++ b/new.txt\n\
+             @@ -0,0 +1,2 @@\n+one\n+two\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn test_binary_files_are_reported_not_written() {
+        let mut binary = file("logo.png", "a", "b", FileStatus::Modified);
+        binary.is_binary = true;
+        let (text, export) = patch_text(&[binary]);
+        assert!(text.is_empty());
+        assert_eq!(export.skipped_binary, vec!["logo.png".to_string()]);
+    }
+}
@@ -2,23 +2,33 @@ use std::collections::HashSet;
 
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Padding, Paragraph},
 };
+use unicode_width::UnicodeWidthChar;
 
 use crate::command::diff::context::{compute_context_lines, ContextLine};
+use crate::command::diff::coordinates::{
+    pinned_strip_height, wraps_before, BLAME_WIDTH, BORDER_WIDTH, DUAL_GUTTER_WIDTH,
+    FOCUS_INDICATOR_WIDTH, GUTTER_WIDTH, ORIGIN_WIDTH,
+};
 use crate::command::diff::highlight::{highlight_line_spans, FileHighlighter};
 use crate::command::diff::search::{MatchPanel, SearchState};
-use crate::command::diff::state::HunkAnnotation;
+use crate::command::diff::state::{FooterPrompt, HunkAnnotation};
 use crate::command::diff::theme;
 use crate::command::diff::types::{
-    ChangeType, DiffFullscreen, DiffLine, DiffPanelFocus, DiffViewSettings, FileDiff, FocusedPanel,
-    InlineSegment, Selection, SelectionMode, SidebarItem,
+    conflict_sections, expand_tabs, is_conflict_marker, ChangeType, ConflictSection,
+    DiffFullscreen, DiffLine, DiffPanelFocus, DiffViewMode, DiffViewSettings, FileDiff,
+    FocusedPanel, InlineSegment, PanelBorders, PanelTitles, Selection, SelectionMode, SidebarItem,
+    TabStyle,
 };
 use crate::command::diff::PrInfo;
 
 use super::footer::{render_footer, FooterData};
 use super::sidebar::render_sidebar;
 
+/// Columns before a line's text in a panel with a focus bar: the bar, then the gutter
+const LINE_PREFIX_WIDTH: usize = (FOCUS_INDICATOR_WIDTH + GUTTER_WIDTH) as usize;
+
 /// Render the header bar for stacked diff mode showing commit info with navigation arrows
 fn render_stacked_header(
     frame: &mut Frame,
@@ -58,7 +68,12 @@ fn render_stacked_header(
             // git: just show short SHA
             c.short_id.clone()
         };
-        (id_str, c.summary.clone())
+        // A file's timeline is read by when and by whom, so lead with that
+        let message = match &c.history {
+            Some(revision) => format!("{} · {} · {}", revision.date, revision.author, c.summary),
+            None => c.summary.clone(),
+        };
+        (id_str, message)
     } else {
         ("?".to_string(), "No commit".to_string())
     };
@@ -72,11 +87,12 @@ fn render_stacked_header(
     let available_for_msg =
         (area.width as usize).saturating_sub(60 + vcs_indicator.len() + id_label.len());
 
-    let truncated_msg = if commit_msg.len() > available_for_msg {
-        format!(
-            "{}...",
-            &commit_msg[..available_for_msg.saturating_sub(3).max(0)]
-        )
+    let truncated_msg = if commit_msg.chars().count() > available_for_msg {
+        let kept: String = commit_msg
+            .chars()
+            .take(available_for_msg.saturating_sub(3))
+            .collect();
+        format!("{}...", kept)
     } else {
         commit_msg
     };
@@ -194,6 +210,60 @@ fn expand_tabs_in_spans<'a>(spans: Vec<Span<'a>>, tab_width: usize) -> Vec<Span<
     out
 }
 
+/// Columns (after tab expansion) where each tab starts, when tabs get a visible glyph.
+fn tab_glyph_columns(spans: &[Span], tabs: TabStyle) -> Vec<usize> {
+    if tabs.glyph.is_none() || tabs.width == 0 {
+        return Vec::new();
+    }
+    let mut columns = Vec::new();
+    let mut col = 0;
+    for ch in spans.iter().flat_map(|span| span.content.chars()) {
+        if ch == '\t' {
+            columns.push(col);
+            col += tabs.width - (col % tabs.width);
+        } else {
+            col += 1;
+        }
+    }
+    columns
+}
+
+/// Draw the tab glyph over the first column of each expanded tab. Runs after search and
+/// emphasis styling, whose ranges are offsets into the space-expanded text; the glyph
+/// keeps the background and only dims the foreground.
+fn draw_tab_glyphs<'a>(spans: Vec<Span<'a>>, columns: &[usize], tabs: TabStyle) -> Vec<Span<'a>> {
+    let Some(glyph) = tabs.glyph.filter(|_| !columns.is_empty()) else {
+        return spans;
+    };
+    let muted = theme::get().ui.text_muted;
+    let mut out: Vec<Span<'a>> = Vec::with_capacity(spans.len() + columns.len());
+    let mut col = 0;
+    for span in spans {
+        let span_cols = span.content.chars().count();
+        if !columns.iter().any(|&c| c >= col && c < col + span_cols) {
+            col += span_cols;
+            out.push(span);
+            continue;
+        }
+        let mut buf = String::new();
+        for ch in span.content.chars() {
+            if columns.contains(&col) {
+                if !buf.is_empty() {
+                    out.push(Span::styled(std::mem::take(&mut buf), span.style));
+                }
+                out.push(Span::styled(glyph.to_string(), span.style.fg(muted)));
+            } else {
+                buf.push(ch);
+            }
+            col += 1;
+        }
+        if !buf.is_empty() {
+            out.push(Span::styled(buf, span.style));
+        }
+    }
+    out
+}
+
 fn apply_search_highlight<'a>(
     text: &str,
     filename: &str,
@@ -201,7 +271,7 @@ fn apply_search_highlight<'a>(
     match_ranges: &[(usize, usize, bool)],
     highlighter: Option<&FileHighlighter>,
     line_number: Option<usize>,
-    tab_width: usize,
+    tabs: TabStyle,
 ) -> Vec<Span<'a>> {
     let t = theme::get();
 
@@ -217,10 +287,11 @@ fn apply_search_highlight<'a>(
     } else {
         highlight_line_spans(text, filename, bg)
     };
-    let base_spans = expand_tabs_in_spans(base_spans, tab_width);
+    let tab_columns = tab_glyph_columns(&base_spans, tabs);
+    let base_spans = expand_tabs_in_spans(base_spans, tabs.width);
 
     if match_ranges.is_empty() {
-        return base_spans;
+        return draw_tab_glyphs(base_spans, &tab_columns, tabs);
     }
     let mut result: Vec<Span<'a>> = Vec::new();
     let mut char_pos = 0;
@@ -276,16 +347,21 @@ fn apply_search_highlight<'a>(
         char_pos = span_end;
     }
 
-    result
+    draw_tab_glyphs(result, &tab_columns, tabs)
 }
 
 /// Convert InlineSegments to emphasis ranges (start, end) positions.
-fn segments_to_emphasis_ranges(segments: &[InlineSegment]) -> Vec<(usize, usize)> {
+/// With `hide_whitespace`, whitespace-only segments (e.g. reindentation) are left unemphasized.
+pub(super) fn segments_to_emphasis_ranges(
+    segments: &[InlineSegment],
+    hide_whitespace: bool,
+) -> Vec<(usize, usize)> {
     let mut ranges = Vec::new();
     let mut pos = 0;
     for segment in segments {
         let len = segment.text.len();
-        if segment.emphasized {
+        let whitespace_only = segment.text.chars().all(char::is_whitespace);
+        if segment.emphasized && !(hide_whitespace && whitespace_only) {
             ranges.push((pos, pos + len));
         }
         pos += len;
@@ -328,7 +404,7 @@ fn boost_muted_fg(fg: Color, default_text: Color) -> Color {
 
 /// Apply syntax highlighting with word-level emphasis backgrounds.
 /// This preserves syntax colors while overlaying emphasis backgrounds for changed words.
-fn apply_word_emphasis_highlight<'a>(
+pub(super) fn apply_word_emphasis_highlight<'a>(
     text: &str,
     filename: &str,
     line_bg: Option<Color>,
@@ -337,7 +413,7 @@ fn apply_word_emphasis_highlight<'a>(
     search_ranges: &[(usize, usize, bool)],
     highlighter: Option<&FileHighlighter>,
     line_number: Option<usize>,
-    tab_width: usize,
+    tabs: TabStyle,
 ) -> Vec<Span<'a>> {
     let t = theme::get();
 
@@ -352,10 +428,11 @@ fn apply_word_emphasis_highlight<'a>(
     } else {
         highlight_line_spans(text, filename, line_bg)
     };
-    let base_spans = expand_tabs_in_spans(base_spans, tab_width);
+    let tab_columns = tab_glyph_columns(&base_spans, tabs);
+    let base_spans = expand_tabs_in_spans(base_spans, tabs.width);
 
     if emphasis_ranges.is_empty() && search_ranges.is_empty() {
-        return base_spans;
+        return draw_tab_glyphs(base_spans, &tab_columns, tabs);
     }
 
     let mut result: Vec<Span<'a>> = Vec::new();
@@ -456,7 +533,7 @@ fn apply_word_emphasis_highlight<'a>(
         byte_pos += span_byte_len;
     }
 
-    result
+    draw_tab_glyphs(result, &tab_columns, tabs)
 }
 
 /// Selection tint color - a subtle blue that blends with any diff background
@@ -592,6 +669,207 @@ fn apply_selection_to_spans<'a>(
     result
 }
 
+/// What a row standing in for other rows says: unchanged lines folded away by the
+/// compact view, or a hunk hidden by `--grep`. None for ordinary rows.
+pub(super) fn folded_label(change_type: ChangeType) -> Option<String> {
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    match change_type {
+        ChangeType::Folded { count, .. } => {
+            Some(format!("⋯ {} unchanged line{} ⋯", count, plural(count)))
+        }
+        ChangeType::Hidden { count, .. } => Some(format!(
+            "⋯ {} changed line{} without a --grep match ⋯",
+            count,
+            plural(count)
+        )),
+        _ => None,
+    }
+}
+
+/// The separator row for `folded_label`.
+fn folded_separator(label: String, t: &theme::Theme) -> Line<'static> {
+    Line::from(Span::styled(
+        format!("      {}", label),
+        Style::default().fg(t.ui.text_muted),
+    ))
+}
+
+/// Gutter bar for the change-bar view: colored by how the line changed, blank when unchanged.
+fn change_bar(change_type: ChangeType, t: &theme::Theme) -> Span<'static> {
+    let (glyph, color) = match change_type {
+        ChangeType::Equal | ChangeType::Folded { .. } | ChangeType::Hidden { .. } => {
+            return Span::raw(" ")
+        }
+        ChangeType::Insert => ("▌", t.ui.status_added),
+        ChangeType::Modified => ("▌", t.ui.status_modified),
+        ChangeType::Delete => ("▁", t.ui.status_deleted),
+    };
+    Span::styled(glyph, Style::default().fg(color))
+}
+
+/// The pinned hunk in unified form: removed lines above the lines that replaced them.
+fn render_pinned_hunk(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    lines: &[DiffLine],
+    tab_width: usize,
+    t: &theme::Theme,
+) {
+    let row = |num: usize, sign: char, text: &str, bg: Color| {
+        Line::from(vec![
+            Span::styled(
+                format!("{:4}{} ", num, sign),
+                Style::default().fg(t.ui.line_number),
+            ),
+            Span::styled(
+                expand_tabs(text, tab_width),
+                Style::default().fg(t.syntax.default_text),
+            ),
+        ])
+        .style(Style::default().bg(bg))
+    };
+    let mut rows = Vec::new();
+    for line in lines {
+        let removed = matches!(line.change_type, ChangeType::Delete | ChangeType::Modified);
+        let added = matches!(line.change_type, ChangeType::Insert | ChangeType::Modified);
+        if let Some((num, text)) = line.old_line.as_ref().filter(|_| removed) {
+            rows.push(row(*num, '-', text, t.diff.deleted_bg));
+        }
+        if let Some((num, text)) = line.new_line.as_ref().filter(|_| added) {
+            rows.push(row(*num, '+', text, t.diff.added_bg));
+        }
+    }
+    let para = Paragraph::new(rows)
+        .style(Style::default().bg(t.ui.bg))
+        .block(
+            Block::default()
+                .title(Line::styled(
+                    format!(" Pinned: {} ", title),
+                    Style::default().fg(t.ui.text_muted),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(t.ui.border_unfocused)),
+        );
+    frame.render_widget(ratatui::widgets::Clear, area);
+    frame.render_widget(para, area);
+}
+
+/// Buckets the diff into `rows` minimap cells, each summarising the lines it covers:
+/// any modified line (or a mix of inserts and deletes) makes it Modified, otherwise
+/// an insert or delete wins over unchanged lines.
+pub(super) fn minimap_cells(side_by_side: &[DiffLine], rows: usize) -> Vec<ChangeType> {
+    let len = side_by_side.len();
+    (0..rows)
+        .map(|row| {
+            let start = row * len / rows.max(1);
+            let end = ((row + 1) * len / rows.max(1)).max(start + 1).min(len);
+            let (mut inserted, mut deleted) = (false, false);
+            for line in side_by_side.get(start..end).unwrap_or_default() {
+                match line.change_type {
+                    ChangeType::Modified => return ChangeType::Modified,
+                    ChangeType::Insert => inserted = true,
+                    ChangeType::Delete => deleted = true,
+                    ChangeType::Equal | ChangeType::Folded { .. } | ChangeType::Hidden { .. } => {}
+                }
+            }
+            match (inserted, deleted) {
+                (true, true) => ChangeType::Modified,
+                (true, false) => ChangeType::Insert,
+                (false, true) => ChangeType::Delete,
+                (false, false) => ChangeType::Equal,
+            }
+        })
+        .collect()
+}
+
+/// Draws the minimap over the diff panel's right border: where the file changed,
+/// with the rows covering the current viewport highlighted. Files that fit on
+/// screen get no minimap.
+fn render_minimap(
+    frame: &mut Frame,
+    area: Rect,
+    side_by_side: &[DiffLine],
+    scroll: usize,
+    t: &theme::Theme,
+) {
+    let rows = area.height.saturating_sub(2) as usize;
+    let len = side_by_side.len();
+    if rows == 0 || area.width < 2 || len <= rows {
+        return;
+    }
+    let viewport_end = scroll + rows;
+    let x = area.x + area.width - 1;
+    for (row, cell) in minimap_cells(side_by_side, rows).into_iter().enumerate() {
+        let (glyph, color) = match cell {
+            ChangeType::Insert => ("▐", t.ui.stats_added),
+            ChangeType::Delete => ("▐", t.ui.stats_removed),
+            ChangeType::Modified => ("▐", t.ui.status_modified),
+            ChangeType::Equal | ChangeType::Folded { .. } | ChangeType::Hidden { .. } => {
+                ("│", t.ui.border_unfocused)
+            }
+        };
+        let mut style = Style::default().fg(color);
+        let start = row * len / rows;
+        let end = ((row + 1) * len / rows).max(start + 1);
+        if start < viewport_end && end > scroll {
+            style = style.bg(t.ui.selection_bg);
+        }
+        let cell_area = Rect::new(x, area.y + 1 + row as u16, 1, 1);
+        frame.render_widget(Paragraph::new(Span::styled(glyph, style)), cell_area);
+    }
+}
+
+/// Soft-wraps a rendered row every `width` display columns of text, moving a wide
+/// character that would straddle the edge down whole. The first `gutter_width`
+/// columns stay on the first row; continuation rows start with a blank gutter in
+/// the gutter's style. Span styles carry over, so line backgrounds and search and
+/// selection highlights continue across the rows. A `width` of 0 leaves the row whole.
+fn wrap_spans<'a>(spans: Vec<Span<'a>>, gutter_width: usize, width: usize) -> Vec<Line<'a>> {
+    if width == 0 {
+        return vec![Line::from(spans)];
+    }
+    let mut rows: Vec<Vec<Span<'a>>> = vec![Vec::new()];
+    let mut gutter_style = Style::default();
+    let mut col = 0;
+    for span in spans {
+        let style = span.style;
+        let mut piece = String::new();
+        for ch in span.content.chars() {
+            let char_width = ch.width().unwrap_or(0);
+            if col >= gutter_width && wraps_before(col - gutter_width, char_width, width) {
+                if !piece.is_empty() {
+                    rows.last_mut()
+                        .unwrap()
+                        .push(Span::styled(std::mem::take(&mut piece), style));
+                }
+                rows.push(vec![Span::styled(" ".repeat(gutter_width), gutter_style)]);
+                col = gutter_width;
+            }
+            piece.push(ch);
+            col += char_width;
+        }
+        if rows.len() == 1 && col <= gutter_width {
+            gutter_style = style;
+        }
+        if !piece.is_empty() {
+            rows.last_mut().unwrap().push(Span::styled(piece, style));
+        }
+    }
+    rows.into_iter().map(Line::from).collect()
+}
+
+/// Pads one diff line's rows in a panel to `count` so both panels stay aligned.
+/// Placeholder stripes repeat; text rows are followed by blank rows.
+fn pad_wrapped_rows(rows: &mut Vec<Line<'_>>, count: usize, placeholder: bool) {
+    let filler = if placeholder {
+        rows.first().cloned().unwrap_or_default()
+    } else {
+        Line::default()
+    };
+    rows.resize(count, filler);
+}
+
 pub fn compute_line_stats(side_by_side: &[DiffLine]) -> LineStats {
     let mut added = 0;
     let mut removed = 0;
@@ -603,26 +881,26 @@ pub fn compute_line_stats(side_by_side: &[DiffLine]) -> LineStats {
                 added += 1;
                 removed += 1;
             }
-            ChangeType::Equal => {}
+            ChangeType::Equal | ChangeType::Folded { .. } | ChangeType::Hidden { .. } => {}
         }
     }
     LineStats { added, removed }
 }
 
 /// Style configuration for rendering a diff line's gutter and background.
-struct DiffLineStyle {
-    old_bg: Option<Color>,
-    old_gutter_bg: Option<Color>,
-    old_gutter_fg: Option<Color>,
-    new_bg: Option<Color>,
-    new_gutter_bg: Option<Color>,
-    new_gutter_fg: Option<Color>,
+pub(super) struct DiffLineStyle {
+    pub(super) old_bg: Option<Color>,
+    pub(super) old_gutter_bg: Option<Color>,
+    pub(super) old_gutter_fg: Option<Color>,
+    pub(super) new_bg: Option<Color>,
+    pub(super) new_gutter_bg: Option<Color>,
+    pub(super) new_gutter_fg: Option<Color>,
 }
 
 impl DiffLineStyle {
-    fn for_change_type(change_type: ChangeType, bg: Color, t: &crate::command::diff::theme::Theme) -> Self {
+    pub(super) fn for_change_type(change_type: ChangeType, bg: Color, t: &crate::command::diff::theme::Theme) -> Self {
         match change_type {
-            ChangeType::Equal => Self {
+            ChangeType::Equal | ChangeType::Folded { .. } | ChangeType::Hidden { .. } => Self {
                 old_bg: Some(bg),
                 old_gutter_bg: Some(bg),
                 old_gutter_fg: Some(t.ui.line_number),
@@ -675,7 +953,7 @@ fn render_context_lines(
     lines: &mut Vec<Line>,
     filename: &str,
     highlighter: &FileHighlighter,
-    tab_width: usize,
+    tabs: TabStyle,
 ) {
     let t = theme::get();
     let context_bg = t.diff.context_bg;
@@ -688,9 +966,12 @@ fn render_context_lines(
                 Style::default().fg(t.ui.line_number).bg(context_bg),
             )];
             // Use FileHighlighter for proper multi-line construct highlighting
-            let hl_spans = expand_tabs_in_spans(
-                highlighter.get_line_spans(cl.line_number, Some(context_bg)),
-                tab_width,
+            let hl_spans = highlighter.get_line_spans(cl.line_number, Some(context_bg));
+            let tab_columns = tab_glyph_columns(&hl_spans, tabs);
+            let hl_spans = draw_tab_glyphs(
+                expand_tabs_in_spans(hl_spans, tabs.width),
+                &tab_columns,
+                tabs,
             );
             if hl_spans.is_empty() {
                 // Fallback to line-by-line highlighting
@@ -712,7 +993,67 @@ fn render_context_lines(
     }
 }
 
-use crate::vcs::StackedCommitInfo;
+use crate::vcs::{BlameLine, LineOrigin, StackedCommitInfo};
+
+/// The blame margin for one old-side line: short commit, author and date, or
+/// blank where there is nothing to attribute.
+fn blame_cell(line: Option<&BlameLine>) -> String {
+    match line {
+        Some(blame) => format!(
+            "{:7.7} {:<8.8} {:10.10} ",
+            blame.short_id, blame.author, blame.date
+        ),
+        None => " ".repeat(BLAME_WIDTH as usize),
+    }
+}
+
+/// The line-origin margin for one new-side line: short commit and subject, or
+/// blank for lines the range did not change.
+fn origin_cell(origin: Option<&LineOrigin>) -> String {
+    match origin {
+        Some(origin) => format!("{:7.7} {:<15.15} ", origin.short_id, origin.summary),
+        None => " ".repeat(ORIGIN_WIDTH as usize),
+    }
+}
+
+/// Lines shown for an annotation in the inline overlay: at most `limit` characters,
+/// then a note of how much was cut. The annotations modal and exports keep the full text.
+fn annotation_overlay_lines(content: &str, limit: usize) -> Vec<String> {
+    let total = content.chars().count();
+    if total <= limit {
+        return content.lines().map(str::to_string).collect();
+    }
+    let shown: String = content.chars().take(limit).collect();
+    let mut lines: Vec<String> = shown.lines().map(str::to_string).collect();
+    if let Some(last) = lines.last_mut() {
+        last.push('…');
+    }
+    lines.push(format!("[+{} more characters · I to read all]", total - limit));
+    lines
+}
+
+/// Annotations shown in a banner above the file's diff: whole-file notes, plus the
+/// first hunk's for added or deleted files, whose one hunk is the whole file.
+fn top_annotations<'a>(
+    annotations: impl Iterator<Item = &'a HunkAnnotation>,
+    filename: &str,
+    with_first_hunk: bool,
+) -> Vec<&'a HunkAnnotation> {
+    let mut top: Vec<&HunkAnnotation> = annotations
+        .filter(|a| a.filename == filename)
+        .filter(|a| a.is_file_level() || (with_first_hunk && a.hunk_index == 0))
+        .collect();
+    top.sort_by_key(|a| !a.is_file_level());
+    top
+}
+
+/// Rows taken by annotation boxes, borders included.
+fn overlays_height(annotations: &[&HunkAnnotation], limit: usize) -> usize {
+    annotations
+        .iter()
+        .map(|a| annotation_overlay_lines(&a.content, limit).len() + 2)
+        .sum()
+}
 
 /// Render annotation overlays at specified positions.
 ///
@@ -728,10 +1069,11 @@ fn render_annotation_overlays(
     max_area: Rect,
     bg: Color,
     t: &crate::command::diff::theme::Theme,
+    limit: usize,
 ) {
     for (line_pos, annotation) in overlays {
         let screen_y = content_start_y + *line_pos as u16;
-        let content_lines: Vec<&str> = annotation.content.lines().collect();
+        let content_lines = annotation_overlay_lines(&annotation.content, limit);
         let num_lines = content_lines.len() + 2; // +2 for top and bottom borders
 
         // Check if annotation is visible
@@ -753,7 +1095,12 @@ fn render_annotation_overlays(
         // Build annotation lines
         let mut ann_lines: Vec<Line> = Vec::new();
         let note_style = Style::default().fg(t.ui.text_muted).italic();
-        let border_style_ann = Style::default().fg(t.ui.border_unfocused);
+        // Unsubmitted GitHub review comments and whole-file notes get their own border color
+        let border_style_ann = match annotation.pending_author {
+            Some(_) => Style::default().fg(t.ui.status_modified),
+            None if annotation.is_file_level() => Style::default().fg(t.ui.highlight),
+            None => Style::default().fg(t.ui.border_unfocused),
+        };
         let border_width = content_width.saturating_sub(3) as usize;
 
         // Add top border
@@ -775,9 +1122,15 @@ fn render_annotation_overlays(
 
         // Add bottom border with time if there's room
         if ann_lines.len() < available_height {
-            let time_str = annotation.format_time();
+            let time_str = match &annotation.pending_author {
+                Some(author) => format!("pending review · @{}", author),
+                None if annotation.is_file_level() => {
+                    format!("file · {}", annotation.format_time())
+                }
+                None => annotation.format_time(),
+            };
             let time_with_padding = format!(" {} ", time_str);
-            let time_len = time_with_padding.len();
+            let time_len = time_with_padding.chars().count();
             let dashes_before = border_width.saturating_sub(time_len + 1);
             ann_lines.push(Line::from(vec![
                 Span::styled(format!(" └{}", "─".repeat(dashes_before)), border_style_ann),
@@ -809,7 +1162,11 @@ pub fn render_diff(
     sidebar_scroll: usize,
     sidebar_h_scroll: u16,
     viewed_files: &HashSet<usize>,
+    changed_since_review: &HashSet<usize>,
+    sidebar_filter: Option<(&str, usize)>,
+    conflict_counts: &[usize],
     settings: &DiffViewSettings,
+    hidden_deleted_count: usize,
     hunk_count: usize,
     diff_fullscreen: DiffFullscreen,
     search_state: &SearchState,
@@ -817,6 +1174,8 @@ pub fn render_diff(
     pr_info: Option<&PrInfo>,
     focused_hunk: Option<usize>,
     hunks: &[usize],
+    reviewed_hunks: &HashSet<(usize, usize)>,
+    skip_reviewed_hunks: bool,
     stacked_mode: bool,
     stacked_commit: Option<&StackedCommitInfo>,
     stacked_index: usize,
@@ -824,11 +1183,24 @@ pub fn render_diff(
     side_by_side: &[DiffLine],
     vcs_name: &str,
     annotations: &[HunkAnnotation],
+    pending_review: &[HunkAnnotation],
     selection: &Selection,
+    status_message: Option<&str>,
+    prompt: Option<&FooterPrompt>,
+    panel_titles: &PanelTitles,
+    blame: Option<&[BlameLine]>,
+    origins: Option<&[Option<LineOrigin>]>,
+    pinned_hunk: Option<(&str, &[DiffLine])>,
 ) {
     let area = frame.area();
     let t = theme::get();
     let bg = t.ui.bg;
+    // Wrapped lines never run past the panel edge
+    let h_scroll = if settings.wrap { 0 } else { h_scroll };
+    let tab_width = settings.tab_width_for(&diff.filename);
+    let tab_style = settings.tab_style(&diff.filename);
+    // Pending review comments render alongside local annotations, after them
+    let all_annotations = || annotations.iter().chain(pending_review);
 
     // Layout: header (if stacked) + main content + footer
     let (content_area, footer_area) = if stacked_mode {
@@ -878,6 +1250,10 @@ pub fn render_diff(
             sidebar_scroll,
             sidebar_h_scroll,
             viewed_files,
+            changed_since_review,
+            sidebar_filter,
+            conflict_counts,
+            &settings.indicators,
             focused_panel == FocusedPanel::Sidebar,
         );
 
@@ -886,6 +1262,20 @@ pub fn render_diff(
         content_area
     };
 
+    // A pinned hunk keeps a strip at the bottom of the diff panel
+    let main_area = match pinned_hunk {
+        Some((title, lines)) if !lines.is_empty() => {
+            let height = pinned_strip_height(lines, main_area.height);
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(height)])
+                .split(main_area);
+            render_pinned_hunk(frame, chunks[1], title, lines, tab_width, &t);
+            chunks[0]
+        }
+        _ => main_area,
+    };
+
     // Handle binary files - show a message instead of trying to diff
     if diff.is_binary {
         let border_style = Style::default().fg(t.ui.border_unfocused);
@@ -923,7 +1313,15 @@ pub fn render_diff(
                 line_stats_removed: 0,
                 hunk_count: 0,
                 focused_hunk: None,
+                file_size: None,
+                reviewed_hunk_count: 0,
+                skip_reviewed_hunks,
+                conflict_count: conflict_counts.iter().sum(),
+                hidden_deleted_count,
+                status_message,
                 search_state,
+                grep: settings.grep.as_ref().map(|grep| grep.pattern.as_str()),
+                prompt,
                 area_width: area.width,
             },
         );
@@ -955,20 +1353,16 @@ pub fn render_diff(
             &diff.filename,
             scroll as usize,
             &settings.context,
-            settings.tab_width,
+            tab_width,
         );
         let context_count = new_context.len();
         let scroll_usize = scroll as usize;
 
-        // Check if there's an annotation for this file (hunk 0 for new files)
-        let annotation = annotations
-            .iter()
-            .find(|a| a.file_index == current_file && a.hunk_index == 0);
+        // The file's notes, then the single hunk's (a new file is one hunk)
+        let file_annotations = top_annotations(all_annotations(), &diff.filename, true);
 
         // Calculate how much space we need for annotation (shown at top)
-        let annotation_height = annotation
-            .map(|a| a.content.lines().count() + 2)
-            .unwrap_or(0);
+        let annotation_height = overlays_height(&file_annotations, settings.annotation_limit);
 
         // Reserve space for annotation at top
         let base_content_height = visible_height.saturating_sub(context_count);
@@ -981,6 +1375,11 @@ pub fn render_diff(
             .collect();
 
         let mut new_lines: Vec<Line> = Vec::new();
+        let wrap_width = if settings.wrap {
+            (main_area.width as usize).saturating_sub(2 * BORDER_WIDTH as usize + LINE_PREFIX_WIDTH)
+        } else {
+            0
+        };
         let mut annotation_overlays: Vec<(usize, &HunkAnnotation)> = Vec::new();
 
         if settings.context.enabled && context_count > 0 {
@@ -990,13 +1389,14 @@ pub fn render_diff(
                 &mut new_lines,
                 &diff.filename,
                 &new_highlighter,
-                settings.tab_width,
+                tab_style,
             );
         }
 
         // For new files, show annotation at top (after context lines)
-        if let Some(annotation) = annotation {
-            let content_lines: Vec<&str> = annotation.content.lines().collect();
+        for annotation in file_annotations {
+            let content_lines =
+                annotation_overlay_lines(&annotation.content, settings.annotation_limit);
             let num_lines = content_lines.len() + 2;
             let annotation_start = new_lines.len();
             for _ in 0..num_lines {
@@ -1023,9 +1423,9 @@ pub fn render_diff(
                     &matches,
                     Some(&new_highlighter),
                     Some(*num),
-                    settings.tab_width,
+                    tab_style,
                 ));
-                new_lines.push(Line::from(spans));
+                new_lines.extend(wrap_spans(spans, LINE_PREFIX_WIDTH, wrap_width));
             }
         }
 
@@ -1041,7 +1441,7 @@ pub fn render_diff(
         let content_x = main_area.x + 1;
         let content_start_y = main_area.y + 1;
         let content_width = main_area.width.saturating_sub(2);
-        render_annotation_overlays(frame, &annotation_overlays, content_x, content_start_y, content_width, main_area, bg, t);
+        render_annotation_overlays(frame, &annotation_overlays, content_x, content_start_y, content_width, main_area, bg, &t, settings.annotation_limit);
     } else if is_deleted_file {
         let visible_height = main_area.height.saturating_sub(2) as usize;
         let old_context = compute_context_lines(
@@ -1049,20 +1449,16 @@ pub fn render_diff(
             &diff.filename,
             scroll as usize,
             &settings.context,
-            settings.tab_width,
+            tab_width,
         );
         let context_count = old_context.len();
         let scroll_usize = scroll as usize;
 
-        // Check if there's an annotation for this file (hunk 0 for deleted files)
-        let annotation = annotations
-            .iter()
-            .find(|a| a.file_index == current_file && a.hunk_index == 0);
+        // The file's notes, then the single hunk's (a deleted file is one hunk)
+        let file_annotations = top_annotations(all_annotations(), &diff.filename, true);
 
         // Calculate how much space we need for annotation (shown at top)
-        let annotation_height = annotation
-            .map(|a| a.content.lines().count() + 2)
-            .unwrap_or(0);
+        let annotation_height = overlays_height(&file_annotations, settings.annotation_limit);
 
         // Reserve space for annotation at top
         let base_content_height = visible_height.saturating_sub(context_count);
@@ -1075,6 +1471,11 @@ pub fn render_diff(
             .collect();
 
         let mut old_lines: Vec<Line> = Vec::new();
+        let wrap_width = if settings.wrap {
+            (main_area.width as usize).saturating_sub(2 * BORDER_WIDTH as usize + LINE_PREFIX_WIDTH)
+        } else {
+            0
+        };
         let mut annotation_overlays: Vec<(usize, &HunkAnnotation)> = Vec::new();
 
         if settings.context.enabled && context_count > 0 {
@@ -1084,13 +1485,14 @@ pub fn render_diff(
                 &mut old_lines,
                 &diff.filename,
                 &old_highlighter,
-                settings.tab_width,
+                tab_style,
             );
         }
 
         // For deleted files, show annotation at top (after context lines)
-        if let Some(annotation) = annotation {
-            let content_lines: Vec<&str> = annotation.content.lines().collect();
+        for annotation in file_annotations {
+            let content_lines =
+                annotation_overlay_lines(&annotation.content, settings.annotation_limit);
             let num_lines = content_lines.len() + 2;
             let annotation_start = old_lines.len();
             for _ in 0..num_lines {
@@ -1117,9 +1519,9 @@ pub fn render_diff(
                     &matches,
                     Some(&old_highlighter),
                     Some(*num),
-                    settings.tab_width,
+                    tab_style,
                 ));
-                old_lines.push(Line::from(spans));
+                old_lines.extend(wrap_spans(spans, LINE_PREFIX_WIDTH, wrap_width));
             }
         }
 
@@ -1135,7 +1537,7 @@ pub fn render_diff(
         let content_x = main_area.x + 1;
         let content_start_y = main_area.y + 1;
         let content_width = main_area.width.saturating_sub(2);
-        render_annotation_overlays(frame, &annotation_overlays, content_x, content_start_y, content_width, main_area, bg, t);
+        render_annotation_overlays(frame, &annotation_overlays, content_x, content_start_y, content_width, main_area, bg, &t, settings.annotation_limit);
     } else {
         let (old_area, new_area) = match diff_fullscreen {
             DiffFullscreen::OldOnly => (Some(main_area), None),
@@ -1154,14 +1556,14 @@ pub fn render_diff(
             &diff.filename,
             scroll as usize,
             &settings.context,
-            settings.tab_width,
+            tab_width,
         );
         let new_context = compute_context_lines(
             &diff.new_content,
             &diff.filename,
             scroll as usize,
             &settings.context,
-            settings.tab_width,
+            tab_width,
         );
         let context_count = old_context.len().max(new_context.len());
 
@@ -1169,7 +1571,12 @@ pub fn render_diff(
         let visible_height = reference_area.height.saturating_sub(2) as usize;
         let scroll_usize = scroll as usize;
 
-        let content_height = visible_height.saturating_sub(context_count);
+        // Whole-file notes take a banner below the context lines
+        let file_annotations = top_annotations(all_annotations(), &diff.filename, false);
+        let banner_height = overlays_height(&file_annotations, settings.annotation_limit);
+        let content_height = visible_height
+            .saturating_sub(context_count)
+            .saturating_sub(banner_height);
         let visible_lines: Vec<&DiffLine> = side_by_side
             .iter()
             .skip(scroll_usize)
@@ -1179,6 +1586,55 @@ pub fn render_diff(
         let mut old_lines: Vec<Line> = Vec::new();
         let mut new_lines: Vec<Line> = Vec::new();
         let mut annotation_overlays: Vec<(usize, &HunkAnnotation)> = Vec::new();
+        let change_bars = settings.view_mode == DiffViewMode::ChangeBars;
+        let unified = settings.view_mode == DiffViewMode::Unified;
+
+        // Rows start with the focus bar and a 5-column gutter; the new panel drops the
+        // focus bar and (unless borders are separate) its left border when the old
+        // panel sits beside it
+        // Line origins sit before the new panel's line numbers
+        let origins = origins.filter(|_| !unified);
+        let origin_width = if origins.is_some() {
+            ORIGIN_WIDTH as usize
+        } else {
+            0
+        };
+        // The unified view numbers every row on both sides, old then new
+        let gutter_width = if unified {
+            DUAL_GUTTER_WIDTH
+        } else {
+            GUTTER_WIDTH
+        } as usize;
+        let new_gutter_width = if old_area.is_some() {
+            gutter_width
+        } else {
+            FOCUS_INDICATOR_WIDTH as usize + gutter_width
+        } + origin_width;
+        let new_left_border =
+            old_area.is_none() || settings.panel_borders == PanelBorders::Separate;
+        // Blame sits between the old panel's focus bar and its line numbers
+        let blame = blame.filter(|_| old_area.is_some() && !unified);
+        let blame_width = if blame.is_some() {
+            BLAME_WIDTH as usize
+        } else {
+            0
+        };
+        let blame_style = Style::default().fg(t.ui.text_muted);
+        let old_wrap_width = match old_area {
+            Some(area) if settings.wrap => {
+                (area.width as usize)
+                    .saturating_sub(2 * BORDER_WIDTH as usize + LINE_PREFIX_WIDTH + blame_width)
+            }
+            _ => 0,
+        };
+        let new_wrap_width = match new_area {
+            Some(area) if settings.wrap => {
+                let borders = if new_left_border { 2 } else { 1 };
+                (area.width as usize)
+                    .saturating_sub(borders * BORDER_WIDTH as usize + new_gutter_width)
+            }
+            _ => 0,
+        };
 
         if settings.context.enabled && context_count > 0 {
             if old_area.is_some() {
@@ -1188,8 +1644,13 @@ pub fn render_diff(
                     &mut old_lines,
                     &diff.filename,
                     &old_highlighter,
-                    settings.tab_width,
+                    tab_style,
                 );
+                if blame.is_some() {
+                    for line in &mut old_lines {
+                        line.spans.insert(0, Span::raw(blame_cell(None)));
+                    }
+                }
             }
             if new_area.is_some() {
                 render_context_lines(
@@ -1198,13 +1659,33 @@ pub fn render_diff(
                     &mut new_lines,
                     &diff.filename,
                     &new_highlighter,
-                    settings.tab_width,
+                    tab_style,
                 );
+                if origins.is_some() {
+                    for line in &mut new_lines {
+                        line.spans.insert(0, Span::raw(origin_cell(None)));
+                    }
+                }
             }
         }
 
+        for annotation in file_annotations {
+            let banner_start = old_lines.len().max(new_lines.len());
+            let num_lines =
+                annotation_overlay_lines(&annotation.content, settings.annotation_limit).len() + 2;
+            for _ in 0..num_lines {
+                if old_area.is_some() {
+                    old_lines.push(Line::from(vec![Span::raw("")]));
+                }
+                if new_area.is_some() {
+                    new_lines.push(Line::from(vec![Span::raw("")]));
+                }
+            }
+            annotation_overlays.push((banner_start, annotation));
+        }
+
         let is_in_focused_hunk = |line_idx: usize, change_type: ChangeType| -> bool {
-            if matches!(change_type, ChangeType::Equal) {
+            if !change_type.is_change() {
                 return false;
             }
             if let Some(hunk_idx) = focused_hunk {
@@ -1236,7 +1717,7 @@ pub fn render_diff(
             }
             let current_line = lines[current_idx_in_slice];
             // Current line must be a change
-            if matches!(current_line.change_type, ChangeType::Equal) {
+            if !current_line.change_type.is_change() {
                 return None;
             }
             // Check next line
@@ -1251,7 +1732,7 @@ pub fn render_diff(
                     false
                 }
             } else {
-                matches!(lines[next_idx].change_type, ChangeType::Equal)
+                !lines[next_idx].change_type.is_change()
             };
             if is_last {
                 get_hunk_for_line(line_idx)
@@ -1260,24 +1741,69 @@ pub fn render_diff(
             }
         };
 
+        let conflicts = conflict_sections(
+            side_by_side
+                .iter()
+                .map(|line| line.new_line.as_ref().map(|(_, text)| text.as_str())),
+        );
         for (i, diff_line) in visible_lines.iter().enumerate() {
             let line_idx = scroll_usize + i;
+            if let Some(label) = folded_label(diff_line.change_type) {
+                let separator = folded_separator(label, &t);
+                if old_area.is_some() {
+                    old_lines.push(separator.clone());
+                }
+                if new_area.is_some() {
+                    new_lines.push(separator);
+                }
+                continue;
+            }
             let in_focused = is_in_focused_hunk(line_idx, diff_line.change_type);
-            let style = DiffLineStyle::for_change_type(diff_line.change_type, bg, t);
+            let style = DiffLineStyle::for_change_type(diff_line.change_type, bg, &t);
 
             // Check selection ranges for this line (O(1) check)
             let old_selection_range = get_selection_range_for_line(line_idx, DiffPanelFocus::Old, selection);
             let new_selection_range = get_selection_range_for_line(line_idx, DiffPanelFocus::New, selection);
 
-            let focus_indicator = if in_focused { "▎" } else { " " };
-            let focus_style = Style::default().fg(t.ui.border_focused);
+            // Reviewed hunks get a checkmark on their first line instead of the focus bar
+            let is_reviewed_hunk_start = hunks
+                .iter()
+                .position(|&h| h == line_idx)
+                .is_some_and(|h| reviewed_hunks.contains(&(current_file, h)));
+            let (focus_indicator, focus_style) = if is_reviewed_hunk_start {
+                ("✓", Style::default().fg(t.ui.viewed))
+            } else if in_focused {
+                ("▎", Style::default().fg(t.ui.border_focused))
+            } else {
+                (" ", Style::default().fg(t.ui.border_focused))
+            };
+
+            let mut old_rows: Vec<Line> = Vec::new();
+            let mut new_rows: Vec<Line> = Vec::new();
 
-            if old_area.is_some() {
+            // The unified view puts a changed line's old side in the one column, above its new side
+            let unified_old_row = unified
+                && diff_line.old_line.is_some()
+                && !matches!(diff_line.change_type, ChangeType::Equal);
+            let unified_new_row = unified && diff_line.new_line.is_some();
+
+            if old_area.is_some() || unified_old_row {
                 let mut old_spans: Vec<Span> = Vec::new();
                 old_spans.push(Span::styled(focus_indicator, focus_style));
+                if let Some(blame) = blame {
+                    let line = diff_line
+                        .old_line
+                        .as_ref()
+                        .and_then(|(num, _)| blame.get(num.saturating_sub(1)));
+                    old_spans.push(Span::styled(blame_cell(line), blame_style));
+                }
                 match &diff_line.old_line {
-                    Some((num, _text)) => {
-                        let prefix = format!("{:4} ", num);
+                    Some((num, text)) => {
+                        let prefix = if unified {
+                            format!("{:4} {:4}-", num, "")
+                        } else {
+                            format!("{:4} ", num)
+                        };
                         old_spans.push(Span::styled(
                             prefix,
                             Style::default()
@@ -1289,9 +1815,12 @@ pub fn render_diff(
                         // Use word-level rendering for modified lines if segments are available
                         let content_spans = if matches!(diff_line.change_type, ChangeType::Modified) {
                             if let Some(ref segments) = diff_line.old_segments {
-                                let emphasis_ranges = segments_to_emphasis_ranges(segments);
+                                let emphasis_ranges = segments_to_emphasis_ranges(
+                                    segments,
+                                    settings.hide_whitespace_emphasis,
+                                );
                                 apply_word_emphasis_highlight(
-                                    _text,
+                                    text,
                                     &diff.filename,
                                     style.old_bg,
                                     t.diff.deleted_word_bg,
@@ -1299,28 +1828,28 @@ pub fn render_diff(
                                     &matches,
                                     Some(&old_highlighter),
                                     Some(*num),
-                                    settings.tab_width,
+                                    tab_style,
                                 )
                             } else {
                                 apply_search_highlight(
-                                    _text,
+                                    text,
                                     &diff.filename,
                                     style.old_bg,
                                     &matches,
                                     Some(&old_highlighter),
                                     Some(*num),
-                                    settings.tab_width,
+                                    tab_style,
                                 )
                             }
                         } else {
                             apply_search_highlight(
-                                _text,
+                                text,
                                 &diff.filename,
                                 style.old_bg,
                                 &matches,
                                 Some(&old_highlighter),
                                 Some(*num),
-                                settings.tab_width,
+                                tab_style,
                             )
                         };
                         // Apply selection highlighting
@@ -1333,7 +1862,7 @@ pub fn render_diff(
                     }
                     None => {
                         let panel_width = old_area.map(|a| a.width as usize).unwrap_or(80);
-                        let content_width = panel_width.saturating_sub(8);
+                        let content_width = panel_width.saturating_sub(8 + blame_width);
                         let pattern = generate_stripe_pattern(content_width);
                         old_spans.push(Span::styled(
                             "     ",
@@ -1345,17 +1874,82 @@ pub fn render_diff(
                         ));
                     }
                 }
-                old_lines.push(Line::from(old_spans));
+                old_rows = if unified {
+                    wrap_spans(old_spans, new_gutter_width, new_wrap_width)
+                } else {
+                    wrap_spans(old_spans, LINE_PREFIX_WIDTH + blame_width, old_wrap_width)
+                };
+                if diff_line.old_line.is_none() {
+                    old_rows.truncate(1);
+                }
             }
 
-            if new_area.is_some() {
+            if new_area.is_some() && (!unified || unified_new_row) {
                 let mut new_spans: Vec<Span> = Vec::new();
                 if old_area.is_none() {
                     new_spans.push(Span::styled(focus_indicator, focus_style));
                 }
+                if let Some(origins) = origins {
+                    let origin = diff_line
+                        .new_line
+                        .as_ref()
+                        .and_then(|(num, _)| origins.get(num.saturating_sub(1)))
+                        .and_then(Option::as_ref);
+                    new_spans.push(Span::styled(origin_cell(origin), blame_style));
+                }
                 match &diff_line.new_line {
-                    Some((num, _text)) => {
-                        let prefix = format!("{:4} ", num);
+                    // The new file as-is: no tint, just a bar where it changed
+                    Some((num, text)) if change_bars => {
+                        new_spans.push(Span::styled(
+                            format!("{:4}", num),
+                            Style::default().fg(t.ui.line_number),
+                        ));
+                        new_spans.push(change_bar(diff_line.change_type, &t));
+                        let matches = search_state.get_matches_for_line(line_idx, MatchPanel::New);
+                        let content_spans = apply_search_highlight(
+                            text,
+                            &diff.filename,
+                            None,
+                            &matches,
+                            Some(&new_highlighter),
+                            Some(*num),
+                            tab_style,
+                        );
+                        new_spans.extend(apply_selection_to_spans(
+                            content_spans,
+                            new_selection_range,
+                            bg,
+                        ));
+                    }
+                    // Removed lines stay in place as thin, dimmed markers
+                    None if change_bars => {
+                        new_spans.push(Span::raw("    "));
+                        new_spans.push(change_bar(diff_line.change_type, &t));
+                        if let Some((_, text)) = &diff_line.old_line {
+                            new_spans.push(Span::styled(
+                                expand_tabs(text, tab_width),
+                                Style::default()
+                                    .fg(t.diff.deleted_gutter_fg)
+                                    .add_modifier(Modifier::DIM | Modifier::CROSSED_OUT),
+                            ));
+                        }
+                    }
+                    Some((num, text)) => {
+                        let unchanged = matches!(diff_line.change_type, ChangeType::Equal);
+                        let sign = if unified_new_row && !unchanged {
+                            '+'
+                        } else {
+                            ' '
+                        };
+                        let prefix = match &diff_line.old_line {
+                            // Unchanged rows carry their old number too; changed ones
+                            // leave it to the removed row above
+                            Some((old_num, _)) if unified && unchanged => {
+                                format!("{:4} {:4}{}", old_num, num, sign)
+                            }
+                            _ if unified => format!("{:4} {:4}{}", "", num, sign),
+                            _ => format!("{:4}{}", num, sign),
+                        };
                         new_spans.push(Span::styled(
                             prefix,
                             Style::default()
@@ -1367,9 +1961,12 @@ pub fn render_diff(
                         // Use word-level rendering for modified lines if segments are available
                         let content_spans = if matches!(diff_line.change_type, ChangeType::Modified) {
                             if let Some(ref segments) = diff_line.new_segments {
-                                let emphasis_ranges = segments_to_emphasis_ranges(segments);
+                                let emphasis_ranges = segments_to_emphasis_ranges(
+                                    segments,
+                                    settings.hide_whitespace_emphasis,
+                                );
                                 apply_word_emphasis_highlight(
-                                    _text,
+                                    text,
                                     &diff.filename,
                                     style.new_bg,
                                     t.diff.added_word_bg,
@@ -1377,41 +1974,73 @@ pub fn render_diff(
                                     &matches,
                                     Some(&new_highlighter),
                                     Some(*num),
-                                    settings.tab_width,
+                                    tab_style,
                                 )
                             } else {
                                 apply_search_highlight(
-                                    _text,
+                                    text,
                                     &diff.filename,
                                     style.new_bg,
                                     &matches,
                                     Some(&new_highlighter),
                                     Some(*num),
-                                    settings.tab_width,
+                                    tab_style,
                                 )
                             }
                         } else {
                             apply_search_highlight(
-                                _text,
+                                text,
                                 &diff.filename,
                                 style.new_bg,
                                 &matches,
                                 Some(&new_highlighter),
                                 Some(*num),
-                                settings.tab_width,
+                                tab_style,
                             )
                         };
+                        let content_spans = if is_conflict_marker(text) {
+                            let marker = Style::default()
+                                .fg(t.ui.status_deleted)
+                                .add_modifier(Modifier::BOLD);
+                            content_spans
+                                .into_iter()
+                                .map(|span| span.patch_style(marker))
+                                .collect()
+                        } else {
+                            content_spans
+                        };
+                        // Tint each side of a conflict, leaving search and word highlights
+                        let conflict_bg = conflicts.get(line_idx).copied().flatten().map(
+                            |section| match section {
+                                ConflictSection::Ours => t.diff.conflict_ours_bg,
+                                ConflictSection::Base => t.diff.conflict_base_bg,
+                                ConflictSection::Theirs => t.diff.conflict_theirs_bg,
+                            },
+                        );
+                        let content_spans: Vec<Span> = match conflict_bg {
+                            Some(conflict_bg) => content_spans
+                                .into_iter()
+                                .map(|span| {
+                                    if span.style.bg == style.new_bg {
+                                        span.patch_style(Style::default().bg(conflict_bg))
+                                    } else {
+                                        span
+                                    }
+                                })
+                                .collect(),
+                            None => content_spans,
+                        };
                         // Apply selection highlighting
                         let content_spans = apply_selection_to_spans(
                             content_spans,
                             new_selection_range,
-                            style.new_bg.unwrap_or(bg),
+                            conflict_bg.or(style.new_bg).unwrap_or(bg),
                         );
                         new_spans.extend(content_spans);
                     }
                     None => {
                         let panel_width = new_area.map(|a| a.width as usize).unwrap_or(80);
-                        let content_width = panel_width.saturating_sub(8);
+                        let content_width = panel_width.saturating_sub(8 + origin_width);
                         let pattern = generate_stripe_pattern(content_width);
                         new_spans.push(Span::styled(
                             "     ",
@@ -1423,17 +2052,37 @@ pub fn render_diff(
                         ));
                     }
                 }
-                new_lines.push(Line::from(new_spans));
+                new_rows = wrap_spans(new_spans, new_gutter_width, new_wrap_width);
+                // Placeholders and change-bar deletion markers keep to one row
+                if diff_line.new_line.is_none() {
+                    new_rows.truncate(1);
+                }
+            }
+
+            if unified {
+                new_lines.extend(old_rows);
+                new_lines.extend(new_rows);
+            } else {
+                let row_count = old_rows.len().max(new_rows.len());
+                if old_area.is_some() {
+                    pad_wrapped_rows(&mut old_rows, row_count, diff_line.old_line.is_none());
+                    old_lines.extend(old_rows);
+                }
+                if new_area.is_some() {
+                    pad_wrapped_rows(&mut new_rows, row_count, diff_line.new_line.is_none());
+                    new_lines.extend(new_rows);
+                }
             }
 
             // Check if we need to add annotation content rows after this line
             if let Some(hunk_idx) = is_last_changed_line_of_hunk(line_idx, &visible_lines) {
-                if let Some(annotation) = annotations
-                    .iter()
-                    .find(|a| a.file_index == current_file && a.hunk_index == hunk_idx)
+                // Local notes first, then any pending review comments for the same hunk
+                for annotation in all_annotations()
+                    .filter(|a| a.filename == diff.filename && a.hunk_index == hunk_idx)
                 {
                     // Add annotation content - track position for overlay rendering
-                    let content_lines: Vec<&str> = annotation.content.lines().collect();
+                    let content_lines =
+                        annotation_overlay_lines(&annotation.content, settings.annotation_limit);
                     let num_lines = content_lines.len() + 2; // +2 for top and bottom borders
 
                     // Use the rendered panel's line count for position
@@ -1459,13 +2108,27 @@ pub fn render_diff(
         }
 
         if let Some(area) = old_area {
+            // Without an inner border the old panel's right edge stays a blank column
+            let (old_borders, old_padding) =
+                if new_area.is_some() && settings.panel_borders == PanelBorders::None {
+                    (
+                        Borders::TOP | Borders::LEFT | Borders::BOTTOM,
+                        Padding::right(1),
+                    )
+                } else {
+                    (Borders::ALL, Padding::ZERO)
+                };
             let old_para = Paragraph::new(old_lines)
                 .style(Style::default().bg(bg))
                 .scroll((0, h_scroll))
                 .block(
                     Block::default()
-                        .title(Line::styled(" [2] Old ", title_style))
-                        .borders(Borders::ALL)
+                        .title(Line::styled(
+                            format!(" [2] {} ", panel_titles.old),
+                            title_style,
+                        ))
+                        .borders(old_borders)
+                        .padding(old_padding)
                         .border_style(border_style),
                 );
             frame.render_widget(old_para, area);
@@ -1473,17 +2136,17 @@ pub fn render_diff(
 
         if let Some(area) = new_area {
             // When both panels are shown, new panel has no left border to share with old panel
-            let new_borders = if old_area.is_some() {
-                Borders::TOP | Borders::RIGHT | Borders::BOTTOM
-            } else {
+            let new_borders = if new_left_border {
                 Borders::ALL
+            } else {
+                Borders::TOP | Borders::RIGHT | Borders::BOTTOM
             };
             let new_para = Paragraph::new(new_lines)
                 .style(Style::default().bg(bg))
                 .scroll((0, h_scroll))
                 .block(
                     Block::default()
-                        .title(Line::styled(" New ", title_style))
+                        .title(Line::styled(format!(" {} ", panel_titles.new), title_style))
                         .borders(new_borders)
                         .style(Style::default().bg(bg))
                         .border_style(border_style),
@@ -1501,9 +2164,17 @@ pub fn render_diff(
             render_area.width.saturating_sub(2)
         };
 
-        render_annotation_overlays(frame, &annotation_overlays, content_x, content_start_y, content_width, main_area, bg, t);
+        render_annotation_overlays(frame, &annotation_overlays, content_x, content_start_y, content_width, main_area, bg, &t, settings.annotation_limit);
     }
 
+    render_minimap(frame, main_area, side_by_side, scroll as usize, &t);
+
+    // A deleted file is measured as it was before the deletion
+    let sized = if diff.new_content.is_empty() {
+        &diff.old_content
+    } else {
+        &diff.new_content
+    };
     render_footer(
         frame,
         footer_area,
@@ -1518,8 +2189,136 @@ pub fn render_diff(
             line_stats_removed: line_stats.removed,
             hunk_count,
             focused_hunk,
+            file_size: Some((sized.lines().count(), sized.len())),
+            reviewed_hunk_count: reviewed_hunks
+                .iter()
+                .filter(|(file_index, _)| *file_index == current_file)
+                .count(),
+            skip_reviewed_hunks,
+            conflict_count: conflict_counts.iter().sum(),
+            hidden_deleted_count,
+            status_message,
             search_state,
+            grep: settings.grep.as_ref().map(|grep| grep.pattern.as_str()),
+            prompt,
             area_width: area.width,
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::diff::coordinates::{wrapped_char_index, wrapped_row_count};
+
+    #[test]
+    fn test_annotation_overlay_lines_cut_at_limit() {
+        assert_eq!(annotation_overlay_lines("short\nnote", 20), vec!["short", "note"]);
+
+        let lines = annotation_overlay_lines("first line\nsecond line", 13);
+        assert_eq!(
+            lines,
+            vec!["first line", "se…", "[+9 more characters · I to read all]"]
+        );
+    }
+
+    #[test]
+    fn test_segments_to_emphasis_ranges_hides_whitespace() {
+        let segment = |text: &str, emphasized| InlineSegment {
+            text: text.to_string(),
+            emphasized,
+        };
+        let segments = vec![
+            segment("    ", true),
+            segment("let x = ", false),
+            segment("2", true),
+            segment(";", false),
+        ];
+
+        assert_eq!(segments_to_emphasis_ranges(&segments, false), vec![(0, 4), (12, 13)]);
+        assert_eq!(segments_to_emphasis_ranges(&segments, true), vec![(12, 13)]);
+    }
+
+    #[test]
+    fn test_tab_glyph_keeps_columns() {
+        let tabs = TabStyle {
+            width: 4,
+            glyph: Some('→'),
+        };
+        let spans = vec![Span::raw("\tif"), Span::raw(" x\ty")];
+        let columns = tab_glyph_columns(&spans, tabs);
+        assert_eq!(columns, vec![0, 8]);
+
+        let drawn = draw_tab_glyphs(expand_tabs_in_spans(spans, tabs.width), &columns, tabs);
+        let text: String = drawn.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text, "→   if x→   y");
+
+        let plain = TabStyle {
+            glyph: None,
+            ..tabs
+        };
+        assert!(tab_glyph_columns(&[Span::raw("\t")], plain).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_spans_keeps_styles_on_continuation_rows() {
+        let gutter = Style::default().bg(Color::Green);
+        let added = Style::default().bg(Color::LightGreen);
+        let spans = vec![Span::styled("  1 ", gutter), Span::styled("abcdefg", added)];
+        let rows = wrap_spans(spans, 4, 3);
+        let text =
+            |line: &Line| -> String { line.spans.iter().map(|s| s.content.as_ref()).collect() };
+        assert_eq!(
+            rows.iter().map(text).collect::<Vec<_>>(),
+            vec!["  1 abc", "    def", "    g"]
+        );
+        assert_eq!(rows[1].spans[0].style, gutter);
+        assert!(rows[1..].iter().all(|row| row.spans[1].style == added));
+
+        assert_eq!(wrap_spans(vec![Span::raw("abcdef")], 0, 0).len(), 1);
+    }
+
+    #[test]
+    fn test_wrap_spans_counts_display_width() {
+        let spans = vec![Span::raw("  1 "), Span::raw("a漢字🦀b")];
+        let rows = wrap_spans(spans, 4, 4);
+        let text =
+            |line: &Line| -> String { line.spans.iter().map(|s| s.content.as_ref()).collect() };
+        assert_eq!(
+            rows.iter().map(text).collect::<Vec<_>>(),
+            vec!["  1 a漢", "    字🦀", "    b"]
+        );
+        assert_eq!(wrapped_row_count("a漢字🦀b", 4), rows.len());
+        assert_eq!(wrapped_char_index("a漢字🦀b", 4, 1, 2), 3);
+        assert_eq!(wrapped_char_index("a漢字🦀b", 4, 0, 3), 2);
+    }
+
+    #[test]
+    fn test_minimap_cells_bucket_changes() {
+        let line = |change_type| DiffLine {
+            old_line: None,
+            new_line: None,
+            change_type,
+            old_segments: None,
+            new_segments: None,
+        };
+        let mut lines: Vec<DiffLine> = (0..8).map(|_| line(ChangeType::Equal)).collect();
+        lines[0] = line(ChangeType::Insert);
+        lines[3] = line(ChangeType::Delete);
+        lines[4] = line(ChangeType::Insert);
+        lines[5] = line(ChangeType::Delete);
+        lines[7] = line(ChangeType::Modified);
+
+        let kinds: Vec<&str> = minimap_cells(&lines, 4)
+            .into_iter()
+            .map(|cell| match cell {
+                ChangeType::Insert => "+",
+                ChangeType::Delete => "-",
+                ChangeType::Modified => "~",
+                _ => " ",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["+", "-", "~", "~"]);
+        assert_eq!(minimap_cells(&lines, 16).len(), 16);
+    }
+}
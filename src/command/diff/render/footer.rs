@@ -3,9 +3,13 @@ use std::collections::HashSet;
 use ratatui::{prelude::*, widgets::Paragraph};
 
 use crate::command::diff::search::{SearchMode, SearchState};
+use crate::command::diff::state::FooterPrompt;
 use crate::command::diff::theme;
 use crate::command::diff::PrInfo;
 
+/// A PR this far behind its base is flagged as stale in the footer.
+const STALE_BEHIND_COMMITS: usize = 20;
+
 pub struct FooterData<'a> {
     pub filename: &'a str,
     pub commit_ref: &'a str,
@@ -17,7 +21,22 @@ pub struct FooterData<'a> {
     pub line_stats_removed: usize,
     pub hunk_count: usize,
     pub focused_hunk: Option<usize>,
+    /// Line count and byte size of the file's newest version; None for binary files
+    pub file_size: Option<(usize, usize)>,
+    /// Number of hunks in the current file marked as reviewed
+    pub reviewed_hunk_count: usize,
+    pub skip_reviewed_hunks: bool,
+    /// Total unresolved conflict blocks across all files in the diff
+    pub conflict_count: usize,
+    /// Deleted files hidden because `show_deleted` is off
+    pub hidden_deleted_count: usize,
+    /// Short-lived notice (e.g. the theme just switched to)
+    pub status_message: Option<&'a str>,
     pub search_state: &'a SearchState,
+    /// The `--grep` pattern the diff is narrowed to
+    pub grep: Option<&'a str>,
+    /// Open text prompt; drawn instead of everything else
+    pub prompt: Option<&'a FooterPrompt>,
     pub area_width: u16,
 }
 
@@ -84,25 +103,70 @@ pub fn truncate_path(path: &str, max_len: usize) -> String {
     }
 }
 
+/// "1,204 lines · 48 KiB": a file's length, for judging how much there is to read.
+pub fn format_file_size(lines: usize, bytes: usize) -> String {
+    let digits = lines.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in ["KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    let size = match unit {
+        "B" => format!("{} B", bytes),
+        _ if size < 10.0 => format!("{:.1} {}", size, unit),
+        _ => format!("{:.0} {}", size, unit),
+    };
+
+    format!(
+        "{} {} · {}",
+        grouped,
+        if lines == 1 { "line" } else { "lines" },
+        size
+    )
+}
+
 pub fn render_footer(frame: &mut Frame, footer_area: Rect, data: FooterData) {
     let t = theme::get();
     let bg = t.ui.bg;
 
-    if data.search_state.is_active() {
-        let prefix = match data.search_state.mode {
-            SearchMode::InputForward => "/",
-            SearchMode::Inactive => "",
+    let input = match data.prompt {
+        Some(prompt) => Some((prompt.label, prompt.input.as_str())),
+        None if data.search_state.is_active() => {
+            let prefix = match data.search_state.mode {
+                SearchMode::InputForward if data.search_state.regex => "regex /",
+                SearchMode::InputForward => "/",
+                SearchMode::Inactive => "",
+            };
+            Some((prefix, data.search_state.query.as_str()))
+        }
+        None => None,
+    };
+
+    if let Some((prefix, text)) = input {
+        let text_color = if data.prompt.is_none() && data.search_state.is_invalid_regex() {
+            t.ui.status_deleted
+        } else {
+            t.ui.text_primary
         };
         let search_spans = vec![
             Span::styled(prefix, Style::default().fg(t.ui.highlight).bg(bg)),
-            Span::styled(
-                &data.search_state.query,
-                Style::default().fg(t.ui.text_primary).bg(bg),
-            ),
+            Span::styled(text, Style::default().fg(text_color).bg(bg)),
             Span::styled("_", Style::default().fg(t.ui.text_muted).bg(bg)),
         ];
-        let remaining_width =
-            footer_area.width as usize - prefix.len() - data.search_state.query.len() - 1;
+        let remaining_width = (footer_area.width as usize)
+            .saturating_sub(prefix.chars().count() + text.chars().count() + 1);
         let mut spans = search_spans;
         spans.push(Span::styled(
             " ".repeat(remaining_width),
@@ -233,29 +297,83 @@ pub fn render_footer(frame: &mut Frame, footer_area: Rect, data: FooterData) {
                 ),
             ]
         } else {
-            vec![
+            let mut spans = Vec::new();
+            if let Some(message) = data.status_message {
+                spans.push(Span::styled(
+                    format!("{} ", message),
+                    Style::default().fg(t.ui.highlight).bg(bg),
+                ));
+            }
+            if let Some(pattern) = data.grep {
+                spans.push(Span::styled(
+                    format!("grep /{}/ ", pattern),
+                    Style::default().fg(t.ui.highlight).bg(bg),
+                ));
+            }
+            if data.conflict_count > 0 {
+                spans.push(Span::styled(
+                    format!(
+                        "{} {} ",
+                        data.conflict_count,
+                        if data.conflict_count == 1 {
+                            "conflict"
+                        } else {
+                            "conflicts"
+                        }
+                    ),
+                    Style::default().fg(t.ui.status_deleted).bg(bg),
+                ));
+            }
+            let behind = data
+                .pr_info
+                .and_then(|pr| Some((pr.base_ref.as_str(), pr.behind_by?)));
+            if let Some((base, count)) = behind.filter(|&(_, count)| count > 0) {
+                let color = if count >= STALE_BEHIND_COMMITS {
+                    t.ui.status_modified
+                } else {
+                    t.ui.text_muted
+                };
+                spans.push(Span::styled(
+                    format!(
+                        "behind {} by {} commit{} ",
+                        base,
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    ),
+                    Style::default().fg(color).bg(bg),
+                ));
+            }
+            if data.hidden_deleted_count > 0 {
+                spans.push(Span::styled(
+                    format!("{} deleted hidden ", data.hidden_deleted_count),
+                    Style::default().fg(t.ui.text_muted).bg(bg),
+                ));
+            }
+            if let Some((lines, bytes)) = data.file_size {
+                spans.push(Span::styled(
+                    format!("{} ", format_file_size(lines, bytes)),
+                    Style::default().fg(t.ui.text_muted).bg(bg),
+                ));
+            }
+            spans.extend([
                 Span::styled(
-                    if let Some(idx) = data.focused_hunk {
-                        format!(
-                            "({}/{} {}) ",
-                            idx + 1,
-                            data.hunk_count,
-                            if data.hunk_count == 1 {
-                                "hunk"
-                            } else {
-                                "hunks"
-                            }
-                        )
-                    } else {
-                        format!(
-                            "({} {}) ",
-                            data.hunk_count,
-                            if data.hunk_count == 1 {
-                                "hunk"
-                            } else {
-                                "hunks"
-                            }
-                        )
+                    {
+                        let hunk_label = if data.hunk_count == 1 {
+                            "hunk"
+                        } else {
+                            "hunks"
+                        };
+                        let position = if let Some(idx) = data.focused_hunk {
+                            format!("{}/{} {}", idx + 1, data.hunk_count, hunk_label)
+                        } else {
+                            format!("{} {}", data.hunk_count, hunk_label)
+                        };
+                        let reviewed = match (data.reviewed_hunk_count, data.skip_reviewed_hunks) {
+                            (0, false) => String::new(),
+                            (n, false) => format!(", {} reviewed", n),
+                            (n, true) => format!(", {} reviewed, skipping", n),
+                        };
+                        format!("({}{}) ", position, reviewed)
                     },
                     Style::default().fg(t.ui.text_muted).bg(bg),
                 ),
@@ -263,7 +381,8 @@ pub fn render_footer(frame: &mut Frame, footer_area: Rect, data: FooterData) {
                     " ? help ",
                     Style::default().fg(t.ui.text_muted).bg(bg),
                 ),
-            ]
+            ]);
+            spans
         };
 
         let left_line = Line::from(left_spans);
@@ -287,3 +406,18 @@ pub fn render_footer(frame: &mut Frame, footer_area: Rect, data: FooterData) {
         frame.render_widget(footer, footer_area);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_file_size() {
+        assert_eq!(format_file_size(1, 12), "1 line · 12 B");
+        assert_eq!(format_file_size(1204, 48 * 1024), "1,204 lines · 48 KiB");
+        assert_eq!(
+            format_file_size(1_000_000, 3 * 1024 * 1024 / 2),
+            "1,000,000 lines · 1.5 MiB"
+        );
+    }
+}
@@ -0,0 +1,338 @@
+//! Review snapshots as standalone HTML: the side-by-side diff with the active
+//! theme's syntax colors, change backgrounds and word emphasis written inline,
+//! so the page reads the same as the terminal without anything else installed.
+
+use std::fmt::Write as _;
+
+use ratatui::prelude::*;
+
+use crate::command::diff::highlight::FileHighlighter;
+use crate::command::diff::theme::{self, Theme, ThemeMode};
+use crate::command::diff::types::{
+    is_conflict_marker, DiffLine, DiffViewSettings, FileDiff, InlineSegment,
+};
+
+use super::diff_view::{
+    apply_word_emphasis_highlight, compute_line_stats, folded_label, segments_to_emphasis_ranges,
+    DiffLineStyle,
+};
+
+/// A file and the rows to show for it, as the viewer lays them out
+/// (folded in compact mode).
+pub struct HtmlFile<'a> {
+    pub diff: &'a FileDiff,
+    pub lines: Vec<DiffLine>,
+}
+
+/// The 16 ANSI colors, as most terminals draw them.
+const ANSI: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 49, 49),
+    (13, 188, 121),
+    (229, 229, 16),
+    (36, 114, 200),
+    (188, 63, 188),
+    (17, 168, 205),
+    (229, 229, 229),
+    (102, 102, 102),
+    (241, 76, 76),
+    (35, 209, 139),
+    (245, 245, 67),
+    (59, 142, 234),
+    (214, 112, 214),
+    (41, 184, 219),
+    (255, 255, 255),
+];
+
+/// CSS hex for a terminal color; None for `Reset`, which leaves the page's own color.
+fn css_color(color: Color) -> Option<String> {
+    let (r, g, b) = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i) if i < 16 => ANSI[i as usize],
+        Color::Indexed(i) if i < 232 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = i - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        Color::Indexed(i) => {
+            let gray = 8 + (i - 232) * 10;
+            (gray, gray, gray)
+        }
+        Color::Black => ANSI[0],
+        Color::Red => ANSI[1],
+        Color::Green => ANSI[2],
+        Color::Yellow => ANSI[3],
+        Color::Blue => ANSI[4],
+        Color::Magenta => ANSI[5],
+        Color::Cyan => ANSI[6],
+        Color::Gray => ANSI[7],
+        Color::DarkGray => ANSI[8],
+        Color::LightRed => ANSI[9],
+        Color::LightGreen => ANSI[10],
+        Color::LightYellow => ANSI[11],
+        Color::LightBlue => ANSI[12],
+        Color::LightMagenta => ANSI[13],
+        Color::LightCyan => ANSI[14],
+        Color::White => ANSI[15],
+    };
+    Some(format!("#{:02x}{:02x}{:02x}", r, g, b))
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `style` attribute for a foreground and background, empty when both are `Reset`.
+fn style_attr(fg: Option<Color>, bg: Option<Color>, bold: bool) -> String {
+    let mut css = String::new();
+    if let Some(fg) = fg.and_then(css_color) {
+        let _ = write!(css, "color:{};", fg);
+    }
+    if let Some(bg) = bg.and_then(css_color) {
+        let _ = write!(css, "background:{};", bg);
+    }
+    if bold {
+        css.push_str("font-weight:bold;");
+    }
+    if css.is_empty() {
+        css
+    } else {
+        format!(" style=\"{}\"", css)
+    }
+}
+
+/// One side of a file: its highlighter and the background that emphasizes changed words.
+struct Side<'a> {
+    filename: &'a str,
+    highlighter: FileHighlighter,
+    word_bg: Color,
+}
+
+impl Side<'_> {
+    /// Gutter and text cells for one side of a row; a hatched blank where the side has no line.
+    fn push_cells(
+        &self,
+        out: &mut String,
+        line: Option<&(usize, String)>,
+        segments: Option<&Vec<InlineSegment>>,
+        line_bg: Option<Color>,
+        gutter: (Option<Color>, Option<Color>),
+        settings: &DiffViewSettings,
+    ) {
+        let t = theme::get();
+        let Some((num, text)) = line else {
+            let stripe = css_color(t.diff.empty_placeholder_fg).unwrap_or_default();
+            let _ = write!(
+                out,
+                "<td class=\"num\"></td><td style=\"background:repeating-linear-gradient(135deg,transparent 0 5px,{} 5px 6px)\"></td>",
+                stripe
+            );
+            return;
+        };
+
+        let emphasis = segments
+            .map(|s| segments_to_emphasis_ranges(s, settings.hide_whitespace_emphasis))
+            .unwrap_or_default();
+        let spans = apply_word_emphasis_highlight(
+            text,
+            self.filename,
+            line_bg,
+            self.word_bg,
+            &emphasis,
+            &[],
+            Some(&self.highlighter),
+            Some(*num),
+            settings.tab_style(self.filename),
+        );
+        let _ = write!(
+            out,
+            "<td class=\"num\"{}>{}</td><td{}>",
+            style_attr(gutter.0, gutter.1, false),
+            num,
+            style_attr(None, line_bg, false)
+        );
+        // Conflict markers stand out as they do in the viewer
+        let marker = is_conflict_marker(text).then_some(t.ui.status_deleted);
+        for span in spans {
+            let bold = marker.is_some() || span.style.add_modifier.contains(Modifier::BOLD);
+            let _ = write!(
+                out,
+                "<span{}>{}</span>",
+                style_attr(marker.or(span.style.fg), span.style.bg, bold),
+                escape(&span.content)
+            );
+        }
+        out.push_str("</td>");
+    }
+}
+
+fn push_file(out: &mut String, file: &HtmlFile, settings: &DiffViewSettings, t: &Theme) {
+    let diff = file.diff;
+    let stats = compute_line_stats(&file.lines);
+    let _ = write!(
+        out,
+        "<h2>{} {} <span{}>+{}</span> <span{}>-{}</span></h2>\n<table>\n",
+        diff.status.symbol(),
+        escape(&diff.filename),
+        style_attr(Some(t.ui.stats_added), None, false),
+        stats.added,
+        style_attr(Some(t.ui.stats_removed), None, false),
+        stats.removed
+    );
+    if diff.is_binary {
+        out.push_str("<tr class=\"note\"><td>Binary file not shown</td></tr>\n</table>\n");
+        return;
+    }
+
+    let old = Side {
+        filename: &diff.filename,
+        highlighter: FileHighlighter::new(&diff.old_content, &diff.filename),
+        word_bg: t.diff.deleted_word_bg,
+    };
+    let new = Side {
+        filename: &diff.filename,
+        highlighter: FileHighlighter::new(&diff.new_content, &diff.filename),
+        word_bg: t.diff.added_word_bg,
+    };
+    for line in &file.lines {
+        if let Some(label) = folded_label(line.change_type) {
+            let _ = writeln!(
+                out,
+                "<tr class=\"note\"><td colspan=\"4\"{}>{}</td></tr>",
+                style_attr(Some(t.ui.text_muted), None, false),
+                label
+            );
+            continue;
+        }
+        let style = DiffLineStyle::for_change_type(line.change_type, t.ui.bg, t);
+        out.push_str("<tr>");
+        old.push_cells(
+            out,
+            line.old_line.as_ref(),
+            line.old_segments.as_ref(),
+            style.old_bg,
+            (style.old_gutter_fg, style.old_gutter_bg),
+            settings,
+        );
+        new.push_cells(
+            out,
+            line.new_line.as_ref(),
+            line.new_segments.as_ref(),
+            style.new_bg,
+            (style.new_gutter_fg, style.new_gutter_bg),
+            settings,
+        );
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+/// A complete HTML page showing `files` one after another under `title`.
+pub fn diff_html(title: &str, files: &[HtmlFile], settings: &DiffViewSettings) -> String {
+    let t = theme::get();
+    // Themes that use the terminal's own background still need one on a page
+    let page_bg = css_color(t.ui.bg).unwrap_or_else(|| {
+        match t.mode {
+            ThemeMode::Dark => "#0d1117",
+            ThemeMode::Light => "#ffffff",
+        }
+        .to_string()
+    });
+    let text = css_color(t.syntax.default_text).unwrap_or_default();
+    let muted = css_color(t.ui.line_number).unwrap_or_default();
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{ margin: 0; padding: 16px 24px; background: {page_bg}; color: {text};
+  font: 13px/1.45 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }}
+h1 {{ font-size: 16px; margin: 0 0 8px; }}
+h2 {{ font-size: 13px; margin: 28px 0 6px; }}
+table {{ width: 100%; border-collapse: collapse; table-layout: fixed; }}
+td {{ padding: 0 8px; vertical-align: top; white-space: pre-wrap; overflow-wrap: anywhere; }}
+td.num {{ width: 4em; text-align: right; color: {muted}; user-select: none; }}
+tr.note td {{ text-align: center; padding: 2px 0; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+",
+        title = escape(title),
+    );
+    for file in files {
+        push_file(&mut out, file, settings, &t);
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::diff::diff_algo::compute_side_by_side;
+    use crate::command::diff::types::FileStatus;
+
+    #[test]
+    fn test_css_color() {
+        assert_eq!(
+            css_color(Color::Rgb(255, 8, 0)),
+            Some("#ff0800".to_string())
+        );
+        assert_eq!(css_color(Color::Reset), None);
+        assert_eq!(css_color(Color::Indexed(16)), Some("#000000".to_string()));
+        assert_eq!(css_color(Color::Indexed(231)), Some("#ffffff".to_string()));
+        assert_eq!(css_color(Color::Indexed(232)), Some("#080808".to_string()));
+    }
+
+    #[test]
+    fn test_diff_html_rows() {
+        let diff = FileDiff {
+            filename: "a.txt".to_string(),
+            old_content: "keep\nif a < b\n".to_string(),
+            new_content: "keep\nif a <= b\nadded\n".to_string(),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        let lines = compute_side_by_side(&diff.old_content, &diff.new_content, 4, false);
+        let settings = DiffViewSettings::default();
+        let html = diff_html(
+            "a.txt <review>",
+            &[HtmlFile { diff: &diff, lines }],
+            &settings,
+        );
+
+        let t = theme::get();
+        assert!(html.contains("<title>a.txt &lt;review&gt;</title>"));
+        assert!(html.contains("M a.txt"));
+        assert!(html.contains("if a &lt;</span>"));
+        // Changed words carry the emphasis background, the added line its own
+        let emphasized = format!(
+            "background:{};\">=</span>",
+            css_color(t.diff.added_word_bg).unwrap()
+        );
+        assert!(html.contains(&emphasized));
+        assert!(html.contains(&css_color(t.diff.added_bg).unwrap()));
+        // The added line has no old side
+        assert!(html.contains("repeating-linear-gradient"));
+        assert_eq!(html.matches("<tr>").count(), 3);
+    }
+}
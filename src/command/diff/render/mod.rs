@@ -1,11 +1,13 @@
 mod diff_view;
 mod footer;
+mod html;
 pub mod modal;
 mod sidebar;
 
-pub use diff_view::{render_diff, render_empty_state};
+pub use diff_view::{compute_line_stats, render_diff, render_empty_state};
 pub use footer::truncate_path;
+pub use html::{diff_html, HtmlFile};
 pub use modal::{
     FilePickerItem, FileStatus as ModalFileStatus, KeyBind, KeyBindSection, Modal, ModalContent,
-    ModalResult,
+    ModalResult, OverviewItem,
 };
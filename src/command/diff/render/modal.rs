@@ -1,11 +1,17 @@
+use std::cmp::Reverse;
+use std::time::Instant;
+
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
 };
 
+use super::footer::truncate_path;
+use crate::command::diff::indicators::StatusIndicators;
 use crate::command::diff::state::HunkAnnotation;
 use crate::command::diff::theme;
+use crate::command::diff::types;
 
 #[derive(Clone)]
 pub struct KeyBind {
@@ -22,9 +28,25 @@ pub struct KeyBindSection {
 #[derive(Clone)]
 pub struct FilePickerItem {
     pub name: String,
+    /// File-type icon prefix; empty when icons are disabled
+    pub icon: &'static str,
+    pub file_index: usize,
+    pub status: FileStatus,
+    pub viewed: bool,
+}
+
+/// One row of the overview landing page.
+#[derive(Clone)]
+pub struct OverviewItem {
+    pub name: String,
+    /// File-type icon prefix; empty when icons are disabled
+    pub icon: &'static str,
     pub file_index: usize,
     pub status: FileStatus,
     pub viewed: bool,
+    pub added: usize,
+    pub removed: usize,
+    pub is_binary: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -38,7 +60,6 @@ pub enum FileStatus {
 pub enum ModalContent {
     #[allow(dead_code)]
     Info { title: String, message: String },
-    #[allow(dead_code)]
     Select {
         title: String,
         items: Vec<String>,
@@ -56,18 +77,39 @@ pub enum ModalContent {
         filtered_indices: Vec<usize>,
         query: String,
         selected: usize,
+        indicators: StatusIndicators,
+    },
+    Overview {
+        title: String,
+        items: Vec<OverviewItem>,
+        selected: usize,
+        scroll: usize,
+        indicators: StatusIndicators,
     },
     Annotations {
         title: String,
         items: Vec<String>,
         annotations: Vec<HunkAnnotation>,
+        /// Pending GitHub review comments, which are shown inline but not listed
+        pending_review: usize,
         selected: usize,
+        /// First visible row; kept so the selection stays in view while navigating
+        scroll: usize,
         export_input: Option<String>,
         /// Error message to display (e.g., for failed export)
         error_message: Option<String>,
     },
+    Explanation {
+        title: String,
+        /// None while the provider request is still running
+        result: Option<Result<String, String>>,
+        started: Instant,
+        scroll: u16,
+    },
 }
 
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 pub struct Modal {
     pub content: ModalContent,
 }
@@ -78,9 +120,9 @@ pub enum ModalResult {
     #[allow(dead_code)]
     Selected(usize, String),
     FileSelected(usize),
-    AnnotationJump { file_index: usize, hunk_index: usize },
-    AnnotationEdit { file_index: usize, hunk_index: usize },
-    AnnotationDelete { file_index: usize, hunk_index: usize },
+    AnnotationJump { filename: String, hunk_index: usize },
+    AnnotationEdit { filename: String, hunk_index: usize },
+    AnnotationDelete { filename: String, hunk_index: usize },
     AnnotationCopyAll,
     AnnotationExport(String),
 }
@@ -96,7 +138,6 @@ impl Modal {
         }
     }
 
-    #[allow(dead_code)]
     pub fn select(title: impl Into<String>, items: Vec<String>) -> Self {
         Self {
             content: ModalContent::Select {
@@ -122,7 +163,11 @@ impl Modal {
         }
     }
 
-    pub fn file_picker(title: impl Into<String>, items: Vec<FilePickerItem>) -> Self {
+    pub fn file_picker(
+        title: impl Into<String>,
+        items: Vec<FilePickerItem>,
+        indicators: StatusIndicators,
+    ) -> Self {
         let filtered_indices: Vec<usize> = (0..items.len()).collect();
         Self {
             content: ModalContent::FilePicker {
@@ -131,6 +176,23 @@ impl Modal {
                 filtered_indices,
                 query: String::new(),
                 selected: 0,
+                indicators,
+            },
+        }
+    }
+
+    pub fn overview(
+        title: impl Into<String>,
+        items: Vec<OverviewItem>,
+        indicators: StatusIndicators,
+    ) -> Self {
+        Self {
+            content: ModalContent::Overview {
+                title: title.into(),
+                items,
+                selected: 0,
+                scroll: 0,
+                indicators,
             },
         }
     }
@@ -139,19 +201,41 @@ impl Modal {
         title: impl Into<String>,
         items: Vec<String>,
         annotations: Vec<HunkAnnotation>,
+        pending_review: usize,
     ) -> Self {
         Self {
             content: ModalContent::Annotations {
                 title: title.into(),
                 items,
                 annotations,
+                pending_review,
                 selected: 0,
+                scroll: 0,
                 export_input: None,
                 error_message: None,
             },
         }
     }
 
+    pub fn explanation(title: impl Into<String>) -> Self {
+        Self {
+            content: ModalContent::Explanation {
+                title: title.into(),
+                result: None,
+                started: Instant::now(),
+                scroll: 0,
+            },
+        }
+    }
+
+    /// Fill in a pending explanation modal with the provider's response.
+    pub fn set_explanation(&mut self, response: Result<String, String>) {
+        if let ModalContent::Explanation { result, scroll, .. } = &mut self.content {
+            *result = Some(response);
+            *scroll = 0;
+        }
+    }
+
     pub fn render(&self, frame: &mut Frame) {
         let area = frame.area();
 
@@ -185,6 +269,13 @@ impl Modal {
                 let height = (items_count + 5).min(area.height * 80 / 100).max(8);
                 (width, height)
             }
+            ModalContent::Overview { items, .. } => {
+                let width = 100.min(area.width.saturating_sub(4));
+                let height = (items.len().min(500) as u16 + 4)
+                    .min(area.height * 80 / 100)
+                    .max(8);
+                (width, height)
+            }
             ModalContent::Annotations {
                 items, export_input, ..
             } => {
@@ -195,6 +286,14 @@ impl Modal {
                 let height = (items_count + extra + 2).min(area.height * 80 / 100).max(8);
                 (width, height)
             }
+            ModalContent::Explanation { result, .. } => {
+                let width = 100.min(area.width.saturating_sub(4));
+                let height = match result {
+                    Some(_) => area.height * 80 / 100,
+                    None => 5,
+                };
+                (width, height.max(5))
+            }
         };
 
         let modal_x = (area.width.saturating_sub(modal_width)) / 2;
@@ -223,6 +322,7 @@ impl Modal {
                 filtered_indices,
                 query,
                 selected,
+                indicators,
             } => {
                 self.render_file_picker(
                     frame,
@@ -232,21 +332,101 @@ impl Modal {
                     filtered_indices,
                     query,
                     *selected,
+                    indicators,
+                );
+            }
+            ModalContent::Overview {
+                title,
+                items,
+                selected,
+                scroll,
+                indicators,
+            } => {
+                self.render_overview(
+                    frame, modal_area, title, items, *selected, *scroll, indicators,
                 );
             }
             ModalContent::Annotations {
                 title,
                 items,
+                annotations,
+                pending_review,
                 selected,
+                scroll,
                 export_input,
                 error_message,
-                ..
             } => {
-                self.render_annotations(frame, modal_area, title, items, *selected, export_input.as_deref(), error_message.as_deref());
+                self.render_annotations(
+                    frame,
+                    modal_area,
+                    title,
+                    items,
+                    annotations,
+                    *pending_review,
+                    *selected,
+                    *scroll,
+                    export_input.as_deref(),
+                    error_message.as_deref(),
+                );
+            }
+            ModalContent::Explanation {
+                title,
+                result,
+                started,
+                scroll,
+            } => {
+                self.render_explanation(frame, modal_area, title, result.as_ref(), *started, *scroll);
             }
         }
     }
 
+    fn render_explanation(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        result: Option<&Result<String, String>>,
+        started: Instant,
+        scroll: u16,
+    ) {
+        let t = theme::get();
+        let block = Block::default()
+            .title(format!(" {} ", title))
+            .title_style(Style::default().fg(t.ui.border_focused).bold())
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(t.ui.border_unfocused));
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let lines: Vec<Line> = match result {
+            None => {
+                let frame_idx = (started.elapsed().as_millis() / 80) as usize % SPINNER_FRAMES.len();
+                vec![Line::from(vec![
+                    Span::styled(
+                        format!(" {} ", SPINNER_FRAMES[frame_idx]),
+                        Style::default().fg(t.ui.highlight),
+                    ),
+                    Span::styled("Generating explanation...", Style::default().fg(t.ui.text_muted)),
+                ])]
+            }
+            Some(Ok(text)) => text
+                .lines()
+                .map(|line| Line::from(Span::styled(line, Style::default().fg(t.ui.text_primary))))
+                .collect(),
+            Some(Err(err)) => vec![Line::from(Span::styled(
+                format!("Error: {}", err),
+                Style::default().fg(t.ui.status_deleted),
+            ))],
+        };
+
+        let para = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0));
+        frame.render_widget(para, inner);
+    }
+
     fn render_info(&self, frame: &mut Frame, area: Rect, title: &str, message: &str) {
         let t = theme::get();
         let block = Block::default()
@@ -385,6 +565,7 @@ impl Modal {
         filtered_indices: &[usize],
         query: &str,
         selected: usize,
+        indicators: &StatusIndicators,
     ) {
         let t = theme::get();
         let block = Block::default()
@@ -430,21 +611,26 @@ impl Modal {
                 let item = &items[idx];
                 let is_selected = i == selected;
 
-                let (status_char, status_color) = match item.status {
-                    FileStatus::Added => ("A", t.ui.status_added),
-                    FileStatus::Modified => ("M", t.ui.status_modified),
-                    FileStatus::Deleted => ("D", t.ui.status_deleted),
+                let status = match item.status {
+                    FileStatus::Added => types::FileStatus::Added,
+                    FileStatus::Modified => types::FileStatus::Modified,
+                    FileStatus::Deleted => types::FileStatus::Deleted,
                 };
+                let status_char = indicators.symbol(status);
+                let status_color = indicators.color(status, &t);
 
                 let viewed_char = if item.viewed { "✓" } else { " " };
+                let matched = fuzzy_match(&item.name, query)
+                    .map(|(_, positions)| positions)
+                    .unwrap_or_default();
 
-                let spans = if is_selected {
+                let mut spans = if is_selected {
                     let selected_style =
                         Style::default().fg(t.ui.selection_fg).bg(t.ui.selection_bg);
                     vec![
                         Span::styled(format!(" {} ", viewed_char), selected_style),
                         Span::styled(format!("{} ", status_char), selected_style),
-                        Span::styled(item.name.clone(), selected_style),
+                        Span::styled(item.icon, selected_style),
                     ]
                 } else {
                     vec![
@@ -456,9 +642,20 @@ impl Modal {
                             format!("{} ", status_char),
                             Style::default().fg(status_color),
                         ),
-                        Span::styled(item.name.clone(), Style::default().fg(t.ui.text_primary)),
+                        Span::styled(item.icon, Style::default().fg(t.ui.text_primary)),
                     ]
                 };
+                let name_style = if is_selected {
+                    Style::default().fg(t.ui.selection_fg).bg(t.ui.selection_bg)
+                } else {
+                    Style::default().fg(t.ui.text_primary)
+                };
+                spans.extend(emphasize_chars(
+                    &item.name,
+                    &matched,
+                    name_style,
+                    name_style.fg(t.ui.highlight).add_modifier(Modifier::BOLD),
+                ));
 
                 ListItem::new(Line::from(spans))
             })
@@ -468,13 +665,171 @@ impl Modal {
         frame.render_widget(list, chunks[2]);
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn render_overview(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        items: &[OverviewItem],
+        selected: usize,
+        scroll: usize,
+        indicators: &StatusIndicators,
+    ) {
+        let t = theme::get();
+        let block = Block::default()
+            .title(format!(" {} ", title))
+            .title_style(Style::default().fg(t.ui.border_focused).bold())
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(t.ui.border_unfocused));
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        use ratatui::layout::{Constraint, Direction, Layout};
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(1),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let total_added: usize = items.iter().map(|item| item.added).sum();
+        let total_removed: usize = items.iter().map(|item| item.removed).sum();
+        let viewed = items.iter().filter(|item| item.viewed).count();
+        let summary = Line::from(vec![
+            Span::styled(
+                format!(" {} files  ", items.len()),
+                Style::default().fg(t.ui.text_primary),
+            ),
+            Span::styled(format!("+{}", total_added), Style::default().fg(t.ui.stats_added)),
+            Span::styled(" ", Style::default()),
+            Span::styled(format!("-{}", total_removed), Style::default().fg(t.ui.stats_removed)),
+            Span::styled(
+                format!("  {}/{} viewed", viewed, items.len()),
+                Style::default().fg(t.ui.text_muted),
+            ),
+        ]);
+        frame.render_widget(Paragraph::new(summary), chunks[0]);
+
+        let list_area = chunks[1];
+        let visible_count = list_area.height as usize;
+        // Re-clamp against the actual area in case the terminal was resized
+        let scroll_offset = scroll_to_keep_visible(scroll, selected, visible_count, items.len());
+        let has_scrollbar = items.len() > visible_count;
+        let available_width = list_area.width.saturating_sub(has_scrollbar as u16) as usize;
+
+        let list_items: Vec<ListItem> = items
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(visible_count)
+            .map(|(i, item)| {
+                let status = match item.status {
+                    FileStatus::Added => types::FileStatus::Added,
+                    FileStatus::Modified => types::FileStatus::Modified,
+                    FileStatus::Deleted => types::FileStatus::Deleted,
+                };
+                let viewed_char = if item.viewed { "✓" } else { " " };
+                let prefix = format!(" {} {} ", viewed_char, indicators.symbol(status));
+                let stats = if item.is_binary {
+                    "binary ".to_string()
+                } else {
+                    format!("+{} -{} ", item.added, item.removed)
+                };
+                let name_width = available_width.saturating_sub(
+                    prefix.chars().count() + stats.len() + item.icon.chars().count() + 1,
+                );
+                let name = truncate_path(&item.name, name_width);
+                let padding = name_width.saturating_sub(name.chars().count()) + 1;
+
+                let spans = if i == selected {
+                    let selected_style =
+                        Style::default().fg(t.ui.selection_fg).bg(t.ui.selection_bg);
+                    vec![
+                        Span::styled(prefix, selected_style),
+                        Span::styled(format!("{}{}", item.icon, name), selected_style),
+                        Span::styled(" ".repeat(padding), selected_style),
+                        Span::styled(stats, selected_style),
+                    ]
+                } else if item.is_binary {
+                    vec![
+                        Span::styled(prefix, Style::default().fg(indicators.color(status, &t))),
+                        Span::styled(
+                            format!("{}{}", item.icon, name),
+                            Style::default().fg(t.ui.text_primary),
+                        ),
+                        Span::styled(" ".repeat(padding), Style::default()),
+                        Span::styled(stats, Style::default().fg(t.ui.text_muted)),
+                    ]
+                } else {
+                    vec![
+                        Span::styled(prefix, Style::default().fg(indicators.color(status, &t))),
+                        Span::styled(
+                            format!("{}{}", item.icon, name),
+                            Style::default().fg(t.ui.text_primary),
+                        ),
+                        Span::styled(" ".repeat(padding), Style::default()),
+                        Span::styled(
+                            format!("+{}", item.added),
+                            Style::default().fg(t.ui.stats_added),
+                        ),
+                        Span::styled(
+                            format!(" -{} ", item.removed),
+                            Style::default().fg(t.ui.stats_removed),
+                        ),
+                    ]
+                };
+
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        frame.render_widget(List::new(list_items), list_area);
+
+        if has_scrollbar {
+            let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .track_symbol(Some("│"))
+                .thumb_symbol("█");
+
+            let mut scrollbar_state =
+                ScrollbarState::new(items.len().saturating_sub(visible_count)).position(scroll_offset);
+
+            frame.render_stateful_widget(scrollbar, list_area, &mut scrollbar_state);
+        }
+
+        let footer = Line::from(vec![
+            Span::styled("enter", Style::default().fg(t.ui.text_muted)),
+            Span::styled(" open  ", Style::default().fg(t.ui.text_muted)),
+            Span::styled("│  ", Style::default().fg(t.ui.border_unfocused)),
+            Span::styled("j/k", Style::default().fg(t.ui.text_muted)),
+            Span::styled(" move  ", Style::default().fg(t.ui.text_muted)),
+            Span::styled("│  ", Style::default().fg(t.ui.border_unfocused)),
+            Span::styled("esc", Style::default().fg(t.ui.text_muted)),
+            Span::styled(" close", Style::default().fg(t.ui.text_muted)),
+        ]);
+        frame.render_widget(
+            Paragraph::new(footer).alignment(ratatui::prelude::Alignment::Center),
+            chunks[2],
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn render_annotations(
         &self,
         frame: &mut Frame,
         area: Rect,
         title: &str,
         items: &[String],
+        annotations: &[HunkAnnotation],
+        pending_review: usize,
         selected: usize,
+        scroll: usize,
         export_input: Option<&str>,
         error_message: Option<&str>,
     ) {
@@ -483,12 +838,23 @@ impl Modal {
         // Compact title with count
         let title_text = format!(" {} ({}) ", title, items.len());
 
-        let block = Block::default()
+        let mut block = Block::default()
             .title(title_text)
             .title_style(Style::default().fg(t.ui.text_secondary))
             .borders(Borders::ALL)
             .border_type(ratatui::widgets::BorderType::Rounded)
             .border_style(Style::default().fg(t.ui.border_focused));
+        // Drafts from the PR's pending review are read-only, so they are not listed
+        if pending_review > 0 {
+            block = block.title_bottom(
+                Line::from(format!(
+                    " +{} pending review comment{} shown under their hunks ",
+                    pending_review,
+                    if pending_review == 1 { "" } else { "s" }
+                ))
+                .right_aligned(),
+            );
+        }
 
         let inner = block.inner(area);
         frame.render_widget(block, area);
@@ -516,11 +882,9 @@ impl Modal {
 
         // Render annotations list
         let visible_count = list_area.height as usize;
-        let scroll_offset = if selected >= visible_count {
-            selected - visible_count + 1
-        } else {
-            0
-        };
+        // Re-clamp against the actual area in case the terminal was resized
+        let scroll_offset = scroll_to_keep_visible(scroll, selected, visible_count, items.len());
+        let has_scrollbar = items.len() > visible_count;
 
         let list_items: Vec<ListItem> = items
             .iter()
@@ -537,8 +901,11 @@ impl Modal {
                 let preview = parts.get(1).unwrap_or(&"");
                 let time = parts.get(2).unwrap_or(&"");
 
-                let available_width = list_area.width as usize;
-                let time_width = time.len() + 2; // " time "
+                // Leave the last column to the scrollbar
+                let available_width = list_area.width.saturating_sub(has_scrollbar as u16) as usize;
+                let stale = annotations.get(i).is_some_and(|a| a.stale);
+                let stale_tag = if stale { " stale " } else { "" };
+                let time_width = time.len() + 2 + stale_tag.len(); // " time "
 
                 // Reserve space for time and calculate remaining space for location + preview
                 let content_width = available_width.saturating_sub(time_width + 4); // 4 for padding/separators
@@ -588,6 +955,10 @@ impl Modal {
                             format!("{:>width$}", "", width = padding),
                             Style::default().bg(t.ui.selection_bg),
                         ),
+                        Span::styled(
+                            stale_tag,
+                            Style::default().fg(t.ui.status_modified).bg(t.ui.selection_bg),
+                        ),
                         Span::styled(
                             format!(" {} ", time),
                             Style::default().fg(t.ui.selection_fg).bg(t.ui.selection_bg),
@@ -607,6 +978,7 @@ impl Modal {
                             format!("{:>width$}", "", width = padding),
                             Style::default(),
                         ),
+                        Span::styled(stale_tag, Style::default().fg(t.ui.status_modified)),
                         Span::styled(
                             format!(" {} ", time),
                             Style::default().fg(t.ui.text_muted),
@@ -621,6 +993,19 @@ impl Modal {
         let list = List::new(list_items);
         frame.render_widget(list, list_area);
 
+        if has_scrollbar {
+            let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .track_symbol(Some("│"))
+                .thumb_symbol("█");
+
+            let mut scrollbar_state =
+                ScrollbarState::new(items.len().saturating_sub(visible_count)).position(scroll_offset);
+
+            frame.render_stateful_widget(scrollbar, list_area, &mut scrollbar_state);
+        }
+
         // Render export input if active
         if let Some(input) = export_input {
             let input_area = Layout::default()
@@ -696,6 +1081,50 @@ impl Modal {
     /// Handle mouse scroll for the modal.
     /// Returns true if the scroll was handled.
     pub fn handle_mouse(&mut self, mouse: MouseEvent, terminal_height: u16) -> bool {
+        if let ModalContent::Overview {
+            items,
+            selected,
+            scroll,
+            ..
+        } = &mut self.content
+        {
+            let visible_count = calculate_overview_visible_count(terminal_height, items.len());
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    *selected = (*selected + 3).min(items.len().saturating_sub(1));
+                }
+                MouseEventKind::ScrollUp => {
+                    *selected = selected.saturating_sub(3);
+                }
+                _ => return false,
+            }
+            *scroll = scroll_to_keep_visible(*scroll, *selected, visible_count, items.len());
+            return true;
+        }
+
+        if let ModalContent::Annotations {
+            items,
+            selected,
+            scroll,
+            export_input,
+            ..
+        } = &mut self.content
+        {
+            let visible_count =
+                calculate_annotations_visible_count(terminal_height, items.len(), export_input.is_some());
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    *selected = (*selected + 3).min(items.len().saturating_sub(1));
+                }
+                MouseEventKind::ScrollUp => {
+                    *selected = selected.saturating_sub(3);
+                }
+                _ => return false,
+            }
+            *scroll = scroll_to_keep_visible(*scroll, *selected, visible_count, items.len());
+            return true;
+        }
+
         if let ModalContent::KeyBindings { scroll, content_height, .. } = &mut self.content {
             let visible_height = calculate_keybindings_visible_height(terminal_height, *content_height);
             let max_scroll = content_height.saturating_sub(visible_height);
@@ -761,6 +1190,43 @@ impl Modal {
                 }
                 _ => None,
             },
+            ModalContent::Explanation { result, scroll, .. } => {
+                // Wrapped height isn't known here, so bound scrolling by the raw line count
+                let max_scroll = match result {
+                    Some(Ok(text)) => text.lines().count().saturating_sub(1) as u16,
+                    _ => 0,
+                };
+                let half_page = terminal_height * 80 / 100 / 2;
+
+                match key.code {
+                    KeyCode::Enter => Some(ModalResult::Dismissed),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *scroll = (*scroll + 1).min(max_scroll);
+                        None
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *scroll = scroll.saturating_sub(1);
+                        None
+                    }
+                    KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        *scroll = (*scroll + half_page).min(max_scroll);
+                        None
+                    }
+                    KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        *scroll = scroll.saturating_sub(half_page);
+                        None
+                    }
+                    KeyCode::Char('g') => {
+                        *scroll = 0;
+                        None
+                    }
+                    KeyCode::Char('G') => {
+                        *scroll = max_scroll;
+                        None
+                    }
+                    _ => None,
+                }
+            }
             ModalContent::KeyBindings { scroll, content_height, .. } => {
                 let visible_height = calculate_keybindings_visible_height(terminal_height, *content_height);
                 let max_scroll = content_height.saturating_sub(visible_height);
@@ -853,14 +1319,57 @@ impl Modal {
                 }
                 _ => None,
             },
+            ModalContent::Overview {
+                items,
+                selected,
+                scroll,
+                ..
+            } => {
+                let visible_count = calculate_overview_visible_count(terminal_height, items.len());
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        if *selected < items.len().saturating_sub(1) {
+                            *selected += 1;
+                        }
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        *selected =
+                            (*selected + visible_count / 2).min(items.len().saturating_sub(1));
+                    }
+                    KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        *selected = selected.saturating_sub(visible_count / 2);
+                    }
+                    KeyCode::Char('g') | KeyCode::Home => {
+                        *selected = 0;
+                    }
+                    KeyCode::Char('G') | KeyCode::End => {
+                        *selected = items.len().saturating_sub(1);
+                    }
+                    KeyCode::Enter => {
+                        return Some(match items.get(*selected) {
+                            Some(item) => ModalResult::FileSelected(item.file_index),
+                            None => ModalResult::Dismissed,
+                        });
+                    }
+                    _ => return None,
+                }
+                *scroll = scroll_to_keep_visible(*scroll, *selected, visible_count, items.len());
+                None
+            }
             ModalContent::Annotations {
                 items,
                 annotations,
                 selected,
+                scroll,
                 export_input,
                 error_message,
                 ..
             } => {
+                let visible_count =
+                    calculate_annotations_visible_count(terminal_height, items.len(), export_input.is_some());
                 // Export input mode
                 if let Some(ref mut input) = export_input {
                     match key.code {
@@ -912,27 +1421,39 @@ impl Modal {
                             if *selected < items.len().saturating_sub(1) {
                                 *selected += 1;
                             }
+                            *scroll = scroll_to_keep_visible(*scroll, *selected, visible_count, items.len());
                             None
                         }
                         KeyCode::Up | KeyCode::Char('k') => {
                             *selected = selected.saturating_sub(1);
+                            *scroll = scroll_to_keep_visible(*scroll, *selected, visible_count, items.len());
+                            None
+                        }
+                        KeyCode::Char('g') | KeyCode::Home => {
+                            *selected = 0;
+                            *scroll = 0;
+                            None
+                        }
+                        KeyCode::Char('G') | KeyCode::End => {
+                            *selected = items.len().saturating_sub(1);
+                            *scroll = scroll_to_keep_visible(*scroll, *selected, visible_count, items.len());
                             None
                         }
                         KeyCode::Enter => annotations.get(*selected).map(|ann| {
                             ModalResult::AnnotationJump {
-                                file_index: ann.file_index,
+                                filename: ann.filename.clone(),
                                 hunk_index: ann.hunk_index,
                             }
                         }),
                         KeyCode::Char('e') => annotations.get(*selected).map(|ann| {
                             ModalResult::AnnotationEdit {
-                                file_index: ann.file_index,
+                                filename: ann.filename.clone(),
                                 hunk_index: ann.hunk_index,
                             }
                         }),
                         KeyCode::Char('d') => annotations.get(*selected).map(|ann| {
                             ModalResult::AnnotationDelete {
-                                file_index: ann.file_index,
+                                filename: ann.filename.clone(),
                                 hunk_index: ann.hunk_index,
                             }
                         }),
@@ -954,33 +1475,145 @@ impl Modal {
         filtered_indices: &mut Vec<usize>,
         selected: &mut usize,
     ) {
-        let query_lower = query.to_lowercase();
-        *filtered_indices = items
+        let mut scored: Vec<(i64, usize)> = items
             .iter()
             .enumerate()
-            .filter(|(_, item)| fuzzy_match(&item.name.to_lowercase(), &query_lower))
-            .map(|(i, _)| i)
+            .filter_map(|(i, item)| fuzzy_match(&item.name, query).map(|(score, _)| (score, i)))
             .collect();
-        if *selected >= filtered_indices.len() {
-            *selected = filtered_indices.len().saturating_sub(1);
-        }
+        // The sort is stable, so ties (and an empty query) keep the original order
+        scored.sort_by_key(|&(score, _)| Reverse(score));
+        *filtered_indices = scored.into_iter().map(|(_, i)| i).collect();
+        // The best match is now on top
+        *selected = 0;
     }
 }
 
-fn fuzzy_match(text: &str, pattern: &str) -> bool {
+const FUZZY_MATCH_SCORE: i64 = 16;
+/// Extra for a character that starts a path segment or word (`src/main`, `fooBar`)
+const FUZZY_BOUNDARY_BONUS: i64 = 10;
+/// Extra for a character right after the previous matched one
+const FUZZY_CONSECUTIVE_BONUS: i64 = 6;
+
+/// Match `pattern` against `text` as an in-order subsequence, ignoring case, like fzf.
+/// Characters at segment or word starts and runs of consecutive characters score
+/// higher, and a match that starts further in scores lower. Returns the best score
+/// with the char positions it matched, or None when `pattern` is not a subsequence.
+fn fuzzy_match(text: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.iter().map(|&c| fold(c)).collect();
+    let pattern: Vec<char> = pattern.chars().map(fold).collect();
     if pattern.is_empty() {
-        return true;
+        return Some((0, Vec::new()));
     }
-    let mut pattern_chars = pattern.chars().peekable();
-    for c in text.chars() {
-        if pattern_chars.peek() == Some(&c) {
-            pattern_chars.next();
+    let bonus = |j: usize| {
+        let boundary = j == 0
+            || matches!(text[j - 1], '/' | '_' | '-' | '.' | ' ')
+            || (text[j - 1].is_lowercase() && text[j].is_uppercase());
+        FUZZY_MATCH_SCORE + if boundary { FUZZY_BOUNDARY_BONUS } else { 0 }
+    };
+
+    // score[i][j]: best score with pattern[..=i] matched and pattern[i] at text[j];
+    // from[i][j]: where pattern[i - 1] sits on that best path
+    let n = text.len();
+    let mut score: Vec<Vec<Option<i64>>> = vec![vec![None; n]; pattern.len()];
+    let mut from = vec![vec![0; n]; pattern.len()];
+    for j in 0..n {
+        if lower[j] == pattern[0] {
+            score[0][j] = Some(bonus(j) - j as i64);
         }
-        if pattern_chars.peek().is_none() {
-            return true;
+    }
+    for i in 1..pattern.len() {
+        // Best earlier match of pattern[i - 1] that is not adjacent to j
+        let mut best: Option<(i64, usize)> = None;
+        for j in 1..n {
+            if let Some(s) = j.checked_sub(2).and_then(|k| score[i - 1][k]) {
+                if best.is_none_or(|(b, _)| s > b) {
+                    best = Some((s, j - 2));
+                }
+            }
+            if lower[j] != pattern[i] {
+                continue;
+            }
+            let adjacent = score[i - 1][j - 1].map(|s| (s + FUZZY_CONSECUTIVE_BONUS, j - 1));
+            let prev = match (best, adjacent) {
+                (Some(b), Some(a)) => Some(if a.0 >= b.0 { a } else { b }),
+                (b, a) => b.or(a),
+            };
+            if let Some((s, k)) = prev {
+                score[i][j] = Some(s + bonus(j));
+                from[i][j] = k;
+            }
         }
     }
-    pattern_chars.peek().is_none()
+
+    let last = pattern.len() - 1;
+    let (end, total) = (0..n)
+        .filter_map(|j| score[last][j].map(|s| (j, s)))
+        .max_by_key(|&(j, s)| (s, Reverse(j)))?;
+    let mut positions = vec![0; pattern.len()];
+    let mut j = end;
+    for i in (0..pattern.len()).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    Some((total, positions))
+}
+
+/// `text` split into spans, with the chars at `positions` in `matched` style.
+fn emphasize_chars(
+    text: &str,
+    positions: &[usize],
+    base: Style,
+    matched: Style,
+) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_matched = positions.contains(&i);
+        if is_matched != run_matched && !run.is_empty() {
+            let style = if run_matched { matched } else { base };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_matched = is_matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, if run_matched { matched } else { base }));
+    }
+    spans
+}
+
+/// Rows available to the annotations list; mirrors the modal sizing in `render`.
+fn calculate_annotations_visible_count(terminal_height: u16, item_count: usize, exporting: bool) -> usize {
+    let items_count = item_count.min(12) as u16;
+    let extra = if exporting { 4 } else { 2 };
+    let modal_height = (items_count + extra + 2).min(terminal_height * 80 / 100).max(8);
+    // Borders and footer line, plus the export input box when shown
+    let chrome = if exporting { 6 } else { 3 };
+    modal_height.saturating_sub(chrome).max(1) as usize
+}
+
+/// Rows available to the overview list; mirrors the modal sizing in `render`.
+fn calculate_overview_visible_count(terminal_height: u16, item_count: usize) -> usize {
+    let modal_height = (item_count.min(500) as u16 + 4).min(terminal_height * 80 / 100).max(8);
+    // Borders, summary line and footer line
+    modal_height.saturating_sub(4).max(1) as usize
+}
+
+/// Adjust `scroll` minimally so `selected` is inside the visible window, clamped to the list.
+fn scroll_to_keep_visible(scroll: usize, selected: usize, visible: usize, total: usize) -> usize {
+    let visible = visible.max(1);
+    let max_scroll = total.saturating_sub(visible);
+    let scroll = if selected < scroll {
+        selected
+    } else if selected >= scroll + visible {
+        selected + 1 - visible
+    } else {
+        scroll
+    };
+    scroll.min(max_scroll)
 }
 
 /// Calculate visible height for keybindings modal based on terminal size.
@@ -990,3 +1623,61 @@ fn calculate_keybindings_visible_height(terminal_height: u16, content_height: u1
     // Subtract 2 for top/bottom borders
     modal_height.saturating_sub(2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_to_keep_visible() {
+        // Moving down past the window scrolls by one row at a time
+        assert_eq!(scroll_to_keep_visible(0, 5, 5, 20), 1);
+        // Moving up inside the window keeps the offset
+        assert_eq!(scroll_to_keep_visible(3, 4, 5, 20), 3);
+        // Moving above the window scrolls up to the selection
+        assert_eq!(scroll_to_keep_visible(3, 2, 5, 20), 2);
+        // Never scrolls past the end, or at all when everything fits
+        assert_eq!(scroll_to_keep_visible(18, 19, 5, 20), 15);
+        assert_eq!(scroll_to_keep_visible(4, 2, 10, 3), 0);
+    }
+
+    #[test]
+    fn test_fuzzy_match_prefers_segment_starts() {
+        assert_eq!(fuzzy_match("src/main.rs", "xyz"), None);
+        assert_eq!(fuzzy_match("src/main.rs", ""), Some((0, Vec::new())));
+        // "mr" takes the m of main and the r of rs, both segment starts
+        let (_, positions) = fuzzy_match("src/main.rs", "mr").unwrap();
+        assert_eq!(positions, vec![4, 9]);
+
+        let score = |text: &str| fuzzy_match(text, "dv").unwrap().0;
+        assert!(score("diff/view.rs") > score("divider.rs"));
+        // An earlier match wins when everything else is equal
+        assert!(score("a/diff/view.rs") > score("abc/diff/view.rs"));
+
+        let items: Vec<FilePickerItem> = ["divider.rs", "src/diff/view.rs", "lib.rs"]
+            .iter()
+            .enumerate()
+            .map(|(file_index, name)| FilePickerItem {
+                name: name.to_string(),
+                icon: "",
+                file_index,
+                status: FileStatus::Modified,
+                viewed: false,
+            })
+            .collect();
+        let (mut filtered, mut selected) = (Vec::new(), 2);
+        Modal::update_filtered_indices(&items, "dv", &mut filtered, &mut selected);
+        assert_eq!((filtered, selected), (vec![1, 0], 0));
+        let mut filtered = Vec::new();
+        Modal::update_filtered_indices(&items, "", &mut filtered, &mut selected);
+        assert_eq!(filtered, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_overview_visible_count() {
+        // Short lists get a minimum-height modal, long ones are capped at 80% of the terminal
+        assert_eq!(calculate_overview_visible_count(50, 2), 4);
+        assert_eq!(calculate_overview_visible_count(50, 10), 10);
+        assert_eq!(calculate_overview_visible_count(50, 1000), 36);
+    }
+}
@@ -5,8 +5,9 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
 };
 
+use crate::command::diff::indicators::StatusIndicators;
 use crate::command::diff::theme;
-use crate::command::diff::types::{FileStatus, SidebarItem};
+use crate::command::diff::types::SidebarItem;
 
 #[allow(clippy::too_many_arguments)]
 pub fn render_sidebar(
@@ -20,6 +21,10 @@ pub fn render_sidebar(
     sidebar_scroll: usize,
     sidebar_h_scroll: u16,
     viewed_files: &HashSet<usize>,
+    changed_since_review: &HashSet<usize>,
+    filter: Option<(&str, usize)>,
+    conflict_counts: &[usize],
+    indicators: &StatusIndicators,
     is_focused: bool,
 ) {
     let t = theme::get();
@@ -64,8 +69,9 @@ pub fn render_sidebar(
                     } else {
                         "  "
                     };
+                    let collapsed = collapsed_dirs.contains(path);
                     let status_symbol = if has_children {
-                        if collapsed_dirs.contains(path) {
+                        if collapsed {
                             "▶"
                         } else {
                             "▼"
@@ -77,7 +83,7 @@ pub fn render_sidebar(
                         format!("{}{}", indent, marker),
                         status_symbol.to_string(),
                         None,
-                        format!(" {}", name),
+                        format!(" {}{}", indicators.dir_icon(collapsed), name),
                         false,
                         all_children_viewed && has_children,
                     )
@@ -91,24 +97,33 @@ pub fn render_sidebar(
                 } => {
                     let indent = "  ".repeat(*depth);
                     let viewed = viewed_files.contains(file_index);
-                    let marker = if viewed { "✓ " } else { "  " };
-                    let status_color = match status {
-                        FileStatus::Modified => Some(t.ui.status_modified),
-                        FileStatus::Added => Some(t.ui.status_added),
-                        FileStatus::Deleted => Some(t.ui.status_deleted),
+                    let marker = if changed_since_review.contains(file_index) {
+                        "* "
+                    } else if viewed {
+                        "✓ "
+                    } else {
+                        "  "
                     };
-                    let status_symbol = status.symbol().to_string();
+                    let status_color = Some(indicators.color(*status, &t));
+                    let status_symbol = indicators.symbol(*status).to_string();
                     (
                         format!("{}{}", indent, marker),
                         status_symbol,
                         status_color,
-                        format!(" {}", name),
+                        format!(" {}{}", indicators.file_icon(name), name),
                         *file_index == current_file,
                         viewed,
                     )
                 }
             };
 
+            let conflicts = match item {
+                SidebarItem::File { file_index, .. } => {
+                    conflict_counts.get(*file_index).copied().unwrap_or(0)
+                }
+                SidebarItem::Directory { .. } => 0,
+            };
+
             let is_selected = i == sidebar_selected;
             let base_style = if is_selected {
                 Style::default().fg(t.ui.selection_fg).bg(if is_focused {
@@ -132,11 +147,20 @@ pub fn render_sidebar(
                 base_style
             };
 
-            Line::from(vec![
+            let mut spans = vec![
                 Span::styled(prefix, base_style),
                 Span::styled(status_symbol, status_style),
                 Span::styled(name, base_style),
-            ])
+            ];
+            if conflicts > 0 {
+                let badge_style = if is_selected {
+                    base_style
+                } else {
+                    Style::default().fg(t.ui.status_deleted)
+                };
+                spans.push(Span::styled(format!(" !{}", conflicts), badge_style));
+            }
+            Line::from(spans)
         })
         .collect();
 
@@ -146,6 +170,10 @@ pub fn render_sidebar(
         Style::default().fg(t.ui.border_unfocused)
     };
     let border_style = Style::default().fg(t.ui.border_unfocused);
+    let title = match filter {
+        Some((pattern, count)) => format!(" [1] Files: {} ({}) ", pattern, count),
+        None => " [1] Files ".to_string(),
+    };
 
     let visible_lines: Vec<Line> = lines
         .into_iter()
@@ -158,7 +186,7 @@ pub fn render_sidebar(
         .scroll((0, sidebar_h_scroll))
         .block(
             Block::default()
-                .title(Line::styled(" [1] Files ", title_style))
+                .title(Line::styled(title, title_style))
                 .borders(Borders::ALL)
                 .border_style(border_style)
                 .style(Style::default().bg(bg)),
@@ -0,0 +1,85 @@
+//! On-disk record in `lumen/reviewed.json` under the per-user data directory of the
+//! file contents last seen in each pull request, so files pushed since then can be
+//! pointed out in the next session, whichever checkout it is opened from.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::PrInfo;
+
+/// Blob sha last seen for each path, per pull request.
+type Store = BTreeMap<String, BTreeMap<String, String>>;
+
+fn store_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("lumen").join("reviewed.json"))
+}
+
+/// Key for `pr` in the store, e.g. `github.com/acme/app#1`.
+fn store_key(pr: &PrInfo) -> String {
+    format!("{}/{}", pr.host, pr.full_ref())
+}
+
+/// Shas seen in `pr` by path; empty when it was never opened or the file is unreadable.
+pub fn load(pr: &PrInfo) -> HashMap<String, String> {
+    match store_path() {
+        Some(path) => load_from(&path, &store_key(pr)),
+        None => HashMap::new(),
+    }
+}
+
+/// Replace the shas recorded for `pr`, leaving other pull requests untouched.
+pub fn save(pr: &PrInfo, seen: &HashMap<String, String>) -> io::Result<()> {
+    let path = store_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user data directory"))?;
+    save_to(&path, &store_key(pr), seen)
+}
+
+fn read_store(path: &Path) -> Store {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn load_from(path: &Path, pr: &str) -> HashMap<String, String> {
+    read_store(path)
+        .remove(pr)
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+fn save_to(path: &Path, pr: &str, seen: &HashMap<String, String>) -> io::Result<()> {
+    if seen.is_empty() {
+        return Ok(());
+    }
+    let mut store = read_store(path);
+    store.insert(pr.to_string(), seen.clone().into_iter().collect());
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(&store).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_per_pr() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lumen/reviewed.json");
+        let seen = HashMap::from([("src/a.rs".to_string(), "abc123".to_string())]);
+
+        save_to(&path, "github.com/acme/app#1", &seen).unwrap();
+        save_to(&path, "github.com/acme/app#2", &HashMap::new()).unwrap();
+
+        assert_eq!(load_from(&path, "github.com/acme/app#1"), seen);
+        assert!(load_from(&path, "github.com/acme/app#2").is_empty());
+        assert!(load_from(&path, "ghe.example.com/acme/app#1").is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-use super::types::{DiffFullscreen, DiffLine};
+use regex::{Regex, RegexBuilder};
+
+use super::types::{ChangeType, DiffFullscreen, DiffLine};
 
 #[derive(Default, Clone, Copy, PartialEq)]
 pub enum SearchMode {
@@ -21,17 +23,42 @@ pub enum MatchPanel {
     New,
 }
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct SearchState {
     pub mode: SearchMode,
     pub query: String,
     pub matches: Vec<SearchMatch>,
     pub current_match: Option<usize>,
+    /// Step from the last match to the first (and back); off stops at either end
+    pub wrap: bool,
+    /// Scroll position when the search bar opened; typing searches from here and
+    /// cancelling returns here
+    pub origin_scroll: usize,
+    /// Treat the query as a regular expression (toggled with ctrl+r while typing)
+    pub regex: bool,
+    /// Last query compiled in regex mode; `None` inside when it is not a valid pattern
+    compiled: Option<(String, Option<Regex>)>,
+}
+
+impl Default for SearchState {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::default(),
+            query: String::new(),
+            matches: Vec::new(),
+            current_match: None,
+            wrap: true,
+            origin_scroll: 0,
+            regex: false,
+            compiled: None,
+        }
+    }
 }
 
 impl SearchState {
-    pub fn start_forward(&mut self) {
+    pub fn start_forward(&mut self, scroll: usize) {
         self.mode = SearchMode::InputForward;
+        self.origin_scroll = scroll;
         self.query.clear();
         self.matches.clear();
         self.current_match = None;
@@ -70,6 +97,31 @@ impl SearchState {
         !self.query.is_empty()
     }
 
+    pub fn toggle_regex(&mut self) {
+        self.regex = !self.regex;
+    }
+
+    /// True in regex mode while the query does not compile; it then matches nothing.
+    pub fn is_invalid_regex(&self) -> bool {
+        self.regex
+            && self.has_query()
+            && matches!(&self.compiled, Some((query, None)) if *query == self.query)
+    }
+
+    /// Compile the query on first use after it changes.
+    fn compiled_regex(&mut self) -> Option<Regex> {
+        if !matches!(&self.compiled, Some((query, _)) if *query == self.query) {
+            let regex = smart_case_regex(&self.query).ok();
+            self.compiled = Some((self.query.clone(), regex));
+        }
+        self.compiled.as_ref().and_then(|(_, regex)| regex.clone())
+    }
+
+    /// Byte ranges of every match of the query in `text`.
+    fn find_in(&self, text: &str, regex: Option<&Regex>) -> Vec<(usize, usize)> {
+        find_matches(&self.query, text, regex)
+    }
+
     pub fn update_matches(&mut self, lines: &[DiffLine], fullscreen: DiffFullscreen) {
         if self.query.is_empty() {
             self.matches.clear();
@@ -83,48 +135,7 @@ impl SearchState {
             .and_then(|idx| self.matches.get(idx))
             .map(|m| (m.line_index, m.start_col, m.end_col, m.panel));
 
-        self.matches.clear();
-
-        let query_lower = self.query.to_lowercase();
-        let query_len = self.query.len();
-
-        for (i, line) in lines.iter().enumerate() {
-            // Find all occurrences in old panel
-            if !matches!(fullscreen, DiffFullscreen::NewOnly) {
-                if let Some((_, text)) = &line.old_line {
-                    let text_lower = text.to_lowercase();
-                    let mut start = 0;
-                    while let Some(pos) = text_lower[start..].find(&query_lower) {
-                        let abs_pos = start + pos;
-                        self.matches.push(SearchMatch {
-                            line_index: i,
-                            start_col: abs_pos,
-                            end_col: abs_pos + query_len,
-                            panel: MatchPanel::Old,
-                        });
-                        start = abs_pos + 1;
-                    }
-                }
-            }
-
-            // Find all occurrences in new panel
-            if !matches!(fullscreen, DiffFullscreen::OldOnly) {
-                if let Some((_, text)) = &line.new_line {
-                    let text_lower = text.to_lowercase();
-                    let mut start = 0;
-                    while let Some(pos) = text_lower[start..].find(&query_lower) {
-                        let abs_pos = start + pos;
-                        self.matches.push(SearchMatch {
-                            line_index: i,
-                            start_col: abs_pos,
-                            end_col: abs_pos + query_len,
-                            panel: MatchPanel::New,
-                        });
-                        start = abs_pos + 1;
-                    }
-                }
-            }
-        }
+        self.matches = self.collect_matches(lines, fullscreen);
 
         // Restore current match by identity, or find next visible one
         if let Some((line_idx, start, end, panel)) = prev_match {
@@ -147,6 +158,62 @@ impl SearchState {
         }
     }
 
+    /// Rows of `lines` holding at least one match, in order.
+    pub fn matching_rows(&mut self, lines: &[DiffLine], fullscreen: DiffFullscreen) -> Vec<usize> {
+        let mut rows: Vec<usize> = self
+            .collect_matches(lines, fullscreen)
+            .iter()
+            .map(|m| m.line_index)
+            .collect();
+        rows.dedup();
+        rows
+    }
+
+    /// Every match of the query in `lines`, leaving out the panel `fullscreen` hides.
+    /// Empty for an empty query or an invalid regex.
+    fn collect_matches(
+        &mut self,
+        lines: &[DiffLine],
+        fullscreen: DiffFullscreen,
+    ) -> Vec<SearchMatch> {
+        let mut matches = Vec::new();
+        if self.query.is_empty() {
+            return matches;
+        }
+        let regex = if self.regex {
+            match self.compiled_regex() {
+                Some(regex) => Some(regex),
+                None => return matches,
+            }
+        } else {
+            None
+        };
+
+        for (i, line) in lines.iter().enumerate() {
+            let panels = [
+                (&line.old_line, MatchPanel::Old, DiffFullscreen::NewOnly),
+                (&line.new_line, MatchPanel::New, DiffFullscreen::OldOnly),
+            ];
+            for (side, panel, hidden_when) in panels {
+                if fullscreen == hidden_when {
+                    continue;
+                }
+                let Some((_, text)) = side else {
+                    continue;
+                };
+                for (start_col, end_col) in self.find_in(text, regex.as_ref()) {
+                    matches.push(SearchMatch {
+                        line_index: i,
+                        start_col,
+                        end_col,
+                        panel,
+                    });
+                }
+            }
+        }
+        matches
+    }
+
     pub fn find_next(&mut self) -> Option<usize> {
         if self.matches.is_empty() {
             return None;
@@ -154,6 +221,9 @@ impl SearchState {
 
         let current = self.current_match.unwrap_or(0);
         let next = if current + 1 >= self.matches.len() {
+            if !self.wrap {
+                return None;
+            }
             0 // wrap around
         } else {
             current + 1
@@ -170,6 +240,9 @@ impl SearchState {
 
         let current = self.current_match.unwrap_or(0);
         let prev = if current == 0 {
+            if !self.wrap {
+                return None;
+            }
             self.matches.len() - 1 // wrap around
         } else {
             current - 1
@@ -217,3 +290,188 @@ impl SearchState {
             .collect()
     }
 }
+
+/// Smart case: the pattern ignores case unless it contains an uppercase letter.
+fn smart_case_regex(query: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(query)
+        .case_insensitive(!has_literal_uppercase(query))
+        .build()
+}
+
+/// Whether `pattern` has an uppercase letter outside escapes, so `\S`, `\W`, `\D`
+/// and classes like `\p{Greek}` don't count.
+fn has_literal_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some('p' | 'P') = chars.next() {
+                    if chars.clone().next() == Some('{') {
+                        chars.by_ref().take_while(|&c| c != '}').for_each(drop);
+                    } else {
+                        chars.next();
+                    }
+                }
+            }
+            c if c.is_uppercase() => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Byte ranges of every match in `text`: of `regex` when given, else of `query` as
+/// plain text, ignoring case.
+fn find_matches(query: &str, text: &str, regex: Option<&Regex>) -> Vec<(usize, usize)> {
+    if let Some(regex) = regex {
+        // Empty matches (e.g. `x*`) have nothing to highlight
+        return regex
+            .find_iter(text)
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.end()))
+            .collect();
+    }
+    let query_lower = query.to_lowercase();
+    let text_lower = text.to_lowercase();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while let Some(pos) = text_lower[start..].find(&query_lower) {
+        let abs_pos = start + pos;
+        ranges.push((abs_pos, abs_pos + query.len()));
+        start = abs_pos + 1;
+    }
+    ranges
+}
+
+/// The `--grep` pattern, a regex matched like regex-mode search. Hunks without an
+/// added or changed line matching it are hidden.
+#[derive(Clone)]
+pub struct GrepFilter {
+    pub pattern: String,
+    regex: Regex,
+}
+
+impl GrepFilter {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let regex =
+            smart_case_regex(pattern).map_err(|e| format!("invalid --grep pattern: {}", e))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    /// Whether any added or changed line among `lines` matches.
+    pub fn matches_added(&self, lines: &[DiffLine]) -> bool {
+        lines.iter().any(|line| {
+            matches!(line.change_type, ChangeType::Insert | ChangeType::Modified)
+                && line.new_line.as_ref().is_some_and(|(_, text)| {
+                    !find_matches(&self.pattern, text, Some(&self.regex)).is_empty()
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with_matches(lines: &[usize]) -> SearchState {
+        SearchState {
+            matches: lines
+                .iter()
+                .map(|&line_index| SearchMatch {
+                    line_index,
+                    start_col: 0,
+                    end_col: 1,
+                    panel: MatchPanel::New,
+                })
+                .collect(),
+            current_match: Some(0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_next_wraps_unless_disabled() {
+        let mut search = state_with_matches(&[3, 9]);
+        assert_eq!(search.find_next(), Some(9));
+        assert_eq!(search.find_next(), Some(3));
+
+        search.wrap = false;
+        assert_eq!(search.find_prev(), None);
+        assert_eq!(search.find_next(), Some(9));
+        assert_eq!(search.find_next(), None);
+        assert_eq!(search.current_match, Some(1));
+    }
+
+    #[test]
+    fn test_regex_mode_uses_smart_case_and_survives_bad_patterns() {
+        use crate::command::diff::types::ChangeType;
+        let lines = vec![DiffLine {
+            old_line: None,
+            new_line: Some((1, "let Foo = foo_bar(42);".to_string())),
+            change_type: ChangeType::Insert,
+            old_segments: None,
+            new_segments: None,
+        }];
+        let ranges = |search: &SearchState| -> Vec<(usize, usize)> {
+            search
+                .matches
+                .iter()
+                .map(|m| (m.start_col, m.end_col))
+                .collect()
+        };
+        let mut search = SearchState {
+            regex: true,
+            ..Default::default()
+        };
+
+        search.query = r"foo_?\w*".to_string();
+        search.update_matches(&lines, DiffFullscreen::None);
+        assert_eq!(ranges(&search), vec![(4, 7), (10, 17)]);
+
+        search.query = r"Foo".to_string();
+        search.update_matches(&lines, DiffFullscreen::None);
+        assert_eq!(
+            ranges(&search),
+            vec![(4, 7)],
+            "an uppercase letter makes it case-sensitive"
+        );
+
+        search.query = r"foo(".to_string();
+        search.update_matches(&lines, DiffFullscreen::None);
+        assert!(search.is_invalid_regex());
+        assert_eq!(search.match_count(), 0);
+
+        search.query = r"\d+".to_string();
+        search.update_matches(&lines, DiffFullscreen::None);
+        assert!(!search.is_invalid_regex());
+        assert_eq!(ranges(&search), vec![(18, 20)]);
+
+        search.query = r"FOO\S".to_string();
+        search.update_matches(&lines, DiffFullscreen::None);
+        assert_eq!(
+            ranges(&search),
+            vec![],
+            "a literal uppercase letter still counts"
+        );
+
+        search.query = r"foo\W".to_string();
+        search.update_matches(&lines, DiffFullscreen::None);
+        assert_eq!(
+            ranges(&search),
+            vec![(4, 8)],
+            "escapes such as \\W are not uppercase letters"
+        );
+    }
+
+    #[test]
+    fn test_has_literal_uppercase() {
+        assert!(has_literal_uppercase("Foo"));
+        assert!(has_literal_uppercase(r"\wA"));
+        assert!(!has_literal_uppercase(r"\S\W\D\B"));
+        assert!(!has_literal_uppercase(r"\p{Greek}x\PL"));
+        assert!(has_literal_uppercase(r"\p{Lu}X"));
+    }
+}
@@ -1,24 +1,55 @@
 use std::collections::{HashMap, HashSet};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::command::diff::diff_algo::{compute_side_by_side, find_hunk_starts};
+use serde::{Deserialize, Serialize};
+
+use crate::command::diff::coordinates::{
+    pinned_strip_height, wrapped_char_index, wrapped_line_at, PanelLayout, RowLayout, BLAME_WIDTH,
+    DUAL_GUTTER_WIDTH, GUTTER_WIDTH, ORIGIN_WIDTH,
+};
+use crate::command::diff::diff_algo::{
+    compute_side_by_side, find_conflict_starts, find_hunk_starts, fold_unchanged,
+    hide_unmatched_hunks, hunk_at_row,
+};
+use crate::command::diff::git::COMMIT_MESSAGE_FILE;
+use crate::command::diff::highlight::pattern_matches;
+use crate::command::diff::patch::hunk_patch;
 
 /// Maximum number of diff lines to include inline when exporting annotations.
 /// Hunks with more lines than this will not include the diff content in the export
 /// to keep the output concise.
 const MAX_EXPORT_DIFF_LINES: usize = 5;
+
+/// How long a footer status message stays visible.
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+use crate::command::diff::render::{diff_html, HtmlFile};
 use crate::command::diff::search::SearchState;
+use crate::command::diff::PendingReviewComment;
 use crate::command::diff::types::{
-    build_file_tree, ChangeType, CursorPosition, DiffFullscreen, DiffLine, DiffPanelFocus,
-    DiffViewSettings, FileDiff, FocusedPanel, Selection, SelectionMode, SidebarItem,
+    build_file_tree, build_flat_file_list, count_conflict_markers, expand_tabs, ChangeType,
+    CursorPosition, DiffFullscreen, DiffLine, DiffPanelFocus, DiffViewMode, DiffViewSettings,
+    ExportTemplate, FileDiff, FileStatus, FocusedPanel, PanelTitles, Selection, SelectionMode,
+    SidebarItem,
 };
-use crate::vcs::StackedCommitInfo;
+use crate::vcs::{format_epoch_secs, BlameLine, LineOrigin, StackedCommitInfo};
 
 #[derive(Default, Clone, Copy, PartialEq)]
 pub enum PendingKey {
     #[default]
     None,
     G,
+    /// `]` was pressed; holds the fullscreen mode to restore if it turns into `]c`
+    BracketClose(DiffFullscreen),
+    /// `[` was pressed; holds the fullscreen mode to restore if it turns into `[c`
+    BracketOpen(DiffFullscreen),
+    /// `z` was pressed, starting a fold command (`za`, `zR`, `zM`)
+    Z,
+    /// `q` was pressed with `confirm_quit` on, at this time
+    Quit(Instant),
+    /// `m` was pressed; the next letter names the mark to set
+    SetMark,
+    /// `'` or `` ` `` was pressed; the next letter names the mark to jump to
+    JumpToMark,
 }
 
 fn sidebar_item_path(item: &SidebarItem) -> &str {
@@ -35,15 +66,112 @@ fn is_child_path(path: &str, parent: &str) -> bool {
     path.starts_with(&format!("{}/", parent))
 }
 
+/// Replace each `{name}` in `template` with its value, in one pass so values are never
+/// expanded themselves. Unknown names are left as written.
+fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after.find('}').and_then(|close| {
+            let name = &after[..close];
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (*value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                out.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `value` as a CSV field: quoted, with quotes doubled, when it holds a comma, a quote
+/// or a line break; as is otherwise.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Character column under display column `x` of wrapped row `row` of one side of a line
+fn wrapped_column(side: Option<&(usize, String)>, width: usize, row: usize, x: usize) -> usize {
+    match side {
+        Some((_, text)) if width > 0 => wrapped_char_index(text, width, row, x),
+        _ => row * width + x,
+    }
+}
+
+/// Where each displayed row starts in the file's unfolded rows: a folded gap or hidden
+/// hunk stands for `count` of them.
+fn full_row_starts(lines: &[DiffLine]) -> Vec<usize> {
+    let mut at = 0;
+    lines
+        .iter()
+        .map(|line| {
+            let start = at;
+            at = match line.change_type {
+                ChangeType::Folded { from, count } | ChangeType::Hidden { from, count } => {
+                    from + count
+                }
+                _ => at + 1,
+            };
+            start
+        })
+        .collect()
+}
+
+/// Whether the sidebar lists a file, given the deleted-files toggle and the glob filter.
+fn file_shown(path: &str, status: FileStatus, show_deleted: bool, filter: Option<&str>) -> bool {
+    (show_deleted || status != FileStatus::Deleted)
+        && filter.is_none_or(|pattern| pattern_matches(pattern, path))
+}
+
 fn build_sidebar_visible_indices(
     items: &[SidebarItem],
     collapsed_dirs: &HashSet<String>,
+    show_deleted: bool,
+    filter: Option<&str>,
 ) -> Vec<usize> {
     let mut visible = Vec::new();
     let mut collapsed_stack: Vec<String> = Vec::new();
 
+    // Directories whose files are all hidden disappear along with them
+    let shown_file_paths: Vec<&str> = items
+        .iter()
+        .filter_map(|item| match item {
+            SidebarItem::File { path, status, .. }
+                if file_shown(path, *status, show_deleted, filter) =>
+            {
+                Some(path.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+
     for (idx, item) in items.iter().enumerate() {
         let path = sidebar_item_path(item);
+        let shown = match item {
+            SidebarItem::File { status, .. } => file_shown(path, *status, show_deleted, filter),
+            SidebarItem::Directory { path, .. } => shown_file_paths
+                .iter()
+                .any(|file| is_child_path(file, path)),
+        };
+        if !shown {
+            continue;
+        }
         while let Some(last) = collapsed_stack.last() {
             if is_child_path(path, last) {
                 break;
@@ -69,27 +197,57 @@ fn build_sidebar_visible_indices(
     visible
 }
 
-/// An annotation attached to a specific hunk in a file.
+/// One-line text input drawn in place of the footer, e.g. the base-ref prompt.
+pub struct FooterPrompt {
+    pub kind: PromptKind,
+    pub label: &'static str,
+    pub input: String,
+}
+
+/// What the footer prompt's input is for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PromptKind {
+    /// A ref to pin as the old side
+    PinBase,
+    /// A glob to narrow the sidebar to
+    SidebarFilter,
+}
+
+/// Hunk index of an annotation about the whole file rather than one hunk.
+pub const FILE_ANNOTATION_HUNK: usize = usize::MAX;
+
+/// An annotation attached to a specific hunk in a file, or to the whole file.
 ///
 /// Annotations allow users to add notes to code changes during review.
-/// Each annotation is uniquely identified by its file index and hunk index.
-#[derive(Clone)]
+/// Each annotation is uniquely identified by its filename and hunk index; the
+/// file's position in `file_diffs` is looked up when needed, so sorting or
+/// reloading the file list cannot move a note onto another file.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct HunkAnnotation {
-    /// Index of the file in the file_diffs vector
-    pub file_index: usize,
-    /// Index of the hunk within the file (0-based)
+    /// Index of the hunk within the file (0-based), or `FILE_ANNOTATION_HUNK`
     pub hunk_index: usize,
     /// The annotation text content (supports multi-line)
     pub content: String,
     /// Line range in the new file (start_line, end_line) for display purposes
     pub line_range: (usize, usize),
-    /// The filename for display in export and UI
+    /// Path of the annotated file; the key that ties the note to its file
     pub filename: String,
     /// When the annotation was created
     pub created_at: SystemTime,
+    /// GitHub login when this is a comment from an unsubmitted review rather than a local note
+    #[serde(skip)]
+    pub pending_author: Option<String>,
+    /// Restored from disk, but its hunk no longer covers the lines it was written for
+    #[serde(skip)]
+    pub stale: bool,
 }
 
 impl HunkAnnotation {
+    /// A note on the whole file, shown as a banner above its diff
+    pub fn is_file_level(&self) -> bool {
+        self.hunk_index == FILE_ANNOTATION_HUNK
+    }
+
     /// Format the creation time as HH:MM in local time
     #[cfg(feature = "jj")]
     pub fn format_time(&self) -> String {
@@ -123,32 +281,88 @@ pub struct AppState {
     pub h_scroll: u16,
     pub focused_panel: FocusedPanel,
     pub viewed_files: HashSet<usize>,
+    /// Files the reader turned wrapping on for; `settings.wrap` follows the current file
+    pub wrapped_files: HashSet<usize>,
+    /// Number of unresolved conflict blocks per file (indexed like file_diffs)
+    pub conflict_counts: Vec<usize>,
+    /// Files whose content on the PR changed since you last looked at them
+    pub changed_since_review: HashSet<usize>,
+    /// Content hash of each PR file as you last saw it, by path
+    pub seen_shas: HashMap<String, String>,
+    /// Content hash of each PR file on the host now, by path
+    remote_shas: HashMap<String, String>,
     pub show_sidebar: bool,
+    /// Sidebar lists files by basename instead of as a directory tree
+    pub flat_sidebar: bool,
+    /// Glob the sidebar is narrowed to; the full tree stays in `sidebar_items`
+    pub sidebar_filter: Option<String>,
+    /// Directories at this tree depth or deeper start out collapsed
+    pub tree_depth: Option<usize>,
     pub settings: DiffViewSettings,
     pub diff_fullscreen: DiffFullscreen,
     pub search_state: SearchState,
     pub pending_key: PendingKey,
     pub needs_reload: bool,
     pub focused_hunk: Option<usize>,
+    /// Hunks marked as reviewed, as (file_index, hunk_index) pairs
+    pub reviewed_hunks: HashSet<(usize, usize)>,
+    /// Whether hunk navigation skips hunks marked as reviewed
+    pub skip_reviewed_hunks: bool,
     // Annotation fields
     pub annotations: Vec<HunkAnnotation>,
+    /// Your unsubmitted GitHub review comments, one entry per hunk; shown, never exported
+    pub pending_review: Vec<HunkAnnotation>,
+    /// The comments `pending_review` was grouped from, to regroup when hunks change
+    pending_review_comments: Vec<PendingReviewComment>,
     // Stacked mode fields
     pub stacked_mode: bool,
     pub stacked_commits: Vec<StackedCommitInfo>,
     pub current_commit_index: usize,
     /// Tracks viewed files per commit SHA (commit SHA -> set of viewed filenames)
     stacked_viewed_files: HashMap<String, HashSet<String>>,
-    /// VCS backend name ("git" or "jj")
+    /// VCS backend name ("git", "jj" or "hg")
     pub vcs_name: &'static str,
     /// The commit reference used to open the diff (e.g., "HEAD~2..HEAD", "main..feature")
     pub diff_reference: Option<String>,
+    /// The user's annotation export format, in place of the built-in one
+    pub export_template: Option<ExportTemplate>,
+    /// Labels for the old/new panels outside stacked mode
+    panel_titles: PanelTitles,
     // Selection state
     /// Which panel has selection focus
     pub diff_panel_focus: DiffPanelFocus,
     /// Current text selection
     pub selection: Selection,
+    /// Transient footer notice and when it was set
+    status_message: Option<(String, Instant)>,
+    /// Text input shown in the footer while it is open
+    pub prompt: Option<FooterPrompt>,
+    /// Base pinned from the diff view; reloads compare the working tree against it
+    pub pinned_base: Option<String>,
+    /// Hunk kept in view below the diff while navigating elsewhere, as (filename, hunk_index)
+    pinned_hunk: Option<(String, usize)>,
+    /// `pinned_hunk_view` for the pinned hunk, refreshed when it or the diff changes
+    pinned_hunk_rows: Option<(String, Vec<DiffLine>)>,
     /// Whether a mouse drag is in progress
     pub is_dragging: bool,
+    /// Folded gaps opened with Enter in compact mode, as (file_index, first hidden row)
+    expanded_gaps: HashSet<(usize, usize)>,
+    /// Files shown in full, nothing folded or hidden, while compact mode or `--grep`
+    /// is on; by filename so it survives reloads
+    pub full_context_files: HashSet<String>,
+    /// Whether the old panel shows who last changed each line
+    pub show_blame: bool,
+    /// Old-side blame per file index; empty when blame failed, so it is not retried
+    blame_cache: HashMap<usize, Vec<BlameLine>>,
+    /// Whether the new panel shows which commit in the range added each line
+    pub show_origins: bool,
+    /// New-side line origins per file index; empty when they could not be found
+    origin_cache: HashMap<usize, Vec<Option<LineOrigin>>>,
+    /// Vim-style marks: letter -> (filename, scroll). Keyed by name so a reload that
+    /// reorders the files keeps them pointing at the right one
+    pub marks: HashMap<char, (String, u16)>,
+    /// Where you left each file this session, as (scroll, h_scroll, focused_hunk)
+    file_positions: HashMap<usize, (u16, u16, Option<usize>)>,
     // Cached diff computation
     /// Cached side_by_side diff for current file (invalidated on file change)
     cached_side_by_side: Option<(usize, Vec<DiffLine>)>,
@@ -160,7 +374,13 @@ impl AppState {
     pub fn new(file_diffs: Vec<FileDiff>, focus_file: Option<&str>) -> Self {
         let sidebar_items = build_file_tree(&file_diffs);
         let collapsed_dirs = HashSet::new();
-        let sidebar_visible = build_sidebar_visible_indices(&sidebar_items, &collapsed_dirs);
+        let settings = DiffViewSettings::default();
+        let sidebar_visible = build_sidebar_visible_indices(
+            &sidebar_items,
+            &collapsed_dirs,
+            settings.show_deleted,
+            None,
+        );
         let (sidebar_selected, current_file) = if let Some(focus_path) = focus_file {
             if let Some(file_idx) = file_diffs.iter().position(|f| f.filename == focus_path) {
                 let sidebar_idx = sidebar_visible
@@ -180,11 +400,15 @@ impl AppState {
         } else {
             Self::find_first_file(&sidebar_items, &sidebar_visible)
         };
-        let settings = DiffViewSettings::default();
+        let conflict_counts = Self::compute_conflict_counts(&file_diffs);
         let (scroll, focused_hunk) = if !file_diffs.is_empty() && current_file < file_diffs.len() {
             let diff = &file_diffs[current_file];
-            let side_by_side =
-                compute_side_by_side(&diff.old_content, &diff.new_content, settings.tab_width);
+            let side_by_side = compute_side_by_side(
+                &diff.old_content,
+                &diff.new_content,
+                settings.tab_width_for(&diff.filename),
+                settings.ignore_whitespace,
+            );
             let hunks = find_hunk_starts(&side_by_side);
             let scroll = hunks
                 .first()
@@ -209,23 +433,50 @@ impl AppState {
             h_scroll: 0,
             focused_panel: FocusedPanel::default(),
             viewed_files: HashSet::new(),
+            wrapped_files: HashSet::new(),
+            conflict_counts,
+            changed_since_review: HashSet::new(),
+            seen_shas: HashMap::new(),
+            remote_shas: HashMap::new(),
             show_sidebar: true,
+            flat_sidebar: false,
+            sidebar_filter: None,
+            tree_depth: None,
             settings,
             diff_fullscreen: DiffFullscreen::default(),
             search_state: SearchState::default(),
             pending_key: PendingKey::default(),
             needs_reload: false,
             focused_hunk,
+            reviewed_hunks: HashSet::new(),
+            skip_reviewed_hunks: false,
             annotations: Vec::new(),
+            pending_review: Vec::new(),
+            pending_review_comments: Vec::new(),
             stacked_mode: false,
             stacked_commits: Vec::new(),
             current_commit_index: 0,
             stacked_viewed_files: HashMap::new(),
             vcs_name: "git", // Default, will be set by caller
             diff_reference: None,
+            export_template: None,
+            panel_titles: PanelTitles::default(),
             diff_panel_focus: DiffPanelFocus::default(),
             selection: Selection::default(),
+            status_message: None,
+            prompt: None,
+            pinned_base: None,
+            pinned_hunk: None,
+            pinned_hunk_rows: None,
             is_dragging: false,
+            expanded_gaps: HashSet::new(),
+            full_context_files: HashSet::new(),
+            show_blame: false,
+            blame_cache: HashMap::new(),
+            show_origins: false,
+            origin_cache: HashMap::new(),
+            marks: HashMap::new(),
+            file_positions: HashMap::new(),
             cached_side_by_side: None,
             cached_hunks: None,
         }
@@ -240,6 +491,213 @@ impl AppState {
         (0, 0)
     }
 
+    fn compute_conflict_counts(file_diffs: &[FileDiff]) -> Vec<usize> {
+        file_diffs
+            .iter()
+            .map(|diff| {
+                if diff.is_binary {
+                    0
+                } else {
+                    count_conflict_markers(&diff.new_content)
+                }
+            })
+            .collect()
+    }
+
+    /// Find the next (or previous) file containing conflict markers, in sidebar order.
+    /// Wraps around, skipping files the sidebar hides (deleted files or the filter);
+    /// returns None if no other shown file has conflicts.
+    pub fn find_conflict_file(&self, forward: bool) -> Option<usize> {
+        let order = self.sidebar_file_order();
+        let pos = order.iter().position(|&idx| idx == self.current_file)?;
+        let has_conflicts = |idx: &usize| {
+            self.conflict_counts.get(*idx).copied().unwrap_or(0) > 0 && self.is_file_shown(*idx)
+        };
+
+        let candidates: Vec<usize> = if forward {
+            order[pos + 1..]
+                .iter()
+                .chain(&order[..pos])
+                .copied()
+                .collect()
+        } else {
+            order[..pos]
+                .iter()
+                .rev()
+                .chain(order[pos + 1..].iter().rev())
+                .copied()
+                .collect()
+        };
+        candidates.into_iter().find(has_conflicts)
+    }
+
+    /// The display row opening the conflict block in the focused hunk, if it has one.
+    pub fn focused_conflict_row(&self) -> Option<usize> {
+        let hunk = self.focused_hunk?;
+        let lines = self.display_lines(self.current_file);
+        let hunks = find_hunk_starts(&lines);
+        find_conflict_starts(&lines)
+            .into_iter()
+            .find(|&row| hunk_at_row(&lines, &hunks, row) == Some(hunk))
+    }
+
+    /// The display row of the next (or previous) conflict block in the current file,
+    /// counted from the focused hunk, or from the top of the screen when no hunk is
+    /// focused. None past the last (or first) conflict.
+    pub fn find_conflict_in_file(&self, forward: bool) -> Option<usize> {
+        let lines = self.display_lines(self.current_file);
+        let starts = find_conflict_starts(&lines);
+        let hunks = find_hunk_starts(&lines);
+        let anchor = match self.focused_hunk.and_then(|hunk| hunks.get(hunk)) {
+            Some(&hunk_start) => self.focused_conflict_row().unwrap_or(hunk_start),
+            // Unfocused, a conflict at the top of the screen comes next
+            None if forward => return starts.into_iter().find(|&row| row >= self.scroll as usize),
+            None => self.scroll as usize,
+        };
+        if forward {
+            starts.into_iter().find(|&row| row > anchor)
+        } else {
+            starts.into_iter().rev().find(|&row| row < anchor)
+        }
+    }
+
+    /// Scroll to the conflict block opening at `row` and focus the hunk it falls in.
+    /// With `row` of None, the first (or, going back, last) conflict in the file.
+    pub fn focus_conflict(
+        &mut self,
+        row: Option<usize>,
+        forward: bool,
+        visible_height: usize,
+        max_scroll: usize,
+    ) {
+        let lines = self.display_lines(self.current_file);
+        let starts = find_conflict_starts(&lines);
+        let edge = if forward {
+            starts.first()
+        } else {
+            starts.last()
+        };
+        let Some(row) = row.or(edge.copied()) else {
+            return;
+        };
+        let hunks = find_hunk_starts(&lines);
+        self.focused_hunk = hunk_at_row(&lines, &hunks, row);
+        self.scroll = adjust_scroll_for_hunk(row, self.scroll, visible_height, max_scroll);
+    }
+
+    /// Re-run the in-progress search after each keystroke and scroll to its first
+    /// match below where the search started. Without a match the view stays put.
+    pub fn update_incremental_search(&mut self) {
+        let fullscreen = self.search_fullscreen();
+        self.get_side_by_side();
+        let Some((_, lines)) = &self.cached_side_by_side else {
+            return;
+        };
+        self.search_state.update_matches(lines, fullscreen);
+        let origin = self.search_state.origin_scroll;
+        let line = self
+            .unfold_search_match(origin, true, true)
+            .or_else(|| self.search_state.jump_to_first_match(origin));
+        if let Some(line) = line {
+            self.scroll = line.saturating_sub(5) as u16;
+        }
+    }
+
+    /// Step to the next (or previous) search match, like `SearchState::find_next`, but
+    /// looking through the unfolded rows: a match in a folded gap or hidden hunk is
+    /// unfolded first. Returns the row of the match.
+    pub fn find_search_match(&mut self, forward: bool) -> Option<usize> {
+        let matches = &self.search_state.matches;
+        let current = self
+            .search_state
+            .current_match
+            .and_then(|i| Some((i, matches.get(i)?.line_index)));
+        let unfolded = match current {
+            Some((i, row)) => {
+                let neighbour = if forward {
+                    matches.get(i + 1)
+                } else {
+                    i.checked_sub(1).and_then(|j| matches.get(j))
+                };
+                // Another match on the same row comes first
+                if neighbour.is_some_and(|m| m.line_index == row) {
+                    None
+                } else {
+                    self.unfold_search_match(row, false, forward)
+                }
+            }
+            None => self.unfold_search_match(self.scroll as usize, true, forward),
+        };
+        match unfolded {
+            Some(row) => Some(row),
+            None if forward => self.search_state.find_next(),
+            None => self.search_state.find_prev(),
+        }
+    }
+
+    /// Find the first row with a search match past display row `row` (from it on when
+    /// `inclusive`; before it when searching backward), wrapping if search wraps. When
+    /// that row is inside a folded gap or hidden hunk, unfold it, make its match the
+    /// current one and return its new display row. None when it is already visible.
+    fn unfold_search_match(&mut self, row: usize, inclusive: bool, forward: bool) -> Option<usize> {
+        let fullscreen = self.search_fullscreen();
+        let diff = self.file_diffs.get(self.current_file)?;
+        let full = compute_side_by_side(
+            &diff.old_content,
+            &diff.new_content,
+            self.settings.tab_width_for(&diff.filename),
+            self.settings.ignore_whitespace,
+        );
+        let matching = self.search_state.matching_rows(&full, fullscreen);
+        let starts = full_row_starts(self.get_side_by_side());
+        let start = starts.get(row).copied().unwrap_or(full.len());
+        let wrapped = |rows: Option<&usize>| rows.filter(|_| self.search_state.wrap).copied();
+        let target = if forward {
+            let end = starts.get(row + 1).copied().unwrap_or(full.len());
+            let from = if inclusive { start } else { end };
+            matching
+                .iter()
+                .find(|&&r| r >= from)
+                .copied()
+                .or_else(|| wrapped(matching.first()))
+        } else {
+            matching
+                .iter()
+                .rev()
+                .find(|&&r| r < start)
+                .copied()
+                .or_else(|| wrapped(matching.last()))
+        }?;
+
+        let gap = self
+            .get_side_by_side()
+            .iter()
+            .zip(&starts)
+            .find_map(|(line, &at)| match line.change_type {
+                ChangeType::Folded { from, count } | ChangeType::Hidden { from, count }
+                    if (at..at + count).contains(&target) =>
+                {
+                    Some(from)
+                }
+                _ => None,
+            })?;
+        self.expanded_gaps.insert((self.current_file, gap));
+        self.invalidate_cache();
+
+        let lines = self.display_lines(self.current_file);
+        self.search_state.update_matches(&lines, fullscreen);
+        let new_row = full_row_starts(&lines)
+            .iter()
+            .position(|&at| at == target)?;
+        let matches = &self.search_state.matches;
+        self.search_state.current_match = if forward {
+            matches.iter().position(|m| m.line_index == new_row)
+        } else {
+            matches.iter().rposition(|m| m.line_index == new_row)
+        };
+        Some(new_row)
+    }
+
     /// Get cached side_by_side diff for current file, computing if necessary
     pub fn get_side_by_side(&mut self) -> &[DiffLine] {
         if self.file_diffs.is_empty() {
@@ -253,12 +711,7 @@ impl AppState {
         };
 
         if needs_recompute {
-            let diff = &self.file_diffs[current];
-            let side_by_side = compute_side_by_side(
-                &diff.old_content,
-                &diff.new_content,
-                self.settings.tab_width,
-            );
+            let side_by_side = self.display_lines(current);
             let hunks = find_hunk_starts(&side_by_side);
             self.cached_side_by_side = Some((current, side_by_side));
             self.cached_hunks = Some((current, hunks));
@@ -274,6 +727,309 @@ impl AppState {
         &self.cached_hunks.as_ref().unwrap().1
     }
 
+    /// Rows of `file_index` as the diff view shows them, with long unchanged runs
+    /// folded in compact mode. With `--grep`, unmatched hunks are hidden and unchanged
+    /// runs always folded, unless the file is in `full_context_files`. Hunk indices are
+    /// the same either way.
+    pub fn display_lines(&self, file_index: usize) -> Vec<DiffLine> {
+        let diff = &self.file_diffs[file_index];
+        let lines = compute_side_by_side(
+            &diff.old_content,
+            &diff.new_content,
+            self.settings.tab_width_for(&diff.filename),
+            self.settings.ignore_whitespace,
+        );
+        if (!self.settings.compact && self.settings.grep.is_none())
+            || self.full_context_files.contains(&diff.filename)
+        {
+            return lines;
+        }
+        let expanded: HashSet<usize> = self
+            .expanded_gaps
+            .iter()
+            .filter(|(file, _)| *file == file_index)
+            .map(|&(_, from)| from)
+            .collect();
+        let lines = match &self.settings.grep {
+            Some(filter) => hide_unmatched_hunks(lines, filter, &expanded),
+            None => lines,
+        };
+        fold_unchanged(lines, self.settings.fold_threshold, &expanded)
+    }
+
+    /// File indices in the order the sidebar lists them, collapsed directories included.
+    pub fn sidebar_file_order(&self) -> Vec<usize> {
+        self.sidebar_items
+            .iter()
+            .filter_map(|item| match item {
+                SidebarItem::File { file_index, .. } => Some(*file_index),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Take the host's current file hashes and mark the files that differ from what you
+    /// last saw. Without an earlier record, everything as it is now counts as seen.
+    pub fn set_remote_shas(&mut self, shas: HashMap<String, String>) {
+        if self.seen_shas.is_empty() {
+            self.seen_shas = shas.clone();
+        }
+        self.remote_shas = shas;
+        self.refresh_changed_since_review();
+    }
+
+    fn refresh_changed_since_review(&mut self) {
+        self.changed_since_review = self
+            .file_diffs
+            .iter()
+            .enumerate()
+            .filter(|(_, diff)| {
+                self.remote_shas
+                    .get(&diff.filename)
+                    .is_some_and(|now| self.seen_shas.get(&diff.filename) != Some(now))
+            })
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Remember `file_index` as seen in its current version. Returns whether that
+    /// changed the record, so callers know to save it.
+    pub fn mark_seen(&mut self, file_index: usize) -> bool {
+        self.changed_since_review.remove(&file_index);
+        let Some(diff) = self.file_diffs.get(file_index) else {
+            return false;
+        };
+        let Some(sha) = self.remote_shas.get(&diff.filename) else {
+            return false;
+        };
+        if self.seen_shas.get(&diff.filename) == Some(sha) {
+            return false;
+        }
+        self.seen_shas.insert(diff.filename.clone(), sha.clone());
+        true
+    }
+
+    /// The next file in sidebar order, after the current one and wrapping around, that
+    /// changed since you last saw it.
+    pub fn next_changed_since_review(&self) -> Option<usize> {
+        let order = self.sidebar_file_order();
+        let current = order.iter().position(|&i| i == self.current_file).unwrap_or(0);
+        order
+            .iter()
+            .cycle()
+            .skip(current + 1)
+            .take(order.len())
+            .copied()
+            .find(|i| self.changed_since_review.contains(i))
+    }
+
+    /// A standalone HTML page showing `files` as the viewer lays them out.
+    pub fn html_snapshot(&self, title: &str, files: &[usize]) -> String {
+        let files: Vec<HtmlFile> = files
+            .iter()
+            .map(|&index| HtmlFile {
+                diff: &self.file_diffs[index],
+                lines: self.display_lines(index),
+            })
+            .collect();
+        diff_html(title, &files, &self.settings)
+    }
+
+    /// Switch compact mode, keeping the focused hunk in view since row positions change.
+    pub fn toggle_compact(&mut self) {
+        self.settings.compact = !self.settings.compact;
+        self.invalidate_cache();
+        self.clear_selection();
+        let hunk_start = self
+            .focused_hunk
+            .and_then(|h| self.get_hunks().get(h).copied());
+        self.scroll = hunk_start.map_or(0, |start| (start as u16).saturating_sub(5));
+    }
+
+    /// Show the current file in full, or fold it again, keeping the focused hunk in view.
+    /// Returns whether it is now shown in full.
+    pub fn toggle_full_context(&mut self) -> bool {
+        let Some(diff) = self.file_diffs.get(self.current_file) else {
+            return false;
+        };
+        let full = if self.full_context_files.remove(&diff.filename) {
+            false
+        } else {
+            self.full_context_files.insert(diff.filename.clone());
+            true
+        };
+        self.invalidate_cache();
+        self.clear_selection();
+        let hunk_start = self
+            .focused_hunk
+            .and_then(|h| self.get_hunks().get(h).copied());
+        self.scroll = hunk_start.map_or(0, |start| (start as u16).saturating_sub(5));
+        full
+    }
+
+    /// Whether the current file still needs its old side blamed before drawing.
+    pub fn needs_blame(&self) -> bool {
+        self.show_blame
+            && self.has_both_sides()
+            && !self.blame_cache.contains_key(&self.current_file)
+    }
+
+    pub fn set_blame(&mut self, file_index: usize, lines: Vec<BlameLine>) {
+        self.blame_cache.insert(file_index, lines);
+    }
+
+    /// Blame for the current file's old panel, when it is shown beside the new one.
+    pub fn current_blame(&self) -> Option<&[BlameLine]> {
+        if !self.show_blame || !self.has_both_sides() || self.is_unified_file() {
+            return None;
+        }
+        self.blame_cache
+            .get(&self.current_file)
+            .filter(|lines| !lines.is_empty())
+            .map(Vec::as_slice)
+    }
+
+    /// Columns the blame margin takes in the old panel.
+    pub fn blame_width(&self) -> u16 {
+        if self.current_blame().is_some() {
+            BLAME_WIDTH
+        } else {
+            0
+        }
+    }
+
+    /// Whether the current file still needs its new side's line origins before drawing.
+    pub fn needs_origins(&self) -> bool {
+        self.show_origins
+            && self.has_new_side()
+            && !self.origin_cache.contains_key(&self.current_file)
+    }
+
+    pub fn set_origins(&mut self, file_index: usize, origins: Vec<Option<LineOrigin>>) {
+        self.origin_cache.insert(file_index, origins);
+    }
+
+    /// Line origins for the current file's new panel, when it is shown on its own
+    /// or beside the old one.
+    pub fn current_origins(&self) -> Option<&[Option<LineOrigin>]> {
+        if !self.show_origins || !self.has_new_side() || self.is_unified_file() {
+            return None;
+        }
+        self.origin_cache
+            .get(&self.current_file)
+            .filter(|origins| !origins.is_empty())
+            .map(Vec::as_slice)
+    }
+
+    /// Columns the line-origin margin takes in the new panel.
+    pub fn origin_width(&self) -> u16 {
+        if self.current_origins().is_some() {
+            ORIGIN_WIDTH
+        } else {
+            0
+        }
+    }
+
+    /// Columns of line numbers before the text: both sides' in the unified view.
+    pub fn gutter_width(&self) -> u16 {
+        if self.is_unified_file() {
+            DUAL_GUTTER_WIDTH
+        } else {
+            GUTTER_WIDTH
+        }
+    }
+
+    /// The current file has a new side (it was not deleted).
+    fn has_new_side(&self) -> bool {
+        self.file_diffs
+            .get(self.current_file)
+            .is_some_and(|d| !d.new_content.is_empty())
+    }
+
+    /// The current file is the commit message shown above a commit's files, which has
+    /// no path in the repository to blame, link to or export.
+    pub fn is_commit_message(&self) -> bool {
+        self.file_diffs
+            .get(self.current_file)
+            .is_some_and(|diff| diff.filename == COMMIT_MESSAGE_FILE)
+    }
+
+    /// The current file has an old and a new side (it is neither added nor deleted).
+    fn has_both_sides(&self) -> bool {
+        self.file_diffs
+            .get(self.current_file)
+            .is_some_and(|d| !d.old_content.is_empty() && !d.new_content.is_empty())
+    }
+
+    /// Unfold the first folded gap or hidden hunk among the `visible_height` rows on
+    /// screen. Returns false when none is visible.
+    pub fn expand_visible_gap(&mut self, visible_height: usize) -> bool {
+        let scroll = self.scroll as usize;
+        let gap = self
+            .get_side_by_side()
+            .iter()
+            .skip(scroll)
+            .take(visible_height)
+            .find_map(|line| match line.change_type {
+                ChangeType::Folded { from, .. } | ChangeType::Hidden { from, .. } => Some(from),
+                _ => None,
+            });
+        let Some(from) = gap else {
+            return false;
+        };
+        self.expanded_gaps.insert((self.current_file, from));
+        self.invalidate_cache();
+        true
+    }
+
+    /// Old-side line number to point a blame link at: the first old line of an
+    /// old-panel selection, else the first removed/changed line of the focused hunk.
+    pub fn old_line_for_blame(&mut self) -> Option<usize> {
+        let selection = self.selection.clone();
+        let focused_hunk = self.focused_hunk;
+        let hunk_start = focused_hunk.and_then(|h| self.get_hunks().get(h).copied());
+        let lines = self.get_side_by_side();
+
+        if selection.is_active() && selection.panel == DiffPanelFocus::Old {
+            let (start, end) = selection.normalized_range();
+            return lines
+                .get(start.line..=end.line.min(lines.len().saturating_sub(1)))?
+                .iter()
+                .find_map(|line| line.old_line.as_ref().map(|(n, _)| *n));
+        }
+
+        let start = hunk_start?;
+        lines
+            .get(start..)?
+            .iter()
+            .take_while(|line| !matches!(line.change_type, ChangeType::Equal))
+            .find_map(|line| line.old_line.as_ref().map(|(n, _)| *n))
+            // Pure insertion: blame the old line just above it for context
+            .or_else(|| {
+                lines[..start]
+                    .iter()
+                    .rev()
+                    .find_map(|line| line.old_line.as_ref().map(|(n, _)| *n))
+            })
+    }
+
+    /// First and last new-side line numbers covered by the selection; None when
+    /// nothing is selected, the selection is in the old panel or only covers removals.
+    pub fn selected_new_lines(&mut self) -> Option<(usize, usize)> {
+        let selection = self.selection.clone();
+        if !selection.is_active() || selection.panel == DiffPanelFocus::Old {
+            return None;
+        }
+        let (start, end) = selection.normalized_range();
+        let lines = self.get_side_by_side();
+        let mut numbers = lines
+            .get(start.line..=end.line.min(lines.len().saturating_sub(1)))?
+            .iter()
+            .filter_map(|line| line.new_line.as_ref().map(|(n, _)| *n));
+        let first = numbers.next()?;
+        Some((first, numbers.next_back().unwrap_or(first)))
+    }
+
     /// Invalidate the cache (call when file changes)
     pub fn invalidate_cache(&mut self) {
         self.cached_side_by_side = None;
@@ -347,10 +1103,17 @@ impl AppState {
                 _ => None,
             })
             .collect();
-        self.collapsed_dirs
-            .retain(|path| existing_dirs.contains(path));
-        self.sidebar_visible =
-            build_sidebar_visible_indices(&self.sidebar_items, &self.collapsed_dirs);
+        // The flat list has no directories; keep collapse state for when the tree returns
+        if !self.flat_sidebar {
+            self.collapsed_dirs
+                .retain(|path| existing_dirs.contains(path));
+        }
+        self.sidebar_visible = build_sidebar_visible_indices(
+            &self.sidebar_items,
+            &self.collapsed_dirs,
+            self.settings.show_deleted,
+            self.sidebar_filter.as_deref(),
+        );
 
         if self.sidebar_visible.is_empty() {
             self.sidebar_selected = 0;
@@ -409,59 +1172,511 @@ impl AppState {
         }
     }
 
-    pub fn reveal_file(&mut self, file_index: usize) {
-        if file_index >= self.file_diffs.len() {
-            return;
-        }
-        let path = self.file_diffs[file_index].filename.clone();
-        let parts: Vec<&str> = path.split('/').collect();
-        if parts.len() > 1 {
-            for i in 0..parts.len() - 1 {
-                let dir_path = parts[..=i].join("/");
-                self.collapsed_dirs.remove(&dir_path);
+    /// Attach pending review comments to the hunks they point at. Comments on lines
+    /// outside every hunk go to the nearest hunk above (or the first one).
+    pub fn set_pending_review_comments(&mut self, comments: &[PendingReviewComment]) {
+        let mut grouped: Vec<HunkAnnotation> = Vec::new();
+        for comment in comments {
+            let Some(file_index) = self
+                .file_diffs
+                .iter()
+                .position(|d| d.filename == comment.path)
+            else {
+                continue;
+            };
+            let diff = &self.file_diffs[file_index];
+            let side_by_side = compute_side_by_side(
+                &diff.old_content,
+                &diff.new_content,
+                self.settings.tab_width_for(&diff.filename),
+                self.settings.ignore_whitespace,
+            );
+            let hunks = find_hunk_starts(&side_by_side);
+            let row = side_by_side.iter().position(|line| {
+                let numbered = if comment.old_side {
+                    &line.old_line
+                } else {
+                    &line.new_line
+                };
+                numbered.as_ref().is_some_and(|(num, _)| *num == comment.line)
+            });
+            let hunk_index = row
+                .and_then(|row| hunks.iter().rposition(|&start| start <= row))
+                .unwrap_or(0);
+
+            let text = format!("L{}  {}", comment.line, comment.body.trim());
+            match grouped
+                .iter_mut()
+                .find(|a| a.filename == comment.path && a.hunk_index == hunk_index)
+            {
+                Some(existing) => {
+                    existing.content.push('\n');
+                    existing.content.push_str(&text);
+                    existing.line_range.0 = existing.line_range.0.min(comment.line);
+                    existing.line_range.1 = existing.line_range.1.max(comment.line);
+                }
+                None => grouped.push(HunkAnnotation {
+                    hunk_index,
+                    content: text,
+                    line_range: (comment.line, comment.line),
+                    filename: comment.path.clone(),
+                    created_at: SystemTime::now(),
+                    pending_author: Some(comment.author.clone()),
+                    stale: false,
+                }),
             }
         }
-        self.rebuild_sidebar_visible();
-        if let Some(idx) = self.sidebar_visible_index_for_file(file_index) {
-            self.sidebar_selected = idx;
+        self.pending_review = grouped;
+        self.pending_review_comments = comments.to_vec();
+    }
+
+    /// Panel layout actually drawn: the change-bar and unified views draw one
+    /// column, in the new panel's place.
+    pub fn effective_fullscreen(&self) -> DiffFullscreen {
+        match self.settings.view_mode {
+            DiffViewMode::SideBySide => self.diff_fullscreen,
+            DiffViewMode::ChangeBars | DiffViewMode::Unified => DiffFullscreen::NewOnly,
         }
     }
 
-    /// Set the diff reference string (e.g., "HEAD~2..HEAD")
-    pub fn set_diff_reference(&mut self, reference: Option<String>) {
-        self.diff_reference = reference;
+    /// Sides that search looks through: both for the unified view, whose single
+    /// column shows removed lines too, otherwise the sides on screen.
+    pub fn search_fullscreen(&self) -> DiffFullscreen {
+        match self.settings.view_mode {
+            DiffViewMode::Unified => DiffFullscreen::None,
+            _ => self.effective_fullscreen(),
+        }
     }
 
-    /// Initialize stacked mode with commits
-    pub fn init_stacked_mode(&mut self, commits: Vec<StackedCommitInfo>) {
-        self.stacked_mode = true;
-        self.stacked_commits = commits;
-        self.current_commit_index = 0;
+    /// How diff lines map to screen rows, or `None` while every line takes one row
+    /// (no wrapping, two columns). Mirrors the layout `render_diff` draws.
+    pub fn row_layout(&self, term_width: u16) -> Option<RowLayout> {
+        let unified = self.is_unified_file();
+        if !self.settings.wrap {
+            return unified.then_some(RowLayout {
+                unified,
+                ..RowLayout::default()
+            });
+        }
+        let layout = self.panel_layout(term_width)?;
+        Some(RowLayout {
+            old_width: layout.text_width(DiffPanelFocus::Old),
+            new_width: layout.text_width(DiffPanelFocus::New),
+            unified,
+        })
     }
 
-    /// Get the current commit info if in stacked mode
-    pub fn current_commit(&self) -> Option<&StackedCommitInfo> {
-        if self.stacked_mode {
-            self.stacked_commits.get(self.current_commit_index)
+    /// Panels `render_diff` draws for the current file. New and deleted files take
+    /// the whole diff area, whatever the view mode.
+    fn panel_layout(&self, term_width: u16) -> Option<PanelLayout> {
+        let diff = self.file_diffs.get(self.current_file)?;
+        let fullscreen = if diff.old_content.is_empty() && !diff.new_content.is_empty() {
+            DiffFullscreen::NewOnly
+        } else if !diff.old_content.is_empty() && diff.new_content.is_empty() {
+            DiffFullscreen::OldOnly
         } else {
-            None
-        }
+            self.effective_fullscreen()
+        };
+        let sidebar_width = (term_width / 4).clamp(20, 35);
+        Some(
+            PanelLayout::calculate(
+                term_width,
+                sidebar_width,
+                self.show_sidebar,
+                fullscreen,
+                self.settings.panel_borders,
+                self.blame_width(),
+                self.origin_width(),
+            )
+            .with_gutter_width(self.gutter_width()),
+        )
     }
 
-    /// Save current viewed files for the current commit (stacked mode only)
-    pub fn save_stacked_viewed_files(&mut self) {
-        if !self.stacked_mode {
-            return;
-        }
-        if let Some(commit) = self.stacked_commits.get(self.current_commit_index) {
-            let viewed_filenames: HashSet<String> = self
-                .viewed_files
-                .iter()
-                .filter_map(|&idx| self.file_diffs.get(idx).map(|f| f.filename.clone()))
-                .collect();
-            self.stacked_viewed_files
-                .insert(commit.commit_id.clone(), viewed_filenames);
-        }
+    /// The current file is drawn as one unified column (it has both sides to stack).
+    fn is_unified_file(&self) -> bool {
+        self.settings.view_mode == DiffViewMode::Unified && self.has_both_sides()
+    }
+
+    /// Horizontal scroll that brings the end of the longest line among the
+    /// `visible_height` lines on screen into view (`$`).
+    pub fn h_scroll_to_line_end(&mut self, visible_height: usize, term_width: u16) -> u16 {
+        let Some(layout) = self.panel_layout(term_width) else {
+            return 0;
+        };
+        let unified = self.is_unified_file();
+        let tab_width = self
+            .settings
+            .tab_width_for(&self.file_diffs[self.current_file].filename);
+        let scroll = self.scroll as usize;
+        let text_len = |side: &Option<(usize, String)>| {
+            side.as_ref()
+                .map_or(0, |(_, text)| expand_tabs(text, tab_width).chars().count())
+        };
+        let (old_len, new_len) = self
+            .get_side_by_side()
+            .iter()
+            .skip(scroll)
+            .take(visible_height)
+            .fold((0, 0), |(old, new), line| {
+                (
+                    old.max(text_len(&line.old_line)),
+                    new.max(text_len(&line.new_line)),
+                )
+            });
+
+        // Hidden panels have no width; the unified column shows both sides
+        let old_width = if unified {
+            layout.text_width(DiffPanelFocus::New)
+        } else {
+            layout.text_width(DiffPanelFocus::Old)
+        };
+        let new_width = layout.text_width(DiffPanelFocus::New);
+        let overflow = |len: usize, width: usize| {
+            if width == 0 {
+                0
+            } else {
+                len.saturating_sub(width)
+            }
+        };
+        overflow(old_len, old_width).max(overflow(new_len, new_width)) as u16
+    }
+
+    /// Diff position under a point `rel_y` rows into the diff and `text_x` columns into
+    /// the line text, following continuation rows while lines wrap.
+    pub fn position_at(
+        &mut self,
+        rel_y: usize,
+        text_x: usize,
+        panel: DiffPanelFocus,
+        term_width: u16,
+    ) -> CursorPosition {
+        match self.row_layout(term_width) {
+            // In the unified view the one column is the new panel, so a removed
+            // row selects from the new side of its line
+            Some(layout) => {
+                let scroll = self.scroll as usize;
+                let (line, row) = wrapped_line_at(self.get_side_by_side(), scroll, rel_y, layout);
+                let diff_line = self.get_side_by_side().get(line);
+                let column = match (panel, layout.new_width) {
+                    (DiffPanelFocus::Old, _) => wrapped_column(
+                        diff_line.and_then(|l| l.old_line.as_ref()),
+                        layout.old_width,
+                        row,
+                        text_x,
+                    ),
+                    (_, 0) => text_x + self.h_scroll as usize,
+                    (_, width) => wrapped_column(
+                        diff_line.and_then(|l| l.new_line.as_ref()),
+                        width,
+                        row,
+                        text_x,
+                    ),
+                };
+                CursorPosition { line, column }
+            }
+            None => CursorPosition {
+                line: self.scroll as usize + rel_y,
+                column: text_x + self.h_scroll as usize,
+            },
+        }
+    }
+
+    /// Toggle the selected directory, or the directory holding the selected file (`za`).
+    pub fn toggle_fold_at_selection(&mut self) {
+        let Some(item) = self.sidebar_item_at_visible(self.sidebar_selected) else {
+            return;
+        };
+        let dir = match item {
+            SidebarItem::Directory { path, .. } => Some(path.clone()),
+            SidebarItem::File { path, .. } => self.innermost_dir_of(path),
+        };
+        if let Some(dir) = dir {
+            self.toggle_directory(&dir);
+        }
+    }
+
+    /// Expand every directory (`zR`).
+    pub fn expand_all_dirs(&mut self) {
+        let selected = self.sidebar_item_at_visible(self.sidebar_selected).cloned();
+        self.collapsed_dirs.clear();
+        self.rebuild_sidebar_visible();
+        self.reselect_after_fold(selected);
+    }
+
+    /// Collapse every directory (`zM`); the selection moves to its top-level directory.
+    pub fn collapse_all_dirs(&mut self) {
+        let selected = self.sidebar_item_at_visible(self.sidebar_selected).cloned();
+        self.collapsed_dirs = self
+            .sidebar_items
+            .iter()
+            .filter_map(|item| match item {
+                SidebarItem::Directory { path, .. } => Some(path.clone()),
+                _ => None,
+            })
+            .collect();
+        self.rebuild_sidebar_visible();
+        self.reselect_after_fold(selected);
+    }
+
+    /// The deepest sidebar directory containing `path`.
+    fn innermost_dir_of(&self, path: &str) -> Option<String> {
+        self.sidebar_items
+            .iter()
+            .filter_map(|item| match item {
+                SidebarItem::Directory { path: dir, .. } if is_child_path(path, dir) => Some(dir),
+                _ => None,
+            })
+            .max_by_key(|dir| dir.len())
+            .cloned()
+    }
+
+    /// Keep the previously selected item selected, or its nearest visible directory
+    /// when a fold hid it.
+    fn reselect_after_fold(&mut self, selected: Option<SidebarItem>) {
+        let Some(selected) = selected else {
+            return;
+        };
+        let index = match &selected {
+            SidebarItem::Directory { path, .. } => self.sidebar_visible_index_for_dir(path),
+            SidebarItem::File { file_index, .. } => {
+                self.sidebar_visible_index_for_file(*file_index)
+            }
+        };
+        let index = index.or_else(|| {
+            let path = sidebar_item_path(&selected);
+            self.sidebar_visible
+                .iter()
+                .enumerate()
+                .filter(|(_, &idx)| {
+                    is_child_path(path, sidebar_item_path(&self.sidebar_items[idx]))
+                })
+                .max_by_key(|(_, &idx)| sidebar_item_path(&self.sidebar_items[idx]).len())
+                .map(|(visible, _)| visible)
+        });
+        if let Some(index) = index {
+            self.sidebar_selected = index;
+        }
+    }
+
+    /// Number of deleted files currently hidden from the sidebar and file navigation
+    pub fn hidden_deleted_count(&self) -> usize {
+        if self.settings.show_deleted {
+            return 0;
+        }
+        self.file_diffs
+            .iter()
+            .filter(|diff| diff.status == FileStatus::Deleted)
+            .count()
+    }
+
+    fn build_sidebar_items(&self) -> Vec<SidebarItem> {
+        if self.flat_sidebar {
+            build_flat_file_list(&self.file_diffs)
+        } else {
+            build_file_tree(&self.file_diffs)
+        }
+    }
+
+    /// Switch the sidebar between the directory tree and a flat list of basenames.
+    pub fn toggle_flat_sidebar(&mut self) {
+        self.flat_sidebar = !self.flat_sidebar;
+        self.sidebar_items = self.build_sidebar_items();
+        if !self.flat_sidebar {
+            self.reveal_file(self.current_file);
+        }
+        self.rebuild_sidebar_visible();
+        self.sidebar_scroll = 0;
+    }
+
+    /// Collapse every directory at `depth` or deeper; `None` expands the whole tree.
+    /// Directories can still be expanded by hand afterwards.
+    pub fn set_tree_depth(&mut self, depth: Option<usize>) {
+        self.tree_depth = depth;
+        self.collapsed_dirs = self.dirs_beyond_tree_depth();
+        self.rebuild_sidebar_visible();
+        self.sidebar_scroll = 0;
+    }
+
+    /// Directories that the tree depth limit collapses by default.
+    fn dirs_beyond_tree_depth(&self) -> HashSet<String> {
+        let Some(limit) = self.tree_depth else {
+            return HashSet::new();
+        };
+        // Always measured on the tree so the limit survives a trip through the flat list
+        build_file_tree(&self.file_diffs)
+            .into_iter()
+            .filter_map(|item| match item {
+                SidebarItem::Directory { path, depth, .. } if depth >= limit => Some(path),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Show or hide deleted files. When the current file gets hidden,
+    /// moves to the first file still shown in the sidebar.
+    pub fn toggle_show_deleted(&mut self) {
+        self.settings.show_deleted = !self.settings.show_deleted;
+        self.rebuild_sidebar_visible();
+        self.select_first_if_hidden();
+    }
+
+    /// Narrow the sidebar to files matching a glob (`*.rs`, `src/**`), or list every file
+    /// again with `None`. The current file moves to the first match when it is filtered out.
+    pub fn set_sidebar_filter(&mut self, filter: Option<String>) {
+        self.sidebar_filter = filter;
+        self.rebuild_sidebar_visible();
+        self.select_first_if_hidden();
+    }
+
+    /// Whether the sidebar lists `file_index`, given the deleted-files toggle and the
+    /// filter. Files under a collapsed directory still count as shown.
+    fn is_file_shown(&self, file_index: usize) -> bool {
+        self.file_diffs.get(file_index).is_some_and(|diff| {
+            file_shown(
+                &diff.filename,
+                diff.status,
+                self.settings.show_deleted,
+                self.sidebar_filter.as_deref(),
+            )
+        })
+    }
+
+    /// How many files the sidebar filter lets through.
+    pub fn sidebar_filter_count(&self) -> usize {
+        self.sidebar_items
+            .iter()
+            .filter(|item| match item {
+                SidebarItem::File { path, status, .. } => file_shown(
+                    path,
+                    *status,
+                    self.settings.show_deleted,
+                    self.sidebar_filter.as_deref(),
+                ),
+                SidebarItem::Directory { .. } => false,
+            })
+            .count()
+    }
+
+    /// Select the first listed file when the current one is no longer in the sidebar.
+    fn select_first_if_hidden(&mut self) {
+        if self.sidebar_visible_index_for_file(self.current_file).is_none() {
+            let (sidebar_idx, file_index) =
+                Self::find_first_file(&self.sidebar_items, &self.sidebar_visible);
+            if file_index < self.file_diffs.len()
+                && self.sidebar_visible_index_for_file(file_index).is_some()
+            {
+                self.select_file(file_index);
+                self.sidebar_selected = sidebar_idx;
+            }
+        }
+    }
+
+    pub fn reveal_file(&mut self, file_index: usize) {
+        if file_index >= self.file_diffs.len() {
+            return;
+        }
+        let path = self.file_diffs[file_index].filename.clone();
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts.len() > 1 {
+            for i in 0..parts.len() - 1 {
+                let dir_path = parts[..=i].join("/");
+                self.collapsed_dirs.remove(&dir_path);
+            }
+        }
+        self.rebuild_sidebar_visible();
+        if let Some(idx) = self.sidebar_visible_index_for_file(file_index) {
+            self.sidebar_selected = idx;
+        }
+    }
+
+    /// Show a short-lived notice in the footer
+    pub fn set_status_message(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+    }
+
+    /// The footer notice, if it hasn't expired yet
+    pub fn status_message(&self) -> Option<&str> {
+        self.status_message
+            .as_ref()
+            .filter(|(_, set_at)| set_at.elapsed() < STATUS_MESSAGE_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Set the diff reference string (e.g., "HEAD~2..HEAD")
+    pub fn set_diff_reference(&mut self, reference: Option<String>) {
+        self.diff_reference = reference;
+    }
+
+    pub fn set_panel_titles(&mut self, titles: PanelTitles) {
+        self.panel_titles = titles;
+    }
+
+    /// Compare the working tree against `base` from the next reload on.
+    pub fn pin_base(&mut self, base: String, titles: PanelTitles) {
+        self.set_diff_reference(Some(format!("{} vs working tree", base)));
+        self.set_panel_titles(titles);
+        self.set_status_message(format!("pinned base: {}", base));
+        self.pinned_base = Some(base);
+        self.needs_reload = true;
+    }
+
+    /// Go back to the diff the session opened on, described by `reference` and
+    /// `titles`. Returns false when no base was pinned.
+    pub fn unpin_base(&mut self, reference: Option<String>, titles: PanelTitles) -> bool {
+        if self.pinned_base.take().is_none() {
+            return false;
+        }
+        self.set_diff_reference(reference);
+        self.set_panel_titles(titles);
+        self.set_status_message("base unpinned");
+        self.needs_reload = true;
+        true
+    }
+
+    /// Labels for the old/new panels; in stacked mode, the current commit and its parent.
+    pub fn panel_titles(&self) -> PanelTitles {
+        match self.current_commit() {
+            Some(commit) => {
+                let parent_suffix = if self.vcs_name == "jj" { "-" } else { "^" };
+                PanelTitles {
+                    old: format!("{}{}", commit.short_id, parent_suffix),
+                    new: commit.short_id.clone(),
+                }
+            }
+            None => self.panel_titles.clone(),
+        }
+    }
+
+    /// Initialize stacked mode with commits
+    pub fn init_stacked_mode(&mut self, commits: Vec<StackedCommitInfo>) {
+        self.stacked_mode = true;
+        self.stacked_commits = commits;
+        self.current_commit_index = 0;
+    }
+
+    /// Get the current commit info if in stacked mode
+    pub fn current_commit(&self) -> Option<&StackedCommitInfo> {
+        if self.stacked_mode {
+            self.stacked_commits.get(self.current_commit_index)
+        } else {
+            None
+        }
+    }
+
+    /// Save current viewed files for the current commit (stacked mode only)
+    pub fn save_stacked_viewed_files(&mut self) {
+        if !self.stacked_mode {
+            return;
+        }
+        if let Some(commit) = self.stacked_commits.get(self.current_commit_index) {
+            let viewed_filenames: HashSet<String> = self
+                .viewed_files
+                .iter()
+                .filter_map(|&idx| self.file_diffs.get(idx).map(|f| f.filename.clone()))
+                .collect();
+            self.stacked_viewed_files
+                .insert(commit.commit_id.clone(), viewed_filenames);
+        }
     }
 
     /// Load viewed files for the current commit (stacked mode only)
@@ -494,6 +1709,11 @@ impl AppState {
             .map(|f| f.filename.clone());
         let old_scroll = self.scroll;
         let old_h_scroll = self.h_scroll;
+        // Gaps are keyed by row, which new contents can shift
+        self.expanded_gaps.clear();
+        // The old side may be a different commit now (stacked mode, a pinned base)
+        self.blame_cache.clear();
+        self.origin_cache.clear();
 
         // Convert viewed_files indices to filenames (to handle index changes after reload)
         let mut viewed_filenames: HashSet<String> = self
@@ -509,10 +1729,36 @@ impl AppState {
             }
         }
 
+        let wrapped_filenames: HashSet<String> = self
+            .wrapped_files
+            .iter()
+            .filter_map(|&idx| self.file_diffs.get(idx).map(|f| f.filename.clone()))
+            .collect();
+
+        // Reviewed hunks are keyed by index too; remember them by filename
+        let reviewed_by_name: Vec<(String, usize)> = self
+            .reviewed_hunks
+            .iter()
+            .filter_map(|&(idx, hunk_idx)| {
+                let filename = &self.file_diffs.get(idx)?.filename;
+                let changed = changed_files.is_some_and(|c| c.contains(filename));
+                (!changed).then(|| (filename.clone(), hunk_idx))
+            })
+            .collect();
+
+        let old_deep_dirs = self.dirs_beyond_tree_depth();
+
         self.file_diffs = file_diffs;
-        self.sidebar_items = build_file_tree(&self.file_diffs);
+        self.sidebar_items = self.build_sidebar_items();
+        // Directories new to this reload start collapsed too; the rest keep their state
+        for dir in self.dirs_beyond_tree_depth() {
+            if !old_deep_dirs.contains(&dir) {
+                self.collapsed_dirs.insert(dir);
+            }
+        }
+        self.conflict_counts = Self::compute_conflict_counts(&self.file_diffs);
 
-        // Update annotations: remap file indices and remove stale ones
+        // Drop annotations whose file or hunk is gone; the rest follow their filename
         // Build a map of filename -> (new_file_index, hunk_count)
         let file_info: HashMap<&str, (usize, usize)> = self
             .file_diffs
@@ -522,29 +1768,30 @@ impl AppState {
                 let side_by_side = compute_side_by_side(
                     &diff.old_content,
                     &diff.new_content,
-                    self.settings.tab_width,
+                    self.settings.tab_width_for(&diff.filename),
+                    self.settings.ignore_whitespace,
                 );
                 let hunk_count = find_hunk_starts(&side_by_side).len();
                 (diff.filename.as_str(), (idx, hunk_count))
             })
             .collect();
 
-        // Filter and update annotations
-        self.annotations.retain_mut(|ann| {
-            if let Some(&(new_file_index, hunk_count)) = file_info.get(ann.filename.as_str()) {
-                // File still exists - check if hunk index is valid
-                if ann.hunk_index < hunk_count {
-                    ann.file_index = new_file_index;
-                    true
-                } else {
-                    // Hunk no longer exists
-                    false
-                }
-            } else {
-                // File no longer exists
-                false
-            }
-        });
+        // Notes whose file or hunk is gone are kept, but marked stale
+        for ann in &mut self.annotations {
+            let exists = file_info
+                .get(ann.filename.as_str())
+                .is_some_and(|&(_, hunk_count)| ann.is_file_level() || ann.hunk_index < hunk_count);
+            ann.stale |= !exists;
+        }
+
+        // Remap reviewed hunks the same way, dropping hunks that no longer exist
+        self.reviewed_hunks = reviewed_by_name
+            .into_iter()
+            .filter_map(|(name, hunk_idx)| {
+                let &(new_idx, hunk_count) = file_info.get(name.as_str())?;
+                (hunk_idx < hunk_count).then_some((new_idx, hunk_idx))
+            })
+            .collect();
 
         // Convert viewed filenames back to indices in the new file_diffs
         self.viewed_files = self
@@ -554,6 +1801,13 @@ impl AppState {
             .filter(|(_, f)| viewed_filenames.contains(&f.filename))
             .map(|(i, _)| i)
             .collect();
+        self.wrapped_files = self
+            .file_diffs
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| wrapped_filenames.contains(&f.filename))
+            .map(|(i, _)| i)
+            .collect();
 
         // Preserve current file selection
         if let Some(name) = old_filename {
@@ -566,8 +1820,10 @@ impl AppState {
         if self.current_file >= self.file_diffs.len() && !self.file_diffs.is_empty() {
             self.current_file = self.file_diffs.len() - 1;
         }
+        self.settings.wrap = self.wrapped_files.contains(&self.current_file);
 
         self.rebuild_sidebar_visible();
+        self.refresh_changed_since_review();
 
         // Preserve scroll position instead of resetting
         if !self.file_diffs.is_empty() {
@@ -576,38 +1832,297 @@ impl AppState {
             let side_by_side = compute_side_by_side(
                 &diff.old_content,
                 &diff.new_content,
-                self.settings.tab_width,
+                self.settings.tab_width_for(&diff.filename),
+                self.settings.ignore_whitespace,
             );
             let max_scroll = side_by_side.len().saturating_sub(10);
             self.scroll = old_scroll.min(max_scroll as u16);
             self.h_scroll = old_h_scroll;
         }
 
+        // Line numbers may have shifted, so remembered positions no longer fit
+        self.file_positions.clear();
+
         self.needs_reload = false;
         self.invalidate_cache(); // Clear cache after reload
+        self.refresh_pinned_hunk();
     }
 
+    /// Open `file_index`, back where you left it if you viewed it before this
+    /// session; otherwise at its first hunk.
     pub fn select_file(&mut self, file_index: usize) {
+        if file_index != self.current_file && self.current_file < self.file_diffs.len() {
+            self.file_positions.insert(
+                self.current_file,
+                (self.scroll, self.h_scroll, self.focused_hunk),
+            );
+        }
+        let returning = (file_index != self.current_file)
+            .then(|| self.file_positions.get(&file_index).copied())
+            .flatten();
         self.current_file = file_index;
+        self.settings.wrap = self.wrapped_files.contains(&file_index);
         self.diff_fullscreen = DiffFullscreen::None;
         self.clear_selection(); // Clear selection when changing files
         self.invalidate_cache(); // Clear cache for new file
 
-        // Use cached computation
+        // Use cached computation; start on the first hunk `--grep` kept
         let hunks = self.get_hunks().to_vec();
+        let first = (0..hunks.len())
+            .find(|&h| !self.is_hidden_hunk(h))
+            .unwrap_or(0);
         self.scroll = hunks
-            .first()
+            .get(first)
             .map(|&h| (h as u16).saturating_sub(5))
             .unwrap_or(0);
         self.h_scroll = 0;
+        self.focused_hunk = if hunks.is_empty() { None } else { Some(first) };
+
+        if let Some((scroll, h_scroll, focused_hunk)) = returning {
+            self.scroll = scroll;
+            self.h_scroll = h_scroll;
+            self.focused_hunk = focused_hunk.filter(|&h| h < hunks.len());
+        }
+    }
+
+    /// Toggle wrapping for the current file only; other files keep their own setting.
+    pub fn toggle_wrap(&mut self) {
+        if !self.wrapped_files.remove(&self.current_file) {
+            self.wrapped_files.insert(self.current_file);
+        }
+        self.settings.wrap = self.wrapped_files.contains(&self.current_file);
+        self.h_scroll = 0;
+    }
+
+    /// Toggle matching lines regardless of whitespace. The hunks change, so the
+    /// view returns to the first one. Reviewed marks, annotations and the pinned
+    /// hunk follow the lines they were made on; marks and annotations whose hunk
+    /// now covers different lines are dropped and marked stale respectively.
+    pub fn toggle_ignore_whitespace(&mut self) {
+        let reviewed: Vec<(usize, (usize, usize))> = self
+            .reviewed_hunks
+            .iter()
+            .filter_map(|&(file_index, hunk_index)| {
+                Some((file_index, self.hunk_line_range(file_index, hunk_index)?))
+            })
+            .collect();
+        let pinned = self
+            .pinned_hunk
+            .as_ref()
+            .and_then(|(filename, hunk_index)| {
+                let range = self.hunk_line_range(self.file_index_of(filename)?, *hunk_index)?;
+                Some((filename.clone(), range))
+            });
+
+        self.settings.ignore_whitespace = !self.settings.ignore_whitespace;
+        self.invalidate_cache();
+
+        self.reviewed_hunks = reviewed
+            .into_iter()
+            .filter_map(|(file_index, range)| {
+                let hunk_index = self
+                    .hunk_line_ranges(file_index)
+                    .iter()
+                    .position(|&r| r == range)?;
+                Some((file_index, hunk_index))
+            })
+            .collect();
+        // The pinned hunk goes to whichever hunk now holds its first lines
+        self.pinned_hunk = pinned.and_then(|(filename, (start, end))| {
+            let hunk_index = self
+                .hunk_line_ranges(self.file_index_of(&filename)?)
+                .iter()
+                .position(|&(s, e)| s <= end && start <= e)?;
+            Some((filename, hunk_index))
+        });
+        self.refresh_pinned_hunk();
+        let annotations = std::mem::take(&mut self.annotations);
+        for mut annotation in annotations {
+            if !annotation.is_file_level() {
+                let hunk_index = self.file_index_of(&annotation.filename).and_then(|idx| {
+                    self.hunk_line_ranges(idx)
+                        .iter()
+                        .position(|&r| r == annotation.line_range)
+                });
+                match hunk_index {
+                    Some(hunk_index) => {
+                        annotation.hunk_index = hunk_index;
+                        annotation.stale = false;
+                    }
+                    None => annotation.stale = true,
+                }
+            }
+            self.annotations.push(annotation);
+        }
+        // A note that moved onto a stale one's hunk index is the one to show there
+        self.annotations.sort_by_key(|a| a.stale);
+        let comments = std::mem::take(&mut self.pending_review_comments);
+        self.set_pending_review_comments(&comments);
+
+        self.clear_selection();
+        let hunks = self.get_hunks().to_vec();
+        self.scroll = hunks
+            .first()
+            .map(|&h| (h as u16).saturating_sub(5))
+            .unwrap_or(0);
         self.focused_hunk = if hunks.is_empty() { None } else { Some(0) };
     }
 
+    /// Remember the current file and scroll position under `mark`
+    pub fn set_mark(&mut self, mark: char) {
+        let Some(diff) = self.file_diffs.get(self.current_file) else {
+            return;
+        };
+        self.marks
+            .insert(mark, (diff.filename.clone(), self.scroll));
+        self.set_status_message(format!("mark {} set", mark));
+    }
+
+    /// Open the file `mark` was set in, at the position it was set. The focused
+    /// hunk becomes the first one from there down. Returns false for unset marks
+    /// and marks whose file is gone.
+    pub fn jump_to_mark(&mut self, mark: char) -> bool {
+        let Some((filename, scroll)) = self.marks.get(&mark) else {
+            return false;
+        };
+        let scroll = *scroll;
+        let Some(file_index) = self.file_index_of(filename) else {
+            return false;
+        };
+        if file_index != self.current_file {
+            self.reveal_file(file_index);
+            self.select_file(file_index);
+        }
+        self.scroll = scroll;
+        let hunks = self.get_hunks();
+        self.focused_hunk = hunks
+            .iter()
+            .position(|&start| start >= scroll as usize)
+            .or(hunks.len().checked_sub(1));
+        true
+    }
+
+    /// Marks in letter order, as (mark, filename, scroll)
+    pub fn sorted_marks(&self) -> Vec<(char, &str, u16)> {
+        let mut marks: Vec<_> = self
+            .marks
+            .iter()
+            .map(|(&mark, (filename, scroll))| (mark, filename.as_str(), *scroll))
+            .collect();
+        marks.sort_unstable();
+        marks
+    }
+
+    /// Toggle the reviewed state of a hunk in a file
+    pub fn toggle_hunk_reviewed(&mut self, file_index: usize, hunk_index: usize) {
+        if !self.reviewed_hunks.remove(&(file_index, hunk_index)) {
+            self.reviewed_hunks.insert((file_index, hunk_index));
+        }
+    }
+
+    /// Whether hunk navigation should stop at this hunk of the current file
+    fn is_hunk_navigable(&self, hunk_index: usize) -> bool {
+        !self.is_hidden_hunk(hunk_index)
+            && (!self.skip_reviewed_hunks
+                || !self
+                    .reviewed_hunks
+                    .contains(&(self.current_file, hunk_index)))
+    }
+
+    /// Whether `--grep` hides this hunk of the current file, going by the cached rows
+    fn is_hidden_hunk(&self, hunk_index: usize) -> bool {
+        let (Some((file, lines)), Some((_, hunks))) =
+            (&self.cached_side_by_side, &self.cached_hunks)
+        else {
+            return false;
+        };
+        *file == self.current_file
+            && hunks
+                .get(hunk_index)
+                .and_then(|&row| lines.get(row))
+                .is_some_and(|line| matches!(line.change_type, ChangeType::Hidden { .. }))
+    }
+
+    /// First navigable hunk at or after `from` in the current file
+    pub fn next_navigable_hunk(&self, from: usize, hunk_count: usize) -> Option<usize> {
+        (from..hunk_count).find(|&idx| self.is_hunk_navigable(idx))
+    }
+
+    /// Last navigable hunk at or before `from` in the current file
+    pub fn prev_navigable_hunk(&self, from: usize) -> Option<usize> {
+        (0..=from).rev().find(|&idx| self.is_hunk_navigable(idx))
+    }
+
+    /// First and last line (new side, else old) of a hunk's changed rows,
+    /// or `None` when the file has no such hunk.
+    pub fn hunk_line_range(&self, file_index: usize, hunk_index: usize) -> Option<(usize, usize)> {
+        self.hunk_line_ranges(file_index).get(hunk_index).copied()
+    }
+
+    /// `hunk_line_range` for every hunk of a file, in order
+    fn hunk_line_ranges(&self, file_index: usize) -> Vec<(usize, usize)> {
+        let Some(diff) = self.file_diffs.get(file_index) else {
+            return Vec::new();
+        };
+        let side_by_side = compute_side_by_side(
+            &diff.old_content,
+            &diff.new_content,
+            self.settings.tab_width_for(&diff.filename),
+            self.settings.ignore_whitespace,
+        );
+        let hunks = find_hunk_starts(&side_by_side);
+        let line_number = |row: usize| {
+            let line = &side_by_side[row];
+            line.new_line
+                .as_ref()
+                .or(line.old_line.as_ref())
+                .map(|(n, _)| *n)
+        };
+        (0..hunks.len())
+            .map(|hunk_index| {
+                let hunk_start = hunks[hunk_index];
+                let next_hunk_start = hunks
+                    .get(hunk_index + 1)
+                    .copied()
+                    .unwrap_or(side_by_side.len());
+
+                // The hunk ends at its last changed line, not at the start of the next hunk
+                let hunk_end = (hunk_start..next_hunk_start)
+                    .rev()
+                    .find(|&i| !matches!(side_by_side[i].change_type, ChangeType::Equal))
+                    .unwrap_or(hunk_start);
+                let start = line_number(hunk_start).unwrap_or(1);
+                (start, line_number(hunk_end).unwrap_or(start))
+            })
+            .collect()
+    }
+
+    /// Attach annotations saved in an earlier session. Ones whose file or hunk is
+    /// gone, or whose hunk now covers different lines, are kept but marked stale.
+    pub fn restore_annotations(&mut self, saved: Vec<HunkAnnotation>) {
+        for mut annotation in saved {
+            let file_index = self.file_index_of(&annotation.filename);
+            annotation.stale = if annotation.is_file_level() {
+                file_index.is_none()
+            } else {
+                let current_range = file_index
+                    .and_then(|file_index| self.hunk_line_range(file_index, annotation.hunk_index));
+                current_range != Some(annotation.line_range)
+            };
+            self.set_annotation(annotation);
+        }
+    }
+
+    /// Current position of `filename` in `file_diffs`
+    pub fn file_index_of(&self, filename: &str) -> Option<usize> {
+        self.file_diffs.iter().position(|d| d.filename == filename)
+    }
+
     /// Get annotation for a specific hunk in a file
-    pub fn get_annotation(&self, file_index: usize, hunk_index: usize) -> Option<&HunkAnnotation> {
+    pub fn get_annotation(&self, filename: &str, hunk_index: usize) -> Option<&HunkAnnotation> {
         self.annotations
             .iter()
-            .find(|a| a.file_index == file_index && a.hunk_index == hunk_index)
+            .find(|a| a.filename == filename && a.hunk_index == hunk_index)
     }
 
     /// Add or update an annotation
@@ -615,7 +2130,7 @@ impl AppState {
         if let Some(existing) = self
             .annotations
             .iter_mut()
-            .find(|a| a.file_index == annotation.file_index && a.hunk_index == annotation.hunk_index)
+            .find(|a| a.filename == annotation.filename && a.hunk_index == annotation.hunk_index)
         {
             *existing = annotation;
         } else {
@@ -624,13 +2139,16 @@ impl AppState {
     }
 
     /// Remove an annotation
-    pub fn remove_annotation(&mut self, file_index: usize, hunk_index: usize) {
+    pub fn remove_annotation(&mut self, filename: &str, hunk_index: usize) {
         self.annotations
-            .retain(|a| !(a.file_index == file_index && a.hunk_index == hunk_index));
+            .retain(|a| !(a.filename == filename && a.hunk_index == hunk_index));
     }
 
     /// Format all annotations for export with full diff context
     pub fn format_annotations_for_export(&self) -> String {
+        if let Some(template) = &self.export_template {
+            return self.format_annotations_with(template);
+        }
         let mut result = String::new();
 
         // Add header with diff reference context
@@ -643,12 +2161,16 @@ impl AppState {
             .iter()
             .map(|a| {
                 // Try to get the diff content for this hunk
-                let diff_content = self.get_hunk_diff_content(a.file_index, a.hunk_index);
+                let diff_content = self
+                    .file_index_of(&a.filename)
+                    .and_then(|file_index| self.get_hunk_diff_content(file_index, a.hunk_index));
 
                 let mut output = format!("- {}", a.filename);
 
                 // Add line info based on what we have
-                if let Some((old_range, new_range, _)) = &diff_content {
+                if a.is_file_level() {
+                    output.push_str(" (whole file)");
+                } else if let Some((old_range, new_range, _)) = &diff_content {
                     // Format line ranges intelligently
                     match (old_range, new_range) {
                         (Some(_), Some((new_start, new_end))) => {
@@ -725,7 +2247,159 @@ impl AppState {
         result
     }
 
-    /// Get the diff content for a specific hunk
+    /// Annotations rendered through a user's export template. Lines are the hunk's on
+    /// the new side, or the old side for a pure deletion; empty for whole-file notes.
+    fn format_annotations_with(&self, template: &ExportTemplate) -> String {
+        let escape = |value: &str| {
+            if template.csv {
+                csv_field(value)
+            } else {
+                value.to_string()
+            }
+        };
+        let reference = &escape(self.diff_reference.as_deref().unwrap_or("working tree"));
+        let mut result = fill_template(&template.header, &[("reference", reference)]);
+        for a in &self.annotations {
+            let (line_start, line_end) = if a.is_file_level() {
+                (String::new(), String::new())
+            } else {
+                let (start, end) = self
+                    .file_index_of(&a.filename)
+                    .and_then(|file_index| self.get_hunk_diff_content(file_index, a.hunk_index))
+                    .and_then(|(old_range, new_range, _)| new_range.or(old_range))
+                    .unwrap_or(a.line_range);
+                (start.to_string(), end.to_string())
+            };
+            let secs = a.created_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let time = format_epoch_secs(secs as i64);
+            result.push_str(&fill_template(
+                &template.item,
+                &[
+                    ("file", &escape(&a.filename)),
+                    ("line_start", &line_start),
+                    ("line_end", &line_end),
+                    ("content", &escape(&a.content)),
+                    ("time", &time),
+                    ("reference", reference),
+                ],
+            ));
+        }
+        result.push_str(&fill_template(
+            &template.footer,
+            &[("reference", reference)],
+        ));
+        result
+    }
+
+    /// The focused hunk as a fenced Markdown `diff` block followed by a blank line,
+    /// ready to paste into a review comment and write the note under.
+    pub fn hunk_comment_block(&self) -> Option<String> {
+        let hunk_index = self.focused_hunk?;
+        let (_, _, diff_lines) = self.get_hunk_diff_content(self.current_file, hunk_index)?;
+        Some(format!("```diff\n{}```\n\n", diff_lines))
+    }
+
+    /// The focused hunk as a patch `git apply` accepts: `---`/`+++` header, `@@` line
+    /// and the hunk with its context.
+    pub fn focused_hunk_patch(&self) -> Option<String> {
+        let hunk_index = self.focused_hunk?;
+        let diff = self.file_diffs.get(self.current_file)?;
+        let rows = compute_side_by_side(
+            &diff.old_content,
+            &diff.new_content,
+            self.settings.tab_width_for(&diff.filename),
+            self.settings.ignore_whitespace,
+        );
+        let start = *find_hunk_starts(&rows).get(hunk_index)?;
+        hunk_patch(diff, &rows, start)
+    }
+
+    /// Whether a quit key should exit now. With `confirm_quit` the first press only
+    /// asks, and a second one confirms while the hint is still showing.
+    pub fn quit_confirmed(&mut self, confirm_quit: bool, pending_key: PendingKey) -> bool {
+        if !confirm_quit {
+            return true;
+        }
+        if let PendingKey::Quit(asked_at) = pending_key {
+            if asked_at.elapsed() < STATUS_MESSAGE_DURATION {
+                return true;
+            }
+        }
+        self.pending_key = PendingKey::Quit(Instant::now());
+        self.set_status_message("press q again to quit");
+        false
+    }
+
+    /// Pin the focused hunk below the diff, replacing any pinned one.
+    /// Returns false when no hunk is focused.
+    pub fn pin_focused_hunk(&mut self) -> bool {
+        let Some(hunk_index) = self.focused_hunk else {
+            return false;
+        };
+        let Some(diff) = self.file_diffs.get(self.current_file) else {
+            return false;
+        };
+        self.pinned_hunk = Some((diff.filename.clone(), hunk_index));
+        self.refresh_pinned_hunk();
+        true
+    }
+
+    /// Stop showing the pinned hunk. Returns false when nothing was pinned.
+    pub fn unpin_hunk(&mut self) -> bool {
+        self.pinned_hunk_rows = None;
+        self.pinned_hunk.take().is_some()
+    }
+
+    /// The pinned hunk's rows and a title naming where it sits, e.g. `src/lib.rs:42`.
+    /// None when nothing is pinned or the hunk went away on reload.
+    pub fn pinned_hunk_view(&self) -> Option<(&str, &[DiffLine])> {
+        self.pinned_hunk_rows
+            .as_ref()
+            .map(|(title, lines)| (title.as_str(), lines.as_slice()))
+    }
+
+    /// Rows the pinned hunk takes from the bottom of the diff panel in a terminal
+    /// `term_height` rows tall; 0 when nothing is pinned.
+    pub fn pinned_strip_height(&self, term_height: u16) -> u16 {
+        let header_height = if self.stacked_mode { 1 } else { 0 };
+        let footer_height = 1;
+        self.pinned_hunk_view().map_or(0, |(_, lines)| {
+            pinned_strip_height(
+                lines,
+                term_height.saturating_sub(header_height + footer_height),
+            )
+        })
+    }
+
+    fn refresh_pinned_hunk(&mut self) {
+        self.pinned_hunk_rows = self.compute_pinned_hunk();
+    }
+
+    fn compute_pinned_hunk(&self) -> Option<(String, Vec<DiffLine>)> {
+        let (filename, hunk_index) = self.pinned_hunk.as_ref()?;
+        let diff = &self.file_diffs[self.file_index_of(filename)?];
+        let mut lines = compute_side_by_side(
+            &diff.old_content,
+            &diff.new_content,
+            self.settings.tab_width_for(&diff.filename),
+            self.settings.ignore_whitespace,
+        );
+        let start = *find_hunk_starts(&lines).get(*hunk_index)?;
+        let len = lines[start..]
+            .iter()
+            .take_while(|line| line.change_type.is_change())
+            .count();
+        let first_line = lines[start]
+            .new_line
+            .as_ref()
+            .or(lines[start].old_line.as_ref())
+            .map_or(0, |(num, _)| *num);
+        lines.truncate(start + len);
+        lines.drain(..start);
+        Some((format!("{}:{}", filename, first_line), lines))
+    }
+
+    /// Get the diff content for a specific hunk
     /// Returns (old_line_range, new_line_range, diff_lines)
     fn get_hunk_diff_content(
         &self,
@@ -733,8 +2407,12 @@ impl AppState {
         hunk_index: usize,
     ) -> Option<(Option<(usize, usize)>, Option<(usize, usize)>, String)> {
         let diff = self.file_diffs.get(file_index)?;
-        let side_by_side =
-            compute_side_by_side(&diff.old_content, &diff.new_content, self.settings.tab_width);
+        let side_by_side = compute_side_by_side(
+            &diff.old_content,
+            &diff.new_content,
+            self.settings.tab_width_for(&diff.filename),
+            self.settings.ignore_whitespace,
+        );
         let hunks = find_hunk_starts(&side_by_side);
 
         let hunk_start = *hunks.get(hunk_index)?;
@@ -748,7 +2426,7 @@ impl AppState {
 
         for i in hunk_start..next_hunk_start {
             let dl = &side_by_side[i];
-            if matches!(dl.change_type, ChangeType::Equal) {
+            if !dl.change_type.is_change() {
                 continue;
             }
 
@@ -787,7 +2465,7 @@ impl AppState {
                         new_end = Some(*num);
                     }
                 }
-                ChangeType::Equal => {}
+                ChangeType::Equal | ChangeType::Folded { .. } | ChangeType::Hidden { .. } => {}
             }
         }
 
@@ -796,6 +2474,61 @@ impl AppState {
 
         Some((old_range, new_range, diff_lines))
     }
+
+    /// Build a unified-style diff for a whole file: changed lines with three lines of
+    /// context, gaps marked by `@@` separators. Returns None for binary or missing files.
+    pub fn file_diff_text(&self, file_index: usize) -> Option<String> {
+        const CONTEXT: usize = 3;
+
+        let diff = self.file_diffs.get(file_index)?;
+        if diff.is_binary {
+            return None;
+        }
+        let side_by_side = compute_side_by_side(
+            &diff.old_content,
+            &diff.new_content,
+            self.settings.tab_width_for(&diff.filename),
+            self.settings.ignore_whitespace,
+        );
+
+        let mut keep = vec![false; side_by_side.len()];
+        for (i, dl) in side_by_side.iter().enumerate() {
+            if !matches!(dl.change_type, ChangeType::Equal) {
+                let end = (i + CONTEXT + 1).min(keep.len());
+                keep[i.saturating_sub(CONTEXT)..end].fill(true);
+            }
+        }
+
+        let mut out = String::new();
+        let mut last_emitted: Option<usize> = None;
+        for (i, dl) in side_by_side.iter().enumerate() {
+            if !keep[i] {
+                continue;
+            }
+            if last_emitted.is_none_or(|last| i > last + 1) {
+                out.push_str("@@\n");
+            }
+            last_emitted = Some(i);
+
+            match dl.change_type {
+                ChangeType::Equal => {
+                    if let Some((_, text)) = &dl.new_line {
+                        out.push_str(&format!(" {}\n", text));
+                    }
+                }
+                _ => {
+                    if let Some((_, text)) = &dl.old_line {
+                        out.push_str(&format!("-{}\n", text));
+                    }
+                    if let Some((_, text)) = &dl.new_line {
+                        out.push_str(&format!("+{}\n", text));
+                    }
+                }
+            }
+        }
+
+        Some(out)
+    }
 }
 pub fn adjust_scroll_to_line(
     line: usize,
@@ -852,7 +2585,8 @@ pub fn adjust_scroll_for_hunk(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::command::diff::types::FileStatus;
+    use crate::command::diff::search::GrepFilter;
+    use crate::command::diff::types::{is_conflict_marker, FileStatus, PanelBorders};
 
     fn make_file_diff(filename: &str) -> FileDiff {
         FileDiff {
@@ -861,6 +2595,8 @@ mod tests {
             new_content: "content\n".to_string(),
             status: FileStatus::Added,
             is_binary: false,
+            old_mode: None,
+            new_mode: None,
         }
     }
 
@@ -914,6 +2650,1170 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reveal_file_expands_collapsed_parents() {
+        let diffs = vec![make_file_diff("a.rs"), make_file_diff("src/deep/b.rs")];
+        let mut state = AppState::new(diffs, Some("src/deep/b.rs"));
+        state.set_tree_depth(Some(0));
+        assert!(state.sidebar_visible_index_for_file(1).is_none());
+
+        state.reveal_file(1);
+
+        let visible = state.sidebar_visible_index_for_file(1);
+        assert!(visible.is_some());
+        assert_eq!(Some(state.sidebar_selected), visible);
+    }
+
+    fn make_conflicted_diff(filename: &str) -> FileDiff {
+        FileDiff {
+            new_content: "a\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> branch\nb\n".to_string(),
+            status: FileStatus::Modified,
+            ..make_file_diff(filename)
+        }
+    }
+
+    #[test]
+    fn test_count_conflict_markers_requires_complete_block() {
+        assert_eq!(count_conflict_markers(&make_conflicted_diff("a.rs").new_content), 1);
+        assert_eq!(count_conflict_markers("Title\n=======\n"), 0);
+        assert_eq!(count_conflict_markers("<<<<<<< HEAD\nours\n=======\n"), 0);
+        assert!(is_conflict_marker(">>>>>>> 1a2b3c4 (Add cache)"));
+        assert!(is_conflict_marker("======="));
+        assert!(!is_conflict_marker("========"));
+    }
+
+    #[test]
+    fn test_find_conflict_file_wraps_in_sidebar_order() {
+        let diffs = vec![
+            make_conflicted_diff("ccc.rs"),
+            make_file_diff("aaa.rs"),
+            make_conflicted_diff("bbb.rs"),
+        ];
+
+        let mut state = AppState::new(diffs, Some("aaa.rs"));
+        assert_eq!(state.conflict_counts, vec![1, 0, 1]);
+
+        assert_eq!(state.find_conflict_file(true), Some(2));
+        assert_eq!(state.find_conflict_file(false), Some(0));
+
+        state.select_file(0);
+        // Wraps past the end back to bbb.rs, skipping the unconflicted aaa.rs
+        assert_eq!(state.find_conflict_file(true), Some(2));
+
+        // Files the sidebar filters out are not stepped into
+        state.set_sidebar_filter(Some("c*".to_string()));
+        assert_eq!(state.find_conflict_file(true), None);
+    }
+
+    #[test]
+    fn test_conflict_sections_follow_markers() {
+        use crate::command::diff::types::{conflict_sections, ConflictSection::*};
+
+        let lines = [
+            "a",
+            "<<<<<<< HEAD",
+            "ours",
+            "||||||| base",
+            "base",
+            "=======",
+            "theirs",
+            ">>>>>>> topic",
+            "=======",
+        ];
+        let mut sections = conflict_sections(lines.iter().map(|line| Some(*line)));
+        assert_eq!(
+            sections,
+            vec![
+                None,
+                Some(Ours),
+                Some(Ours),
+                Some(Base),
+                Some(Base),
+                Some(Theirs),
+                Some(Theirs),
+                Some(Theirs),
+                None
+            ]
+        );
+
+        // Rows missing from this side stay unmarked without ending the section
+        sections = conflict_sections([Some("<<<<<<< HEAD"), None, Some("ours")]);
+        assert_eq!(sections, vec![Some(Ours), None, Some(Ours)]);
+    }
+
+    #[test]
+    fn test_conflict_navigation_within_file() {
+        let block =
+            |n: usize| format!("<<<<<<< HEAD\nours{n}\n=======\ntheirs{n}\n>>>>>>> topic\n");
+        let diff = FileDiff {
+            old_content: format!("a\nours1\nb\n{}ours2\nz\n", "x\n".repeat(12)),
+            new_content: format!("a\n{}b\n{}{}z\n", block(1), "x\n".repeat(12), block(2)),
+            status: FileStatus::Modified,
+            ..make_file_diff("a.rs")
+        };
+        let mut state = AppState::new(vec![diff], None);
+
+        // Unfocused, the first conflict from the top of the screen is next
+        state.focused_hunk = None;
+        assert_eq!(state.find_conflict_in_file(true), Some(1));
+        assert_eq!(state.find_conflict_in_file(false), None);
+
+        state.focus_conflict(None, true, 40, 100);
+        assert_eq!(
+            (state.focused_hunk, state.focused_conflict_row()),
+            (Some(0), Some(1))
+        );
+        assert_eq!(state.find_conflict_in_file(true), Some(19));
+
+        state.focus_conflict(None, false, 40, 100);
+        assert_eq!(
+            (state.focused_hunk, state.focused_conflict_row()),
+            (Some(2), Some(19))
+        );
+        assert_eq!(state.find_conflict_in_file(true), None);
+        assert_eq!(state.find_conflict_in_file(false), Some(1));
+    }
+
+    #[test]
+    fn test_files_changed_since_last_review() {
+        let diffs = vec![
+            make_file_diff("aaa.rs"),
+            make_file_diff("bbb.rs"),
+            make_file_diff("ccc.rs"),
+        ];
+        let shas = |a: &str, b: &str, c: &str| -> HashMap<String, String> {
+            [("aaa.rs", a), ("bbb.rs", b), ("ccc.rs", c)]
+                .into_iter()
+                .map(|(path, sha)| (path.to_string(), sha.to_string()))
+                .collect()
+        };
+
+        // A first visit has nothing to compare against
+        let mut state = AppState::new(diffs, None);
+        state.set_remote_shas(shas("1", "1", "1"));
+        assert!(state.changed_since_review.is_empty());
+        assert!(!state.mark_seen(0));
+
+        // New pushes to bbb.rs and ccc.rs mid-review
+        state.set_remote_shas(shas("1", "2", "2"));
+        assert_eq!(state.changed_since_review, HashSet::from([1, 2]));
+        assert_eq!(state.next_changed_since_review(), Some(1));
+
+        assert!(state.mark_seen(1));
+        state.select_file(1);
+        assert_eq!(state.next_changed_since_review(), Some(2));
+        state.select_file(2);
+        // Wraps around to the current file while it is still unseen
+        assert_eq!(state.next_changed_since_review(), Some(2));
+        assert!(state.mark_seen(2));
+        assert_eq!(state.next_changed_since_review(), None);
+        assert_eq!(state.seen_shas["ccc.rs"], "2");
+    }
+
+    fn make_two_hunk_diff(filename: &str) -> FileDiff {
+        FileDiff {
+            filename: filename.to_string(),
+            old_content: "a\nb\nc\nd\ne\nf\ng\nh\n".to_string(),
+            new_content: "A\nb\nc\nd\ne\nf\ng\nH\n".to_string(),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        }
+    }
+
+    #[test]
+    fn test_skip_reviewed_hunks_in_navigation() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+        assert_eq!(state.get_hunks().len(), 2);
+
+        state.toggle_hunk_reviewed(0, 0);
+        assert_eq!(state.next_navigable_hunk(0, 2), Some(0));
+
+        state.skip_reviewed_hunks = true;
+        assert_eq!(state.next_navigable_hunk(0, 2), Some(1));
+        assert_eq!(state.prev_navigable_hunk(1), Some(1));
+        assert_eq!(state.prev_navigable_hunk(0), None);
+
+        state.toggle_hunk_reviewed(0, 0);
+        assert_eq!(state.prev_navigable_hunk(1), Some(1));
+        assert_eq!(state.prev_navigable_hunk(0), Some(0));
+    }
+
+    #[test]
+    fn test_grep_skips_hidden_hunks_until_unfolded() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+        state.settings.grep = Some(GrepFilter::new("H").unwrap());
+        state.select_file(0);
+        assert_eq!(state.get_hunks().len(), 2);
+        assert_eq!(state.focused_hunk, Some(1));
+        assert_eq!(state.next_navigable_hunk(0, 2), Some(1));
+        assert_eq!(state.prev_navigable_hunk(0), None);
+
+        state.scroll = 0;
+        assert!(state.expand_visible_gap(10));
+        let _ = state.get_hunks();
+        assert_eq!(state.next_navigable_hunk(0, 2), Some(0));
+    }
+
+    #[test]
+    fn test_old_line_for_blame_uses_focused_hunk() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+
+        state.focused_hunk = Some(0);
+        assert_eq!(state.old_line_for_blame(), Some(1));
+        state.focused_hunk = Some(1);
+        assert_eq!(state.old_line_for_blame(), Some(8));
+        state.focused_hunk = None;
+        assert_eq!(state.old_line_for_blame(), None);
+    }
+
+    #[test]
+    fn test_pinned_base_survives_reload_until_unpinned() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+        state.set_diff_reference(Some("HEAD~2..HEAD".to_string()));
+        let pinned_titles = PanelTitles {
+            old: "main".to_string(),
+            new: "working tree".to_string(),
+        };
+
+        state.pin_base("main".to_string(), pinned_titles.clone());
+        assert_eq!(state.pinned_base.as_deref(), Some("main"));
+        assert_eq!(
+            state.diff_reference.as_deref(),
+            Some("main vs working tree")
+        );
+        assert_eq!(state.panel_titles, pinned_titles);
+        assert!(state.needs_reload);
+
+        state.reload(vec![make_two_hunk_diff("a.rs")], None);
+        assert!(!state.needs_reload);
+        assert_eq!(state.pinned_base.as_deref(), Some("main"));
+        assert_eq!(
+            state.diff_reference.as_deref(),
+            Some("main vs working tree")
+        );
+
+        assert!(state.unpin_base(Some("HEAD~2..HEAD".to_string()), PanelTitles::default()));
+        assert_eq!(state.pinned_base, None);
+        assert_eq!(state.diff_reference.as_deref(), Some("HEAD~2..HEAD"));
+        assert_eq!(state.panel_titles, PanelTitles::default());
+        assert!(state.needs_reload);
+
+        // Nothing left to unpin
+        state.needs_reload = false;
+        assert!(!state.unpin_base(None, PanelTitles::default()));
+        assert!(!state.needs_reload);
+    }
+
+    #[test]
+    fn test_reload_remaps_reviewed_hunks_by_filename() {
+        let diffs = vec![make_two_hunk_diff("b.rs"), make_two_hunk_diff("c.rs")];
+        let mut state = AppState::new(diffs, None);
+        state.toggle_hunk_reviewed(1, 1);
+        state.toggle_hunk_reviewed(0, 0);
+
+        let reloaded = vec![
+            make_file_diff("a.rs"),
+            make_two_hunk_diff("c.rs"),
+            make_two_hunk_diff("b.rs"),
+        ];
+        let changed: HashSet<String> = ["b.rs".to_string()].into_iter().collect();
+        state.reload(reloaded, Some(&changed));
+
+        // c.rs moved to index 1 and keeps its mark; b.rs changed so its mark is dropped
+        assert_eq!(state.reviewed_hunks, [(1, 1)].into_iter().collect());
+    }
+
+    #[test]
+    fn test_incremental_search_follows_first_match_while_typing() {
+        let new_content: String = (0..40).map(|i| format!("line {}\n", i)).collect();
+        let diff = FileDiff {
+            new_content,
+            ..make_file_diff("a.rs")
+        };
+        let mut state = AppState::new(vec![diff], None);
+        state.search_state.start_forward(10);
+
+        for c in "line 3".chars() {
+            state.search_state.push_char(c);
+            state.update_incremental_search();
+        }
+        // "line 3" first matches at or below the origin on "line 30"
+        assert_eq!(state.search_state.match_count(), 11);
+        assert_eq!(state.scroll, 25);
+
+        state.search_state.push_char('x');
+        state.update_incremental_search();
+        assert_eq!(state.search_state.match_count(), 0);
+        assert_eq!(state.scroll, 25, "no match leaves the view where it was");
+    }
+
+    #[test]
+    fn test_annotations_follow_their_file_across_reload() {
+        let diffs = vec![make_two_hunk_diff("b.rs"), make_two_hunk_diff("c.rs")];
+        let mut state = AppState::new(diffs, None);
+        state.set_annotation(HunkAnnotation {
+            hunk_index: 1,
+            content: "check this".to_string(),
+            line_range: (9, 9),
+            filename: "c.rs".to_string(),
+            created_at: SystemTime::now(),
+            pending_author: None,
+            stale: false,
+        });
+
+        let reloaded = vec![
+            make_two_hunk_diff("c.rs"),
+            make_file_diff("a.rs"),
+            make_two_hunk_diff("b.rs"),
+        ];
+        state.reload(reloaded, None);
+
+        assert_eq!(state.file_index_of("c.rs"), Some(0));
+        assert!(state.get_annotation("c.rs", 1).is_some());
+        assert!(state.get_annotation("b.rs", 1).is_none());
+
+        state.reload(vec![make_file_diff("c.rs")], None);
+        let note = state.get_annotation("c.rs", 1).unwrap();
+        assert!(note.stale, "the hunk no longer exists");
+    }
+
+    #[test]
+    fn test_file_level_annotations_outlive_hunk_changes() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("b.rs")], None);
+        state.set_annotation(HunkAnnotation {
+            hunk_index: FILE_ANNOTATION_HUNK,
+            content: "needs tests".to_string(),
+            line_range: (1, 1),
+            filename: "b.rs".to_string(),
+            created_at: SystemTime::now(),
+            pending_author: None,
+            stale: false,
+        });
+
+        let export = state.format_annotations_for_export();
+        assert!(export.contains("- b.rs (whole file)\ncomment: needs tests"));
+
+        state.reload(vec![make_file_diff("b.rs")], None);
+        assert!(state.get_annotation("b.rs", FILE_ANNOTATION_HUNK).is_some());
+
+        state.reload(vec![make_file_diff("a.rs")], None);
+        let note = state.get_annotation("b.rs", FILE_ANNOTATION_HUNK).unwrap();
+        assert!(note.stale, "the file is gone");
+    }
+
+    #[test]
+    fn test_hunk_comment_block() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("b.rs")], None);
+        state.focused_hunk = Some(1);
+        assert_eq!(
+            state.hunk_comment_block().as_deref(),
+            Some("```diff\n- h\n+ H\n```\n\n")
+        );
+
+        state.focused_hunk = None;
+        assert_eq!(state.hunk_comment_block(), None);
+    }
+
+    #[test]
+    fn test_tab_width_follows_extension() {
+        let tabbed = |filename: &str| FileDiff {
+            old_content: "\tx\n".to_string(),
+            new_content: "\ty\n".to_string(),
+            ..make_file_diff(filename)
+        };
+        let mut state = AppState::new(vec![tabbed("main.go"), tabbed("app.ts")], None);
+        state.settings.tab_widths = HashMap::from([("go".to_string(), 8)]);
+
+        let new_text =
+            |state: &AppState, index| state.display_lines(index)[0].new_line.clone().unwrap().1;
+        assert_eq!(new_text(&state, 0), format!("{}y", " ".repeat(8)));
+        assert_eq!(new_text(&state, 1), format!("{}y", " ".repeat(4)));
+        assert_eq!(state.settings.tab_style("Makefile").width, 4);
+    }
+
+    #[test]
+    fn test_pinned_hunk_survives_file_change() {
+        let mut state = AppState::new(
+            vec![make_two_hunk_diff("a.rs"), make_two_hunk_diff("b.rs")],
+            None,
+        );
+        state.focused_hunk = None;
+        assert!(!state.pin_focused_hunk());
+
+        state.focused_hunk = Some(1);
+        assert!(state.pin_focused_hunk());
+        state.select_file(1);
+
+        let (title, lines) = state.pinned_hunk_view().expect("pinned hunk");
+        assert_eq!(title, "a.rs:8");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            lines[0].new_line.as_ref().map(|(_, text)| text.as_str()),
+            Some("H")
+        );
+        // The modified line takes a removed and an added row, plus the borders
+        assert_eq!(state.pinned_strip_height(40), 4);
+        assert_eq!(state.pinned_strip_height(9), 3);
+
+        state.pinned_hunk = Some(("a.rs".to_string(), 5));
+        state.refresh_pinned_hunk();
+        assert!(state.pinned_hunk_view().is_none());
+        assert_eq!(state.pinned_strip_height(40), 0);
+    }
+
+    #[test]
+    fn test_export_template_fills_placeholders_once() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("b.rs")], None);
+        state.set_diff_reference(Some("main..HEAD".to_string()));
+        let range = state.hunk_line_range(0, 1).unwrap();
+        for (hunk_index, content) in [(1, "uses {file} literally"), (FILE_ANNOTATION_HUNK, "ok")] {
+            state.set_annotation(HunkAnnotation {
+                hunk_index,
+                content: content.to_string(),
+                line_range: (0, 0),
+                filename: "b.rs".to_string(),
+                created_at: UNIX_EPOCH + Duration::from_secs(86400),
+                pending_author: None,
+                stale: false,
+            });
+        }
+        state.export_template = Some(ExportTemplate {
+            header: "# {reference}\n".to_string(),
+            item: "{file},{line_start},{line_end},{time},{content},{other}\n".to_string(),
+            footer: "end".to_string(),
+            csv: false,
+        });
+
+        let export = state.format_annotations_for_export();
+        assert_eq!(
+            export,
+            format!(
+                "# main..HEAD\nb.rs,{},{},1970-01-02 00:00:00,uses {{file}} literally,{{other}}\n\
+                 b.rs,,,1970-01-02 00:00:00,ok,{{other}}\nend",
+                range.0, range.1
+            )
+        );
+
+        // CSV templates quote the values that need it
+        state.export_template = Some(ExportTemplate {
+            header: "file,comment\n".to_string(),
+            item: "{file},{content}\n".to_string(),
+            footer: String::new(),
+            csv: true,
+        });
+        state.set_annotation(HunkAnnotation {
+            hunk_index: 1,
+            content: "say \"hi\", twice\nthen stop".to_string(),
+            line_range: (0, 0),
+            filename: "b.rs".to_string(),
+            created_at: UNIX_EPOCH,
+            pending_author: None,
+            stale: false,
+        });
+        let export = state.format_annotations_for_export();
+        assert!(export.starts_with("file,comment\n"));
+        assert!(export.contains("b.rs,\"say \"\"hi\"\", twice\nthen stop\"\n"));
+        assert!(export.contains("b.rs,ok\n"));
+    }
+
+    #[test]
+    fn test_restored_annotations_flag_hunks_that_moved() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("b.rs")], None);
+        let range = state.hunk_line_range(0, 1).unwrap();
+        let saved = |hunk_index: usize, line_range: (usize, usize)| HunkAnnotation {
+            hunk_index,
+            content: "note".to_string(),
+            line_range,
+            filename: "b.rs".to_string(),
+            created_at: SystemTime::now(),
+            pending_author: None,
+            stale: false,
+        };
+        state.restore_annotations(vec![
+            saved(1, range),
+            saved(0, (range.0 + 100, range.1 + 100)),
+            saved(5, (1, 1)),
+        ]);
+
+        assert!(!state.get_annotation("b.rs", 1).unwrap().stale);
+        assert!(state.get_annotation("b.rs", 0).unwrap().stale);
+        assert!(state.get_annotation("b.rs", 5).unwrap().stale);
+
+        state.reload(vec![make_two_hunk_diff("b.rs")], None);
+        assert_eq!(state.annotations.len(), 3, "stale notes survive a reload");
+
+        // A note whose file leaves the diff is kept and marked stale, not dropped
+        state.reload(vec![make_two_hunk_diff("c.rs")], None);
+        assert_eq!(state.annotations.len(), 3);
+        assert!(state.get_annotation("b.rs", 1).unwrap().stale);
+    }
+
+    #[test]
+    fn test_ignore_whitespace_follows_hunks_by_line_range() {
+        // Ignoring whitespace drops the first hunk, so the second becomes hunk 0
+        let diff = FileDiff {
+            old_content: "a\nb\nc\nd\ne\nf\ng\nh\n".to_string(),
+            new_content: "a \nb\nc\nd\ne\nf\ng\nH\n".to_string(),
+            ..make_two_hunk_diff("b.rs")
+        };
+        let mut state = AppState::new(vec![diff], None);
+        let ranges = [
+            state.hunk_line_range(0, 0).unwrap(),
+            state.hunk_line_range(0, 1).unwrap(),
+        ];
+        for (hunk_index, line_range) in ranges.into_iter().enumerate() {
+            state.set_annotation(HunkAnnotation {
+                hunk_index,
+                content: format!("note {}", hunk_index),
+                line_range,
+                filename: "b.rs".to_string(),
+                created_at: SystemTime::now(),
+                pending_author: None,
+                stale: false,
+            });
+        }
+        state.toggle_hunk_reviewed(0, 1);
+        state.focused_hunk = Some(1);
+        assert!(state.pin_focused_hunk());
+
+        state.toggle_ignore_whitespace();
+        assert_eq!(state.get_hunks().len(), 1);
+        assert_eq!(state.reviewed_hunks, [(0, 0)].into_iter().collect());
+        assert_eq!(state.pinned_hunk, Some(("b.rs".to_string(), 0)));
+        let moved = state.get_annotation("b.rs", 0).unwrap();
+        assert_eq!((moved.content.as_str(), moved.stale), ("note 1", false));
+        assert!(state
+            .annotations
+            .iter()
+            .any(|a| a.content == "note 0" && a.stale));
+
+        state.toggle_ignore_whitespace();
+        assert_eq!(state.reviewed_hunks, [(0, 1)].into_iter().collect());
+        assert_eq!(state.pinned_hunk, Some(("b.rs".to_string(), 1)));
+        for hunk_index in 0..2 {
+            let annotation = state.get_annotation("b.rs", hunk_index).unwrap();
+            assert_eq!(annotation.content, format!("note {}", hunk_index));
+            assert!(!annotation.stale);
+        }
+    }
+
+    #[test]
+    fn test_file_diff_text_includes_context_and_gaps() {
+        let diff = FileDiff {
+            filename: "a.rs".to_string(),
+            old_content: "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n".to_string(),
+            new_content: "one\n2\n3\n4\n5\n6\n7\n8\n9\nten\n".to_string(),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        let state = AppState::new(vec![diff], None);
+        let text = state.file_diff_text(0).unwrap();
+        assert_eq!(
+            text,
+            "@@\n-1\n+one\n 2\n 3\n 4\n@@\n 7\n 8\n 9\n-10\n+ten\n"
+        );
+    }
+
+    #[test]
+    fn test_toggle_show_deleted_hides_files_and_empty_dirs() {
+        let mut gone = make_file_diff("old/gone.rs");
+        gone.status = FileStatus::Deleted;
+        let diffs = vec![gone, make_file_diff("src/kept.rs")];
+        let mut state = AppState::new(diffs, Some("old/gone.rs"));
+        assert_eq!(state.current_file, 0);
+        assert_eq!(state.hidden_deleted_count(), 0);
+
+        state.toggle_show_deleted();
+
+        assert_eq!(state.hidden_deleted_count(), 1);
+        assert!(state.sidebar_visible_index_for_dir("old").is_none());
+        assert!(state.sidebar_visible_index_for_file(0).is_none());
+        // The hidden current file is replaced by the first shown one
+        assert_eq!(state.current_file, 1);
+        assert_eq!(state.sidebar_visible_index_for_file(1), Some(state.sidebar_selected));
+
+        state.toggle_show_deleted();
+        assert!(state.sidebar_visible_index_for_file(0).is_some());
+        assert_eq!(state.hidden_deleted_count(), 0);
+    }
+
+    #[test]
+    fn test_flat_sidebar_disambiguates_basenames() {
+        let diffs = vec![
+            make_file_diff("src/a/mod.rs"),
+            make_file_diff("src/b/mod.rs"),
+            make_file_diff("src/main.rs"),
+        ];
+        let mut state = AppState::new(diffs, Some("src/b/mod.rs"));
+
+        state.toggle_flat_sidebar();
+
+        let names: Vec<&str> = state
+            .sidebar_items
+            .iter()
+            .map(|item| match item {
+                SidebarItem::File { name, .. } => name.as_str(),
+                SidebarItem::Directory { name, .. } => name.as_str(),
+            })
+            .collect();
+        assert_eq!(names, vec!["a/mod.rs", "b/mod.rs", "main.rs"]);
+        // The current file stays selected across the switch
+        assert_eq!(state.sidebar_visible_index_for_file(1), Some(state.sidebar_selected));
+
+        state.toggle_flat_sidebar();
+        assert!(state.sidebar_visible_index_for_dir("src").is_some());
+        assert_eq!(state.sidebar_visible_index_for_file(1), Some(state.sidebar_selected));
+    }
+
+    #[test]
+    fn test_tree_depth_collapses_deep_directories() {
+        let diffs = vec![
+            make_file_diff("a/x.rs"),
+            make_file_diff("a/b/y.rs"),
+            make_file_diff("a/b/c/z.rs"),
+        ];
+        let mut state = AppState::new(diffs, None);
+
+        state.set_tree_depth(Some(1));
+        let collapsed: HashSet<&str> = state.collapsed_dirs.iter().map(|s| s.as_str()).collect();
+        assert_eq!(collapsed, HashSet::from(["a/b", "a/b/c"]));
+        assert!(state.sidebar_visible_index_for_dir("a/b").is_some());
+        assert!(state.sidebar_visible_index_for_file(1).is_none());
+
+        // Manual expansion still works and survives a reload
+        state.toggle_directory("a/b");
+        state.reload(
+            vec![
+                make_file_diff("a/x.rs"),
+                make_file_diff("a/b/y.rs"),
+                make_file_diff("a/b/c/z.rs"),
+                make_file_diff("a/d/e/w.rs"),
+            ],
+            None,
+        );
+        assert!(state.sidebar_visible_index_for_file(1).is_some());
+        assert!(state.collapsed_dirs.contains("a/b/c"));
+        assert!(state.collapsed_dirs.contains("a/d/e"));
+
+        state.set_tree_depth(None);
+        assert!(state.collapsed_dirs.is_empty());
+    }
+
+    #[test]
+    fn test_pending_review_comments_attach_to_hunks() {
+        let diff = FileDiff {
+            filename: "a.rs".to_string(),
+            old_content: "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n".to_string(),
+            new_content: "1\nX\n3\n4\n5\n6\n7\n8\nY\n10\n".to_string(),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        let mut state = AppState::new(vec![diff], None);
+        let comment = |line: usize, old_side: bool, body: &str| PendingReviewComment {
+            path: "a.rs".to_string(),
+            line,
+            old_side,
+            body: body.to_string(),
+            author: "ann".to_string(),
+        };
+        state.set_pending_review_comments(&[
+            comment(9, false, "second hunk"),
+            comment(2, true, "first hunk"),
+            comment(2, false, "also first"),
+            comment(1, false, "on a context line"),
+        ]);
+
+        let hunks: Vec<(usize, &str)> = state
+            .pending_review
+            .iter()
+            .map(|a| (a.hunk_index, a.content.as_str()))
+            .collect();
+        assert_eq!(
+            hunks,
+            vec![
+                (1, "L9  second hunk"),
+                (0, "L2  first hunk\nL2  also first\nL1  on a context line"),
+            ]
+        );
+        assert_eq!(state.pending_review[1].line_range, (1, 2));
+        assert!(state.annotations.is_empty());
+    }
+
+    #[test]
+    fn test_change_bar_view_shows_new_side_only() {
+        let mut state = AppState::new(vec![make_file_diff("a.rs")], None);
+        state.diff_fullscreen = DiffFullscreen::OldOnly;
+        assert_eq!(state.effective_fullscreen(), DiffFullscreen::OldOnly);
+
+        state.settings.view_mode = DiffViewMode::parse("change-bars").unwrap();
+        assert_eq!(state.effective_fullscreen(), DiffFullscreen::NewOnly);
+        assert_eq!(state.settings.view_mode.next(), DiffViewMode::SideBySide);
+        assert!(DiffViewMode::parse("inline").is_err());
+    }
+
+    #[test]
+    fn test_h_scroll_to_line_end_fits_longest_visible_line() {
+        let long = "x".repeat(300);
+        let diff = FileDiff {
+            filename: "a.rs".to_string(),
+            old_content: "short\n".to_string(),
+            new_content: format!("short\n{}\n", long),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        let mut state = AppState::new(vec![diff], None);
+        state.scroll = 0;
+        let layout = state.panel_layout(160).unwrap();
+        let new_width = layout.text_width(DiffPanelFocus::New);
+
+        assert_eq!(
+            state.h_scroll_to_line_end(40, 160) as usize,
+            300 - new_width
+        );
+        // Only the short first line on screen: nothing overflows
+        assert_eq!(state.h_scroll_to_line_end(1, 160), 0);
+    }
+
+    #[test]
+    fn test_unified_view_searches_both_sides() {
+        let mut state = AppState::new(vec![make_file_diff("a.rs")], None);
+        state.settings.view_mode = state.settings.view_mode.toggle_unified();
+        assert_eq!(state.settings.view_mode, DiffViewMode::Unified);
+        assert_eq!(state.effective_fullscreen(), DiffFullscreen::NewOnly);
+        assert_eq!(state.search_fullscreen(), DiffFullscreen::None);
+        assert_eq!(
+            state.settings.view_mode.toggle_unified(),
+            DiffViewMode::SideBySide
+        );
+        assert!(state.row_layout(120).is_none(), "an added file has one column anyway");
+
+        let modified = FileDiff {
+            filename: "b.rs".to_string(),
+            old_content: "a\nb\nc\n".to_string(),
+            new_content: "a\nB\nc\nd\n".to_string(),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        state.reload(vec![modified], None);
+        let layout = state.row_layout(120).expect("unified rows");
+        let rows: Vec<usize> = state
+            .get_side_by_side()
+            .iter()
+            .map(|line| layout.rows(line))
+            .collect();
+        assert_eq!(rows, vec![1, 2, 1, 1], "a modified line shows both sides");
+    }
+
+    #[test]
+    fn test_unified_view_gutter_numbers_both_sides() {
+        let modified = FileDiff {
+            filename: "b.rs".to_string(),
+            old_content: "a\nb\nc\n".to_string(),
+            new_content: "a\nB\nc\nd\n".to_string(),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        let mut state = AppState::new(vec![modified], None);
+        state.settings.wrap = true;
+        state.settings.view_mode = DiffViewMode::ChangeBars;
+        assert_eq!(state.gutter_width(), GUTTER_WIDTH);
+        let single = state.row_layout(120).expect("wrapped rows").new_width;
+
+        state.settings.view_mode = DiffViewMode::Unified;
+        assert_eq!(state.gutter_width(), DUAL_GUTTER_WIDTH);
+        let layout = state.row_layout(120).expect("wrapped rows");
+        assert_eq!(
+            layout.new_width,
+            single - (DUAL_GUTTER_WIDTH - GUTTER_WIDTH) as usize,
+            "text wraps after both columns of line numbers"
+        );
+
+        // Clicks map to text after the focus bar and both line numbers
+        let panels = state.panel_layout(120).expect("panels");
+        assert_eq!(
+            panels.content_x_offset(DiffPanelFocus::New),
+            crate::command::diff::coordinates::FOCUS_INDICATOR_WIDTH + DUAL_GUTTER_WIDTH
+        );
+    }
+
+    #[test]
+    fn test_fold_all_directories() {
+        let diffs = vec![
+            make_file_diff("a/x.rs"),
+            make_file_diff("a/b/y.rs"),
+            make_file_diff("c/z.rs"),
+        ];
+        let mut state = AppState::new(diffs, Some("a/b/y.rs"));
+
+        state.collapse_all_dirs();
+        assert_eq!(state.collapsed_dirs.len(), 3);
+        // Only the top-level directories remain, with the selection on the one holding the file
+        assert_eq!(state.sidebar_visible_len(), 2);
+        assert_eq!(
+            state.sidebar_visible_index_for_dir("a"),
+            Some(state.sidebar_selected)
+        );
+
+        state.expand_all_dirs();
+        assert!(state.collapsed_dirs.is_empty());
+        assert_eq!(
+            state.sidebar_visible_index_for_dir("a"),
+            Some(state.sidebar_selected)
+        );
+
+        // `za` on a file folds the directory that holds it
+        let y = state.sidebar_visible_index_for_file(1).unwrap();
+        state.sidebar_selected = y;
+        state.toggle_fold_at_selection();
+        assert!(state.collapsed_dirs.contains("a/b"));
+        assert_eq!(
+            state.sidebar_visible_index_for_dir("a/b"),
+            Some(state.sidebar_selected)
+        );
+        state.toggle_fold_at_selection();
+        assert!(state.collapsed_dirs.is_empty());
+    }
+
+    #[test]
+    fn test_sidebar_filter_keeps_matching_files_and_their_dirs() {
+        let diffs = vec![
+            make_file_diff("docs/guide.md"),
+            make_file_diff("src/lib.rs"),
+            make_file_diff("src/util/io.rs"),
+        ];
+        let mut state = AppState::new(diffs, Some("docs/guide.md"));
+        let all = state.sidebar_visible.len();
+
+        state.set_sidebar_filter(Some("*.rs".to_string()));
+        assert_eq!(state.sidebar_filter_count(), 2);
+        assert_eq!(state.sidebar_visible_index_for_dir("docs"), None);
+        assert!(state.sidebar_visible_index_for_dir("src/util").is_some());
+        // The current file was filtered out, so the first match takes its place
+        assert_eq!(state.file_diffs[state.current_file].filename, "src/lib.rs");
+
+        state.set_sidebar_filter(Some("src/util/**".to_string()));
+        assert_eq!(state.sidebar_filter_count(), 1);
+
+        state.set_sidebar_filter(None);
+        assert_eq!(state.sidebar_visible.len(), all);
+    }
+
+    #[test]
+    fn test_wrap_is_remembered_per_file() {
+        let diffs = vec![make_file_diff("a.rs"), make_file_diff("b.rs")];
+        let mut state = AppState::new(diffs, Some("a.rs"));
+        state.toggle_wrap();
+        assert!(state.settings.wrap);
+
+        state.select_file(1);
+        assert!(!state.settings.wrap);
+        state.select_file(0);
+        assert!(state.settings.wrap);
+
+        // The setting follows the file, not its index
+        state.reload(vec![make_file_diff("b.rs"), make_file_diff("a.rs")], None);
+        assert_eq!(state.current_file, 1);
+        assert!(state.settings.wrap);
+        assert_eq!(state.wrapped_files, HashSet::from([1]));
+    }
+
+    #[test]
+    fn test_separate_panel_borders_take_a_column_from_the_new_panel() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+        state.settings.wrap = true;
+        let shared = state.row_layout(120).unwrap();
+
+        state.settings.panel_borders = PanelBorders::Separate;
+        let separate = state.row_layout(120).unwrap();
+        assert_eq!(separate.old_width, shared.old_width);
+        assert_eq!(separate.new_width, shared.new_width - 1);
+
+        state.settings.panel_borders = PanelBorders::None;
+        assert_eq!(state.row_layout(120).unwrap().new_width, shared.new_width);
+    }
+
+    #[test]
+    fn test_blame_margin_narrows_the_old_panel() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+        state.settings.wrap = true;
+        let plain = state.row_layout(160).unwrap();
+
+        state.show_blame = true;
+        assert!(state.needs_blame());
+        state.set_blame(0, Vec::new());
+        // A failed blame is cached but shows no margin
+        assert!(!state.needs_blame());
+        assert!(state.current_blame().is_none());
+
+        let line = BlameLine {
+            short_id: "1a2b3c4".to_string(),
+            author: "Ann".to_string(),
+            date: "2024-01-31".to_string(),
+        };
+        state.set_blame(0, vec![line; 3]);
+        let blamed = state.row_layout(160).unwrap();
+        assert_eq!(blamed.old_width, plain.old_width - BLAME_WIDTH as usize);
+        assert_eq!(blamed.new_width, plain.new_width);
+
+        state.settings.view_mode = DiffViewMode::Unified;
+        assert!(state.current_blame().is_none());
+        state.settings.view_mode = DiffViewMode::default();
+        state.reload(vec![make_two_hunk_diff("a.rs")], None);
+        assert!(state.needs_blame());
+    }
+
+    #[test]
+    fn test_confirm_quit_needs_a_second_press() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+        assert!(state.quit_confirmed(false, PendingKey::None));
+
+        assert!(!state.quit_confirmed(true, PendingKey::None));
+        assert_eq!(state.status_message(), Some("press q again to quit"));
+        let asked = state.pending_key;
+        assert!(matches!(asked, PendingKey::Quit(_)));
+        assert!(state.quit_confirmed(true, asked));
+
+        // A press long after the hint went away asks again
+        if let Some(stale) = Instant::now().checked_sub(Duration::from_secs(60)) {
+            assert!(!state.quit_confirmed(true, PendingKey::Quit(stale)));
+        }
+    }
+
+    #[test]
+    fn test_origin_margin_narrows_the_new_panel() {
+        let mut state = AppState::new(vec![make_two_hunk_diff("a.rs")], None);
+        state.settings.wrap = true;
+        let plain = state.row_layout(160).unwrap();
+
+        state.show_origins = true;
+        assert!(state.needs_origins());
+        state.set_origins(0, Vec::new());
+        assert!(!state.needs_origins());
+        assert!(state.current_origins().is_none());
+
+        let origin = LineOrigin {
+            short_id: "1a2b3c4".to_string(),
+            summary: "Fix parsing".to_string(),
+        };
+        state.set_origins(0, vec![None, Some(origin)]);
+        let traced = state.row_layout(160).unwrap();
+        assert_eq!(traced.new_width, plain.new_width - ORIGIN_WIDTH as usize);
+        assert_eq!(traced.old_width, plain.old_width);
+
+        state.reload(vec![make_two_hunk_diff("a.rs")], None);
+        assert!(state.needs_origins());
+    }
+
+    #[test]
+    fn test_select_file_restores_position_until_reload() {
+        let mut state = AppState::new(
+            vec![make_two_hunk_diff("a.rs"), make_two_hunk_diff("b.rs")],
+            None,
+        );
+        state.select_file(0);
+        let first_scroll = state.scroll;
+        state.scroll = 6;
+        state.h_scroll = 3;
+        state.focused_hunk = Some(1);
+
+        // A file not viewed before starts at its first hunk
+        state.select_file(1);
+        assert_eq!((state.scroll, state.h_scroll), (first_scroll, 0));
+        assert_eq!(state.focused_hunk, Some(0));
+
+        state.scroll = 6;
+        state.select_file(0);
+        assert_eq!((state.scroll, state.h_scroll), (6, 3));
+        assert_eq!(state.focused_hunk, Some(1));
+
+        // Reloading forgets where b.rs was left
+        state.reload(
+            vec![make_two_hunk_diff("a.rs"), make_two_hunk_diff("b.rs")],
+            None,
+        );
+        state.select_file(1);
+        assert_eq!((state.scroll, state.h_scroll), (first_scroll, 0));
+    }
+
+    #[test]
+    fn test_marks_survive_file_switches() {
+        let mut state = AppState::new(
+            vec![make_two_hunk_diff("a.rs"), make_two_hunk_diff("b.rs")],
+            None,
+        );
+        state.select_file(0);
+        let hunks = state.get_hunks().to_vec();
+        state.scroll = hunks[1] as u16;
+        state.set_mark('a');
+
+        state.select_file(1);
+        state.set_mark('b');
+        state.scroll = 0;
+
+        assert!(state.jump_to_mark('a'));
+        assert_eq!(state.current_file, 0);
+        assert_eq!(state.scroll, hunks[1] as u16);
+        assert_eq!(state.focused_hunk, Some(1));
+
+        assert!(state.jump_to_mark('b'));
+        assert_eq!(state.current_file, 1);
+        assert!(!state.jump_to_mark('c'));
+        assert_eq!(
+            state.sorted_marks(),
+            vec![('a', "a.rs", hunks[1] as u16), ('b', "b.rs", 0)]
+        );
+    }
+
+    #[test]
+    fn test_marks_follow_their_file_across_reload() {
+        let mut state = AppState::new(
+            vec![make_two_hunk_diff("a.rs"), make_two_hunk_diff("b.rs")],
+            None,
+        );
+        state.select_file(1);
+        state.set_mark('b');
+
+        state.reload(
+            vec![
+                make_file_diff("0.rs"),
+                make_two_hunk_diff("a.rs"),
+                make_two_hunk_diff("b.rs"),
+            ],
+            None,
+        );
+        state.select_file(0);
+        assert!(state.jump_to_mark('b'));
+        assert_eq!(state.file_diffs[state.current_file].filename, "b.rs");
+
+        state.reload(vec![make_two_hunk_diff("a.rs")], None);
+        assert!(!state.jump_to_mark('b'));
+    }
+
+    #[test]
+    fn test_compact_view_folds_and_unfolds_gaps() {
+        let middle = "same\n".repeat(20);
+        let diff = FileDiff {
+            filename: "a.rs".to_string(),
+            old_content: format!("a\n{}z\n", middle),
+            new_content: format!("A\n{}Z\n", middle),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        let mut state = AppState::new(vec![diff], None);
+        assert_eq!(state.get_side_by_side().len(), 22);
+
+        state.toggle_compact();
+        // Three lines of context either side of the fold
+        let rows = state.get_side_by_side();
+        assert_eq!(rows.len(), 9);
+        assert!(matches!(
+            rows[4].change_type,
+            ChangeType::Folded { count: 14, .. }
+        ));
+        assert_eq!(state.get_hunks().len(), 2);
+
+        assert!(state.expand_visible_gap(20));
+        assert_eq!(state.get_side_by_side().len(), 22);
+        assert!(!state.expand_visible_gap(20));
+    }
+
+    #[test]
+    fn test_search_unfolds_the_gap_holding_a_match() {
+        let middle = format!("{}needle\n{}", "same\n".repeat(10), "same\n".repeat(10));
+        let compact_state = || {
+            let diff = FileDiff {
+                filename: "a.rs".to_string(),
+                old_content: format!("a\n{}z\n", middle),
+                new_content: format!("A\n{}Z\n", middle),
+                status: FileStatus::Modified,
+                is_binary: false,
+                old_mode: None,
+                new_mode: None,
+            };
+            let mut state = AppState::new(vec![diff], None);
+            state.toggle_compact();
+            assert_eq!(state.get_side_by_side().len(), 9);
+            state
+        };
+        let row_text = |state: &mut AppState, row: usize| {
+            state.get_side_by_side()[row]
+                .new_line
+                .as_ref()
+                .map(|(_, text)| text.clone())
+        };
+
+        // Typing the query unfolds the gap and jumps to the match
+        let mut state = compact_state();
+        state.search_state.start_forward(0);
+        for c in "needle".chars() {
+            state.search_state.push_char(c);
+            state.update_incremental_search();
+        }
+        assert_eq!(state.get_side_by_side().len(), 23);
+        assert_eq!(state.search_state.match_count(), 2);
+        let row = state.search_state.matches[0].line_index;
+        assert_eq!(row_text(&mut state, row).as_deref(), Some("needle"));
+
+        // So do n and N once the query is set
+        for forward in [true, false] {
+            let mut state = compact_state();
+            state.search_state.query = "needle".to_string();
+            let lines = state.display_lines(0);
+            state
+                .search_state
+                .update_matches(&lines, DiffFullscreen::None);
+            assert_eq!(state.search_state.match_count(), 0);
+
+            let row = state.find_search_match(forward).unwrap();
+            assert_eq!(row_text(&mut state, row).as_deref(), Some("needle"));
+            assert_eq!(state.search_state.match_count(), 2);
+            assert!(state.search_state.current_match.is_some());
+        }
+    }
+
+    #[test]
+    fn test_full_context_overrides_compact_per_file() {
+        let middle = "same\n".repeat(20);
+        let diff = |filename: &str| FileDiff {
+            filename: filename.to_string(),
+            old_content: format!("a\n{}z\n", middle),
+            new_content: format!("A\n{}Z\n", middle),
+            status: FileStatus::Modified,
+            is_binary: false,
+            old_mode: None,
+            new_mode: None,
+        };
+        let mut state = AppState::new(vec![diff("a.rs"), diff("b.rs")], None);
+        state.toggle_compact();
+        state.focused_hunk = Some(1);
+
+        assert!(state.toggle_full_context());
+        assert_eq!(state.get_side_by_side().len(), 22);
+        assert_eq!(state.scroll, 16, "the focused hunk stays in view");
+        assert_eq!(state.display_lines(1).len(), 9, "other files stay folded");
+
+        state.reload(vec![diff("b.rs"), diff("a.rs")], None);
+        assert_eq!(state.display_lines(1).len(), 22, "kept by filename");
+
+        state.select_file(1);
+        assert!(!state.toggle_full_context());
+        assert_eq!(state.get_side_by_side().len(), 9);
+    }
+
     #[test]
     fn test_focus_empty_diffs() {
         let diffs = vec![];
@@ -1,8 +1,13 @@
-use once_cell::sync::OnceCell;
 use ratatui::prelude::Color;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 
-static THEME: OnceCell<Theme> = OnceCell::new();
+/// Active theme and the preset it came from. Behind a lock so it can be swapped at runtime.
+static THEME: RwLock<Option<(ThemePreset, Arc<Theme>)>> = RwLock::new(None);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ThemeMode {
@@ -49,6 +54,45 @@ impl FromStr for ThemePreset {
     }
 }
 
+impl ThemePreset {
+    /// All presets, in the order they are cycled through
+    pub const ALL: [ThemePreset; 11] = [
+        Self::DefaultDark,
+        Self::DefaultLight,
+        Self::CatppuccinMocha,
+        Self::CatppuccinLatte,
+        Self::Dracula,
+        Self::Nord,
+        Self::GruvboxDark,
+        Self::GruvboxLight,
+        Self::OneDark,
+        Self::SolarizedDark,
+        Self::SolarizedLight,
+    ];
+
+    /// Canonical name, as accepted by `--theme` and the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DefaultDark => "default-dark",
+            Self::DefaultLight => "default-light",
+            Self::CatppuccinMocha => "catppuccin-mocha",
+            Self::CatppuccinLatte => "catppuccin-latte",
+            Self::Dracula => "dracula",
+            Self::Nord => "nord",
+            Self::GruvboxDark => "gruvbox-dark",
+            Self::GruvboxLight => "gruvbox-light",
+            Self::OneDark => "one-dark",
+            Self::SolarizedDark => "solarized-dark",
+            Self::SolarizedLight => "solarized-light",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
 impl ThemeMode {
     pub fn detect() -> Self {
         match terminal_light::luma() {
@@ -56,9 +100,16 @@ impl ThemeMode {
             _ => ThemeMode::Dark,
         }
     }
+
+    pub fn preset(self) -> ThemePreset {
+        match self {
+            ThemeMode::Dark => ThemePreset::DefaultDark,
+            ThemeMode::Light => ThemePreset::DefaultLight,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyntaxColors {
     pub comment: Color,
     pub keyword: Color,
@@ -78,7 +129,7 @@ pub struct SyntaxColors {
     pub default_text: Color,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiffColors {
     pub added_bg: Color,
     pub added_gutter_bg: Color,
@@ -92,9 +143,13 @@ pub struct DiffColors {
     pub added_word_bg: Color,
     /// Word-level highlight for deleted text (darker red)
     pub deleted_word_bg: Color,
+    /// Background of a merge conflict's sections: ours, the merge base (diff3), theirs
+    pub conflict_ours_bg: Color,
+    pub conflict_base_bg: Color,
+    pub conflict_theirs_bg: Color,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiColors {
     pub border_focused: Color,
     pub border_unfocused: Color,
@@ -123,7 +178,6 @@ pub struct UiColors {
 
 #[derive(Debug, Clone)]
 pub struct Theme {
-    #[allow(dead_code)]
     pub mode: ThemeMode,
     pub syntax: SyntaxColors,
     pub diff: DiffColors,
@@ -163,6 +217,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(55, 60, 70),
                 added_word_bg: Color::Rgb(40, 85, 55),
                 deleted_word_bg: Color::Rgb(100, 50, 50),
+                conflict_ours_bg: Color::Rgb(30, 45, 70),
+                conflict_base_bg: Color::Rgb(50, 50, 55),
+                conflict_theirs_bg: Color::Rgb(55, 38, 70),
             },
             ui: UiColors {
                 border_focused: Color::Cyan,
@@ -224,6 +281,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(200, 205, 212),
                 added_word_bg: Color::Rgb(171, 242, 188),
                 deleted_word_bg: Color::Rgb(255, 184, 174),
+                conflict_ours_bg: Color::Rgb(218, 232, 252),
+                conflict_base_bg: Color::Rgb(236, 236, 236),
+                conflict_theirs_bg: Color::Rgb(238, 226, 250),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(9, 105, 218),
@@ -253,13 +313,6 @@ impl Theme {
         }
     }
 
-    pub fn from_mode(mode: ThemeMode) -> Self {
-        match mode {
-            ThemeMode::Dark => Self::dark(),
-            ThemeMode::Light => Self::light(),
-        }
-    }
-
     pub fn from_preset(preset: ThemePreset) -> Self {
         match preset {
             ThemePreset::DefaultDark => Self::dark(),
@@ -308,6 +361,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(69, 71, 90), // surface1
                 added_word_bg: Color::Rgb(50, 90, 60),
                 deleted_word_bg: Color::Rgb(100, 50, 60),
+                conflict_ours_bg: Color::Rgb(30, 45, 70),
+                conflict_base_bg: Color::Rgb(50, 50, 55),
+                conflict_theirs_bg: Color::Rgb(55, 38, 70),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(137, 180, 250),   // blue
@@ -369,6 +425,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(188, 192, 204), // surface1
                 added_word_bg: Color::Rgb(160, 230, 180),
                 deleted_word_bg: Color::Rgb(255, 180, 180),
+                conflict_ours_bg: Color::Rgb(218, 232, 252),
+                conflict_base_bg: Color::Rgb(236, 236, 236),
+                conflict_theirs_bg: Color::Rgb(238, 226, 250),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(30, 102, 245),
@@ -430,6 +489,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(68, 71, 90), // current line
                 added_word_bg: Color::Rgb(50, 100, 60),
                 deleted_word_bg: Color::Rgb(100, 50, 60),
+                conflict_ours_bg: Color::Rgb(30, 45, 70),
+                conflict_base_bg: Color::Rgb(50, 50, 55),
+                conflict_theirs_bg: Color::Rgb(55, 38, 70),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(189, 147, 249), // purple
@@ -491,6 +553,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(59, 66, 82), // nord1
                 added_word_bg: Color::Rgb(60, 100, 75),
                 deleted_word_bg: Color::Rgb(110, 65, 70),
+                conflict_ours_bg: Color::Rgb(30, 45, 70),
+                conflict_base_bg: Color::Rgb(50, 50, 55),
+                conflict_theirs_bg: Color::Rgb(55, 38, 70),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(136, 192, 208), // nord8
@@ -552,6 +617,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(60, 56, 54), // bg1
                 added_word_bg: Color::Rgb(70, 100, 55),
                 deleted_word_bg: Color::Rgb(115, 55, 50),
+                conflict_ours_bg: Color::Rgb(30, 45, 70),
+                conflict_base_bg: Color::Rgb(50, 50, 55),
+                conflict_theirs_bg: Color::Rgb(55, 38, 70),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(250, 189, 47), // yellow
@@ -613,6 +681,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(213, 196, 161), // bg2
                 added_word_bg: Color::Rgb(180, 235, 165),
                 deleted_word_bg: Color::Rgb(255, 195, 180),
+                conflict_ours_bg: Color::Rgb(218, 232, 252),
+                conflict_base_bg: Color::Rgb(236, 236, 236),
+                conflict_theirs_bg: Color::Rgb(238, 226, 250),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(69, 133, 136), // blue
@@ -674,6 +745,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(62, 68, 81), // gutter
                 added_word_bg: Color::Rgb(55, 100, 65),
                 deleted_word_bg: Color::Rgb(110, 55, 60),
+                conflict_ours_bg: Color::Rgb(30, 45, 70),
+                conflict_base_bg: Color::Rgb(50, 50, 55),
+                conflict_theirs_bg: Color::Rgb(55, 38, 70),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(97, 175, 239), // blue
@@ -735,6 +809,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(7, 54, 66), // base02
                 added_word_bg: Color::Rgb(20, 85, 75),
                 deleted_word_bg: Color::Rgb(100, 50, 45),
+                conflict_ours_bg: Color::Rgb(30, 45, 70),
+                conflict_base_bg: Color::Rgb(50, 50, 55),
+                conflict_theirs_bg: Color::Rgb(55, 38, 70),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(38, 139, 210), // blue
@@ -796,6 +873,9 @@ impl Theme {
                 empty_placeholder_fg: Color::Rgb(238, 232, 213), // base2
                 added_word_bg: Color::Rgb(175, 235, 190),
                 deleted_word_bg: Color::Rgb(255, 190, 185),
+                conflict_ours_bg: Color::Rgb(218, 232, 252),
+                conflict_base_bg: Color::Rgb(236, 236, 236),
+                conflict_theirs_bg: Color::Rgb(238, 226, 250),
             },
             ui: UiColors {
                 border_focused: Color::Rgb(38, 139, 210),
@@ -826,26 +906,170 @@ impl Theme {
     }
 }
 
+/// A user theme file: colors by section and field name, e.g. `[diff] added_bg = "#233228"`.
+/// Anything left out comes from `base`, or the detected default when that is unset.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    syntax: BTreeMap<String, String>,
+    diff: BTreeMap<String, String>,
+    ui: BTreeMap<String, String>,
+}
+
+impl Theme {
+    /// Parse a TOML theme over its base theme. Unknown fields and colors that don't
+    /// parse are skipped and reported in the returned warnings.
+    pub fn from_toml(text: &str, detected: ThemeMode) -> Result<(Self, Vec<String>), String> {
+        let file: ThemeFile = toml_edit::de::from_str(text).map_err(|e| e.to_string())?;
+        let base = match file.base.as_deref() {
+            Some(name) => name.parse::<ThemePreset>()?,
+            None => detected.preset(),
+        };
+        let mut theme = Theme::from_preset(base);
+        let mut warnings = Vec::new();
+        overlay_colors(&mut theme.syntax, "syntax", &file.syntax, &mut warnings);
+        overlay_colors(&mut theme.diff, "diff", &file.diff, &mut warnings);
+        overlay_colors(&mut theme.ui, "ui", &file.ui, &mut warnings);
+        Ok((theme, warnings))
+    }
+}
+
+/// Lay a theme file section over `colors`, matching keys to the serde field names.
+/// Unknown keys and values that don't parse leave the base color in place.
+fn overlay_colors<T: Serialize + DeserializeOwned>(
+    colors: &mut T,
+    section: &str,
+    values: &BTreeMap<String, String>,
+    warnings: &mut Vec<String>,
+) {
+    let Ok(serde_json::Value::Object(mut fields)) = serde_json::to_value(&*colors) else {
+        return;
+    };
+    for (key, value) in values {
+        let Some(slot) = fields.get_mut(key) else {
+            warnings.push(format!("unknown theme color {}.{}", section, key));
+            continue;
+        };
+        match Color::from_str(value) {
+            Ok(color) => *slot = serde_json::json!(color),
+            Err(_) => warnings.push(format!(
+                "invalid color for {}.{}: {:?} (expected hex like \"#a6e3a1\" or a name like \"green\")",
+                section, key, value
+            )),
+        }
+    }
+    if let Ok(merged) = serde_json::from_value(serde_json::Value::Object(fields)) {
+        *colors = merged;
+    }
+}
+
+/// Where a theme that isn't a preset lives: the path itself for `*.toml`, otherwise
+/// `~/.config/lumen/themes/<name>.toml` when that file exists.
+fn custom_theme_path(name: &str) -> Option<PathBuf> {
+    if name.ends_with(".toml") {
+        return Some(PathBuf::from(name));
+    }
+    let mut path = dirs::home_dir()?;
+    path.push(".config/lumen/themes");
+    path.push(format!("{}.toml", name));
+    path.exists().then_some(path)
+}
+
+fn load_theme_file(path: &Path) -> Result<Theme, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (theme, warnings) = Theme::from_toml(&text, ThemeMode::detect())
+        .map_err(|e| format!("{}: {}", path.display(), e.trim_end()))?;
+    for warning in warnings {
+        eprintln!("\x1b[33mwarning:\x1b[0m {}: {}", path.display(), warning);
+    }
+    Ok(theme)
+}
+
 pub fn init(config_theme: Option<&str>) {
     // Priority: config file > env var > OS auto-detect
     let theme_name = config_theme
         .map(|s| s.to_string())
         .or_else(|| std::env::var("LUMEN_THEME").ok());
 
-    let theme = if let Some(name) = theme_name {
-        match name.parse::<ThemePreset>() {
-            Ok(preset) => Theme::from_preset(preset),
-            Err(err) => {
-                eprintln!("\x1b[33mwarning:\x1b[0m {}", err);
-                Theme::from_mode(ThemeMode::detect())
-            }
-        }
-    } else {
-        Theme::from_mode(ThemeMode::detect())
+    let Some(name) = theme_name else {
+        set(ThemeMode::detect().preset());
+        return;
+    };
+    let err = match name.parse::<ThemePreset>() {
+        Ok(preset) => return set(preset),
+        Err(err) => err,
     };
-    let _ = THEME.set(theme);
+    match custom_theme_path(&name).map(|path| load_theme_file(&path)) {
+        Some(Ok(theme)) => return set_custom(theme),
+        Some(Err(file_err)) => eprintln!("\x1b[33mwarning:\x1b[0m {}", file_err),
+        None => eprintln!(
+            "\x1b[33mwarning:\x1b[0m {}, or a theme file in ~/.config/lumen/themes/",
+            err
+        ),
+    }
+    set(ThemeMode::detect().preset());
+}
+
+/// Use a theme loaded from a file; cycling with `T` continues from its base mode's preset.
+fn set_custom(theme: Theme) {
+    let mut active = THEME.write().unwrap_or_else(|e| e.into_inner());
+    *active = Some((theme.mode.preset(), Arc::new(theme)));
+}
+
+/// Replace the active theme; subsequent `get()` calls return the new colors.
+pub fn set(preset: ThemePreset) {
+    let mut active = THEME.write().unwrap_or_else(|e| e.into_inner());
+    *active = Some((preset, Arc::new(Theme::from_preset(preset))));
 }
 
-pub fn get() -> &'static Theme {
-    THEME.get_or_init(|| Theme::from_mode(ThemeMode::detect()))
+/// Switch to the next preset and return it.
+pub fn cycle() -> ThemePreset {
+    let next = current_preset().next();
+    set(next);
+    next
+}
+
+pub fn current_preset() -> ThemePreset {
+    active().0
+}
+
+pub fn get() -> Arc<Theme> {
+    active().1
+}
+
+fn active() -> (ThemePreset, Arc<Theme>) {
+    if let Some((preset, theme)) = THEME.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return (*preset, Arc::clone(theme));
+    }
+    set(ThemeMode::detect().preset());
+    active()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_theme_overrides_base() {
+        let text = "base = \"nord\"\n[syntax]\ntype = \"#010203\"\n[diff]\nadded_bg = \"#102030\"\n[ui]\nhighlight = \"yellow\"\n";
+        let (theme, warnings) = Theme::from_toml(text, ThemeMode::Dark).unwrap();
+        let nord = Theme::from_preset(ThemePreset::Nord);
+        assert!(warnings.is_empty());
+        assert_eq!(theme.diff.added_bg, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(theme.ui.highlight, Color::Yellow);
+        assert_eq!(theme.syntax.r#type, Color::Rgb(1, 2, 3));
+        assert_eq!(theme.diff.deleted_bg, nord.diff.deleted_bg);
+        assert_eq!(theme.syntax.keyword, nord.syntax.keyword);
+    }
+
+    #[test]
+    fn test_toml_theme_warns_on_bad_colors() {
+        let text = "[syntax]\nkeyword = \"#zzzzzz\"\nsparkle = \"#ffffff\"\n";
+        let (theme, warnings) = Theme::from_toml(text, ThemeMode::Light).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(theme.mode, ThemeMode::Light);
+        assert_eq!(theme.syntax.keyword, Theme::light().syntax.keyword);
+        assert!(Theme::from_toml("base = \"neon\"", ThemeMode::Dark).is_err());
+    }
 }
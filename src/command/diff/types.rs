@@ -1,4 +1,9 @@
+use std::collections::HashMap;
+use std::path::Path;
+
 use super::context::ContextConfig;
+use super::indicators::StatusIndicators;
+use super::search::GrepFilter;
 
 pub fn expand_tabs(s: &str, tab_width: usize) -> String {
     if tab_width == 0 {
@@ -44,6 +49,9 @@ pub struct FileDiff {
     pub new_content: String,
     pub status: FileStatus,
     pub is_binary: bool,
+    /// Git file modes of each side (e.g. `0o100755`), when the source records them
+    pub old_mode: Option<u32>,
+    pub new_mode: Option<u32>,
 }
 
 /// Detect if content is binary by checking for null bytes in the first 8KB
@@ -51,12 +59,138 @@ pub fn is_binary_content(content: &str) -> bool {
     content.bytes().take(8192).any(|b| b == 0)
 }
 
+/// Count unresolved merge conflicts, i.e. complete `<<<<<<<` / `=======` / `>>>>>>>` blocks.
+/// Stray markers (e.g. a `=======` heading underline) are not counted on their own.
+pub fn count_conflict_markers(content: &str) -> usize {
+    let mut count = 0;
+    let mut in_ours = false;
+    let mut in_theirs = false;
+    for line in content.lines() {
+        if line.starts_with("<<<<<<<") {
+            in_ours = true;
+            in_theirs = false;
+        } else if in_ours && line.starts_with("=======") {
+            in_ours = false;
+            in_theirs = true;
+        } else if in_theirs && line.starts_with(">>>>>>>") {
+            in_theirs = false;
+            count += 1;
+        }
+    }
+    count
+}
+
+/// A line git writes around conflicting hunks: `<<<<<<< ours`, `||||||| base`,
+/// `=======` or `>>>>>>> theirs`. Longer runs, such as heading underlines, are not.
+pub fn is_conflict_marker(line: &str) -> bool {
+    ["<<<<<<<", "|||||||", "=======", ">>>>>>>"]
+        .iter()
+        .any(|marker| {
+            line.strip_prefix(marker)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+}
+
+/// The part of a merge conflict a line sits in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictSection {
+    Ours,
+    /// The merge base, shown between `|||||||` and `=======` in diff3 style
+    Base,
+    Theirs,
+}
+
+/// The conflict section of each line, markers included. `None` entries (lines missing
+/// from this side) keep their slot but never open or close a section.
+pub fn conflict_sections<'a>(
+    lines: impl IntoIterator<Item = Option<&'a str>>,
+) -> Vec<Option<ConflictSection>> {
+    let mut section = None;
+    lines
+        .into_iter()
+        .map(|line| {
+            let line = line?;
+            if !is_conflict_marker(line) {
+                return section;
+            }
+            match (&line[..1], section) {
+                ("<", _) => {
+                    section = Some(ConflictSection::Ours);
+                    section
+                }
+                ("|", Some(ConflictSection::Ours)) => {
+                    section = Some(ConflictSection::Base);
+                    section
+                }
+                ("=", Some(ConflictSection::Ours | ConflictSection::Base)) => {
+                    section = Some(ConflictSection::Theirs);
+                    section
+                }
+                (">", Some(ConflictSection::Theirs)) => section.take(),
+                _ => section,
+            }
+        })
+        .collect()
+}
+
 /// Settings for the diff view UI. Designed to be easily extended
 /// with additional configuration options in the future.
 #[derive(Clone)]
 pub struct DiffViewSettings {
     pub context: ContextConfig,
+    /// Tab width for files whose extension has none in `tab_widths`
     pub tab_width: usize,
+    /// Tab width by file extension (without the dot), e.g. `go` → 8
+    pub tab_widths: HashMap<String, usize>,
+    /// Whether deleted files are listed in the sidebar and file navigation
+    pub show_deleted: bool,
+    /// Status symbols, colors and icons for the sidebar and file picker
+    pub indicators: StatusIndicators,
+    /// Skip whitespace-only segments when emphasizing changed words
+    pub hide_whitespace_emphasis: bool,
+    /// Match lines ignoring indentation and runs of inner whitespace
+    pub ignore_whitespace: bool,
+    /// Drawn in the first column of each tab instead of a space
+    pub tab_glyph: Option<char>,
+    pub view_mode: DiffViewMode,
+    /// Characters of an annotation shown inline before it is cut off
+    pub annotation_limit: usize,
+    /// Soft-wrap long lines onto continuation rows instead of scrolling sideways
+    pub wrap: bool,
+    /// Fold long unchanged runs into one separator row
+    pub compact: bool,
+    /// Unchanged runs longer than this are folded in compact mode
+    pub fold_threshold: usize,
+    /// How the border between the side-by-side panels is drawn
+    pub panel_borders: PanelBorders,
+    /// `--grep`: hunks with no matching added or changed line are hidden
+    pub grep: Option<GrepFilter>,
+}
+
+/// How tabs are drawn: expanded to `width` columns, optionally marked with a glyph.
+#[derive(Clone, Copy, Debug)]
+pub struct TabStyle {
+    pub width: usize,
+    pub glyph: Option<char>,
+}
+
+impl DiffViewSettings {
+    /// Tab width for `filename`, from its extension when `tab_widths` has one.
+    pub fn tab_width_for(&self, filename: &str) -> usize {
+        Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.tab_widths.get(ext))
+            .copied()
+            .unwrap_or(self.tab_width)
+    }
+
+    pub fn tab_style(&self, filename: &str) -> TabStyle {
+        TabStyle {
+            width: self.tab_width_for(filename),
+            glyph: self.tab_glyph,
+        }
+    }
 }
 
 impl Default for DiffViewSettings {
@@ -64,6 +198,35 @@ impl Default for DiffViewSettings {
         Self {
             context: ContextConfig::default(),
             tab_width: 4,
+            tab_widths: HashMap::new(),
+            show_deleted: true,
+            indicators: StatusIndicators::default(),
+            hide_whitespace_emphasis: false,
+            ignore_whitespace: false,
+            tab_glyph: None,
+            view_mode: DiffViewMode::default(),
+            annotation_limit: 500,
+            wrap: false,
+            compact: false,
+            fold_threshold: 10,
+            panel_borders: PanelBorders::default(),
+            grep: None,
+        }
+    }
+}
+
+/// Labels for the old and new diff panels, naming what each side shows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PanelTitles {
+    pub old: String,
+    pub new: String,
+}
+
+impl Default for PanelTitles {
+    fn default() -> Self {
+        Self {
+            old: "Old".to_string(),
+            new: "New".to_string(),
         }
     }
 }
@@ -93,6 +256,26 @@ pub enum ChangeType {
     Insert,
     /// A paired delete+insert, shown on the same row (GitHub-style)
     Modified,
+    /// `count` unchanged lines, starting at `from` in the full diff, folded into
+    /// one separator row by the compact view
+    Folded {
+        from: usize,
+        count: usize,
+    },
+    /// A hunk of `count` rows, starting at `from` in the full diff, hidden by
+    /// `--grep` because none of its added or changed lines match
+    Hidden {
+        from: usize,
+        count: usize,
+    },
+}
+
+impl ChangeType {
+    /// Whether the row is part of a hunk (neither unchanged nor a folded gap). A
+    /// hidden hunk's row is, so hunk indices stay the same with `--grep`.
+    pub fn is_change(self) -> bool {
+        !matches!(self, ChangeType::Equal | ChangeType::Folded { .. })
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Default)]
@@ -215,6 +398,95 @@ impl Selection {
     }
 }
 
+/// Layout of the diff view for files that exist on both sides.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum DiffViewMode {
+    /// Old and new panels next to each other
+    #[default]
+    SideBySide,
+    /// The new file as-is, with colored change bars in the gutter and removed
+    /// lines shown as dimmed markers (GitLab's single-column layout)
+    ChangeBars,
+    /// One column with removed lines (`-`) stacked above added ones (`+`), like `git diff`
+    Unified,
+}
+
+impl DiffViewMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "side-by-side" => Ok(Self::SideBySide),
+            "change-bars" => Ok(Self::ChangeBars),
+            "unified" => Ok(Self::Unified),
+            _ => Err(format!(
+                "invalid view_mode: {} (expected side-by-side, change-bars or unified)",
+                value
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SideBySide => "side-by-side",
+            Self::ChangeBars => "change-bars",
+            Self::Unified => "unified",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::SideBySide => Self::ChangeBars,
+            Self::ChangeBars | Self::Unified => Self::SideBySide,
+        }
+    }
+
+    /// The `u` toggle: unified, or back to side by side from it.
+    pub fn toggle_unified(self) -> Self {
+        match self {
+            Self::Unified => Self::SideBySide,
+            _ => Self::Unified,
+        }
+    }
+}
+
+/// The border between the old and new panels when both are shown.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum PanelBorders {
+    /// One line, the old panel's right border, which the new panel shares
+    #[default]
+    Shared,
+    /// Each panel draws all four sides, so two lines meet in the middle
+    Separate,
+    /// No line between the panels, only a blank column
+    None,
+}
+
+impl PanelBorders {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "shared" => Ok(Self::Shared),
+            "separate" => Ok(Self::Separate),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "invalid panel_borders: {} (expected shared, separate or none)",
+                value
+            )),
+        }
+    }
+}
+
+/// A user-defined annotation export format. `item` is written once per annotation
+/// with `{file}`, `{line_start}`, `{line_end}`, `{content}` and `{time}` filled in;
+/// `{reference}`, the diff being reviewed, works in all three parts.
+#[derive(Clone, Debug, Default)]
+pub struct ExportTemplate {
+    pub header: String,
+    pub item: String,
+    pub footer: String,
+    /// Values are written as CSV fields: quoted when they hold a comma, quote or
+    /// line break, with quotes doubled
+    pub csv: bool,
+}
+
 #[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub enum DiffFullscreen {
     #[default]
@@ -425,3 +697,61 @@ pub fn build_file_tree(file_diffs: &[FileDiff]) -> Vec<SidebarItem> {
 
     items
 }
+
+/// Flat sidebar: one entry per file labelled by basename, sorted by label.
+/// Colliding basenames get as many parent directories as it takes to tell them apart.
+pub fn build_flat_file_list(file_diffs: &[FileDiff]) -> Vec<SidebarItem> {
+    use std::collections::HashMap;
+
+    // Number of trailing path components needed for each file's label
+    let mut suffix_len = vec![1usize; file_diffs.len()];
+    loop {
+        let label = |idx: usize| path_suffix(&file_diffs[idx].filename, suffix_len[idx]);
+        let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+        for idx in 0..file_diffs.len() {
+            groups.entry(label(idx)).or_default().push(idx);
+        }
+
+        let mut grew = false;
+        for group in groups.values().filter(|group| group.len() > 1) {
+            for &idx in group {
+                let components = file_diffs[idx].filename.split('/').count();
+                if suffix_len[idx] < components {
+                    suffix_len[idx] += 1;
+                    grew = true;
+                }
+            }
+        }
+        if !grew {
+            break;
+        }
+    }
+
+    let mut items: Vec<SidebarItem> = file_diffs
+        .iter()
+        .enumerate()
+        .map(|(idx, diff)| SidebarItem::File {
+            name: path_suffix(&diff.filename, suffix_len[idx]).to_string(),
+            path: diff.filename.clone(),
+            file_index: idx,
+            depth: 0,
+            status: diff.status,
+        })
+        .collect();
+    items.sort_by(|a, b| match (a, b) {
+        (
+            SidebarItem::File { name: a_name, path: a_path, .. },
+            SidebarItem::File { name: b_name, path: b_path, .. },
+        ) => a_name.cmp(b_name).then_with(|| a_path.cmp(b_path)),
+        _ => std::cmp::Ordering::Equal,
+    });
+    items
+}
+
+/// The last `components` segments of a `/`-separated path.
+fn path_suffix(path: &str, components: usize) -> &str {
+    match path.rmatch_indices('/').nth(components.saturating_sub(1)) {
+        Some((pos, _)) => &path[pos + 1..],
+        None => path,
+    }
+}
@@ -5,11 +5,28 @@ use std::time::Duration;
 
 use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
 
+use super::types::FileDiff;
+
 /// Changed files from file system watcher
 pub struct WatchEvent {
     pub changed_files: HashSet<String>,
 }
 
+/// Files whose content differs between two loads, for reloads that didn't
+/// come from a file-system event (e.g. the `--watch-interval` timer).
+pub fn changed_files(old: &[FileDiff], new: &[FileDiff]) -> HashSet<String> {
+    new.iter()
+        .filter(|diff| {
+            old.iter()
+                .find(|o| o.filename == diff.filename)
+                .is_none_or(|o| {
+                    o.new_content != diff.new_content || o.old_content != diff.old_content
+                })
+        })
+        .map(|diff| diff.filename.clone())
+        .collect()
+}
+
 pub fn setup_watcher() -> Option<Receiver<WatchEvent>> {
     let (tx, rx) = mpsc::channel();
 
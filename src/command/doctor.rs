@@ -0,0 +1,200 @@
+use super::diff::child_command;
+use super::providers::{KeyStatus, ProvidersCommand};
+use crate::config::{LumenConfig, ProviderInfo};
+use crate::error::LumenError;
+
+#[derive(Debug, PartialEq)]
+enum CheckStatus {
+    Pass,
+    /// Missing, but only an optional feature depends on it
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// First line of a command's stdout, or `None` when it is not installed or fails.
+fn tool_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = child_command(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// Checks that a tool is on PATH; `needed_for` names the features that use it.
+fn tool_check(name: &'static str, args: &[&str], needed_for: &str) -> Check {
+    match tool_output(name, args) {
+        Some(version) => Check::new(name, CheckStatus::Pass, version),
+        None => Check::new(
+            name,
+            CheckStatus::Warn,
+            format!("not found on PATH (needed for {})", needed_for),
+        ),
+    }
+}
+
+/// Checks that a forge CLI is logged in; skipped when the CLI itself is missing.
+fn auth_check(name: &'static str, program: &str) -> Option<Check> {
+    tool_output(program, &["--version"])?;
+    let logged_in = child_command(program)
+        .args(["auth", "status"])
+        .output()
+        .is_ok_and(|o| o.status.success());
+    Some(if logged_in {
+        Check::new(name, CheckStatus::Pass, "authenticated")
+    } else {
+        Check::new(
+            name,
+            CheckStatus::Warn,
+            format!("not logged in (run `{} auth login`)", program),
+        )
+    })
+}
+
+fn truecolor_check(colorterm: Option<&str>) -> Check {
+    match colorterm {
+        Some(value) if value == "truecolor" || value == "24bit" => Check::new(
+            "truecolor",
+            CheckStatus::Pass,
+            format!("COLORTERM={}", value),
+        ),
+        _ => Check::new(
+            "truecolor",
+            CheckStatus::Warn,
+            "COLORTERM is not truecolor/24bit; themes may render with approximate colors",
+        ),
+    }
+}
+
+/// Command to check the environment for everything lumen's features rely on.
+pub struct DoctorCommand;
+
+impl DoctorCommand {
+    /// Prints a pass/warn/fail checklist. `repository` is the detected backend name,
+    /// or the reason no repository was found.
+    pub async fn execute(
+        config: &LumenConfig,
+        repository: Result<&str, String>,
+    ) -> Result<(), LumenError> {
+        let mut checks = vec![match repository {
+            Ok(backend) => Check::new(
+                "repository",
+                CheckStatus::Pass,
+                format!("using the {} backend", backend),
+            ),
+            Err(e) => Check::new("repository", CheckStatus::Fail, e),
+        }];
+
+        checks.push(tool_check(
+            "git",
+            &["--version"],
+            "git hooks and workflows; lumen reads repositories itself",
+        ));
+        let jj_needed_for = if cfg!(feature = "jj") {
+            "jj repositories"
+        } else {
+            "jj repositories, which this build was compiled without"
+        };
+        checks.push(tool_check("jj", &["--version"], jj_needed_for));
+        checks.push(tool_check("hg", &["--version"], "Mercurial repositories"));
+        checks.push(tool_check("gh", &["--version"], "`lumen diff --pr`"));
+        checks.extend(auth_check("gh auth", "gh"));
+        checks.push(tool_check("glab", &["--version"], "`lumen diff --mr`"));
+        checks.extend(auth_check("glab auth", "glab"));
+        checks.push(tool_check("fzf", &["--version"], "`lumen explain --list`"));
+        checks.push(truecolor_check(std::env::var("COLORTERM").ok().as_deref()));
+        checks.push(Self::provider_check(config).await);
+
+        let name_width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        println!();
+        for check in &checks {
+            let mark = match check.status {
+                CheckStatus::Pass => "\x1b[32m✓\x1b[0m",
+                CheckStatus::Warn => "\x1b[93m!\x1b[0m",
+                CheckStatus::Fail => "\x1b[91m✗\x1b[0m",
+            };
+            println!("  {} {:name_width$}  {}", mark, check.name, check.detail);
+        }
+
+        let failures = checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Fail)
+            .count();
+        if failures == 0 {
+            println!("\n  \x1b[2mNo problems found.\x1b[0m\n");
+        } else {
+            println!(
+                "\n  \x1b[2m{} problem{} found.\x1b[0m\n",
+                failures,
+                if failures == 1 { "" } else { "s" }
+            );
+        }
+        Ok(())
+    }
+
+    /// Sends a tiny request to the active provider to confirm its key and model.
+    async fn provider_check(config: &LumenConfig) -> Check {
+        let info = ProviderInfo::for_provider(config.provider);
+        let key_status = KeyStatus::resolve(info, config.api_key.as_deref());
+        if let KeyStatus::Missing(_) = key_status {
+            return Check::new(
+                "provider",
+                CheckStatus::Fail,
+                format!("{}: API key {}", info.display_name, key_status.describe()),
+            );
+        }
+
+        let model = config
+            .model
+            .clone()
+            .unwrap_or_else(|| info.default_model.to_string());
+        match ProvidersCommand::ping(info, key_status.key(), &model).await {
+            Ok(()) => Check::new(
+                "provider",
+                CheckStatus::Pass,
+                format!("{} ({}) responded", info.display_name, model),
+            ),
+            Err(e) => Check::new(
+                "provider",
+                CheckStatus::Fail,
+                format!("{} ({}): {}", info.display_name, model, e),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truecolor_check() {
+        assert_eq!(truecolor_check(Some("truecolor")).status, CheckStatus::Pass);
+        assert_eq!(truecolor_check(Some("24bit")).status, CheckStatus::Pass);
+        assert_eq!(truecolor_check(None).status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn test_missing_tool_is_a_warning() {
+        let check = tool_check("lumen-no-such-tool", &["--version"], "nothing");
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.contains("not found"));
+        assert!(auth_check("auth", "lumen-no-such-tool").is_none());
+    }
+}
@@ -1,18 +1,21 @@
-use std::io::{IsTerminal, Write};
+use std::io::{self, IsTerminal, Write};
 
 use crate::{
-    config::configuration::DraftConfig, error::LumenError, git_entity::GitEntity,
-    provider::LumenProvider,
+    command::diff::child_command, config::configuration::DraftConfig, error::LumenError,
+    git_entity::GitEntity, provider::AiProvider,
 };
 
 pub struct DraftCommand {
     pub git_entity: GitEntity,
     pub context: Option<String>,
     pub draft_config: DraftConfig,
+    pub ignore_whitespace: bool,
+    /// The message replaces HEAD's, after asking, via `git commit --amend`
+    pub amend: bool,
 }
 
 impl DraftCommand {
-    pub async fn execute(&self, provider: &LumenProvider) -> Result<(), LumenError> {
+    pub async fn execute(&self, provider: &dyn AiProvider) -> Result<(), LumenError> {
         let result = provider.draft(self).await?;
 
         // Only add newline when outputting to terminal, not when piped (e.g., `lumen draft | pbcopy`)
@@ -22,6 +25,35 @@ impl DraftCommand {
             print!("{result}");
         }
         std::io::stdout().flush()?;
+
+        // Piped output is for another program; only offer the amend interactively
+        if self.amend && io::stdin().is_terminal() && io::stdout().is_terminal() {
+            amend_with_message(result.trim())?;
+        }
         Ok(())
     }
 }
+
+/// Ask before running `git commit --amend -m <message>`, then run it with git's own output.
+fn amend_with_message(message: &str) -> Result<(), LumenError> {
+    print!("\nAmend HEAD with this message? [y/N] ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if !input.trim().eq_ignore_ascii_case("y") {
+        println!("Amend canceled.");
+        return Ok(());
+    }
+
+    let status = child_command("git")
+        .args(["commit", "--amend", "-m", message])
+        .status()?;
+    if !status.success() {
+        return Err(LumenError::CommandError(format!(
+            "git commit --amend failed with exit code: {:?}",
+            status.code()
+        )));
+    }
+    Ok(())
+}
@@ -1,16 +1,60 @@
+use std::io::{self, IsTerminal, Write};
+
+use serde::Serialize;
 use spinoff::{spinners, Color, Spinner};
 
-use crate::{error::LumenError, git_entity::GitEntity, provider::LumenProvider};
+use crate::{
+    config::cli::OutputFormat,
+    error::LumenError,
+    git_entity::{diff::split_by_file, GitEntity},
+    provider::AiProvider,
+};
 
 use super::LumenCommand;
 
+/// Diffs estimated above this many tokens are explained file by file, then summarized.
+const CHUNKED_TOKEN_THRESHOLD: usize = 100_000;
+
+/// Rough token estimate (about four bytes per token for code and English).
+fn estimate_tokens(text: &str) -> usize {
+    text.len() / 4
+}
+
+/// `--format json` output: one object, so scripts need no markdown parsing.
+#[derive(Serialize)]
+struct ExplainOutput<'a> {
+    reference: String,
+    provider: &'a str,
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<&'a str>,
+    explanation: &'a str,
+}
+
 pub struct ExplainCommand {
     pub git_entity: GitEntity,
     pub query: Option<String>,
+    /// Always use the per-file map-reduce mode, regardless of diff size
+    pub chunked: bool,
+    /// Print the explanation as it arrives in a terminal. Streamed text is printed
+    /// raw, since mdcat needs the whole document, so this trades rendering for speed.
+    pub stream: bool,
+    pub format: OutputFormat,
 }
 
 impl ExplainCommand {
-    pub async fn execute(&self, provider: &LumenProvider) -> Result<(), LumenError> {
+    pub async fn execute(&self, provider: &dyn AiProvider) -> Result<(), LumenError> {
+        if self.format == OutputFormat::Json {
+            // No headers or spinner: stdout carries only the object
+            let result = if self.should_chunk() {
+                self.explain_chunked(provider, |_| {}).await?
+            } else {
+                provider.explain(self).await?
+            };
+            println!("{}", self.to_json(provider, &result)?);
+            return Ok(());
+        }
+
         LumenCommand::print_with_mdcat(self.git_entity.format_static_details(provider))?;
         if let Some(query) = &self.query {
             LumenCommand::print_with_mdcat(format!("`query`: {query}"))?;
@@ -22,10 +66,92 @@ impl ExplainCommand {
         };
 
         let mut spinner = Spinner::new(spinners::Dots, spinner_text, Color::Blue);
-        let result = provider.explain(self).await?;
+        if self.stream && !self.should_chunk() && io::stdout().is_terminal() {
+            return self.print_streamed(provider, spinner).await;
+        }
+        let result = if self.should_chunk() {
+            self.explain_chunked(provider, |text| spinner.update_text(text))
+                .await?
+        } else {
+            provider.explain(self).await?
+        };
         spinner.success("Done");
 
         LumenCommand::print_with_mdcat(result)?;
         Ok(())
     }
+
+    /// Print the explanation as it is generated, replacing the spinner once the first
+    /// text arrives. The text is printed as the model writes it, without going through
+    /// mdcat. Ctrl-C abandons the request.
+    async fn print_streamed(
+        &self,
+        provider: &dyn AiProvider,
+        spinner: Spinner,
+    ) -> Result<(), LumenError> {
+        let mut spinner = Some(spinner);
+        let mut on_chunk = |chunk: &str| {
+            if let Some(mut spinner) = spinner.take() {
+                spinner.clear();
+            }
+            print!("{}", chunk);
+            let _ = io::stdout().flush();
+        };
+        let result = tokio::select! {
+            result = provider.explain_streaming(self, &mut on_chunk) => result.map(|_| ()),
+            _ = tokio::signal::ctrl_c() => Err(LumenError::Interrupted),
+        };
+        match spinner {
+            // Nothing arrived, so the spinner is still running
+            Some(mut spinner) if result.is_ok() => spinner.success("Done"),
+            Some(mut spinner) if matches!(result, Err(LumenError::Interrupted)) => spinner.clear(),
+            Some(mut spinner) => spinner.fail("Failed"),
+            None => println!(),
+        }
+        result
+    }
+
+    pub(crate) fn to_json(
+        &self,
+        provider: &dyn AiProvider,
+        explanation: &str,
+    ) -> Result<String, LumenError> {
+        let output = ExplainOutput {
+            reference: self.git_entity.reference(),
+            provider: provider.name(),
+            model: provider.model(),
+            query: self.query.as_deref(),
+            explanation,
+        };
+        Ok(serde_json::to_string_pretty(&output)?)
+    }
+
+    fn should_chunk(&self) -> bool {
+        self.chunked || estimate_tokens(self.git_entity.diff()) > CHUNKED_TOKEN_THRESHOLD
+    }
+
+    /// Explain each file's diff separately, then ask for a summary of those explanations.
+    /// `progress` receives a status line before every request.
+    pub(crate) async fn explain_chunked(
+        &self,
+        provider: &dyn AiProvider,
+        mut progress: impl FnMut(String),
+    ) -> Result<String, LumenError> {
+        let files = split_by_file(self.git_entity.diff());
+        if files.len() < 2 {
+            // Nothing to split; a single request is all we can do
+            return provider.explain(self).await;
+        }
+
+        let total = files.len();
+        let mut explanations = Vec::with_capacity(total);
+        for (i, (filename, diff)) in files.iter().enumerate() {
+            progress(format!("Explaining {} ({}/{})...", filename, i + 1, total));
+            let explanation = provider.explain_file(filename, diff).await?;
+            explanations.push((filename.clone(), explanation));
+        }
+
+        progress(format!("Summarizing {} files...", total));
+        provider.summarize_explanations(self, &explanations).await
+    }
 }
@@ -1,7 +1,8 @@
 use crate::{
+    config::cli::OutputFormat,
     error::LumenError,
     git_entity::{commit::Commit, GitEntity},
-    provider::LumenProvider,
+    provider::AiProvider,
     vcs::VcsBackend,
 };
 
@@ -12,7 +13,7 @@ pub struct ListCommand;
 impl ListCommand {
     pub async fn execute(
         &self,
-        provider: &LumenProvider,
+        provider: &dyn AiProvider,
         backend: &dyn VcsBackend,
     ) -> Result<(), LumenError> {
         let sha = LumenCommand::get_sha_from_fzf(backend)?;
@@ -21,6 +22,9 @@ impl ListCommand {
         ExplainCommand {
             git_entity,
             query: None,
+            chunked: false,
+            stream: true,
+            format: OutputFormat::Text,
         }
         .execute(provider)
         .await
@@ -4,23 +4,31 @@ use list::ListCommand;
 use operate::OperateCommand;
 use std::process::Stdio;
 
+use crate::config::cli::OutputFormat;
 use crate::config::configuration::DraftConfig;
 use crate::error::LumenError;
 use crate::git_entity::GitEntity;
-use crate::provider::LumenProvider;
+use crate::provider::AiProvider;
 use crate::vcs::VcsBackend;
 
 pub mod configure;
 pub mod diff;
+pub mod doctor;
 pub mod draft;
 pub mod explain;
 pub mod list;
+pub mod models;
 pub mod operate;
+pub mod providers;
 
 pub enum CommandType<'a> {
     Explain {
         git_entity: GitEntity,
         query: Option<String>,
+        chunked: bool,
+        no_stream: bool,
+        ignore_whitespace: bool,
+        format: OutputFormat,
     },
     List {
         backend: &'a dyn VcsBackend,
@@ -29,6 +37,8 @@ pub enum CommandType<'a> {
         git_entity: GitEntity,
         context: Option<String>,
         draft_config: DraftConfig,
+        ignore_whitespace: bool,
+        amend: bool,
     },
     Operate {
         query: String,
@@ -36,37 +46,73 @@ pub enum CommandType<'a> {
 }
 
 pub struct LumenCommand {
-    provider: LumenProvider,
+    provider: Box<dyn AiProvider>,
 }
 
 impl LumenCommand {
-    pub fn new(provider: LumenProvider) -> Self {
+    pub fn new(provider: Box<dyn AiProvider>) -> Self {
         LumenCommand { provider }
     }
 
     pub async fn execute(&self, command_type: CommandType<'_>) -> Result<(), LumenError> {
+        let result = self.run(command_type).await;
+        // stderr, so piped output (`--format json`, drafts) stays clean
+        if let (Ok(()), Some(provider)) = (&result, self.provider.fallback_used()) {
+            eprintln!("answered by fallback provider {}", provider);
+        }
+        result
+    }
+
+    async fn run(&self, command_type: CommandType<'_>) -> Result<(), LumenError> {
         match command_type {
-            CommandType::Explain { git_entity, query } => {
-                ExplainCommand { git_entity, query }
-                    .execute(&self.provider)
+            CommandType::Explain {
+                git_entity,
+                query,
+                chunked,
+                no_stream,
+                ignore_whitespace,
+                format,
+            } => {
+                let git_entity = if ignore_whitespace {
+                    git_entity.without_whitespace_changes().ok_or_else(|| {
+                        LumenError::CommandError(
+                            "changes are whitespace-only; nothing to explain with --ignore-whitespace"
+                                .to_string(),
+                        )
+                    })?
+                } else {
+                    git_entity
+                };
+                ExplainCommand {
+                    git_entity,
+                    query,
+                    chunked,
+                    stream: !no_stream,
+                    format,
+                }
+                    .execute(self.provider.as_ref())
                     .await
             }
-            CommandType::List { backend } => ListCommand.execute(&self.provider, backend).await,
+            CommandType::List { backend } => ListCommand.execute(self.provider.as_ref(), backend).await,
             CommandType::Draft {
                 git_entity,
                 context,
                 draft_config,
+                ignore_whitespace,
+                amend,
             } => {
                 DraftCommand {
                     git_entity,
                     draft_config,
                     context,
+                    ignore_whitespace,
+                    amend,
                 }
-                .execute(&self.provider)
+                .execute(self.provider.as_ref())
                 .await
             }
             CommandType::Operate { query } => {
-                OperateCommand { query }.execute(&self.provider).await
+                OperateCommand { query }.execute(self.provider.as_ref()).await
             }
         }
     }
@@ -76,7 +122,7 @@ impl LumenCommand {
         let log = backend.get_commit_log_for_fzf()?;
 
         // Pipe to fzf for selection
-        let mut fzf = std::process::Command::new("fzf")
+        let mut fzf = diff::child_command("fzf")
             .args(["--ansi", "--reverse", "--bind=enter:become(echo {1})"])
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
@@ -117,14 +163,14 @@ impl LumenCommand {
     }
 
     fn print_with_mdcat(content: String) -> Result<(), LumenError> {
-        match std::process::Command::new("mdcat")
+        match diff::child_command("mdcat")
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .spawn()
         {
             Ok(mut mdcat) => {
                 if let Some(stdin) = mdcat.stdin.take() {
-                    std::process::Command::new("echo")
+                    diff::child_command("echo")
                         .arg(&content)
                         .stdout(stdin)
                         .spawn()?
@@ -142,10 +188,7 @@ impl LumenCommand {
 
     #[allow(dead_code)]
     fn execute_bash_command(command: &str) -> Result<(), LumenError> {
-        let output = std::process::Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()?;
+        let output = diff::child_command("sh").arg("-c").arg(command).output()?;
 
         if !output.status.success() {
             let mut stderr = String::from_utf8(output.stderr)?;
@@ -0,0 +1,165 @@
+use serde_json::Value;
+
+use super::providers::KeyStatus;
+use crate::config::cli::ProviderType;
+use crate::config::{LumenConfig, ProviderInfo};
+use crate::error::LumenError;
+use crate::provider::ProviderError;
+
+/// Where a provider publishes the models it serves.
+enum ModelSource {
+    /// OpenAI-style `GET {base}/models` with a bearer token, answering `{"data": [{"id"}]}`
+    OpenAiCompatible(&'static str),
+    /// `GET /v1/models` with `x-api-key`; same response shape as OpenAI
+    Anthropic,
+    /// `GET /v1beta/models?key=...`, answering `{"models": [{"name": "models/..."}]}`
+    Gemini,
+    /// Local `GET /api/tags`, answering `{"models": [{"name"}]}`
+    Ollama,
+    /// No list endpoint; a curated list instead
+    Static(&'static [&'static str]),
+}
+
+const OPENCODE_ZEN_MODELS: &[&str] = &[
+    "claude-sonnet-4-5",
+    "claude-opus-4-1",
+    "claude-haiku-4-5",
+    "gpt-5",
+    "gpt-5-codex",
+    "qwen3-coder",
+    "kimi-k2",
+    "grok-code",
+];
+
+fn model_source(provider: ProviderType) -> ModelSource {
+    match provider {
+        ProviderType::Openai => ModelSource::OpenAiCompatible("https://api.openai.com/v1"),
+        ProviderType::Groq => ModelSource::OpenAiCompatible("https://api.groq.com/openai/v1"),
+        ProviderType::Claude => ModelSource::Anthropic,
+        ProviderType::Ollama => ModelSource::Ollama,
+        ProviderType::OpencodeZen => ModelSource::Static(OPENCODE_ZEN_MODELS),
+        ProviderType::Openrouter => ModelSource::OpenAiCompatible("https://openrouter.ai/api/v1"),
+        ProviderType::Deepseek => ModelSource::OpenAiCompatible("https://api.deepseek.com"),
+        ProviderType::Gemini => ModelSource::Gemini,
+        ProviderType::Xai => ModelSource::OpenAiCompatible("https://api.x.ai/v1"),
+        ProviderType::Vercel => ModelSource::OpenAiCompatible("https://ai-gateway.vercel.sh/v1"),
+    }
+}
+
+/// Model names from a list response: `data[].id` or `models[].name`, sorted.
+/// Gemini prefixes its names with `models/`, which the chat API does not want.
+fn parse_model_names(body: &Value) -> Vec<String> {
+    let entries = body["data"]
+        .as_array()
+        .map(|data| (data, "id"))
+        .or_else(|| body["models"].as_array().map(|models| (models, "name")));
+    let Some((entries, field)) = entries else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| entry[field].as_str())
+        .map(|name| name.strip_prefix("models/").unwrap_or(name).to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Command to list the models the configured provider offers.
+pub struct ModelsCommand;
+
+impl ModelsCommand {
+    /// Prints one model per line, marking the configured (or default) model.
+    pub async fn execute(config: &LumenConfig) -> Result<(), LumenError> {
+        let info = ProviderInfo::for_provider(config.provider);
+        let current = config
+            .model
+            .clone()
+            .unwrap_or_else(|| info.default_model.to_string());
+
+        let (models, curated) = match model_source(config.provider) {
+            ModelSource::Static(models) => (models.iter().map(|m| m.to_string()).collect(), true),
+            source => {
+                let key_status = KeyStatus::resolve(info, config.api_key.as_deref());
+                if let KeyStatus::Missing(_) = key_status {
+                    return Err(LumenError::ConfigurationError(format!(
+                        "{}: API key {}",
+                        info.display_name,
+                        key_status.describe()
+                    )));
+                }
+                let body = Self::fetch(source, key_status.key().unwrap_or_default()).await?;
+                (parse_model_names(&body), false)
+            }
+        };
+
+        println!("\n  \x1b[1m{} models\x1b[0m", info.display_name);
+        if models.is_empty() {
+            println!("  \x1b[2m(none reported)\x1b[0m");
+        }
+        for model in &models {
+            if *model == current {
+                println!("  \x1b[1;32m*\x1b[0m {}", model);
+            } else {
+                println!("    {}", model);
+            }
+        }
+        if curated {
+            println!("\n  \x1b[2mThis provider has no model-list endpoint; the list above is curated.\x1b[0m");
+        }
+        println!(
+            "\n  \x1b[2m* current model — change it with `lumen configure` or --model\x1b[0m\n"
+        );
+        Ok(())
+    }
+
+    async fn fetch(source: ModelSource, key: &str) -> Result<Value, LumenError> {
+        let client = reqwest::Client::new();
+        let request = match source {
+            ModelSource::OpenAiCompatible(base) => {
+                client.get(format!("{}/models", base)).bearer_auth(key)
+            }
+            ModelSource::Anthropic => client
+                .get("https://api.anthropic.com/v1/models?limit=1000")
+                .header("x-api-key", key)
+                .header("anthropic-version", "2023-06-01"),
+            ModelSource::Gemini => client
+                .get("https://generativelanguage.googleapis.com/v1beta/models?pageSize=1000")
+                .query(&[("key", key)]),
+            ModelSource::Ollama => {
+                let host = std::env::var("OLLAMA_HOST")
+                    .unwrap_or_else(|_| "http://localhost:11434".to_string());
+                client.get(format!("{}/api/tags", host.trim_end_matches('/')))
+            }
+            ModelSource::Static(_) => unreachable!("static lists are not fetched"),
+        };
+
+        let response = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(ProviderError::from)?;
+        Ok(response.json().await.map_err(ProviderError::from)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_model_names() {
+        let openai = json!({ "data": [{ "id": "gpt-5" }, { "id": "gpt-4.1" }] });
+        assert_eq!(parse_model_names(&openai), vec!["gpt-4.1", "gpt-5"]);
+
+        let gemini = json!({ "models": [{ "name": "models/gemini-2.5-flash" }] });
+        assert_eq!(parse_model_names(&gemini), vec!["gemini-2.5-flash"]);
+
+        let ollama = json!({ "models": [{ "name": "llama3.2:latest" }] });
+        assert_eq!(parse_model_names(&ollama), vec!["llama3.2:latest"]);
+
+        assert!(parse_model_names(&json!({ "error": "nope" })).is_empty());
+    }
+}
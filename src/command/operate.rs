@@ -1,5 +1,6 @@
 use spinoff::{spinners, Color, Spinner};
 use std::io::{self, Write};
+use std::process::Command;
 use thiserror::Error;
 use xml::reader::{EventReader, XmlEvent};
 
@@ -17,8 +18,9 @@ pub struct ExtractError {
     message: String,
 }
 
-use crate::{error::LumenError, provider::LumenProvider};
+use crate::{error::LumenError, provider::AiProvider};
 
+use super::diff::child_command;
 use super::LumenCommand;
 
 pub struct OperateCommand {
@@ -90,6 +92,188 @@ pub fn extract_operate_response(ai_response: &str) -> Result<OperateResult, Extr
     })
 }
 
+/// Git operations that can lose work or rewrite shared history, each with the sets of
+/// normalized flags that make it dangerous (an empty set always does). A command that
+/// matches always needs "yes" typed out, whatever the model's own warning says.
+const DANGEROUS_OPERATIONS: &[(&str, &[&[&str]], &str)] = &[
+    (
+        "push",
+        &[&["--force"], &["--delete"]],
+        "force push or remote branch deletion",
+    ),
+    ("reset", &[&["--hard"]], "hard reset"),
+    (
+        "branch",
+        &[&["--delete", "--force"]],
+        "forced branch deletion",
+    ),
+    ("clean", &[&["--force"]], "deleting untracked files"),
+    ("rebase", &[&[]], "rebase"),
+    ("pull", &[&["--rebase"]], "rebase"),
+];
+
+/// Per-subcommand spellings of the flags in `DANGEROUS_OPERATIONS`. `+` and `:` stand
+/// for refspec prefixes: `+main` force-pushes, `:main` deletes.
+const FLAG_ALIASES: &[(&str, &str, &[&str])] = &[
+    ("push", "-f", &["--force"]),
+    ("push", "--force-with-lease", &["--force"]),
+    ("push", "--force-if-includes", &["--force"]),
+    ("push", "+", &["--force"]),
+    ("push", "-d", &["--delete"]),
+    ("push", ":", &["--delete"]),
+    ("branch", "-D", &["--delete", "--force"]),
+    ("branch", "-d", &["--delete"]),
+    ("branch", "-f", &["--force"]),
+    ("clean", "-f", &["--force"]),
+    ("pull", "-r", &["--rebase"]),
+];
+
+/// Global options that take their value as the next word, e.g. `git -C <dir> ...`.
+const GLOBAL_OPTIONS_WITH_VALUE: &[&str] = &[
+    "-C",
+    "-c",
+    "--git-dir",
+    "--work-tree",
+    "--namespace",
+    "--config-env",
+    "--super-prefix",
+];
+
+/// Shells whose `-c` argument is itself a command line worth checking.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh"];
+
+/// `command` run through the platform shell, marked as launched by lumen.
+fn shell_command(command: &str) -> Command {
+    #[cfg(target_family = "windows")]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(target_family = "windows"))]
+    let (shell, flag) = ("sh", "-c");
+
+    let mut child = child_command(shell);
+    child.arg(flag).arg(command);
+    child
+}
+
+/// What makes `command` dangerous, checking every git invocation in a shell line,
+/// including those inside `sh -c '...'`.
+fn dangerous_operation(command: &str) -> Option<&'static str> {
+    shell_segments(command)
+        .iter()
+        .find_map(|words| segment_danger(words))
+}
+
+fn segment_danger(words: &[String]) -> Option<&'static str> {
+    let program = |word: &str| word.rsplit('/').next().unwrap_or(word).to_string();
+    if let Some(i) = words
+        .iter()
+        .position(|w| SHELLS.contains(&program(w).as_str()))
+    {
+        let flag = words.get(i + 1).map_or("", String::as_str);
+        if flag.starts_with('-') && !flag.starts_with("--") && flag.ends_with('c') {
+            return words
+                .get(i + 2)
+                .and_then(|inner| dangerous_operation(inner));
+        }
+    }
+
+    let mut words = words
+        .iter()
+        .map(String::as_str)
+        .skip_while(|w| program(w) != "git")
+        .skip(1);
+    // Global options such as `-C <dir>` come before the subcommand
+    let mut subcommand = None;
+    while let Some(word) = words.next() {
+        match word {
+            w if GLOBAL_OPTIONS_WITH_VALUE.contains(&w) => {
+                words.next();
+            }
+            w if w.starts_with('-') => {}
+            w => {
+                subcommand = Some(w);
+                break;
+            }
+        }
+    }
+    let subcommand = subcommand?;
+    let flags = normalize_flags(subcommand, words);
+    DANGEROUS_OPERATIONS
+        .iter()
+        .find(|(name, required, _)| {
+            *name == subcommand
+                && required
+                    .iter()
+                    .any(|set| set.iter().all(|flag| flags.iter().any(|f| f == flag)))
+        })
+        .map(|(_, _, description)| *description)
+}
+
+/// The flags in `args` spelled the way `DANGEROUS_OPERATIONS` spells them: short
+/// bundles like `-df` split up, `--opt=value` reduced to `--opt` (dropped when the
+/// value is `false`), and aliases of `subcommand` resolved.
+fn normalize_flags<'a>(subcommand: &str, args: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut raw = Vec::new();
+    for arg in args {
+        if let Some(long) = arg.strip_prefix("--") {
+            match long.split_once('=') {
+                Some((_, "false" | "no")) => {}
+                Some((name, _)) => raw.push(format!("--{name}")),
+                None if !long.is_empty() => raw.push(arg.to_string()),
+                None => {}
+            }
+        } else if let Some(short) = arg.strip_prefix('-') {
+            raw.extend(short.chars().map(|c| format!("-{c}")));
+        } else if arg.starts_with('+') || arg.starts_with(':') {
+            raw.push(arg[..1].to_string());
+        }
+    }
+    raw.into_iter()
+        .flat_map(|flag| {
+            FLAG_ALIASES
+                .iter()
+                .find(|(name, alias, _)| *name == subcommand && *alias == flag)
+                .map_or_else(
+                    || vec![flag.clone()],
+                    |(_, _, flags)| flags.iter().map(|f| f.to_string()).collect(),
+                )
+        })
+        .collect()
+}
+
+/// Splits a shell line into the words of each simple command, honouring quotes and
+/// backslash escapes and breaking at `&`, `|`, `;` and newlines outside quotes.
+fn shell_segments(command: &str) -> Vec<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    word.get_or_insert_with(String::new).push(next);
+                }
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, '&' | '|' | ';' | '\n') => {
+                words.extend(word.take());
+                segments.push(std::mem::take(&mut words));
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word.take());
+    segments.push(words);
+    segments
+}
+
 pub fn process_operation(result: OperateResult) -> Result<(), io::Error> {
     // Display the explanation
     println!("\n--- What this will do ---");
@@ -101,30 +285,33 @@ pub fn process_operation(result: OperateResult) -> Result<(), io::Error> {
         println!("\n\x1b[33mWarning: {}\x1b[0m", warning);
     }
 
-    print!("\n{} [y/N] ", result.command);
+    let danger = dangerous_operation(&result.command);
+    match danger {
+        Some(description) => {
+            println!(
+                "\n\x1b[91mdanger:\x1b[0m this is a {}, which can destroy work",
+                description
+            );
+            print!("\n{}\nType \"yes\" to run it: ", result.command);
+        }
+        None => print!("\n{} [y/N] ", result.command),
+    }
     io::stdout().flush()?; // Ensure prompt is shown immediately
 
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
     println!();
 
-    if !input.trim().eq_ignore_ascii_case("y") {
+    let confirmed = match danger {
+        Some(_) => input.trim() == "yes",
+        None => input.trim().eq_ignore_ascii_case("y"),
+    };
+    if !confirmed {
         println!("Operation canceled.");
         return Ok(());
     }
 
-    // Using a shell to execute the git command
-    #[cfg(target_family = "unix")]
-    let output = std::process::Command::new("sh")
-        .arg("-c")
-        .arg(&result.command)
-        .output()?;
-
-    #[cfg(target_family = "windows")]
-    let output = std::process::Command::new("cmd")
-        .arg("/C")
-        .arg(&result.command)
-        .output()?;
+    let output = shell_command(&result.command).output()?;
 
     // Print command output
     if !output.stdout.is_empty() {
@@ -146,7 +333,7 @@ pub fn process_operation(result: OperateResult) -> Result<(), io::Error> {
 }
 
 impl OperateCommand {
-    pub async fn execute(&self, provider: &LumenProvider) -> Result<(), LumenError> {
+    pub async fn execute(&self, provider: &dyn AiProvider) -> Result<(), LumenError> {
         LumenCommand::print_with_mdcat(format!("`query`: {}", &self.query))?;
 
         let spinner_text = "Generating answer...".to_string();
@@ -161,3 +348,109 @@ impl OperateCommand {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dangerous_operation() {
+        assert_eq!(
+            dangerous_operation("git push --force-with-lease origin main"),
+            Some("force push or remote branch deletion")
+        );
+        assert_eq!(
+            dangerous_operation("git add . && git -C repo reset --hard HEAD~1"),
+            Some("hard reset")
+        );
+        assert_eq!(
+            dangerous_operation("git clean -fdx"),
+            Some("deleting untracked files")
+        );
+        assert_eq!(dangerous_operation("git rebase -i HEAD~3"), Some("rebase"));
+        assert_eq!(
+            dangerous_operation("git branch -D old"),
+            Some("forced branch deletion")
+        );
+
+        assert!(dangerous_operation("git push origin :old-branch").is_some());
+        assert_eq!(dangerous_operation("git push origin main"), None);
+        assert_eq!(dangerous_operation("git branch -d merged"), None);
+        assert_eq!(dangerous_operation("git clean -n"), None);
+        assert_eq!(dangerous_operation("git commit -m 'rebase docs'"), None);
+    }
+
+    #[test]
+    fn test_dangerous_operation_normalizes_flags() {
+        for command in [
+            "git branch --delete --force old",
+            "git branch -d -f old",
+            "git branch -df old",
+            "git branch --force -d old",
+        ] {
+            assert_eq!(
+                dangerous_operation(command),
+                Some("forced branch deletion"),
+                "{command}"
+            );
+        }
+        assert_eq!(dangerous_operation("git branch --delete merged"), None);
+        assert_eq!(dangerous_operation("git branch -f main HEAD~1"), None);
+
+        assert_eq!(dangerous_operation("git pull --rebase"), Some("rebase"));
+        assert_eq!(
+            dangerous_operation("git pull --rebase=interactive origin main"),
+            Some("rebase")
+        );
+        assert_eq!(dangerous_operation("git pull -r"), Some("rebase"));
+        assert_eq!(dangerous_operation("git pull --rebase=false"), None);
+        assert_eq!(dangerous_operation("git pull"), None);
+    }
+
+    #[test]
+    fn test_dangerous_operation_skips_global_option_values() {
+        assert_eq!(
+            dangerous_operation("git --git-dir repo/.git reset --hard"),
+            Some("hard reset")
+        );
+        assert_eq!(
+            dangerous_operation("git --work-tree ../checkout clean -f"),
+            Some("deleting untracked files")
+        );
+        assert_eq!(
+            dangerous_operation("git --git-dir=repo/.git push -f origin main"),
+            Some("force push or remote branch deletion")
+        );
+        assert_eq!(dangerous_operation("git --git-dir rebase status"), None);
+    }
+
+    #[test]
+    fn test_dangerous_operation_inside_shell_wrapper() {
+        assert_eq!(
+            dangerous_operation("sh -c 'git reset --hard HEAD~1'"),
+            Some("hard reset")
+        );
+        assert_eq!(
+            dangerous_operation("bash -c \"cd repo && git push --force\""),
+            Some("force push or remote branch deletion")
+        );
+        assert_eq!(
+            dangerous_operation("/bin/sh -lc 'git branch -D old'"),
+            Some("forced branch deletion")
+        );
+        assert_eq!(dangerous_operation("sh -c 'git status'"), None);
+        assert_eq!(
+            dangerous_operation("git commit -m 'a; git reset --hard'"),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_command_marks_lumen_active() {
+        let output = shell_command("printf %s \"$LUMEN_ACTIVE\"")
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "1");
+    }
+}
@@ -0,0 +1,168 @@
+use crate::config::{LumenConfig, ProviderInfo, ALL_PROVIDERS};
+use crate::error::LumenError;
+use crate::provider::{AiProvider, CompletionRequest, LumenProvider};
+
+/// Where a provider's API key comes from, if anywhere.
+#[derive(Debug, PartialEq)]
+pub(super) enum KeyStatus {
+    /// From `--api-key`, `LUMEN_API_KEY` or the config file (active provider only)
+    Config(String),
+    /// From the provider's own environment variable
+    Env(&'static str, String),
+    /// Local provider that needs no key
+    NotNeeded,
+    Missing(&'static str),
+}
+
+impl KeyStatus {
+    pub(super) fn resolve(info: &ProviderInfo, config_key: Option<&str>) -> Self {
+        if info.env_key.is_empty() {
+            return KeyStatus::NotNeeded;
+        }
+        if let Some(key) = config_key.filter(|k| !k.is_empty()) {
+            return KeyStatus::Config(key.to_string());
+        }
+        match std::env::var(info.env_key) {
+            Ok(key) if !key.is_empty() => KeyStatus::Env(info.env_key, key),
+            _ => KeyStatus::Missing(info.env_key),
+        }
+    }
+
+    pub(super) fn key(&self) -> Option<&str> {
+        match self {
+            KeyStatus::Config(key) | KeyStatus::Env(_, key) => Some(key),
+            _ => None,
+        }
+    }
+
+    pub(super) fn describe(&self) -> String {
+        match self {
+            KeyStatus::Config(key) => format!("{} (config)", redact(key)),
+            KeyStatus::Env(var, key) => format!("{} (${})", redact(key), var),
+            KeyStatus::NotNeeded => "not needed".to_string(),
+            KeyStatus::Missing(var) => format!("missing (set ${})", var),
+        }
+    }
+}
+
+/// Show just enough of a key to tell keys apart.
+fn redact(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 12 {
+        return "****".to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+/// Command to list the supported providers and how each is configured.
+pub struct ProvidersCommand;
+
+impl ProvidersCommand {
+    /// Prints one row per provider: active marker, model, and API key status.
+    /// With `check`, sends a tiny request to every provider that has a key.
+    pub async fn execute(config: &LumenConfig, check: bool) -> Result<(), LumenError> {
+        let name_width = ALL_PROVIDERS
+            .iter()
+            .map(|p| p.display_name.len())
+            .max()
+            .unwrap_or(0);
+        let model_width = ALL_PROVIDERS
+            .iter()
+            .map(|p| p.default_model.len())
+            .chain(config.model.as_ref().map(|m| m.len()))
+            .max()
+            .unwrap_or(0);
+
+        println!(
+            "\n    \x1b[1m{:name_width$}  {:model_width$}  API KEY\x1b[0m",
+            "PROVIDER", "MODEL"
+        );
+
+        for info in ALL_PROVIDERS {
+            let active = info.provider_type == config.provider;
+            let (model, config_key) = if active {
+                (
+                    config
+                        .model
+                        .clone()
+                        .unwrap_or_else(|| info.default_model.to_string()),
+                    config.api_key.as_deref(),
+                )
+            } else {
+                (info.default_model.to_string(), None)
+            };
+            let key_status = KeyStatus::resolve(info, config_key);
+
+            let marker = if active { "\x1b[1;32m*\x1b[0m" } else { " " };
+            let key_color = match key_status {
+                KeyStatus::Missing(_) => "\x1b[2m",
+                _ => "",
+            };
+            let mut line = format!(
+                "  {} {:name_width$}  {:model_width$}  {}{}\x1b[0m",
+                marker,
+                info.display_name,
+                model,
+                key_color,
+                key_status.describe()
+            );
+
+            let reachable = key_status.key().is_some() || key_status == KeyStatus::NotNeeded;
+            if check && reachable {
+                match Self::ping(info, key_status.key(), &model).await {
+                    Ok(()) => line.push_str("  \x1b[32m✓ ok\x1b[0m"),
+                    Err(e) => line.push_str(&format!("  \x1b[91m✗ {}\x1b[0m", e)),
+                }
+            }
+            println!("{}", line);
+        }
+
+        println!("\n  \x1b[2m* active provider — change it with `lumen configure` or --provider\x1b[0m\n");
+        Ok(())
+    }
+
+    /// Minimal round trip to confirm the key and model are accepted.
+    pub(super) async fn ping(info: &ProviderInfo, key: Option<&str>, model: &str) -> Result<(), LumenError> {
+        let provider = LumenProvider::new(
+            info.provider_type,
+            key.map(str::to_string),
+            Some(model.to_string()),
+        )?;
+        provider
+            .complete(CompletionRequest {
+                system_prompt: "You are a health check.".to_string(),
+                user_prompt: "Reply with OK.".to_string(),
+            })
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::cli::ProviderType;
+
+    #[test]
+    fn test_redact_keeps_only_ends() {
+        assert_eq!(redact("sk-abcdefghijklmnop1234"), "sk-a…1234");
+        assert_eq!(redact("short"), "****");
+    }
+
+    #[test]
+    fn test_key_status_prefers_config_key() {
+        let ollama = ProviderInfo::for_provider(ProviderType::Ollama);
+        assert_eq!(
+            KeyStatus::resolve(ollama, Some("ignored")),
+            KeyStatus::NotNeeded
+        );
+
+        let openai = ProviderInfo::for_provider(ProviderType::Openai);
+        assert_eq!(
+            KeyStatus::resolve(openai, Some("from-config")),
+            KeyStatus::Config("from-config".to_string())
+        );
+    }
+}
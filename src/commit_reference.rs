@@ -23,7 +23,7 @@ impl FromStr for CommitReference {
         }
 
         // Handle the ... and .. cases
-        if let Some((from, to)) = s.split_once("...") {
+        if let Some((from, to)) = split_top_level(s, "...") {
             let from = if from.is_empty() { "HEAD" } else { from };
             let to = if to.is_empty() { "HEAD" } else { to };
 
@@ -31,7 +31,7 @@ impl FromStr for CommitReference {
                 from: from.to_string(),
                 to: to.to_string(),
             })
-        } else if let Some((from, to)) = s.split_once("..") {
+        } else if let Some((from, to)) = split_top_level(s, "..") {
             let from = if from.is_empty() { "HEAD" } else { from };
             let to = if to.is_empty() { "HEAD" } else { to };
 
@@ -45,6 +45,27 @@ impl FromStr for CommitReference {
     }
 }
 
+/// Split at the first `sep` outside parentheses and quotes, so revset
+/// expressions like `(main..@) & mine()` stay a single reference.
+fn split_top_level<'a>(s: &'a str, sep: &str) -> Option<(&'a str, &'a str)> {
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, _) if depth == 0 && s[i..].starts_with(sep) => {
+                return Some((&s[..i], &s[i + sep.len()..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -180,4 +201,24 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_jj_revset_with_nested_range() {
+        // ranges inside parentheses belong to the revset, not to lumen
+        assert_eq!(
+            "(main..@) & mine()".parse::<CommitReference>().unwrap(),
+            CommitReference::Single("(main..@) & mine()".to_string())
+        );
+        assert_eq!(
+            "description(\"a..b\")".parse::<CommitReference>().unwrap(),
+            CommitReference::Single("description(\"a..b\")".to_string())
+        );
+        assert_eq!(
+            "trunk()..@".parse::<CommitReference>().unwrap(),
+            CommitReference::Range {
+                from: "trunk()".to_string(),
+                to: "@".to_string(),
+            }
+        );
+    }
 }
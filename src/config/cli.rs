@@ -1,5 +1,7 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::commit_reference::CommitReference;
 
@@ -10,6 +12,18 @@ pub enum VcsOverride {
     Git,
     /// Use jj (Jujutsu) backend
     Jj,
+    /// Use hg (Mercurial) backend
+    Hg,
+}
+
+/// Output format for commands that can print machine-readable results
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Default)]
+pub enum OutputFormat {
+    /// Rendered markdown with progress spinners
+    #[default]
+    Text,
+    /// A single JSON object on stdout, nothing on stderr
+    Json,
 }
 
 #[derive(Parser)]
@@ -91,6 +105,26 @@ pub enum Commands {
         /// Select commit interactively using fuzzy finder
         #[arg(long)]
         list: bool,
+
+        /// Explain each file separately, then summarize (automatic for very large diffs)
+        #[arg(long)]
+        chunked: bool,
+
+        /// Wait for the whole explanation and render it with mdcat, instead of printing it as it arrives
+        #[arg(long)]
+        no_stream: bool,
+
+        /// Leave whitespace-only changes out of the prompt
+        #[arg(short = 'w', long)]
+        ignore_whitespace: bool,
+
+        /// Explain a unified diff read from stdin instead of asking the VCS, e.g. `git diff | lumen explain --stdin-diff`
+        #[arg(long, conflicts_with_all = ["reference", "staged", "list"])]
+        stdin_diff: bool,
+
+        /// Output format; `json` prints the reference, provider, model and explanation as one object
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     /// List all commits in an interactive fuzzy-finder, and summarize the changes
     List,
@@ -99,6 +133,14 @@ pub enum Commands {
         /// Add context to communicate intent
         #[arg(short, long)]
         context: Option<String>,
+
+        /// Leave whitespace-only changes out of the prompt
+        #[arg(short = 'w', long)]
+        ignore_whitespace: bool,
+
+        /// Write a new message for the last commit (HEAD) and offer to `git commit --amend` with it
+        #[arg(long)]
+        amend: bool,
     },
 
     Operate {
@@ -106,44 +148,166 @@ pub enum Commands {
         query: String,
     },
     /// Launch interactive side-by-side diff viewer
-    Diff {
-        /// Commit reference: SHA, HEAD, HEAD~3..HEAD, main..feature, main...feature
-        /// Can also be a PR number or URL (e.g., 123 or https://github.com/owner/repo/pull/123)
-        #[arg(value_parser = clap::value_parser!(CommitReference))]
-        reference: Option<CommitReference>,
-
-        /// View a GitHub pull request (number or URL)
+    Diff(Box<DiffArgs>),
+    /// Interactively configure Lumen (provider, API key)
+    Configure,
+    /// List supported providers, their models, and whether an API key is available
+    Providers {
+        /// Send a tiny request to each provider that has a key, to verify it works
         #[arg(long)]
-        pr: Option<String>,
+        check: bool,
+    },
+    /// List the models the configured provider offers
+    Models,
+    /// Check the environment: repository, optional tools, terminal colors and the provider key
+    Doctor,
+}
 
-        /// Filter to specific files
-        #[arg(short, long)]
-        file: Option<Vec<String>>,
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Commit reference: SHA, HEAD, HEAD~3..HEAD, main..feature, main...feature
+    /// A single SHA shows that commit's own changes; see --against to compare it with your working tree.
+    /// Can also be a PR number or URL (e.g., 123 or https://github.com/owner/repo/pull/123)
+    #[arg(value_parser = clap::value_parser!(CommitReference))]
+    pub reference: Option<CommitReference>,
 
-        /// Watch for file changes and auto-reload
-        #[arg(short, long)]
-        watch: bool,
+    /// View a GitHub pull request (number or URL); GitLab merge request URLs work too
+    #[arg(long)]
+    pub pr: Option<String>,
 
-        /// Color theme (e.g., dracula, nord, gruvbox-dark, catppuccin-mocha)
-        #[arg(short, long)]
-        theme: Option<String>,
+    /// View a GitLab merge request (number or URL), fetched via glab
+    #[arg(long, value_name = "MR", conflicts_with_all = ["reference", "pr", "upstream", "my_pr", "amend"])]
+    pub mr: Option<String>,
 
-        /// Show commits stacked (commit-by-commit navigation with ctrl+l/h)
-        #[arg(long)]
-        stacked: bool,
+    /// Diff the current branch against its upstream tracking branch (what you are about to push)
+    #[arg(long, conflicts_with_all = ["reference", "pr"])]
+    pub upstream: bool,
 
-        /// Initially focus on this file path
-        #[arg(long)]
-        focus: Option<String>,
-    },
-    /// Interactively configure Lumen (provider, API key)
-    Configure,
+    /// Only what the branch itself contributed: diff from its merge base with the default
+    /// branch (or with A, for A..B) to its tip, leaving out changes merged in from the base
+    #[arg(long, conflicts_with_all = ["pr", "mr", "upstream", "my_pr", "amend", "against", "history", "in_progress"])]
+    pub branch_only: bool,
+
+    /// Diff your working tree against this commit (like `git diff <sha>`), rather than showing the commit itself
+    #[arg(long, value_name = "SHA", conflicts_with_all = ["reference", "pr", "upstream", "my_pr", "amend", "stacked"])]
+    pub against: Option<String>,
+
+    /// Step through every commit that changed this file, oldest first (ctrl+h/ctrl+l); follows renames
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["reference", "pr", "upstream", "my_pr", "amend", "against", "stacked"])]
+    pub history: Option<String>,
+
+    /// Review your own open pull request (picks from a list if you have several)
+    #[arg(long, conflicts_with_all = ["reference", "pr", "upstream", "amend", "stacked"])]
+    pub my_pr: bool,
+
+    /// Review the last `git commit --amend`: HEAD against the commit it replaced, message included
+    #[arg(long, conflicts_with_all = ["reference", "pr", "upstream", "stacked"])]
+    pub amend: bool,
+
+    /// Review the commit a stopped rebase or cherry-pick is applying, opening on its first conflict
+    #[arg(long, conflicts_with_all = ["reference", "pr", "mr", "upstream", "my_pr", "amend", "against", "history", "stacked"])]
+    pub in_progress: bool,
+
+    /// Review only the working tree files that still hold conflict markers, opening on the first conflict
+    #[arg(long, conflicts_with_all = ["reference", "pr", "mr", "upstream", "my_pr", "amend", "against", "history", "stacked", "branch_only", "file"])]
+    pub conflicts: bool,
+
+    /// Filter to specific files
+    #[arg(short, long)]
+    pub file: Option<Vec<String>>,
+
+    /// Watch for file changes and auto-reload
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Also reload on a timer (e.g. 2s, 500ms, 1m), for filesystems where change events are unreliable. Implies --watch
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_interval)]
+    pub watch_interval: Option<Duration>,
+
+    /// Color theme (e.g., dracula, nord, gruvbox-dark, catppuccin-mocha), or a custom theme's TOML file or name
+    #[arg(short, long)]
+    pub theme: Option<String>,
+
+    /// Show commits stacked (commit-by-commit navigation with ctrl+l/h); with a PR, walks its commits
+    #[arg(long)]
+    pub stacked: bool,
+
+    /// Initially focus on this file path
+    #[arg(long)]
+    pub focus: Option<String>,
+
+    /// Start on an overview of all changed files with their stats (reopen with O)
+    #[arg(long)]
+    pub overview: bool,
+
+    /// Show only hunks with an added or changed line matching this regex (smart case), in the files that have one
+    #[arg(long, value_name = "PATTERN", conflicts_with_all = ["export_patch", "export_html"])]
+    pub grep: Option<String>,
+
+    /// Write the whole diff to this file as a patch (applies with `git apply`) instead of opening the viewer
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["stacked", "history", "watch", "watch_interval"])]
+    pub export_patch: Option<PathBuf>,
+
+    /// Print each file's status and added/removed lines, with a total, instead of opening the viewer
+    #[arg(long, conflicts_with_all = ["stacked", "history", "watch", "watch_interval", "export_patch", "export_html"])]
+    pub stat: bool,
+
+    /// Write every file's side-by-side diff, in the theme's colors, to one HTML page instead of opening the viewer
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["stacked", "history", "watch", "watch_interval", "export_patch"])]
+    pub export_html: Option<PathBuf>,
+
+    /// Collapse sidebar directories at this depth or deeper (0 collapses everything; cycle with L)
+    #[arg(long, value_name = "N")]
+    pub tree_depth: Option<usize>,
+
+    /// List the commits behind submodule pointer bumps (submodules must be checked out)
+    #[arg(long)]
+    pub recurse_submodules: bool,
+
+    /// Disable syntax highlighting (faster on slow machines or over SSH)
+    #[arg(long)]
+    pub no_highlight: bool,
+
+    /// Print startup diagnostics, e.g. languages whose highlighting failed to load
+    #[arg(long)]
+    pub verbose: bool,
+}
+
+/// Parse an interval such as `2s`, `500ms` or `1m`; a bare number means seconds.
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let invalid = || format!("invalid interval '{}': expected e.g. 2s or 500ms", s);
+    let value: u64 = number.parse().map_err(|_| invalid())?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(value),
+        "" | "s" => Duration::from_secs(value),
+        "m" => Duration::from_secs(value.checked_mul(60).ok_or_else(invalid)?),
+        _ => return Err(format!("invalid interval unit '{}': use ms, s or m", unit)),
+    };
+    if duration.is_zero() {
+        return Err("interval must be greater than zero".to_string());
+    }
+    Ok(duration)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_interval("1m"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_interval("3"), Ok(Duration::from_secs(3)));
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("2h").is_err());
+        assert!(parse_interval("fast").is_err());
+        assert!(parse_interval(&format!("{}m", u64::MAX / 59)).is_err());
+    }
+
     #[test]
     fn test_vcs_git_parses() {
         let cli = Cli::try_parse_from(["lumen", "--vcs", "git", "diff"]).unwrap();
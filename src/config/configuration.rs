@@ -24,11 +24,137 @@ pub struct LumenConfig {
     #[serde(default = "default_api_key")]
     pub api_key: Option<String>,
 
+    /// Providers tried in order when the main one is rate limited or unreachable
+    #[serde(default)]
+    pub fallback_providers: Vec<FallbackProviderConfig>,
+
     #[serde(default = "default_draft_config")]
     pub draft: DraftConfig,
 
     #[serde(default)]
     pub theme: Option<String>,
+
+    #[serde(default)]
+    pub file_status: FileStatusConfig,
+
+    /// Syntax highlighting in the diff viewer
+    #[serde(default = "default_syntax")]
+    pub syntax: bool,
+
+    /// Leave whitespace-only changes out of word-level emphasis
+    #[serde(default)]
+    pub hide_whitespace_emphasis: bool,
+
+    /// Character drawn where a tab starts in the diff viewer (e.g. "→"); unset shows spaces
+    #[serde(default)]
+    pub tab_glyph: Option<String>,
+
+    /// Most files fetched for a pull request in the diff viewer; 0 fetches them all
+    #[serde(default = "default_max_pr_files")]
+    pub max_pr_files: usize,
+
+    /// Filename globs mapped to a highlighting language, e.g. {"*.conf": "toml"}
+    #[serde(default)]
+    pub languages: HashMap<String, String>,
+
+    /// Tab width by file extension in the diff viewer, e.g. {"go": 8, "ts": 2}; others use 4
+    #[serde(default)]
+    pub tab_widths: HashMap<String, usize>,
+
+    /// File extensions shown without syntax highlighting, e.g. ["json"] for huge generated files
+    #[serde(default)]
+    pub no_highlight: Vec<String>,
+
+    /// Soft length limit for diff viewer annotations; longer ones are cut short inline
+    #[serde(default = "default_annotation_limit")]
+    pub annotation_limit: usize,
+
+    /// Templates for copying and exporting diff viewer annotations
+    #[serde(default)]
+    pub annotation_export: AnnotationExportConfig,
+
+    /// Diff viewer layout: "side-by-side" (default), "change-bars" or "unified"
+    #[serde(default)]
+    pub view_mode: Option<String>,
+
+    /// Border between the side-by-side panels: "shared" (default), "separate" or "none"
+    #[serde(default)]
+    pub panel_borders: Option<String>,
+
+    /// Let `n`/`N` in the diff viewer wrap from the last match to the first and back
+    #[serde(default = "default_search_wrap")]
+    pub search_wrap: bool,
+
+    /// Compact diff view (`s`): unchanged runs longer than this fold into one row
+    #[serde(default = "default_fold_threshold")]
+    pub fold_threshold: usize,
+
+    /// Redraw the diff viewer on every tick even when nothing changed, as older versions
+    /// did; for terminals that lose the screen between redraws
+    #[serde(default)]
+    pub idle_redraw: bool,
+
+    /// Ask for a second `q` (or `y`) before the diff viewer quits
+    #[serde(default)]
+    pub confirm_quit: bool,
+
+    /// Base for `lumen diff --upstream` when the branch tracks nothing, e.g. "main";
+    /// unset falls back to the remote's default branch (`origin/HEAD`)
+    #[serde(default)]
+    pub default_branch: Option<String>,
+
+    /// GitHub Enterprise host, e.g. "github.example.com", for PRs given by number;
+    /// PR URLs carry their own host. Unset uses `GH_HOST`, then github.com
+    #[serde(default)]
+    pub github_host: Option<String>,
+
+    /// How the diff viewer copies: "auto" (default), "arboard", "osc52" or "command"
+    #[serde(default)]
+    pub clipboard: Option<String>,
+
+    /// Program for `"clipboard": "command"`, given the text on stdin, e.g. "pbcopy"
+    #[serde(default)]
+    pub clipboard_command: Option<String>,
+}
+
+/// One entry of `fallback_providers`: `{ "provider": "anthropic", "model": "..." }`.
+/// Without `api_key` the provider's usual environment variable is used.
+#[derive(Debug, Deserialize)]
+pub struct FallbackProviderConfig {
+    #[serde(deserialize_with = "deserialize_ai_provider")]
+    pub provider: ProviderType,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+/// Status indicators for the diff viewer's sidebar and file picker.
+/// Unset symbols fall back to `A`/`M`/`D`, unset colors to the theme.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct FileStatusConfig {
+    pub added: Option<String>,
+    pub modified: Option<String>,
+    pub deleted: Option<String>,
+    pub added_color: Option<String>,
+    pub modified_color: Option<String>,
+    pub deleted_color: Option<String>,
+    /// Show nerd-font file-type icons next to filenames
+    #[serde(default)]
+    pub icons: bool,
+}
+
+/// Annotation export format for the diff viewer. `item` is rendered per annotation
+/// with `{file}`, `{line_start}`, `{line_end}`, `{content}` and `{time}`; `header` and
+/// `footer` wrap the list. Without `item` the built-in format is used.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct AnnotationExportConfig {
+    pub header: Option<String>,
+    pub item: Option<String>,
+    pub footer: Option<String>,
+    /// Write each filled-in value as a CSV field, quoted when it needs to be
+    #[serde(default)]
+    pub csv: bool,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -90,6 +216,26 @@ where
     serde_json::to_string(&commit_types_map).map_err(serde::de::Error::custom)
 }
 
+fn default_syntax() -> bool {
+    true
+}
+
+fn default_max_pr_files() -> usize {
+    1000
+}
+
+fn default_annotation_limit() -> usize {
+    500
+}
+
+fn default_search_wrap() -> bool {
+    true
+}
+
+fn default_fold_threshold() -> usize {
+    10
+}
+
 fn default_draft_config() -> DraftConfig {
     DraftConfig {
         commit_types: default_commit_types(),
@@ -124,8 +270,33 @@ impl LumenConfig {
             provider,
             model,
             api_key,
+            fallback_providers: config.fallback_providers,
             draft: config.draft,
             theme: config.theme,
+            file_status: config.file_status,
+            syntax: config.syntax,
+            hide_whitespace_emphasis: config.hide_whitespace_emphasis,
+            tab_glyph: config.tab_glyph,
+            // 0 lifts the cap rather than fetching nothing
+            max_pr_files: match config.max_pr_files {
+                0 => usize::MAX,
+                limit => limit,
+            },
+            languages: config.languages,
+            tab_widths: config.tab_widths,
+            no_highlight: config.no_highlight,
+            annotation_limit: config.annotation_limit,
+            annotation_export: config.annotation_export,
+            view_mode: config.view_mode,
+            panel_borders: config.panel_borders,
+            search_wrap: config.search_wrap,
+            fold_threshold: config.fold_threshold,
+            idle_redraw: config.idle_redraw,
+            confirm_quit: config.confirm_quit,
+            default_branch: config.default_branch,
+            github_host: config.github_host,
+            clipboard: config.clipboard,
+            clipboard_command: config.clipboard_command,
         })
     }
 
@@ -149,8 +320,29 @@ impl Default for LumenConfig {
             provider: default_ai_provider(),
             model: default_model(),
             api_key: default_api_key(),
+            fallback_providers: Vec::new(),
             draft: default_draft_config(),
             theme: None,
+            file_status: FileStatusConfig::default(),
+            syntax: default_syntax(),
+            hide_whitespace_emphasis: false,
+            tab_glyph: None,
+            max_pr_files: default_max_pr_files(),
+            languages: HashMap::new(),
+            tab_widths: HashMap::new(),
+            no_highlight: Vec::new(),
+            annotation_limit: default_annotation_limit(),
+            annotation_export: AnnotationExportConfig::default(),
+            view_mode: None,
+            panel_borders: None,
+            search_wrap: default_search_wrap(),
+            fold_threshold: default_fold_threshold(),
+            idle_redraw: false,
+            confirm_quit: false,
+            default_branch: None,
+            github_host: None,
+            clipboard: None,
+            clipboard_command: None,
         }
     }
 }
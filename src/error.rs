@@ -29,6 +29,10 @@ pub enum LumenError {
     #[error("{0}")]
     CommandError(String),
 
+    /// The user pressed Ctrl-C; reported as a notice with exit status 130, not an error
+    #[error("aborted")]
+    Interrupted,
+
     #[error(transparent)]
     ProviderError(#[from] ProviderError),
 
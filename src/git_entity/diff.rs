@@ -1,3 +1,4 @@
+use crate::command::diff::git::strip_ansi_codes;
 use crate::error::LumenError;
 use thiserror::Error;
 
@@ -5,6 +6,9 @@ use thiserror::Error;
 pub enum DiffError {
     #[error("diff{} is empty", if *staged { " (staged)" } else { "" })]
     EmptyDiff { staged: bool },
+
+    #[error("input is not a unified diff (expected `diff --git`, `---`/`+++` or `@@` lines)")]
+    NotADiff,
 }
 
 #[derive(Clone, Debug)]
@@ -18,6 +22,10 @@ pub enum Diff {
         to: String,
         diff: String,
     },
+    /// A diff produced outside lumen, e.g. piped in on stdin
+    Patch {
+        diff: String,
+    },
 }
 
 impl Diff {
@@ -36,4 +44,300 @@ impl Diff {
         }
         Ok(Diff::CommitsRange { from, to, diff })
     }
+
+    /// Create a diff from unified diff text of unknown origin, rejecting anything
+    /// that has no file or hunk headers. Colors from `--color=always` are stripped.
+    pub fn from_patch(diff: String) -> Result<Self, LumenError> {
+        let diff = strip_ansi_codes(&diff);
+        if diff.trim().is_empty() {
+            return Err(DiffError::EmptyDiff { staged: false }.into());
+        }
+        let is_diff = diff.lines().any(|line| {
+            line.starts_with("diff ") || line.starts_with("+++ ") || line.starts_with("@@ ")
+        });
+        if !is_diff {
+            return Err(DiffError::NotADiff.into());
+        }
+        Ok(Diff::Patch { diff })
+    }
+}
+
+/// Split a unified diff into per-file sections, keyed by the new-side path.
+/// Text before the first `diff ` header is dropped.
+pub fn split_by_file(diff: &str) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        if let Some(header) = line.strip_prefix("diff ") {
+            let name = header
+                .rsplit_once(" b/")
+                .map(|(_, path)| path.trim_end().to_string())
+                .unwrap_or_else(|| header.trim_end().to_string());
+            files.push((name, String::new()));
+        }
+        if let Some((_, section)) = files.last_mut() {
+            section.push_str(line);
+        }
+    }
+    files
+}
+
+/// Drop whitespace-only changes from a unified diff.
+///
+/// Runs of removed/added lines that are identical once whitespace is ignored are turned
+/// into context; hunks left without changes are dropped, as are files left without hunks.
+pub fn strip_whitespace_changes(diff: &str) -> String {
+    let mut output = String::new();
+    let mut file_header: Vec<&str> = Vec::new();
+    let mut hunks: Vec<String> = Vec::new();
+    let mut hunk: Vec<&str> = Vec::new();
+    let mut had_hunks = false;
+
+    fn flush_file(output: &mut String, header: &[&str], hunks: &[String], had_hunks: bool) {
+        if header.is_empty() || (had_hunks && hunks.is_empty()) {
+            return;
+        }
+        for line in header {
+            output.push_str(line);
+            output.push('\n');
+        }
+        for hunk in hunks {
+            output.push_str(hunk);
+        }
+    }
+
+    for line in diff.lines() {
+        if line.starts_with("diff ") {
+            if let Some(h) = filter_hunk(&hunk) {
+                hunks.push(h);
+            }
+            hunk.clear();
+            flush_file(&mut output, &file_header, &hunks, had_hunks);
+            file_header.clear();
+            hunks.clear();
+            had_hunks = false;
+            file_header.push(line);
+        } else if line.starts_with("@@") {
+            if let Some(h) = filter_hunk(&hunk) {
+                hunks.push(h);
+            }
+            hunk.clear();
+            had_hunks = true;
+            hunk.push(line);
+        } else if hunk.is_empty() {
+            file_header.push(line);
+        } else {
+            hunk.push(line);
+        }
+    }
+    if let Some(h) = filter_hunk(&hunk) {
+        hunks.push(h);
+    }
+    flush_file(&mut output, &file_header, &hunks, had_hunks);
+
+    output
+}
+
+/// Rewrite one hunk (header line first) without its whitespace-only change runs.
+/// Returns `None` when nothing substantive is left.
+fn filter_hunk(lines: &[&str]) -> Option<String> {
+    let (header, body) = lines.split_first()?;
+    let mut kept: Vec<String> = Vec::new();
+    let mut has_changes = false;
+    // Lines the hunk spans on each side; a squashed run still spans its removed
+    // lines on the old side even though only its added lines are kept as context
+    let (mut old_len, mut new_len) = (0, 0);
+    let mut i = 0;
+
+    while i < body.len() {
+        if !is_change_line(body[i]) {
+            if !body[i].starts_with('\\') {
+                old_len += 1;
+                new_len += 1;
+            }
+            kept.push(body[i].to_string());
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < body.len() && (is_change_line(body[i]) || body[i].starts_with('\\')) {
+            i += 1;
+        }
+        let run = &body[start..i];
+        old_len += run.iter().filter(|l| l.starts_with('-')).count();
+        new_len += run.iter().filter(|l| l.starts_with('+')).count();
+        let squash = |prefix: char| -> String {
+            run.iter()
+                .filter_map(|l| l.strip_prefix(prefix))
+                .flat_map(|l| l.chars())
+                .filter(|c| !c.is_whitespace())
+                .collect()
+        };
+
+        if squash('-') == squash('+') {
+            kept.extend(
+                run.iter()
+                    .filter_map(|l| l.strip_prefix('+'))
+                    .map(|l| format!(" {l}")),
+            );
+        } else {
+            has_changes = true;
+            kept.extend(run.iter().map(|l| l.to_string()));
+        }
+    }
+
+    if !has_changes {
+        return None;
+    }
+
+    let mut out = rewrite_hunk_header(header, old_len, new_len);
+    out.push('\n');
+    for line in kept {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+fn is_change_line(line: &str) -> bool {
+    line.starts_with('-') || line.starts_with('+')
+}
+
+/// Replace the line counts in `@@ -a,b +c,d @@ ...`, keeping the starts and trailing text.
+fn rewrite_hunk_header(header: &str, old_len: usize, new_len: usize) -> String {
+    let mut parts = header.splitn(4, ' ');
+    let (Some("@@"), Some(old), Some(new), rest) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return header.to_string();
+    };
+    let old_start = old.trim_start_matches('-').split(',').next().unwrap_or("0");
+    let new_start = new.trim_start_matches('+').split(',').next().unwrap_or("0");
+    match rest {
+        Some(rest) => format!("@@ -{old_start},{old_len} +{new_start},{new_len} {rest}"),
+        None => format!("@@ -{old_start},{old_len} +{new_start},{new_len}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_patch_requires_diff_headers() {
+        let patch = "--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n".to_string();
+        assert!(matches!(Diff::from_patch(patch), Ok(Diff::Patch { .. })));
+
+        assert!(Diff::from_patch("  \n".to_string()).is_err());
+        assert!(Diff::from_patch("abc123\n".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_from_patch_strips_colors() {
+        let colored = "\x1b[1mdiff --git a/x b/x\x1b[m\n\x1b[36m@@ -1 +1 @@\x1b[m\n\x1b[31m-a\x1b[m\n\x1b[32m+b\x1b[m\n";
+        let Ok(Diff::Patch { diff }) = Diff::from_patch(colored.to_string()) else {
+            panic!("colored diff should parse");
+        };
+        assert_eq!(diff, "diff --git a/x b/x\n@@ -1 +1 @@\n-a\n+b\n");
+    }
+
+    #[test]
+    fn test_strip_whitespace_changes_keeps_substantive_hunks() {
+        let diff = "\
+diff --git a/src/a.rs b/src/a.rs
+index 111..222 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,3 +1,3 @@ fn main() {
+-fn a(){
++fn a() {
+     body();
+ }
+@@ -10,2 +10,2 @@
+-let x = 1;
++let x = 2;
+ end();
+diff --git a/src/b.rs b/src/b.rs
+index 333..444 100644
+--- a/src/b.rs
++++ b/src/b.rs
+@@ -1,2 +1,3 @@
+ one();
++
+ two();
+";
+        let stripped = strip_whitespace_changes(diff);
+
+        assert!(stripped.contains("diff --git a/src/a.rs b/src/a.rs"));
+        assert!(!stripped.contains("fn a()"));
+        assert!(stripped.contains("@@ -10,2 +10,2 @@\n-let x = 1;\n+let x = 2;\n end();\n"));
+        assert!(!stripped.contains("src/b.rs"));
+    }
+
+    #[test]
+    fn test_split_by_file() {
+        let diff = "\
+diff --git a/src/a.rs b/src/a.rs
+@@ -1 +1 @@
+-a
++b
+diff --git a/old.txt b/new.txt
+similarity index 100%
+";
+        let files = split_by_file(diff);
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "src/a.rs");
+        assert_eq!(files[0].1, "diff --git a/src/a.rs b/src/a.rs\n@@ -1 +1 @@\n-a\n+b\n");
+        assert_eq!(files[1].0, "new.txt");
+        assert!(files[1].1.ends_with("similarity index 100%\n"));
+    }
+
+    #[test]
+    fn test_strip_whitespace_changes_rewrites_hunk_counts() {
+        let diff = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,2 +1,3 @@
+-  indented
++indented
++added
+ tail
+";
+        let stripped = strip_whitespace_changes(diff);
+        assert!(stripped.contains("@@ -1,2 +1,3 @@\n-  indented\n+indented\n+added\n tail\n"));
+
+        let diff = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,2 @@
+-  indented
++indented
+ tail
+-
+";
+        assert_eq!(strip_whitespace_changes(diff), "");
+    }
+
+    #[test]
+    fn test_strip_whitespace_changes_counts_unequal_runs() {
+        // The whitespace-only run removes two lines and adds one
+        let diff = "\
+diff --git a/a.txt b/a.txt
+--- a/a.txt
++++ b/a.txt
+@@ -1,5 +1,4 @@
+-foo(a,
+-    b)
++foo(a, b)
+ mid
+-old
++new
+ tail
+";
+        let stripped = strip_whitespace_changes(diff);
+        assert!(stripped.contains("@@ -1,5 +1,4 @@\n foo(a, b)\n mid\n-old\n+new\n tail\n"));
+    }
 }
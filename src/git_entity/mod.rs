@@ -2,7 +2,7 @@ use commit::Commit;
 use diff::Diff;
 use indoc::formatdoc;
 
-use crate::provider::LumenProvider;
+use crate::provider::AiProvider;
 
 pub mod commit;
 pub mod diff;
@@ -14,7 +14,45 @@ pub enum GitEntity {
 }
 
 impl GitEntity {
-    pub fn format_static_details(&self, provider: &LumenProvider) -> String {
+    /// The raw unified diff carried by this entity.
+    pub fn diff(&self) -> &str {
+        match self {
+            GitEntity::Commit(commit) => &commit.diff,
+            GitEntity::Diff(
+                Diff::WorkingTree { diff, .. }
+                | Diff::CommitsRange { diff, .. }
+                | Diff::Patch { diff },
+            ) => diff,
+        }
+    }
+
+    /// This entity with its whitespace-only changes dropped (see
+    /// `diff::strip_whitespace_changes`), or None when nothing else is left.
+    pub fn without_whitespace_changes(mut self) -> Option<Self> {
+        let diff = match &mut self {
+            GitEntity::Commit(commit) => &mut commit.diff,
+            GitEntity::Diff(
+                Diff::WorkingTree { diff, .. }
+                | Diff::CommitsRange { diff, .. }
+                | Diff::Patch { diff },
+            ) => diff,
+        };
+        *diff = diff::strip_whitespace_changes(diff);
+        (!diff.trim().is_empty()).then_some(self)
+    }
+
+    /// What was explained: the full commit hash, `from..to`, or the working tree.
+    pub fn reference(&self) -> String {
+        match self {
+            GitEntity::Commit(commit) => commit.full_hash.clone(),
+            GitEntity::Diff(Diff::WorkingTree { staged: true, .. }) => "staged".to_string(),
+            GitEntity::Diff(Diff::WorkingTree { staged: false, .. }) => "working tree".to_string(),
+            GitEntity::Diff(Diff::CommitsRange { from, to, .. }) => format!("{}..{}", from, to),
+            GitEntity::Diff(Diff::Patch { .. }) => "stdin".to_string(),
+        }
+    }
+
+    pub fn format_static_details(&self, provider: &dyn AiProvider) -> String {
         match self {
             GitEntity::Commit(commit) => formatdoc! {"
                 # Entity: Commit
@@ -40,6 +78,10 @@ impl GitEntity {
                 `{from}` -> `{to}`
                 # Provider: {provider}
             "},
+            GitEntity::Diff(Diff::Patch { .. }) => formatdoc! {"
+                # Entity: Diff from stdin
+                # Provider: {provider}"
+            },
         }
     }
 }
@@ -1,13 +1,13 @@
 use clap::Parser;
 use command::LumenCommand;
 use commit_reference::CommitReference;
-use config::cli::{Cli, Commands};
+use config::cli::{Cli, Commands, DiffArgs};
 use config::LumenConfig;
 use error::LumenError;
 use git_entity::{commit::Commit, diff::Diff, GitEntity};
-use std::io::Read;
+use std::io::{IsTerminal, Read};
 use std::process;
-use vcs::VcsBackendType;
+use vcs::{VcsBackend, VcsBackendType};
 
 mod ai_prompt;
 mod command;
@@ -20,9 +20,16 @@ mod vcs;
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        eprintln!("\x1b[91m\rerror:\x1b[0m {e}");
-        process::exit(1);
+    match run().await {
+        Ok(()) => {}
+        Err(LumenError::Interrupted) => {
+            eprintln!("aborted");
+            process::exit(130);
+        }
+        Err(e) => {
+            eprintln!("\x1b[91m\rerror:\x1b[0m {e}");
+            process::exit(1);
+        }
     }
 }
 
@@ -34,13 +41,43 @@ async fn run() -> Result<(), LumenError> {
         Err(e) => return Err(e),
     };
 
-    let provider = provider::LumenProvider::new(config.provider, config.api_key, config.model)?;
-    let command = command::LumenCommand::new(provider);
-
     // Get VCS backend based on CLI override or auto-detection
     let cwd = std::env::current_dir()?;
     let vcs_override = cli.vcs.map(VcsBackendType::from);
-    let backend = vcs::get_backend(&cwd, vcs_override)?;
+    let backend = vcs::get_backend(&cwd, vcs_override);
+
+    // The checklist reports a missing repository instead of failing on it
+    if let Commands::Doctor = cli.command {
+        let repository = backend
+            .as_ref()
+            .map(|b| b.name())
+            .map_err(|e| e.to_string());
+        return command::doctor::DoctorCommand::execute(&config, repository).await;
+    }
+    // A piped diff is explained as is, so it needs no repository
+    if let Commands::Explain {
+        stdin_diff: true,
+        query,
+        chunked,
+        no_stream,
+        ignore_whitespace,
+        format,
+        ..
+    } = cli.command
+    {
+        let git_entity = GitEntity::Diff(Diff::from_patch(read_diff_from_stdin()?)?);
+        return build_command(&config)?
+            .execute(command::CommandType::Explain {
+                git_entity,
+                query,
+                chunked,
+                no_stream,
+                ignore_whitespace,
+                format,
+            })
+            .await;
+    }
+    let backend = backend?;
 
     match cli.command {
         Commands::Explain {
@@ -48,13 +85,18 @@ async fn run() -> Result<(), LumenError> {
             staged,
             query,
             list,
+            chunked,
+            no_stream,
+            ignore_whitespace,
+            format,
+            stdin_diff: _,
         } => {
             let git_entity = if list {
                 let sha = LumenCommand::get_sha_from_fzf(backend.as_ref())?;
                 let info = backend.get_commit(&sha)?;
                 GitEntity::Commit(Commit::from_commit_info(info))
             } else {
-                match reference {
+                match expand_revset_range(reference, backend.as_ref())? {
                     Some(CommitReference::Single(input)) => {
                         let sha = if input == "-" {
                             read_from_stdin()?
@@ -80,63 +122,404 @@ async fn run() -> Result<(), LumenError> {
                 }
             };
 
-            command
-                .execute(command::CommandType::Explain { git_entity, query })
+            build_command(&config)?
+                .execute(command::CommandType::Explain {
+                    git_entity,
+                    query,
+                    chunked,
+                    no_stream,
+                    ignore_whitespace,
+                    format,
+                })
                 .await?;
         }
         Commands::List => {
             eprintln!("Warning: 'lumen list' is deprecated. Use 'lumen explain --list' instead.");
-            command
+            build_command(&config)?
                 .execute(command::CommandType::List {
                     backend: backend.as_ref(),
                 })
                 .await?
         }
-        Commands::Draft { context } => {
-            // Draft always uses staged diff (git convention)
-            let diff = backend.get_working_tree_diff(true)?;
-            let git_entity = GitEntity::Diff(Diff::from_working_tree_diff(diff, true)?);
-            command
+        Commands::Draft {
+            context,
+            ignore_whitespace,
+            amend,
+        } => {
+            let git_entity = if amend {
+                draft_amend_entity(backend.as_ref())?
+            } else {
+                // Draft uses the staged diff (git convention)
+                let diff = backend.get_working_tree_diff(true)?;
+                GitEntity::Diff(Diff::from_working_tree_diff(diff, true)?)
+            };
+            build_command(&config)?
                 .execute(command::CommandType::Draft {
                     git_entity,
                     context,
                     draft_config: config.draft,
+                    ignore_whitespace,
+                    amend,
                 })
                 .await?
         }
         Commands::Operate { query } => {
-            command
+            build_command(&config)?
                 .execute(command::CommandType::Operate { query })
                 .await?;
         }
-        Commands::Diff {
-            reference,
-            pr,
-            file,
-            watch,
-            theme,
-            stacked,
-            focus,
-        } => {
-            let options = command::diff::DiffOptions {
+        Commands::Diff(args) => {
+            let DiffArgs {
                 reference,
+                against,
+                history,
                 pr,
+                mr,
+                my_pr,
+                upstream,
+                branch_only,
+                amend,
+                in_progress,
+                conflicts,
                 file,
                 watch,
+                watch_interval,
+                theme,
+                stacked,
+                focus,
+                overview,
+                grep,
+                export_patch,
+                stat,
+                export_html,
+                tree_depth,
+                recurse_submodules,
+                no_highlight,
+                verbose,
+            } = *args;
+            let reference = if upstream {
+                Some(upstream_reference(
+                    backend.as_ref(),
+                    config.default_branch.as_deref(),
+                )?)
+            } else if branch_only {
+                Some(branch_only_reference(
+                    reference,
+                    backend.as_ref(),
+                    config.default_branch.as_deref(),
+                )?)
+            } else if amend {
+                Some(amend_reference(backend.as_ref())?)
+            } else {
+                expand_revset_range(reference, backend.as_ref())?
+            };
+            let in_progress = if in_progress {
+                Some(backend.operation_in_progress()?.ok_or_else(|| {
+                    LumenError::InvalidArguments(
+                        "no rebase or cherry-pick is in progress".to_string(),
+                    )
+                })?)
+            } else {
+                None
+            };
+            let file = if conflicts {
+                let files = backend.get_conflicted_files()?;
+                if files.is_empty() {
+                    return Err(LumenError::InvalidArguments(
+                        "no files in the working tree have conflict markers".to_string(),
+                    ));
+                }
+                Some(files)
+            } else {
+                file
+            };
+            let pr_forge = mr.is_some().then_some(command::diff::Forge::GitLab);
+            let pr = if my_pr {
+                Some(
+                    command::diff::pick_own_pr(
+                        config.github_host.as_deref(),
+                        theme.as_deref().or(config.theme.as_deref()),
+                    )
+                    .map_err(LumenError::CommandError)?,
+                )
+            } else {
+                pr.or(mr)
+            };
+            let options = command::diff::DiffOptions {
+                reference,
+                against,
+                history,
+                pr,
+                pr_forge,
+                file,
+                watch: watch || watch_interval.is_some(),
+                watch_interval,
                 theme: theme.or(config.theme.clone()),
                 stacked,
                 focus,
+                overview,
+                grep: grep
+                    .as_deref()
+                    .map(command::diff::GrepFilter::new)
+                    .transpose()
+                    .map_err(LumenError::InvalidArguments)?,
+                export_patch,
+                stat,
+                export_html,
+                tree_depth,
+                recurse_submodules,
+                highlight: !no_highlight && config.syntax,
+                verbose,
+                languages: config.languages.clone().into_iter().collect(),
+                tab_widths: config
+                    .tab_widths
+                    .iter()
+                    .map(|(ext, width)| (ext.trim_start_matches('.').to_string(), *width))
+                    .collect(),
+                no_highlight: config
+                    .no_highlight
+                    .iter()
+                    .map(|ext| ext.trim_start_matches('.').to_string())
+                    .collect(),
+                indicators: command::diff::indicators::StatusIndicators::from_config(
+                    &config.file_status,
+                )
+                .map_err(LumenError::InvalidConfiguration)?,
+                hide_whitespace_emphasis: config.hide_whitespace_emphasis,
+                tab_glyph: config.tab_glyph.as_deref().and_then(|g| g.chars().next()),
+                amend,
+                in_progress,
+                conflicts,
+                max_pr_files: config.max_pr_files,
+                annotation_limit: config.annotation_limit,
+                annotation_export: config.annotation_export.item.clone().map(|item| {
+                    command::diff::ExportTemplate {
+                        header: config.annotation_export.header.clone().unwrap_or_default(),
+                        item,
+                        footer: config.annotation_export.footer.clone().unwrap_or_default(),
+                        csv: config.annotation_export.csv,
+                    }
+                }),
+                view_mode: config
+                    .view_mode
+                    .as_deref()
+                    .map(command::diff::DiffViewMode::parse)
+                    .transpose()
+                    .map_err(LumenError::InvalidConfiguration)?
+                    .unwrap_or_default(),
+                panel_borders: config
+                    .panel_borders
+                    .as_deref()
+                    .map(command::diff::PanelBorders::parse)
+                    .transpose()
+                    .map_err(LumenError::InvalidConfiguration)?
+                    .unwrap_or_default(),
+                search_wrap: config.search_wrap,
+                fold_threshold: config.fold_threshold,
+                idle_redraw: config.idle_redraw,
+                confirm_quit: config.confirm_quit,
+                github_host: config.github_host.clone(),
+                clipboard: config
+                    .clipboard
+                    .as_deref()
+                    .map(|method| {
+                        command::diff::ClipboardMethod::parse(
+                            method,
+                            config.clipboard_command.as_deref(),
+                        )
+                    })
+                    .transpose()
+                    .map_err(LumenError::InvalidConfiguration)?
+                    .unwrap_or_default(),
+                ai: command::diff::AiSettings {
+                    provider: config.provider,
+                    api_key: config.api_key.clone(),
+                    model: config.model.clone(),
+                },
             };
             command::diff::run_diff_ui(options, backend.as_ref())?;
         }
         Commands::Configure => {
             command::configure::ConfigureCommand::execute()?;
         }
+        Commands::Providers { check } => {
+            command::providers::ProvidersCommand::execute(&config, check).await?;
+        }
+        Commands::Models => {
+            command::models::ModelsCommand::execute(&config).await?;
+        }
+        Commands::Doctor => unreachable!("handled before the repository is required"),
     }
 
     Ok(())
 }
 
+/// Construct the provider-backed command runner. Only the AI subcommands call this,
+/// so `diff` and `configure` work without any provider or API key configured.
+fn build_command(config: &LumenConfig) -> Result<LumenCommand, LumenError> {
+    let provider = provider::LumenProvider::new(
+        config.provider,
+        config.api_key.clone(),
+        config.model.clone(),
+    )?;
+    if config.fallback_providers.is_empty() {
+        return Ok(LumenCommand::new(Box::new(provider)));
+    }
+    let mut providers: Vec<Box<dyn provider::AiProvider>> = vec![Box::new(provider)];
+    for fallback in &config.fallback_providers {
+        providers.push(Box::new(provider::LumenProvider::new(
+            fallback.provider,
+            fallback.api_key.clone(),
+            fallback.model.clone(),
+        )?));
+    }
+    let provider = provider::FallbackProvider::new(providers);
+    Ok(LumenCommand::new(Box::new(provider)))
+}
+
+/// Build `<upstream>...HEAD` for `lumen diff --upstream`: the changes a push would publish.
+/// Without a tracking branch the base is the configured `default_branch`, and only when
+/// that is unset the remote's default branch.
+fn upstream_reference(
+    backend: &dyn VcsBackend,
+    default_branch: Option<&str>,
+) -> Result<CommitReference, LumenError> {
+    let base = match backend.get_upstream_branch()? {
+        Some(upstream) => Some(upstream),
+        None => default_base(backend, default_branch)?,
+    };
+    match base {
+        Some(base) => Ok(CommitReference::TripleDots {
+            from: base,
+            to: "HEAD".to_string(),
+        }),
+        None => Err(LumenError::InvalidArguments(
+            "the current branch has no upstream tracking branch and origin/HEAD is not set. \
+             Set one with `git branch --set-upstream-to=<remote>/<branch>`, add \
+             `\"default_branch\": \"main\"` to the config, or pass the base explicitly \
+             (e.g. `lumen diff origin/main...HEAD`)"
+                .to_string(),
+        )),
+    }
+}
+
+/// The configured `default_branch`, or the remote's default branch when that is unset.
+fn default_base(
+    backend: &dyn VcsBackend,
+    default_branch: Option<&str>,
+) -> Result<Option<String>, LumenError> {
+    match default_branch {
+        Some(branch) => Ok(Some(branch.to_string())),
+        None => Ok(backend.get_default_branch()?),
+    }
+}
+
+/// Build `<base>...<branch>` for `lumen diff --branch-only`: the diff from the merge base
+/// to the branch tip, so base-branch commits merged into the branch drop out. `A..B` keeps
+/// its ends; a single reference (or none, meaning `HEAD`) is set against the default branch.
+fn branch_only_reference(
+    reference: Option<CommitReference>,
+    backend: &dyn VcsBackend,
+    default_branch: Option<&str>,
+) -> Result<CommitReference, LumenError> {
+    let tip = match reference {
+        Some(CommitReference::Range { from, to } | CommitReference::TripleDots { from, to }) => {
+            return Ok(CommitReference::TripleDots { from, to });
+        }
+        Some(CommitReference::Single(tip)) => tip,
+        None => "HEAD".to_string(),
+    };
+    match default_base(backend, default_branch)? {
+        Some(base) => Ok(CommitReference::TripleDots {
+            from: base,
+            to: tip,
+        }),
+        None => Err(LumenError::InvalidArguments(
+            "--branch-only needs a base branch and origin/HEAD is not set. Add \
+             `\"default_branch\": \"main\"` to the config, or pass the range explicitly \
+             (e.g. `lumen diff --branch-only origin/main..HEAD`)"
+                .to_string(),
+        )),
+    }
+}
+
+/// HEAD's own changes for `lumen draft --amend`. Refuses when something is staged,
+/// since `git commit --amend` would fold it into the commit along with the new message.
+fn draft_amend_entity(backend: &dyn VcsBackend) -> Result<GitEntity, LumenError> {
+    if backend.name() != "git" {
+        return Err(LumenError::InvalidArguments(
+            "--amend runs `git commit --amend`; with jj, use `jj describe` instead".to_string(),
+        ));
+    }
+    if !backend.get_working_tree_diff(true)?.is_empty() {
+        return Err(LumenError::InvalidArguments(
+            "staged changes would be swept into the amend; commit or unstage them first"
+                .to_string(),
+        ));
+    }
+    let commit = Commit::from_commit_info(backend.get_commit("HEAD")?);
+    if commit.diff.is_empty() {
+        return Err(LumenError::InvalidArguments(
+            "HEAD changes no files; nothing to draft a message from".to_string(),
+        ));
+    }
+    Ok(GitEntity::Commit(commit))
+}
+
+/// The range from the commit the last amend replaced (`HEAD@{1}`) to `HEAD`.
+fn amend_reference(backend: &dyn VcsBackend) -> Result<CommitReference, LumenError> {
+    if backend.name() != "git" {
+        return Err(LumenError::InvalidArguments(
+            "--amend reads git's reflog; with jj, compare versions from `jj evolog` instead"
+                .to_string(),
+        ));
+    }
+    let head = backend.get_commit_metadata("HEAD")?;
+    let before = backend.get_commit_metadata("HEAD@{1}").map_err(|_| {
+        LumenError::InvalidArguments("HEAD has no previous position in the reflog".to_string())
+    })?;
+    // An amend keeps the parents; anything else means HEAD moved some other way last
+    if before.commit_id == head.commit_id || before.parents != head.parents {
+        return Err(LumenError::InvalidArguments(
+            "the last change to HEAD was not an amend (HEAD@{1} is not an earlier version of HEAD)"
+                .to_string(),
+        ));
+    }
+    Ok(CommitReference::Range {
+        from: before.commit_id,
+        to: head.commit_id,
+    })
+}
+
+/// Turn a single reference that names several commits (a jj revset such as
+/// `main::@` or `mine() & ::@`) into the equivalent range diff.
+fn expand_revset_range(
+    reference: Option<CommitReference>,
+    backend: &dyn VcsBackend,
+) -> Result<Option<CommitReference>, LumenError> {
+    match reference {
+        Some(CommitReference::Single(input)) if input != "-" => {
+            match backend.resolve_revset_range(&input)? {
+                Some((from, to)) => Ok(Some(CommitReference::Range { from, to })),
+                None => Ok(Some(CommitReference::Single(input))),
+            }
+        }
+        other => Ok(other),
+    }
+}
+
+fn read_diff_from_stdin() -> Result<String, LumenError> {
+    if std::io::stdin().is_terminal() {
+        return Err(LumenError::InvalidArguments(
+            "--stdin-diff reads a diff from stdin, e.g. `git diff | lumen explain --stdin-diff`"
+                .to_string(),
+        ));
+    }
+    let mut buffer = String::new();
+    std::io::stdin().read_to_string(&mut buffer)?;
+    Ok(buffer)
+}
+
 fn read_from_stdin() -> Result<String, LumenError> {
     let mut buffer = String::new();
     std::io::stdin().read_to_string(&mut buffer)?;
@@ -144,3 +527,74 @@ fn read_from_stdin() -> Result<String, LumenError> {
     eprintln!("Reading commit SHA from stdin: '{}'", buffer.trim());
     Ok(buffer)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::test_utils::{git, RepoGuard};
+    use crate::vcs::GitBackend;
+
+    #[test]
+    fn test_branch_only_reference_diffs_from_the_merge_base() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        // An explicit range keeps its ends and gains three-dot semantics
+        let range = CommitReference::Range {
+            from: "main".to_string(),
+            to: "feature".to_string(),
+        };
+        assert_eq!(
+            branch_only_reference(Some(range), &backend, None).unwrap(),
+            CommitReference::TripleDots {
+                from: "main".to_string(),
+                to: "feature".to_string(),
+            }
+        );
+
+        // A single tip, or none for HEAD, is set against the configured default branch
+        assert_eq!(
+            branch_only_reference(
+                Some(CommitReference::Single("feature".to_string())),
+                &backend,
+                Some("trunk"),
+            )
+            .unwrap(),
+            CommitReference::TripleDots {
+                from: "trunk".to_string(),
+                to: "feature".to_string(),
+            }
+        );
+        assert_eq!(
+            branch_only_reference(None, &backend, Some("trunk")).unwrap(),
+            CommitReference::TripleDots {
+                from: "trunk".to_string(),
+                to: "HEAD".to_string(),
+            }
+        );
+
+        // Without a remote there is no default branch to fall back on
+        assert!(matches!(
+            branch_only_reference(None, &backend, None),
+            Err(LumenError::InvalidArguments(_))
+        ));
+    }
+
+    #[test]
+    fn test_draft_amend_entity_refuses_staged_changes() {
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        match draft_amend_entity(&backend) {
+            Ok(GitEntity::Commit(commit)) => assert!(commit.diff.contains("README.md")),
+            _ => panic!("a clean tree drafts from HEAD's own changes"),
+        }
+
+        std::fs::write(repo.dir.join("notes.txt"), "staged\n").unwrap();
+        git(&repo.dir, &["add", "notes.txt"]);
+        assert!(matches!(
+            draft_amend_entity(&backend),
+            Err(LumenError::InvalidArguments(_))
+        ));
+    }
+}
@@ -1,7 +1,14 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::StreamExt;
 use genai::adapter::AdapterKind;
-use genai::chat::{ChatMessage, ChatRequest};
+use genai::chat::{ChatMessage, ChatRequest, ChatStreamEvent};
 use genai::resolver::{AuthData, Endpoint, ServiceTargetResolver};
+use genai::webc;
 use genai::{Client, ClientBuilder, ModelIden, ServiceTarget};
+use reqwest::StatusCode;
 use thiserror::Error;
 
 use crate::ai_prompt::{AIPrompt, AIPromptError};
@@ -25,6 +32,145 @@ pub enum ProviderError {
     AIPromptError(#[from] AIPromptError),
 }
 
+impl ProviderError {
+    /// Whether another provider might succeed where this one failed: rate limits,
+    /// server and network errors. Bad credentials or a bad prompt fail everywhere.
+    pub fn is_retryable(&self) -> bool {
+        let retryable_status = |status: StatusCode| {
+            status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        };
+        match self {
+            ProviderError::GenAIError(error) => match error {
+                genai::Error::WebModelCall { webc_error, .. }
+                | genai::Error::WebAdapterCall { webc_error, .. } => match webc_error {
+                    webc::Error::ResponseFailedStatus { status, .. } => retryable_status(*status),
+                    webc::Error::Reqwest(_) => true,
+                    _ => false,
+                },
+                genai::Error::ReqwestEventSource(error) => match error.as_ref() {
+                    reqwest_eventsource::Error::InvalidStatusCode(status, _) => {
+                        retryable_status(*status)
+                    }
+                    reqwest_eventsource::Error::Transport(_) => true,
+                    _ => false,
+                },
+                genai::Error::WebStream { .. } | genai::Error::NoChatResponse { .. } => true,
+                _ => false,
+            },
+            ProviderError::RequestError(_) | ProviderError::NoCompletionChoice => true,
+            ProviderError::AIPromptError(_) => false,
+        }
+    }
+}
+
+/// A single prompt sent to an AI provider.
+#[derive(Clone)]
+pub struct CompletionRequest {
+    pub system_prompt: String,
+    pub user_prompt: String,
+}
+
+impl From<AIPrompt> for CompletionRequest {
+    fn from(prompt: AIPrompt) -> Self {
+        Self {
+            system_prompt: prompt.system_prompt,
+            user_prompt: prompt.user_prompt,
+        }
+    }
+}
+
+/// The provider's reply to a `CompletionRequest`.
+pub struct CompletionResponse {
+    pub text: String,
+}
+
+pub type CompletionFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<CompletionResponse, LumenError>> + Send + 'a>>;
+
+/// Receives a streamed reply piece by piece.
+pub type ChunkHandler<'a> = &'a mut (dyn FnMut(&str) + Send);
+
+/// Abstraction over AI backends. The command layer only depends on this trait,
+/// so new providers plug in here and tests can substitute a fake.
+pub trait AiProvider: std::fmt::Display + Send + Sync {
+    fn complete(&self, request: CompletionRequest) -> CompletionFuture<'_>;
+
+    /// Like `complete`, but hands the reply to `on_chunk` as it is generated. Providers
+    /// that cannot stream send the whole reply as one chunk once it arrives.
+    fn complete_streaming<'a>(
+        &'a self,
+        request: CompletionRequest,
+        on_chunk: ChunkHandler<'a>,
+    ) -> CompletionFuture<'a> {
+        Box::pin(async move {
+            let response = self.complete(request).await?;
+            on_chunk(&response.text);
+            Ok(response)
+        })
+    }
+
+    /// Human-readable provider name, e.g. "OpenAI"
+    fn name(&self) -> &str;
+
+    /// Model the requests are sent to
+    fn model(&self) -> String;
+
+    /// The provider that answered the last request, when it was a fallback rather
+    /// than the first choice
+    fn fallback_used(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Prompt-building helpers shared by every provider
+impl dyn AiProvider + '_ {
+    async fn complete_prompt(&self, prompt: AIPrompt) -> Result<String, LumenError> {
+        Ok(self.complete(prompt.into()).await?.text)
+    }
+
+    pub async fn explain(&self, command: &ExplainCommand) -> Result<String, LumenError> {
+        let prompt = AIPrompt::build_explain_prompt(command).map_err(ProviderError::from)?;
+        self.complete_prompt(prompt).await
+    }
+
+    /// `explain`, with the reply passed to `on_chunk` as it is generated.
+    pub async fn explain_streaming(
+        &self,
+        command: &ExplainCommand,
+        on_chunk: ChunkHandler<'_>,
+    ) -> Result<String, LumenError> {
+        let prompt = AIPrompt::build_explain_prompt(command).map_err(ProviderError::from)?;
+        Ok(self.complete_streaming(prompt.into(), on_chunk).await?.text)
+    }
+
+    pub async fn draft(&self, command: &DraftCommand) -> Result<String, LumenError> {
+        let prompt = AIPrompt::build_draft_prompt(command).map_err(ProviderError::from)?;
+        self.complete_prompt(prompt).await
+    }
+
+    pub async fn explain_file(&self, filename: &str, diff: &str) -> Result<String, LumenError> {
+        let prompt =
+            AIPrompt::build_file_explain_prompt(filename, diff).map_err(ProviderError::from)?;
+        self.complete_prompt(prompt).await
+    }
+
+    pub async fn summarize_explanations(
+        &self,
+        command: &ExplainCommand,
+        explanations: &[(String, String)],
+    ) -> Result<String, LumenError> {
+        let prompt = AIPrompt::build_chunk_summary_prompt(command, explanations)
+            .map_err(ProviderError::from)?;
+        self.complete_prompt(prompt).await
+    }
+
+    pub async fn operate(&self, command: &OperateCommand) -> Result<String, LumenError> {
+        let prompt =
+            AIPrompt::build_operate_prompt(command.query.as_str()).map_err(ProviderError::from)?;
+        self.complete_prompt(prompt).await
+    }
+}
+
 enum ProviderBackend {
     GenAI { client: Client, model: String },
 }
@@ -74,11 +220,11 @@ impl LumenProvider {
                 let model = model.unwrap_or_else(|| defaults.default_model.to_string());
                 let model_for_resolver = model.clone();
 
-                // Get API key from CLI/config or environment
-                let auth_env_key = config.env_key;
-                if let Some(key) = api_key {
-                    std::env::set_var(auth_env_key, key);
-                }
+                // API key from CLI/config, else the provider's environment variable
+                let auth = match api_key {
+                    Some(key) => AuthData::from_single(key),
+                    None => AuthData::from_env(config.env_key),
+                };
 
                 let endpoint = config.endpoint;
                 let adapter_kind = config.adapter_kind;
@@ -88,7 +234,7 @@ impl LumenProvider {
                         let ServiceTarget { model, .. } = service_target;
                         Ok(ServiceTarget {
                             endpoint: Endpoint::from_static(endpoint),
-                            auth: AuthData::from_env(auth_env_key),
+                            auth: auth.clone(),
                             model: ModelIden::new(adapter_kind, model.model_name),
                         })
                     },
@@ -112,18 +258,21 @@ impl LumenProvider {
 
                 let model = model.unwrap_or_else(|| defaults.default_model.to_string());
 
-                // If api_key provided via CLI/config, set it in env so genai picks it up
-                if let Some(key) = api_key {
-                    if !defaults.env_key.is_empty() {
-                        std::env::set_var(defaults.env_key, key);
-                    }
-                }
+                // An api_key from CLI/config goes to this client only; the environment is
+                // shared, so a fallback with the same provider would overwrite it there.
+                let client = match api_key {
+                    Some(key) if !defaults.env_key.is_empty() => ClientBuilder::default()
+                        .with_auth_resolver_fn(
+                            move |_: ModelIden| -> Result<Option<AuthData>, genai::resolver::Error> {
+                                Ok(Some(AuthData::from_single(key.clone())))
+                            },
+                        )
+                        .build(),
+                    _ => Client::default(),
+                };
 
                 (
-                    ProviderBackend::GenAI {
-                        client: Client::default(),
-                        model,
-                    },
+                    ProviderBackend::GenAI { client, model },
                     defaults.display_name.to_string(),
                 )
             }
@@ -135,12 +284,12 @@ impl LumenProvider {
         })
     }
 
-    async fn complete(&self, prompt: AIPrompt) -> Result<String, ProviderError> {
+    async fn complete_genai(&self, request: CompletionRequest) -> Result<String, ProviderError> {
         match &self.backend {
             ProviderBackend::GenAI { client, model } => {
                 let chat_req = ChatRequest::new(vec![
-                    ChatMessage::system(prompt.system_prompt),
-                    ChatMessage::user(prompt.user_prompt),
+                    ChatMessage::system(request.system_prompt),
+                    ChatMessage::user(request.user_prompt),
                 ]);
 
                 let response = client.exec_chat(model, chat_req, None).await?;
@@ -153,19 +302,32 @@ impl LumenProvider {
         }
     }
 
-    pub async fn explain(&self, command: &ExplainCommand) -> Result<String, ProviderError> {
-        let prompt = AIPrompt::build_explain_prompt(command)?;
-        self.complete(prompt).await
-    }
-
-    pub async fn draft(&self, command: &DraftCommand) -> Result<String, ProviderError> {
-        let prompt = AIPrompt::build_draft_prompt(command)?;
-        self.complete(prompt).await
-    }
+    async fn stream_genai(
+        &self,
+        request: CompletionRequest,
+        on_chunk: ChunkHandler<'_>,
+    ) -> Result<String, ProviderError> {
+        match &self.backend {
+            ProviderBackend::GenAI { client, model } => {
+                let chat_req = ChatRequest::new(vec![
+                    ChatMessage::system(request.system_prompt),
+                    ChatMessage::user(request.user_prompt),
+                ]);
 
-    pub async fn operate(&self, command: &OperateCommand) -> Result<String, ProviderError> {
-        let prompt = AIPrompt::build_operate_prompt(command.query.as_str())?;
-        self.complete(prompt).await
+                let mut stream = client.exec_chat_stream(model, chat_req, None).await?.stream;
+                let mut text = String::new();
+                while let Some(event) = stream.next().await {
+                    if let ChatStreamEvent::Chunk(chunk) = event? {
+                        on_chunk(&chunk.content);
+                        text.push_str(&chunk.content);
+                    }
+                }
+                if text.is_empty() {
+                    return Err(ProviderError::NoCompletionChoice);
+                }
+                Ok(text)
+            }
+        }
     }
 
     fn get_model(&self) -> String {
@@ -175,8 +337,417 @@ impl LumenProvider {
     }
 }
 
+impl AiProvider for LumenProvider {
+    fn complete(&self, request: CompletionRequest) -> CompletionFuture<'_> {
+        Box::pin(async move {
+            let text = self.complete_genai(request).await?;
+            Ok(CompletionResponse { text })
+        })
+    }
+
+    fn complete_streaming<'a>(
+        &'a self,
+        request: CompletionRequest,
+        on_chunk: ChunkHandler<'a>,
+    ) -> CompletionFuture<'a> {
+        Box::pin(async move {
+            // A provider or model that can't stream fails before its first chunk; ask
+            // it again without streaming and hand over the reply in one piece
+            let mut streamed = false;
+            let mut relay = |chunk: &str| {
+                streamed = true;
+                on_chunk(chunk);
+            };
+            let text = match self.stream_genai(request.clone(), &mut relay).await {
+                Ok(text) => text,
+                Err(_) if !streamed => {
+                    let text = self.complete_genai(request).await?;
+                    on_chunk(&text);
+                    text
+                }
+                Err(e) => return Err(e.into()),
+            };
+            Ok(CompletionResponse { text })
+        })
+    }
+
+    fn name(&self) -> &str {
+        &self.provider_name
+    }
+
+    fn model(&self) -> String {
+        self.get_model()
+    }
+}
+
 impl std::fmt::Display for LumenProvider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} ({})", self.provider_name, self.get_model())
     }
 }
+
+/// Providers tried in order, from the `fallback_providers` setting: the next one is
+/// asked only when the previous failed with a retryable error (see
+/// `ProviderError::is_retryable`). Name and model are the last answering provider's.
+pub struct FallbackProvider {
+    providers: Vec<Box<dyn AiProvider>>,
+    /// Index of the provider that answered the last request
+    answered: AtomicUsize,
+}
+
+impl FallbackProvider {
+    pub fn new(providers: Vec<Box<dyn AiProvider>>) -> Self {
+        assert!(!providers.is_empty(), "FallbackProvider needs a provider");
+        Self {
+            providers,
+            answered: AtomicUsize::new(0),
+        }
+    }
+
+    fn current(&self) -> &dyn AiProvider {
+        self.providers[self.answered.load(Ordering::Relaxed)].as_ref()
+    }
+
+    /// Whether the provider at `index` failing with `error` should hand over to the next
+    fn should_fall_back(&self, index: usize, error: &LumenError) -> bool {
+        index + 1 < self.providers.len()
+            && matches!(error, LumenError::ProviderError(e) if e.is_retryable())
+    }
+}
+
+impl AiProvider for FallbackProvider {
+    fn complete(&self, request: CompletionRequest) -> CompletionFuture<'_> {
+        Box::pin(async move {
+            let mut index = 0;
+            loop {
+                match self.providers[index].complete(request.clone()).await {
+                    Ok(response) => {
+                        self.answered.store(index, Ordering::Relaxed);
+                        return Ok(response);
+                    }
+                    Err(e) if self.should_fall_back(index, &e) => index += 1,
+                    Err(e) => return Err(e),
+                }
+            }
+        })
+    }
+
+    fn complete_streaming<'a>(
+        &'a self,
+        request: CompletionRequest,
+        on_chunk: ChunkHandler<'a>,
+    ) -> CompletionFuture<'a> {
+        Box::pin(async move {
+            let mut index = 0;
+            loop {
+                // Once text has been shown, another provider's reply can't replace it
+                let mut streamed = false;
+                let mut relay = |chunk: &str| {
+                    streamed = true;
+                    on_chunk(chunk);
+                };
+                let result = self.providers[index]
+                    .complete_streaming(request.clone(), &mut relay)
+                    .await;
+                match result {
+                    Ok(response) => {
+                        self.answered.store(index, Ordering::Relaxed);
+                        return Ok(response);
+                    }
+                    Err(e) if !streamed && self.should_fall_back(index, &e) => index += 1,
+                    Err(e) => return Err(e),
+                }
+            }
+        })
+    }
+
+    fn name(&self) -> &str {
+        self.current().name()
+    }
+
+    fn model(&self) -> String {
+        self.current().model()
+    }
+
+    fn fallback_used(&self) -> Option<String> {
+        let answered = self.answered.load(Ordering::Relaxed);
+        (answered > 0).then(|| self.providers[answered].to_string())
+    }
+}
+
+impl std::fmt::Display for FallbackProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::cli::OutputFormat;
+    use crate::git_entity::{commit::Commit, diff::Diff, GitEntity};
+    use std::sync::Mutex;
+
+    /// Provider that records the last request and replies with canned text
+    struct FakeProvider {
+        reply: String,
+        last_request: Mutex<Option<(String, String)>>,
+    }
+
+    impl FakeProvider {
+        fn new(reply: &str) -> Self {
+            Self {
+                reply: reply.to_string(),
+                last_request: Mutex::new(None),
+            }
+        }
+    }
+
+    impl AiProvider for FakeProvider {
+        fn complete(&self, request: CompletionRequest) -> CompletionFuture<'_> {
+            *self.last_request.lock().unwrap() =
+                Some((request.system_prompt, request.user_prompt));
+            let text = self.reply.clone();
+            Box::pin(async move { Ok(CompletionResponse { text }) })
+        }
+
+        fn name(&self) -> &str {
+            "Fake"
+        }
+
+        fn model(&self) -> String {
+            "fake-model".to_string()
+        }
+    }
+
+    impl std::fmt::Display for FakeProvider {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "fake")
+        }
+    }
+
+    /// The API key a provider's client would send for its model.
+    async fn resolved_key(provider: &LumenProvider) -> String {
+        let ProviderBackend::GenAI { client, model } = &provider.backend;
+        let target = client.resolve_service_target(model).await.unwrap();
+        target.auth.single_key_value().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_same_type_providers_keep_their_own_keys() {
+        for provider_type in [ProviderType::Openai, ProviderType::Openrouter] {
+            let primary =
+                LumenProvider::new(provider_type, Some("key-primary".into()), None).unwrap();
+            let fallback =
+                LumenProvider::new(provider_type, Some("key-fallback".into()), None).unwrap();
+
+            assert_eq!(resolved_key(&primary).await, "key-primary");
+            assert_eq!(resolved_key(&fallback).await, "key-fallback");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_explain_sends_diff_through_provider() {
+        let fake = FakeProvider::new("summary");
+        let command = ExplainCommand {
+            git_entity: GitEntity::Diff(Diff::WorkingTree {
+                staged: false,
+                diff: "+added line".to_string(),
+            }),
+            query: Some("why?".to_string()),
+            chunked: false,
+            stream: true,
+            format: OutputFormat::Text,
+        };
+
+        let provider: &dyn AiProvider = &fake;
+        let result = provider.explain(&command).await.unwrap();
+
+        assert_eq!(result, "summary");
+        let (_, user_prompt) = fake.last_request.lock().unwrap().clone().unwrap();
+        assert!(user_prompt.contains("+added line"));
+        assert!(user_prompt.contains("Question: why?"));
+    }
+
+    #[tokio::test]
+    async fn test_explain_streaming_falls_back_to_one_chunk() {
+        let fake = FakeProvider::new("whole reply");
+        let command = ExplainCommand {
+            git_entity: GitEntity::Diff(Diff::WorkingTree {
+                staged: false,
+                diff: "+added line".to_string(),
+            }),
+            query: None,
+            chunked: false,
+            stream: true,
+            format: OutputFormat::Text,
+        };
+
+        let provider: &dyn AiProvider = &fake;
+        let mut chunks = Vec::new();
+        let result = provider
+            .explain_streaming(&command, &mut |chunk: &str| chunks.push(chunk.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(result, "whole reply");
+        assert_eq!(chunks, vec!["whole reply"]);
+    }
+
+    #[tokio::test]
+    async fn test_explain_chunked_summarizes_per_file_explanations() {
+        let fake = FakeProvider::new("per-file");
+        let command = ExplainCommand {
+            git_entity: GitEntity::Diff(Diff::WorkingTree {
+                staged: false,
+                diff: "diff --git a/a.rs b/a.rs\n+a\ndiff --git a/b.rs b/b.rs\n+b\n".to_string(),
+            }),
+            query: None,
+            chunked: true,
+            stream: true,
+            format: OutputFormat::Text,
+        };
+
+        let provider: &dyn AiProvider = &fake;
+        let mut steps = Vec::new();
+        command
+            .explain_chunked(provider, |step| steps.push(step))
+            .await
+            .unwrap();
+
+        assert_eq!(steps.len(), 3, "two files plus the summary: {:?}", steps);
+        let (_, user_prompt) = fake.last_request.lock().unwrap().clone().unwrap();
+        assert!(user_prompt.contains("### a.rs\nper-file"));
+        assert!(user_prompt.contains("### b.rs\nper-file"));
+    }
+
+    #[test]
+    fn test_explain_json_output() {
+        let fake = FakeProvider::new("unused");
+        let command = ExplainCommand {
+            git_entity: GitEntity::Commit(Commit {
+                full_hash: "0123abcd".to_string(),
+                message: "Subject".to_string(),
+                diff: "+a".to_string(),
+                author_name: "Ann".to_string(),
+                author_email: "ann@example.com".to_string(),
+                date: "2024-01-02".to_string(),
+            }),
+            query: None,
+            chunked: false,
+            stream: true,
+            format: OutputFormat::Json,
+        };
+
+        let json = command.to_json(&fake, "It adds a.").unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "reference": "0123abcd",
+                "provider": "Fake",
+                "model": "fake-model",
+                "explanation": "It adds a.",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain_file_rejects_empty_diff_without_request() {
+        let fake = FakeProvider::new("unused");
+        let provider: &dyn AiProvider = &fake;
+
+        assert!(provider.explain_file("a.rs", "  \n").await.is_err());
+        assert!(fake.last_request.lock().unwrap().is_none());
+    }
+
+    /// Provider that always fails with the error `make_error` builds
+    struct FailingProvider {
+        make_error: fn() -> ProviderError,
+    }
+
+    impl AiProvider for FailingProvider {
+        fn complete(&self, _request: CompletionRequest) -> CompletionFuture<'_> {
+            let error = (self.make_error)();
+            Box::pin(async move { Err(error.into()) })
+        }
+
+        fn name(&self) -> &str {
+            "Failing"
+        }
+
+        fn model(&self) -> String {
+            "failing-model".to_string()
+        }
+    }
+
+    impl std::fmt::Display for FailingProvider {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "failing")
+        }
+    }
+
+    fn status_error(status: StatusCode) -> ProviderError {
+        ProviderError::GenAIError(genai::Error::WebModelCall {
+            model_iden: ModelIden::new(AdapterKind::OpenAI, "gpt"),
+            webc_error: webc::Error::ResponseFailedStatus {
+                status,
+                body: String::new(),
+                headers: Box::default(),
+            },
+        })
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            system_prompt: "system".to_string(),
+            user_prompt: "user".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        assert!(status_error(StatusCode::TOO_MANY_REQUESTS).is_retryable());
+        assert!(status_error(StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(!status_error(StatusCode::UNAUTHORIZED).is_retryable());
+        assert!(!ProviderError::GenAIError(genai::Error::RequiresApiKey {
+            model_iden: ModelIden::new(AdapterKind::Anthropic, "claude"),
+        })
+        .is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_fallback_provider_moves_on_after_rate_limit() {
+        let rate_limited = FailingProvider {
+            make_error: || status_error(StatusCode::TOO_MANY_REQUESTS),
+        };
+        let provider = FallbackProvider::new(vec![
+            Box::new(rate_limited),
+            Box::new(FakeProvider::new("from fallback")),
+        ]);
+        assert!(provider.fallback_used().is_none());
+
+        let response = provider.complete(request()).await.unwrap();
+        assert_eq!(response.text, "from fallback");
+        assert_eq!(provider.fallback_used().as_deref(), Some("fake"));
+        assert_eq!(provider.name(), "Fake");
+    }
+
+    #[tokio::test]
+    async fn test_fallback_provider_stops_on_auth_failure() {
+        let unauthorized = FailingProvider {
+            make_error: || status_error(StatusCode::UNAUTHORIZED),
+        };
+        let fallback = FakeProvider::new("unused");
+        let provider = FallbackProvider::new(vec![Box::new(unauthorized), Box::new(fallback)]);
+
+        let mut chunks = Vec::new();
+        let result = provider
+            .complete_streaming(request(), &mut |chunk: &str| chunks.push(chunk.to_string()))
+            .await;
+        assert!(result.is_err());
+        assert!(chunks.is_empty());
+        assert!(provider.fallback_used().is_none());
+    }
+}
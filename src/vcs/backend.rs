@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Error types for VCS operations.
@@ -36,6 +36,51 @@ pub struct StackedCommitInfo {
     pub change_id: Option<String>,
     /// First line of commit message
     pub summary: String,
+    /// The followed file in this commit, for file-history timelines
+    pub history: Option<FileRevision>,
+}
+
+/// One step of a file's history: where the file lived and who changed it.
+#[derive(Clone, Debug)]
+pub struct FileRevision {
+    /// Path of the file in this commit
+    pub path: String,
+    /// Path in the parent commit, when this commit renamed the file
+    pub old_path: Option<String>,
+    pub author: String,
+    /// Commit date as YYYY-MM-DD HH:MM:SS
+    pub date: String,
+}
+
+/// Who last changed one line of a file.
+#[derive(Clone, Debug)]
+pub struct BlameLine {
+    /// Abbreviated commit ID
+    pub short_id: String,
+    pub author: String,
+    /// Commit date as YYYY-MM-DD
+    pub date: String,
+}
+
+/// The commit within a diffed range that last changed one line of its new side.
+#[derive(Clone, Debug)]
+pub struct LineOrigin {
+    /// Abbreviated commit ID
+    pub short_id: String,
+    /// First line of the commit message
+    pub summary: String,
+}
+
+/// A rebase or cherry-pick that stopped partway, usually on a conflict.
+#[derive(Clone, Debug)]
+pub struct InProgressOperation {
+    /// "rebase" or "cherry-pick"
+    pub kind: &'static str,
+    /// The commit being applied
+    pub commit_id: String,
+    pub summary: String,
+    /// Position in the rebase's todo list as (current, total), when known
+    pub step: Option<(usize, usize)>,
 }
 
 /// Information about a commit from any VCS.
@@ -56,7 +101,26 @@ pub struct CommitInfo {
     pub date: String,
 }
 
-/// Abstraction over git and jj backends.
+/// Full commit metadata for display (who, when, ancestry, signature).
+#[derive(Debug, Clone)]
+pub struct CommitMetadata {
+    pub commit_id: String,
+    /// The change ID (jj only, None for git)
+    pub change_id: Option<String>,
+    /// Author name and email
+    pub author: String,
+    pub author_date: String,
+    /// Committer name and email; None when the backend doesn't report one
+    pub committer: Option<String>,
+    pub committer_date: Option<String>,
+    /// Full parent commit IDs
+    pub parents: Vec<String>,
+    pub message: String,
+    /// Signature status, e.g. "signed (gpg, not verified)"; None when unknown
+    pub signature: Option<String>,
+}
+
+/// Abstraction over git, jj and hg backends.
 ///
 /// Note: This trait intentionally does not require `Send + Sync` bounds.
 /// The VCS backend is used synchronously from a single thread - there's no
@@ -67,6 +131,24 @@ pub trait VcsBackend {
     /// Get commit info for a reference (SHA, HEAD, @, etc.)
     fn get_commit(&self, reference: &str) -> Result<CommitInfo, VcsError>;
 
+    /// Get full metadata for a commit.
+    /// The default derives it from `get_commit`, leaving committer, parents and signature
+    /// unset; backends that record them override it.
+    fn get_commit_metadata(&self, reference: &str) -> Result<CommitMetadata, VcsError> {
+        let info = self.get_commit(reference)?;
+        Ok(CommitMetadata {
+            commit_id: info.commit_id,
+            change_id: info.change_id,
+            author: info.author,
+            author_date: info.date,
+            committer: None,
+            committer_date: None,
+            parents: Vec::new(),
+            message: info.message,
+            signature: None,
+        })
+    }
+
     /// Get diff of uncommitted changes (working tree vs HEAD/parent).
     /// `staged` is only relevant for git; jj ignores it.
     fn get_working_tree_diff(&self, staged: bool) -> Result<String, VcsError>;
@@ -83,6 +165,19 @@ pub trait VcsBackend {
     /// Get current branch name (or bookmark for jj).
     fn get_current_branch(&self) -> Result<Option<String>, VcsError>;
 
+    /// Get the upstream tracking branch of the current branch (e.g. `origin/main`).
+    /// Returns None on a detached HEAD, when no upstream is configured, or when the
+    /// backend has no notion of upstream branches.
+    fn get_upstream_branch(&self) -> Result<Option<String>, VcsError> {
+        Ok(None)
+    }
+
+    /// Get the remote's default branch (e.g. `origin/main`) from `origin/HEAD`.
+    /// Returns None when the remote or its HEAD is not configured.
+    fn get_default_branch(&self) -> Result<Option<String>, VcsError> {
+        Ok(None)
+    }
+
     /// Get commit log formatted for fzf selection.
     fn get_commit_log_for_fzf(&self) -> Result<String, VcsError>;
 
@@ -90,11 +185,33 @@ pub trait VcsBackend {
     /// Works with any ref type: git SHA, jj change ID, @, @-, bookmarks, branches, etc.
     fn resolve_ref(&self, reference: &str) -> Result<String, VcsError>;
 
+    /// Expand a single reference that names a contiguous set of commits (a jj
+    /// revset such as `main::@`) into `(from, to)` endpoints for a range diff.
+    /// Returns None when the reference is a single commit or the backend has no
+    /// revset language.
+    fn resolve_revset_range(&self, _reference: &str) -> Result<Option<(String, String)>, VcsError> {
+        Ok(None)
+    }
+
     /// Get list of files changed in working tree (staged + unstaged + untracked).
     /// For git: combines diff --name-only, diff --cached --name-only, ls-files --others.
     /// For jj: diffs @ tree vs @- tree.
     fn get_working_tree_changed_files(&self) -> Result<Vec<String>, VcsError>;
 
+    /// Get list of files that differ between a ref and the working tree
+    /// (committed since, staged, unstaged and untracked), like `git diff <ref>`.
+    /// For jj: diffs the ref's tree against @.
+    fn get_working_tree_changed_files_against(
+        &self,
+        reference: &str,
+    ) -> Result<Vec<String>, VcsError> {
+        Err(VcsError::Other(format!(
+            "diffing the working tree against {} is not supported by {}",
+            reference,
+            self.name()
+        )))
+    }
+
     /// Get the merge base (common ancestor) of two refs.
     /// Used for triple-dot diffs (A...B).
     /// For git: runs 'git merge-base <ref1> <ref2>'.
@@ -129,6 +246,123 @@ pub trait VcsBackend {
         to: &str,
     ) -> Result<Vec<StackedCommitInfo>, VcsError>;
 
-    /// Get the name of this VCS backend ("git" or "jj").
+    /// Get the commits that changed the file at `path`, oldest first, each with a
+    /// `FileRevision`. Follows renames where the backend can detect them.
+    fn get_file_history(&self, path: &Path) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        Err(VcsError::Other(format!(
+            "file history is not supported by {}: {}",
+            self.name(),
+            path.display()
+        )))
+    }
+
+    /// Blame `path` as it was at `commit`, one entry per line of the file.
+    fn blame(&self, path: &Path, _commit: &str) -> Result<Vec<BlameLine>, VcsError> {
+        Err(VcsError::Other(format!(
+            "blame is not supported by {}: {}",
+            self.name(),
+            path.display()
+        )))
+    }
+
+    /// For each line of `path` at `to`, the commit in `from..to` that last changed it;
+    /// None for lines that already read that way at `from`.
+    fn line_origins(
+        &self,
+        path: &Path,
+        _from: &str,
+        _to: &str,
+    ) -> Result<Vec<Option<LineOrigin>>, VcsError> {
+        Err(VcsError::Other(format!(
+            "line origins are not supported by {}: {}",
+            self.name(),
+            path.display()
+        )))
+    }
+
+    /// Changed files in the working tree that still hold merge conflict markers
+    /// (a `<<<<<<<` line with a `>>>>>>>` after it), sorted by path.
+    fn get_conflicted_files(&self) -> Result<Vec<String>, VcsError> {
+        let has_markers = |content: String| {
+            let mut lines = content.lines();
+            lines.any(|line| line.starts_with("<<<<<<<"))
+                && lines.any(|line| line.starts_with(">>>>>>>"))
+        };
+        let root = self.workdir();
+        let mut files: Vec<String> = self
+            .get_working_tree_changed_files()?
+            .into_iter()
+            .filter(|path| std::fs::read_to_string(root.join(path)).is_ok_and(has_markers))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// The rebase or cherry-pick the repository is in the middle of, if any.
+    fn operation_in_progress(&self) -> Result<Option<InProgressOperation>, VcsError> {
+        Ok(None)
+    }
+
+    /// Get the commit a submodule (gitlink) at `path` points to.
+    /// `reference` of None reads the checked-out working tree.
+    /// Returns None when `path` is not a submodule; backends without submodules always do.
+    fn get_submodule_commit(
+        &self,
+        _reference: Option<&str>,
+        _path: &Path,
+    ) -> Result<Option<String>, VcsError> {
+        Ok(None)
+    }
+
+    /// List the submodule's own commits in `from..to` (oldest first).
+    /// Requires the submodule at `path` to be checked out.
+    fn get_submodule_commits(
+        &self,
+        path: &Path,
+        _from: &str,
+        _to: &str,
+    ) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        Err(VcsError::Other(format!(
+            "submodules are not supported by {}: {}",
+            self.name(),
+            path.display()
+        )))
+    }
+
+    /// Get the git file mode of `path` (`0o100644`, `0o100755` for an executable,
+    /// `0o120000` for a symlink). `reference` of None reads the working tree.
+    /// Returns None when the file is missing or the backend doesn't record modes.
+    fn get_file_mode(&self, reference: Option<&str>, path: &Path) -> Option<u32> {
+        match reference {
+            Some(_) => None,
+            None => working_tree_mode(&self.workdir().join(path)),
+        }
+    }
+
+    /// Get the name of this VCS backend ("git", "jj" or "hg").
     fn name(&self) -> &'static str;
+
+    /// Root of the working tree, which repository-relative paths are resolved against.
+    fn workdir(&self) -> PathBuf;
+
+    /// Directory for lumen's own per-repository files, inside the VCS metadata
+    /// directory (e.g. `.git/lumen`) so they are never committed and don't depend on
+    /// where in the repository lumen was started.
+    fn state_dir(&self) -> PathBuf;
+}
+
+/// The git file mode of a file on disk, without following symlinks.
+pub fn working_tree_mode(path: &Path) -> Option<u32> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if metadata.file_type().is_symlink() {
+        return Some(0o120000);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 != 0 {
+            return Some(0o100755);
+        }
+    }
+    metadata.is_file().then_some(0o100644)
 }
@@ -7,6 +7,8 @@ pub enum VcsType {
     Git,
     /// Jujutsu repository (has .jj/), includes colocated repos
     Jj,
+    /// Mercurial repository (has .hg/)
+    Hg,
     /// No VCS detected
     None,
 }
@@ -29,6 +31,10 @@ pub fn detect_vcs_type(start_dir: &Path) -> VcsType {
             return VcsType::Git;
         }
 
+        if current.join(".hg").is_dir() {
+            return VcsType::Hg;
+        }
+
         // Walk up to parent
         match current.parent() {
             Some(parent) => current = parent,
@@ -68,6 +74,14 @@ mod tests {
         assert_eq!(detect_vcs_type(temp.path()), VcsType::Jj);
     }
 
+    #[test]
+    fn test_detect_hg_repo() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join(".hg")).unwrap();
+
+        assert_eq!(detect_vcs_type(temp.path()), VcsType::Hg);
+    }
+
     #[test]
     fn test_detect_no_vcs() {
         let temp = TempDir::new().unwrap();
@@ -1,8 +1,15 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use git2::{Commit, DiffFormat, DiffOptions, Repository, StatusOptions, Time, Tree};
+use git2::{
+    BlameOptions, Commit, Delta, DiffFindOptions, DiffFormat, DiffOptions, ObjectType, Repository,
+    RepositoryState, Sort, StatusOptions, Time, Tree,
+};
 
-use super::backend::{CommitInfo, StackedCommitInfo, VcsBackend, VcsError};
+use super::backend::{
+    working_tree_mode, BlameLine, CommitInfo, CommitMetadata, FileRevision, InProgressOperation,
+    LineOrigin, StackedCommitInfo, VcsBackend, VcsError,
+};
 
 /// Format a duration in seconds as relative time (e.g., "2 hours ago").
 fn format_relative_time(secs_ago: i64) -> String {
@@ -63,12 +70,15 @@ fn format_git_time(time: &Time) -> String {
     let offset_mins = time.offset_minutes();
 
     // Apply timezone offset to get local time
-    let local_secs = secs + (offset_mins as i64 * 60);
+    format_epoch_secs(secs + (offset_mins as i64 * 60))
+}
 
+/// Format seconds since the Unix epoch as YYYY-MM-DD HH:MM:SS, with no offset applied.
+pub fn format_epoch_secs(local_secs: i64) -> String {
     // Calculate date/time components
     // Days since Unix epoch
     let days = local_secs / 86400;
-    let time_of_day = (local_secs % 86400 + 86400) % 86400; // Handle negative values
+    let time_of_day = local_secs.rem_euclid(86400); // Handle negative values
 
     let hours = time_of_day / 3600;
     let minutes = (time_of_day % 3600) / 60;
@@ -100,6 +110,9 @@ fn days_to_ymd(days: i64) -> (i32, u32, u32) {
     (y as i32, m, d)
 }
 
+/// The SHA of git's empty tree, used as the "parent" of a root commit
+pub const EMPTY_TREE_SHA: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
 /// Files to exclude from diff output.
 const EXCLUDED_FILES: &[&str] = &[
     "package-lock.json",
@@ -112,7 +125,7 @@ const EXCLUDED_FILES: &[&str] = &[
 const EXCLUDED_PATTERNS: &[&str] = &["node_modules/"];
 
 /// Check if a path should be excluded from diff output.
-fn should_exclude_path(path: &str) -> bool {
+pub(super) fn should_exclude_path(path: &str) -> bool {
     // Check exact file matches
     if let Some(filename) = path.rsplit('/').next() {
         if EXCLUDED_FILES.contains(&filename) {
@@ -159,6 +172,39 @@ impl GitBackend {
         Ok(())
     }
 
+    /// How far a stopped rebase has got, from the counters git keeps in
+    /// `rebase-merge` (interactive and merge rebases) or `rebase-apply`.
+    fn rebase_step(&self) -> Option<(usize, usize)> {
+        let read = |dir: &str, file: &str| -> Option<usize> {
+            let path = self.repo.path().join(dir).join(file);
+            std::fs::read_to_string(path).ok()?.trim().parse().ok()
+        };
+        read("rebase-merge", "msgnum")
+            .zip(read("rebase-merge", "end"))
+            .or_else(|| read("rebase-apply", "next").zip(read("rebase-apply", "last")))
+    }
+
+    /// The path `path` had in `old_tree`, if `new_tree` renamed it there.
+    fn renamed_from(
+        &self,
+        old_tree: &Tree,
+        new_tree: &Tree,
+        path: &str,
+    ) -> Result<Option<String>, VcsError> {
+        let mut diff = self
+            .repo
+            .diff_tree_to_tree(Some(old_tree), Some(new_tree), None)
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+            .map_err(|e| VcsError::Other(format!("failed to detect renames: {}", e)))?;
+
+        Ok(diff
+            .deltas()
+            .filter(|d| d.status() == Delta::Renamed)
+            .find(|d| d.new_file().path().and_then(|p| p.to_str()) == Some(path))
+            .and_then(|d| d.old_file().path().and_then(|p| p.to_str()).map(String::from)))
+    }
+
     /// Generate unified diff for a commit, comparing to its parent.
     /// For root commits (no parent), compares to an empty tree.
     fn generate_commit_diff(&self, commit: &Commit) -> Result<String, VcsError> {
@@ -262,6 +308,58 @@ impl VcsBackend for GitBackend {
         })
     }
 
+    fn get_commit_metadata(&self, reference: &str) -> Result<CommitMetadata, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+
+        let obj = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let commit = obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+
+        fn person(sig: &git2::Signature<'_>) -> String {
+            format!("{} <{}>", sig.name().unwrap_or(""), sig.email().unwrap_or(""))
+        }
+
+        // Presence and kind of signature only; verifying it needs gpg/ssh keyrings
+        let signature = match self.repo.extract_signature(&commit.id(), None) {
+            Ok((sig, _)) => {
+                let sig = String::from_utf8_lossy(&sig);
+                let kind = if sig.contains("BEGIN SSH SIGNATURE") {
+                    "ssh"
+                } else if sig.contains("BEGIN SIGNED MESSAGE") {
+                    "x509"
+                } else {
+                    "gpg"
+                };
+                format!("signed ({}, not verified)", kind)
+            }
+            Err(_) => "unsigned".to_string(),
+        };
+
+        let author = commit.author();
+        let committer = commit.committer();
+
+        Ok(CommitMetadata {
+            commit_id: commit.id().to_string(),
+            change_id: None,
+            author: person(&author),
+            author_date: format_git_time(&author.when()),
+            committer: Some(person(&committer)),
+            committer_date: Some(format_git_time(&committer.when())),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            message: commit
+                .message()
+                .unwrap_or("")
+                .trim_end_matches('\n')
+                .to_string(),
+            signature: Some(signature),
+        })
+    }
+
     fn get_working_tree_diff(&self, staged: bool) -> Result<String, VcsError> {
         let mut opts = DiffOptions::new();
         opts.show_binary(true);
@@ -516,10 +614,20 @@ impl VcsBackend for GitBackend {
     }
 
     fn get_current_branch(&self) -> Result<Option<String>, VcsError> {
-        let head = self
-            .repo
-            .head()
-            .map_err(|e| VcsError::Other(format!("failed to get HEAD: {}", e)))?;
+        let head = match self.repo.head() {
+            Ok(head) => head,
+            // A new repository is on its branch before the branch exists
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {
+                let head = self
+                    .repo
+                    .find_reference("HEAD")
+                    .map_err(|e| VcsError::Other(format!("failed to get HEAD: {}", e)))?;
+                return Ok(head
+                    .symbolic_target()
+                    .map(|target| target.trim_start_matches("refs/heads/").to_string()));
+            }
+            Err(e) => return Err(VcsError::Other(format!("failed to get HEAD: {}", e))),
+        };
 
         if head.is_branch() {
             Ok(head.shorthand().map(|s| s.to_string()))
@@ -529,6 +637,37 @@ impl VcsBackend for GitBackend {
         }
     }
 
+    fn get_upstream_branch(&self) -> Result<Option<String>, VcsError> {
+        let head = self
+            .repo
+            .head()
+            .map_err(|e| VcsError::Other(format!("failed to get HEAD: {}", e)))?;
+
+        if !head.is_branch() {
+            return Ok(None);
+        }
+
+        // Equivalent of `git rev-parse --abbrev-ref @{u}`; an unset upstream is not an error
+        let Ok(upstream) = git2::Branch::wrap(head).upstream() else {
+            return Ok(None);
+        };
+        upstream
+            .name()
+            .map(|name| name.map(|s| s.to_string()))
+            .map_err(|e| VcsError::Other(format!("failed to read upstream name: {}", e)))
+    }
+
+    fn get_default_branch(&self) -> Result<Option<String>, VcsError> {
+        // `git clone` records it; repos set up with `git remote add` often lack it
+        let Ok(origin_head) = self.repo.find_reference("refs/remotes/origin/HEAD") else {
+            return Ok(None);
+        };
+        Ok(origin_head
+            .symbolic_target()
+            .and_then(|target| target.strip_prefix("refs/remotes/"))
+            .map(str::to_string))
+    }
+
     fn get_commit_log_for_fzf(&self) -> Result<String, VcsError> {
         let mut revwalk = self
             .repo
@@ -607,6 +746,41 @@ impl VcsBackend for GitBackend {
         Ok(files.into_iter().collect())
     }
 
+    fn get_working_tree_changed_files_against(
+        &self,
+        reference: &str,
+    ) -> Result<Vec<String>, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+
+        let tree = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get tree: {}", e)))?;
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true);
+        opts.recurse_untracked_dirs(true);
+
+        let diff = self
+            .repo
+            .diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+
+        Ok(diff
+            .deltas()
+            .filter_map(|d| {
+                d.new_file()
+                    .path()
+                    .and_then(|p| p.to_str().map(String::from))
+            })
+            .collect())
+    }
+
     fn get_merge_base(&self, ref1: &str, ref2: &str) -> Result<String, VcsError> {
         let ref1 = ref1.trim();
         let ref2 = ref2.trim();
@@ -703,8 +877,7 @@ impl VcsBackend for GitBackend {
             Ok(format!("{}^", reference))
         } else {
             // No parent (root commit) - return git's empty tree SHA
-            // This is a well-known constant: the SHA of an empty tree
-            Ok("4b825dc642cb6eb9a060e54bf8d69288fbee4904".to_string())
+            Ok(EMPTY_TREE_SHA.to_string())
         }
     }
 
@@ -774,6 +947,7 @@ impl VcsBackend for GitBackend {
                     short_id,
                     change_id: None,
                     summary,
+                    history: None,
                 });
             }
         }
@@ -783,15 +957,293 @@ impl VcsBackend for GitBackend {
         Ok(commits)
     }
 
+    fn get_file_history(&self, path: &Path) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        let mut tracked = path
+            .to_str()
+            .ok_or_else(|| VcsError::Other(format!("non-UTF-8 path: {}", path.display())))?
+            .to_string();
+
+        let mut revwalk = self
+            .repo
+            .revwalk()
+            .map_err(|e| VcsError::Other(format!("failed to create revwalk: {}", e)))?;
+        revwalk
+            .push_head()
+            .map_err(|e| VcsError::Other(format!("failed to push head: {}", e)))?;
+        revwalk
+            .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+            .map_err(|e| VcsError::Other(format!("failed to sort revwalk: {}", e)))?;
+
+        // Newest first, like `git log --follow`; reversed at the end
+        let mut commits: Vec<StackedCommitInfo> = Vec::new();
+        for oid_result in revwalk {
+            let oid = oid_result.map_err(|e| VcsError::Other(format!("revwalk error: {}", e)))?;
+            let commit = self
+                .repo
+                .find_commit(oid)
+                .map_err(|e| VcsError::Other(format!("failed to find commit: {}", e)))?;
+            // Merges only repeat changes already made on one side
+            if commit.parent_count() > 1 {
+                continue;
+            }
+
+            let tree = commit
+                .tree()
+                .map_err(|e| VcsError::Other(format!("failed to get tree: {}", e)))?;
+            let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+
+            let mut opts = DiffOptions::new();
+            opts.pathspec(&tracked);
+            opts.disable_pathspec_match(true);
+            let diff = self
+                .repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))
+                .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+            let Some(delta) = diff.deltas().next() else {
+                continue;
+            };
+
+            let added = delta.status() == Delta::Added;
+            let old_path = match (&parent_tree, added) {
+                (Some(parent_tree), true) => self.renamed_from(parent_tree, &tree, &tracked)?,
+                _ => None,
+            };
+
+            let commit_id = oid.to_string();
+            commits.push(StackedCommitInfo {
+                short_id: commit_id[..7.min(commit_id.len())].to_string(),
+                commit_id,
+                change_id: None,
+                summary: commit.summary().unwrap_or("").to_string(),
+                history: Some(FileRevision {
+                    path: tracked.clone(),
+                    old_path: old_path.clone(),
+                    author: commit.author().name().unwrap_or("").to_string(),
+                    date: format_git_time(&commit.time()),
+                }),
+            });
+
+            match old_path {
+                Some(old_path) => tracked = old_path,
+                // The file was created here; nothing older to follow
+                None if added => break,
+                None => {}
+            }
+        }
+
+        commits.reverse();
+        Ok(commits)
+    }
+
+    fn blame(&self, path: &Path, commit: &str) -> Result<Vec<BlameLine>, VcsError> {
+        let commit = commit.trim();
+        Self::validate_ref_format(commit)?;
+        let oid = self
+            .repo
+            .revparse_single(commit)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| VcsError::InvalidRef(commit.to_string()))?
+            .id();
+
+        let mut opts = BlameOptions::new();
+        opts.newest_commit(oid);
+        let blame = self
+            .repo
+            .blame_file(path, Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to blame {}: {}", path.display(), e)))?;
+
+        let mut lines = Vec::new();
+        for hunk in blame.iter() {
+            let commit_id = hunk.final_commit_id().to_string();
+            let signature = hunk.final_signature();
+            let date = format_git_time(&signature.when());
+            let line = BlameLine {
+                short_id: commit_id[..7.min(commit_id.len())].to_string(),
+                author: signature.name().unwrap_or("").to_string(),
+                date: date[..10.min(date.len())].to_string(),
+            };
+            lines.extend(std::iter::repeat_n(line, hunk.lines_in_hunk()));
+        }
+        Ok(lines)
+    }
+
+    fn line_origins(
+        &self,
+        path: &Path,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<Option<LineOrigin>>, VcsError> {
+        let resolve = |reference: &str| {
+            let reference = reference.trim();
+            Self::validate_ref_format(reference)?;
+            self.repo
+                .revparse_single(reference)
+                .and_then(|obj| obj.peel_to_commit())
+                .map(|commit| commit.id())
+                .map_err(|_| VcsError::InvalidRef(reference.to_string()))
+        };
+        let mut opts = BlameOptions::new();
+        opts.newest_commit(resolve(to)?)
+            .oldest_commit(resolve(from)?);
+        let blame = self
+            .repo
+            .blame_file(path, Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to blame {}: {}", path.display(), e)))?;
+
+        let mut origins = HashMap::new();
+        let mut lines = Vec::new();
+        for hunk in blame.iter() {
+            // Boundary hunks were last changed at `from` or before it
+            let origin = if hunk.is_boundary() {
+                None
+            } else {
+                let id = hunk.final_commit_id();
+                let origin = origins.entry(id).or_insert_with(|| {
+                    let id_str = id.to_string();
+                    LineOrigin {
+                        short_id: id_str[..7.min(id_str.len())].to_string(),
+                        summary: self
+                            .repo
+                            .find_commit(id)
+                            .ok()
+                            .and_then(|c| c.summary().map(str::to_string))
+                            .unwrap_or_default(),
+                    }
+                });
+                Some(origin.clone())
+            };
+            lines.extend(std::iter::repeat_n(origin, hunk.lines_in_hunk()));
+        }
+        Ok(lines)
+    }
+
+    fn operation_in_progress(&self) -> Result<Option<InProgressOperation>, VcsError> {
+        let (kind, head) = match self.repo.state() {
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge
+            | RepositoryState::ApplyMailboxOrRebase => ("rebase", "REBASE_HEAD"),
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                ("cherry-pick", "CHERRY_PICK_HEAD")
+            }
+            _ => return Ok(None),
+        };
+        // A rebase stopped at `break` or `exec` is not applying any commit
+        let commit = self
+            .repo
+            .revparse_single(head)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| {
+                VcsError::Other(format!(
+                    "a {} is in progress, but no commit is being applied",
+                    kind
+                ))
+            })?;
+        Ok(Some(InProgressOperation {
+            kind,
+            commit_id: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            step: if kind == "rebase" {
+                self.rebase_step()
+            } else {
+                None
+            },
+        }))
+    }
+
+    fn get_submodule_commit(
+        &self,
+        reference: Option<&str>,
+        path: &Path,
+    ) -> Result<Option<String>, VcsError> {
+        match reference {
+            Some(reference) => {
+                let reference = reference.trim();
+                Self::validate_ref_format(reference)?;
+
+                let tree = self
+                    .repo
+                    .revparse_single(reference)
+                    .and_then(|obj| obj.peel_to_tree())
+                    .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+
+                // Submodules are stored as tree entries pointing at a commit
+                Ok(tree
+                    .get_path(path)
+                    .ok()
+                    .filter(|entry| entry.kind() == Some(ObjectType::Commit))
+                    .map(|entry| entry.id().to_string()))
+            }
+            None => {
+                let Some(name) = path.to_str() else {
+                    return Ok(None);
+                };
+                Ok(self
+                    .repo
+                    .find_submodule(name)
+                    .ok()
+                    .and_then(|sm| sm.workdir_id().or_else(|| sm.index_id()))
+                    .map(|oid| oid.to_string()))
+            }
+        }
+    }
+
+    fn get_file_mode(&self, reference: Option<&str>, path: &Path) -> Option<u32> {
+        let Some(reference) = reference else {
+            return working_tree_mode(&self.workdir().join(path));
+        };
+        let reference = reference.trim();
+        Self::validate_ref_format(reference).ok()?;
+        let tree = self
+            .repo
+            .revparse_single(reference)
+            .and_then(|obj| obj.peel_to_tree())
+            .ok()?;
+        let mode = tree.get_path(path).ok()?.filemode();
+        u32::try_from(mode).ok()
+    }
+
+    fn get_submodule_commits(
+        &self,
+        path: &Path,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        let name = path
+            .to_str()
+            .ok_or_else(|| VcsError::FileNotFound(path.display().to_string()))?;
+        let submodule = self
+            .repo
+            .find_submodule(name)
+            .map_err(|_| VcsError::FileNotFound(name.to_string()))?;
+        let repo = submodule
+            .open()
+            .map_err(|_| VcsError::Other(format!("submodule {} is not checked out", name)))?;
+
+        GitBackend { repo }.get_commits_in_range(from, to)
+    }
+
     fn name(&self) -> &'static str {
         "git"
     }
+
+    fn workdir(&self) -> PathBuf {
+        self.repo
+            .workdir()
+            .unwrap_or_else(|| self.repo.path())
+            .to_path_buf()
+    }
+
+    fn state_dir(&self) -> PathBuf {
+        self.repo.path().join("lumen")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::vcs::test_utils::RepoGuard;
+    use crate::vcs::test_utils::{git, make_temp_dir, RepoGuard};
+    use std::fs;
 
     #[test]
     fn test_get_commit_returns_valid_info() {
@@ -806,6 +1258,19 @@ mod tests {
         assert!(!info.diff.is_empty());
     }
 
+    #[test]
+    fn test_get_commit_metadata() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let metadata = backend.get_commit_metadata("HEAD").expect("should get metadata");
+        assert_eq!(metadata.commit_id.len(), 40);
+        assert!(metadata.committer.unwrap().contains("Test User"));
+        assert!(metadata.parents.is_empty(), "root commit has no parents");
+        assert_eq!(metadata.message, "init");
+        assert_eq!(metadata.signature.as_deref(), Some("unsigned"));
+    }
+
     #[test]
     fn test_get_working_tree_diff_returns_string() {
         let _repo = RepoGuard::new();
@@ -834,6 +1299,69 @@ mod tests {
         assert!(branch.is_some());
     }
 
+    #[test]
+    fn test_unborn_branch_and_staged_diff() {
+        let dir = make_temp_dir("git-unborn");
+        git(&dir, &["init"]);
+        fs::write(dir.join("a.txt"), "hi\n").expect("write file");
+        git(&dir, &["add", "a.txt"]);
+        let backend = GitBackend::new(&dir).expect("should open repo");
+
+        let branch = backend
+            .get_current_branch()
+            .expect("unborn HEAD is not an error");
+        assert!(branch.is_some_and(|b| !b.is_empty()));
+        // Drafting works on staged files before the first commit
+        let diff = backend.get_working_tree_diff(true).expect("staged diff");
+        assert!(diff.contains("+hi"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_upstream_branch() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        assert_eq!(backend.get_upstream_branch().expect("no upstream"), None);
+
+        let head = backend.repo.head().expect("head").peel_to_commit().expect("commit");
+        backend.repo.branch("base", &head, false).expect("create branch");
+        let mut current = git2::Branch::wrap(backend.repo.head().expect("head"));
+        current.set_upstream(Some("base")).expect("set upstream");
+        assert_eq!(
+            backend.get_upstream_branch().expect("should read upstream"),
+            Some("base".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_default_branch_reads_origin_head() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        assert_eq!(backend.get_default_branch().expect("no origin"), None);
+
+        let head = backend.repo.head().expect("head").target().expect("oid");
+        backend
+            .repo
+            .reference("refs/remotes/origin/trunk", head, false, "test")
+            .expect("remote branch");
+        backend
+            .repo
+            .reference_symbolic(
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/trunk",
+                false,
+                "test",
+            )
+            .expect("origin HEAD");
+        assert_eq!(
+            backend.get_default_branch().expect("should read origin/HEAD"),
+            Some("origin/trunk".to_string())
+        );
+    }
+
     #[test]
     fn test_get_file_content_at_ref() {
         let _repo = RepoGuard::new();
@@ -1324,6 +1852,293 @@ mod tests {
         let _ = fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn test_get_working_tree_changed_files_against_commit() {
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-wt-against");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        fs::write(dir.join("base.txt"), "base\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "base"]);
+
+        // Committed after the base, then an unstaged edit and an untracked file
+        fs::write(dir.join("committed.txt"), "later\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "later"]);
+        fs::write(dir.join("base.txt"), "edited\n").expect("modify file");
+        fs::write(dir.join("untracked.txt"), "new\n").expect("write file");
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let mut files = backend
+            .get_working_tree_changed_files_against("HEAD~1")
+            .expect("should get changed files");
+        files.sort();
+
+        assert_eq!(files, vec!["base.txt", "committed.txt", "untracked.txt"]);
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_file_history_follows_renames() {
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-file-history");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        let body = "one\ntwo\nthree\nfour\nfive\n";
+        fs::write(dir.join("a.txt"), body).expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "create"]);
+        fs::write(dir.join("other.txt"), "unrelated\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "unrelated"]);
+        fs::rename(dir.join("a.txt"), dir.join("b.txt")).expect("rename file");
+        let repo = Repository::open(&dir).expect("open repo");
+        let mut index = repo.index().expect("get index");
+        index.remove_path(Path::new("a.txt")).expect("unstage old path");
+        index.add_path(Path::new("b.txt")).expect("stage new path");
+        index.write().expect("write index");
+        git(&dir, &["commit", "-m", "rename"]);
+        fs::write(dir.join("b.txt"), format!("{}six\n", body)).expect("modify file");
+        git(&dir, &["add", "b.txt"]);
+        git(&dir, &["commit", "-m", "extend"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let history = backend
+            .get_file_history(Path::new("b.txt"))
+            .expect("should get history");
+        let steps: Vec<(&str, &str, Option<&str>)> = history
+            .iter()
+            .map(|c| {
+                let revision = c.history.as_ref().expect("revision");
+                (
+                    c.summary.as_str(),
+                    revision.path.as_str(),
+                    revision.old_path.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("create", "a.txt", None),
+                ("rename", "b.txt", Some("a.txt")),
+                ("extend", "b.txt", None),
+            ]
+        );
+        assert_eq!(history[0].history.as_ref().unwrap().author, "Test User");
+
+        assert!(backend
+            .get_file_history(Path::new("missing.txt"))
+            .expect("should succeed")
+            .is_empty());
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_blame_at_commit() {
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-blame");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+        fs::write(dir.join("a.txt"), "one\ntwo\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "create"]);
+        fs::write(dir.join("a.txt"), "one\n2\nthree\n").expect("modify file");
+        git(&dir, &["add", "a.txt"]);
+        git(&dir, &["commit", "-m", "change"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let first = backend.resolve_ref("HEAD~1").expect("should resolve");
+        let second = backend.resolve_ref("HEAD").expect("should resolve");
+
+        let at_head = backend
+            .blame(Path::new("a.txt"), "HEAD")
+            .expect("should blame");
+        let ids: Vec<&str> = at_head.iter().map(|l| l.short_id.as_str()).collect();
+        assert_eq!(ids, vec![&first[..7], &second[..7], &second[..7]]);
+        assert_eq!(at_head[0].author, "Test User");
+        assert_eq!(at_head[0].date.len(), 10);
+
+        let at_parent = backend
+            .blame(Path::new("a.txt"), "HEAD~1")
+            .expect("should blame");
+        assert_eq!(at_parent.len(), 2);
+        assert!(at_parent.iter().all(|l| l.short_id == first[..7]));
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_line_origins_within_range() {
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-line-origins");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+        fs::write(dir.join("a.txt"), "one\ntwo\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "base"]);
+        fs::write(dir.join("a.txt"), "one\ntwo\nthree\n").expect("modify file");
+        git(&dir, &["add", "a.txt"]);
+        git(&dir, &["commit", "-m", "add three"]);
+        fs::write(dir.join("a.txt"), "one\n2\nthree\n").expect("modify file");
+        git(&dir, &["add", "a.txt"]);
+        git(&dir, &["commit", "-m", "fix two"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let middle = backend.resolve_ref("HEAD~1").expect("should resolve");
+        let last = backend.resolve_ref("HEAD").expect("should resolve");
+
+        let origins = backend
+            .line_origins(Path::new("a.txt"), "HEAD~2", "HEAD")
+            .expect("should find origins");
+        let ids: Vec<Option<&str>> = origins
+            .iter()
+            .map(|o| o.as_ref().map(|o| o.short_id.as_str()))
+            .collect();
+        assert_eq!(ids, vec![None, Some(&last[..7]), Some(&middle[..7])]);
+        assert_eq!(origins[1].as_ref().unwrap().summary, "fix two");
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_operation_in_progress() {
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-in-progress");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+        fs::write(dir.join("a.txt"), "one\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "Add a"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let head = backend.resolve_ref("HEAD").expect("should resolve");
+        assert!(backend.operation_in_progress().unwrap().is_none());
+
+        // The state files git leaves behind when a pick stops on a conflict
+        fs::write(dir.join(".git/CHERRY_PICK_HEAD"), format!("{}\n", head)).expect("write");
+        let pick = backend
+            .operation_in_progress()
+            .unwrap()
+            .expect("cherry-pick");
+        assert_eq!((pick.kind, pick.summary.as_str()), ("cherry-pick", "Add a"));
+        assert_eq!(pick.commit_id, head);
+        assert_eq!(pick.step, None);
+        fs::remove_file(dir.join(".git/CHERRY_PICK_HEAD")).expect("remove");
+
+        let rebase_dir = dir.join(".git/rebase-merge");
+        fs::create_dir_all(&rebase_dir).expect("create dir");
+        fs::write(rebase_dir.join("interactive"), "").expect("write");
+        fs::write(rebase_dir.join("msgnum"), "3\n").expect("write");
+        fs::write(rebase_dir.join("end"), "7\n").expect("write");
+        assert!(backend.operation_in_progress().is_err());
+        fs::write(dir.join(".git/REBASE_HEAD"), format!("{}\n", head)).expect("write");
+        let rebase = backend.operation_in_progress().unwrap().expect("rebase");
+        assert_eq!(rebase.kind, "rebase");
+        assert_eq!(rebase.step, Some((3, 7)));
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_conflicted_files_scans_working_tree() {
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert!(backend.get_conflicted_files().unwrap().is_empty());
+
+        fs::write(
+            repo.dir.join("README.md"),
+            "<<<<<<< HEAD\nhello\n=======\nhi\n>>>>>>> topic\n",
+        )
+        .expect("write file");
+        fs::write(repo.dir.join("notes.md"), "Title\n=======\n").expect("write file");
+        assert_eq!(backend.get_conflicted_files().unwrap(), vec!["README.md"]);
+    }
+
+    #[test]
+    fn test_get_conflicted_files_from_subdirectory() {
+        let repo = RepoGuard::new();
+        let sub = repo.dir.join("sub");
+        fs::create_dir(&sub).expect("create dir");
+        fs::write(sub.join("a.txt"), "hello\n").expect("write file");
+        git(&repo.dir, &["add", "sub/a.txt"]);
+        git(&repo.dir, &["commit", "-m", "add sub/a.txt"]);
+        fs::write(
+            sub.join("a.txt"),
+            "<<<<<<< HEAD\nhello\n=======\nhi\n>>>>>>> topic\n",
+        )
+        .expect("write file");
+        std::env::set_current_dir(&sub).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(backend.get_conflicted_files().unwrap(), vec!["sub/a.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo = RepoGuard::new();
+        let script = repo.dir.join("run.sh");
+        fs::write(&script, "echo hi\n").expect("write file");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+        git(&repo.dir, &["add", "run.sh"]);
+        git(&repo.dir, &["commit", "-m", "add run.sh"]);
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).expect("chmod");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let path = Path::new("run.sh");
+        assert_eq!(backend.get_file_mode(Some("HEAD"), path), Some(0o100755));
+        assert_eq!(backend.get_file_mode(None, path), Some(0o100644));
+        assert_eq!(
+            backend.get_file_mode(Some("HEAD"), Path::new("missing")),
+            None
+        );
+    }
+
     #[test]
     fn test_get_working_tree_changed_files_clean() {
         let _repo = RepoGuard::new();
@@ -0,0 +1,381 @@
+//! Mercurial (hg) backend implementation, shelling out to the `hg` CLI.
+
+use std::path::{Path, PathBuf};
+
+use super::backend::{CommitInfo, StackedCommitInfo, VcsBackend, VcsError};
+use super::git::should_exclude_path;
+use crate::command::diff::child_command;
+use crate::command::diff::git::strip_ansi_codes;
+
+/// Separates template fields; hg keeps it out of node ids, names and dates.
+const FIELD_SEP: char = '\u{1f}';
+
+/// Mercurial backend running `hg` in the repository root.
+pub struct HgBackend {
+    root: PathBuf,
+}
+
+/// Translate git's `HEAD` spelling to hg's working directory parent `.`, so
+/// `HEAD~2` becomes `.~2`; hg revsets already understand `~N` and `^`.
+fn to_revset(reference: &str) -> String {
+    let reference = reference.trim();
+    match reference.strip_prefix("HEAD") {
+        Some(rest) if rest.is_empty() || rest.starts_with(['~', '^']) => format!(".{}", rest),
+        _ => reference.to_string(),
+    }
+}
+
+/// Drop the sections of a `hg diff --git` patch for lock files and vendored paths,
+/// as the other backends leave them out. Colors that survive `HGPLAIN` (e.g. through
+/// `HGPLAINEXCEPT`) are stripped first.
+fn filter_excluded_files(patch: &str) -> String {
+    let patch = strip_ansi_codes(patch);
+    let mut output = String::new();
+    let mut keep = true;
+    for section in patch.split_inclusive('\n') {
+        if let Some(header) = section.strip_prefix("diff --git ") {
+            keep = !header
+                .split_whitespace()
+                .any(|path| should_exclude_path(path.trim_start_matches("a/")));
+        }
+        if keep {
+            output.push_str(section);
+        }
+    }
+    output
+}
+
+/// Parse `node<SEP>short<SEP>summary<SEP>file count` lines from `hg log`.
+fn parse_stacked_commits(log: &str) -> Vec<StackedCommitInfo> {
+    log.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(FIELD_SEP);
+            let commit_id = fields.next()?.to_string();
+            let short_id = fields.next()?.to_string();
+            let summary = fields.next()?.to_string();
+            // Commits that touch no files (e.g. bookmark-only moves) have nothing to show
+            let files: usize = fields.next()?.trim().parse().ok()?;
+            (files > 0).then_some(StackedCommitInfo {
+                commit_id,
+                short_id,
+                change_id: None,
+                summary,
+                history: None,
+            })
+        })
+        .collect()
+}
+
+impl HgBackend {
+    /// Open the Mercurial repository containing `path`.
+    pub fn new(path: &Path) -> Result<Self, VcsError> {
+        let output = child_command("hg")
+            .arg("root")
+            .current_dir(path)
+            .env("HGPLAIN", "1")
+            .output()
+            .map_err(|_| VcsError::NotARepository)?;
+        if !output.status.success() {
+            return Err(VcsError::NotARepository);
+        }
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(HgBackend {
+            root: PathBuf::from(root),
+        })
+    }
+
+    /// Validate that a reference doesn't look like a flag (defense in depth).
+    fn validate_ref_format(reference: &str) -> Result<(), VcsError> {
+        if reference.trim().starts_with('-') {
+            return Err(VcsError::InvalidRef(format!(
+                "references cannot start with '-': {}",
+                reference
+            )));
+        }
+        Ok(())
+    }
+
+    /// Run `hg` in the repository root with user configuration that changes output
+    /// (aliases, color, localization) disabled by `HGPLAIN`.
+    fn hg(&self, args: &[&str]) -> Result<String, VcsError> {
+        let output = child_command("hg")
+            .args(args)
+            .current_dir(&self.root)
+            .env("HGPLAIN", "1")
+            .output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(VcsError::CommandFailed(format!(
+                "hg {}: {}",
+                args.first().unwrap_or(&""),
+                stderr.trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Full node id of the last revision in `revset`, or None when it is empty.
+    fn node(&self, revset: &str) -> Result<Option<String>, VcsError> {
+        let revset = format!("last({})", revset);
+        let node = self.hg(&["log", "-r", &revset, "-T", "{node}"])?;
+        Ok(Some(node.trim().to_string()).filter(|n| !n.is_empty()))
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String, VcsError> {
+        Self::validate_ref_format(reference)?;
+        self.node(&to_revset(reference))
+            .ok()
+            .flatten()
+            .ok_or_else(|| VcsError::InvalidRef(reference.trim().to_string()))
+    }
+
+    /// `hg status -n` lines as paths.
+    fn status_files(&self, args: &[&str]) -> Result<Vec<String>, VcsError> {
+        let mut status_args = vec!["status", "-n"];
+        status_args.extend_from_slice(args);
+        Ok(self
+            .hg(&status_args)?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect())
+    }
+}
+
+impl VcsBackend for HgBackend {
+    fn get_commit(&self, reference: &str) -> Result<CommitInfo, VcsError> {
+        let node = self.resolve(reference)?;
+        let template = format!(
+            "{{node}}{sep}{{author}}{sep}{{date|isodatesec}}{sep}{{desc}}",
+            sep = FIELD_SEP
+        );
+        let log = self.hg(&["log", "-r", &node, "-T", &template])?;
+        let mut fields = log.splitn(4, FIELD_SEP);
+        let commit_id = fields.next().unwrap_or_default().to_string();
+        let author = fields.next().unwrap_or_default().to_string();
+        // "2024-01-02 03:04:05 +0100" without the offset
+        let date = fields.next().unwrap_or_default().chars().take(19).collect();
+        let message = fields.next().unwrap_or_default().trim_end().to_string();
+
+        let diff = filter_excluded_files(&self.hg(&["diff", "--git", "-c", &node])?);
+
+        Ok(CommitInfo {
+            commit_id,
+            change_id: None,
+            message,
+            diff,
+            author,
+            date,
+        })
+    }
+
+    fn get_working_tree_diff(&self, _staged: bool) -> Result<String, VcsError> {
+        // hg has no staging area, so both views are the whole working directory
+        Ok(filter_excluded_files(&self.hg(&["diff", "--git"])?))
+    }
+
+    fn get_range_diff(&self, from: &str, to: &str, three_dot: bool) -> Result<String, VcsError> {
+        let from = if three_dot {
+            self.get_merge_base(from, to)?
+        } else {
+            self.resolve(from)?
+        };
+        let to = self.resolve(to)?;
+        Ok(filter_excluded_files(
+            &self.hg(&["diff", "--git", "-r", &from, "-r", &to])?,
+        ))
+    }
+
+    fn get_changed_files(&self, reference: &str) -> Result<Vec<String>, VcsError> {
+        let node = self.resolve(reference)?;
+        self.status_files(&["--change", &node])
+    }
+
+    fn get_file_content_at_ref(&self, reference: &str, path: &Path) -> Result<String, VcsError> {
+        let node = self.resolve(reference)?;
+        // `path:` keeps hg from reading the name as a glob or relative to the cwd
+        let pattern = format!("path:{}", path.display());
+        self.hg(&["cat", "-r", &node, &pattern])
+            .map_err(|_| VcsError::FileNotFound(path.display().to_string()))
+    }
+
+    fn get_current_branch(&self) -> Result<Option<String>, VcsError> {
+        // The active bookmark is the closest match to a git branch; the named
+        // branch ("default" unless set) otherwise
+        let names = self.hg(&["log", "-r", ".", "-T", "{activebookmark}\n{branch}"])?;
+        Ok(names
+            .lines()
+            .map(str::trim)
+            .find(|name| !name.is_empty())
+            .map(String::from))
+    }
+
+    fn get_commit_log_for_fzf(&self) -> Result<String, VcsError> {
+        let template = format!(
+            "{{node|short}}{sep}{{desc|firstline}}{sep}{{date|age}}\n",
+            sep = FIELD_SEP
+        );
+        let log = self.hg(&["log", "-r", "reverse(::.)", "-T", &template])?;
+
+        // Format: short_hash summary relative_time, colored like the git backend
+        Ok(log
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(FIELD_SEP);
+                let (short_id, summary, age) = (fields.next()?, fields.next()?, fields.next()?);
+                Some(format!(
+                    "\x1b[33m{}\x1b[0m {} \x1b[90m{}\x1b[0m\n",
+                    short_id, summary, age
+                ))
+            })
+            .collect())
+    }
+
+    fn resolve_ref(&self, reference: &str) -> Result<String, VcsError> {
+        self.resolve(reference)
+    }
+
+    fn get_working_tree_changed_files(&self) -> Result<Vec<String>, VcsError> {
+        self.status_files(&[])
+    }
+
+    fn get_working_tree_changed_files_against(
+        &self,
+        reference: &str,
+    ) -> Result<Vec<String>, VcsError> {
+        let node = self.resolve(reference)?;
+        self.status_files(&["--rev", &node])
+    }
+
+    fn get_merge_base(&self, ref1: &str, ref2: &str) -> Result<String, VcsError> {
+        let (node1, node2) = (self.resolve(ref1)?, self.resolve(ref2)?);
+        self.node(&format!("ancestor({}, {})", node1, node2))?
+            .ok_or_else(|| {
+                VcsError::Other(format!(
+                    "failed to find merge base: {} and {} have no common ancestor",
+                    ref1.trim(),
+                    ref2.trim()
+                ))
+            })
+    }
+
+    fn working_copy_parent_ref(&self) -> &'static str {
+        "."
+    }
+
+    fn get_range_changed_files(&self, from: &str, to: &str) -> Result<Vec<String>, VcsError> {
+        let (from, to) = (self.resolve(from)?, self.resolve(to)?);
+        self.status_files(&["--rev", &from, "--rev", &to])
+    }
+
+    fn get_parent_ref_or_empty(&self, reference: &str) -> Result<String, VcsError> {
+        let node = self.resolve(reference)?;
+        // A root revision's only parent is hg's null revision, which `p1()` leaves out
+        Ok(self.node(&format!("p1({})", node))?.unwrap_or_default())
+    }
+
+    fn get_commits_in_range(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        let (from, to) = (self.resolve(from)?, self.resolve(to)?);
+        // `only(to, from)` is git's `from..to`; merges only repeat their parents' changes
+        let revset = format!("sort(only({}, {}) and not merge(), rev)", to, from);
+        let template = format!(
+            "{{node}}{sep}{{node|short}}{sep}{{desc|firstline}}{sep}{{files|count}}\n",
+            sep = FIELD_SEP
+        );
+        let log = self.hg(&["log", "-r", &revset, "-T", &template])?;
+        Ok(parse_stacked_commits(&log))
+    }
+
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn workdir(&self) -> PathBuf {
+        self.root.clone()
+    }
+
+    fn state_dir(&self) -> PathBuf {
+        self.root.join(".hg").join("lumen")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::test_utils::HgRepoGuard;
+    use std::fs;
+
+    #[test]
+    fn test_to_revset_translates_head() {
+        assert_eq!(to_revset("HEAD"), ".");
+        assert_eq!(to_revset("HEAD~2"), ".~2");
+        assert_eq!(to_revset("HEAD^"), ".^");
+        assert_eq!(to_revset("HEADS"), "HEADS");
+        assert_eq!(to_revset(" default "), "default");
+    }
+
+    #[test]
+    fn test_filter_excluded_files() {
+        let patch = "diff --git a/Cargo.lock b/Cargo.lock\n--- a/Cargo.lock\n+x\n\
+                     diff --git a/src/main.rs b/src/main.rs\n+y\n";
+        assert_eq!(
+            filter_excluded_files(patch),
+            "diff --git a/src/main.rs b/src/main.rs\n+y\n"
+        );
+
+        let colored = "\x1b[1mdiff --git a/Cargo.lock b/Cargo.lock\x1b[0m\n+x\n\
+                       \x1b[1mdiff --git a/src/main.rs b/src/main.rs\x1b[0m\n\x1b[32m+y\x1b[0m\n";
+        assert_eq!(
+            filter_excluded_files(colored),
+            "diff --git a/src/main.rs b/src/main.rs\n+y\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_stacked_commits_skips_empty_commits() {
+        let log = "aaa\u{1f}a\u{1f}first\u{1f}2\nbbb\u{1f}b\u{1f}tag only\u{1f}0\n";
+        let commits = parse_stacked_commits(log);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(
+            (commits[0].short_id.as_str(), commits[0].summary.as_str()),
+            ("a", "first")
+        );
+    }
+
+    #[test]
+    fn test_commit_range_and_merge_base() {
+        let Some(repo) = HgRepoGuard::new() else {
+            eprintln!("Skipping test: hg not available");
+            return;
+        };
+        let backend = HgBackend::new(&repo.dir).expect("should open hg repo");
+
+        fs::write(repo.dir.join("a.txt"), "one\n").unwrap();
+        repo.commit("first");
+        fs::write(repo.dir.join("a.txt"), "two\n").unwrap();
+        repo.commit("second");
+
+        let commit = backend.get_commit("HEAD").expect("should get commit");
+        assert_eq!(commit.message, "second");
+        assert!(commit.diff.contains("+two"));
+        assert_eq!(backend.get_changed_files(".").unwrap(), vec!["a.txt"]);
+        assert_eq!(
+            backend
+                .get_file_content_at_ref("HEAD~1", Path::new("a.txt"))
+                .unwrap(),
+            "one\n"
+        );
+
+        let first = backend.resolve_ref("HEAD~1").unwrap();
+        assert_eq!(backend.get_merge_base("HEAD", &first).unwrap(), first);
+        assert_eq!(backend.get_parent_ref_or_empty(&first).unwrap(), "");
+
+        let commits = backend.get_commits_in_range(&first, "HEAD").unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].summary, "second");
+    }
+}
@@ -1,12 +1,12 @@
 //! Jujutsu (jj) backend implementation using jj-lib.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use chrono::Local;
 use futures::StreamExt;
-use jj_lib::backend::TreeValue;
+use jj_lib::backend::{CommitId, Signature, TreeValue};
 use jj_lib::commit::Commit;
 use jj_lib::config::StackedConfig;
 use jj_lib::conflict_labels::ConflictLabels;
@@ -32,7 +32,7 @@ use jj_lib::tree_merge::MergeOptions;
 use jj_lib::workspace::{default_working_copy_factories, Workspace};
 use pollster::FutureExt;
 
-use super::backend::{CommitInfo, StackedCommitInfo, VcsBackend, VcsError};
+use super::backend::{CommitInfo, CommitMetadata, StackedCommitInfo, VcsBackend, VcsError};
 
 /// Files to exclude from diff output (same as GIT_DIFF_EXCLUSIONS in git_entity).
 const DIFF_EXCLUDED_FILES: &[&str] = &[
@@ -90,6 +90,15 @@ fn truncate_hash(hash: &str, max_len: usize) -> &str {
     &hash[..max_len.min(hash.len())]
 }
 
+/// "Name <email>" and the timestamp (YYYY-MM-DD HH:MM:SS) of a jj signature.
+fn format_signature(sig: &Signature) -> (String, String) {
+    let person = format!("{} <{}>", sig.name, sig.email);
+    let date = chrono::DateTime::from_timestamp_millis(sig.timestamp.timestamp.0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    (person, date)
+}
+
 /// Check if a path should be excluded from diff output.
 fn should_exclude_path(path: &str) -> bool {
     // Check exact file matches
@@ -219,6 +228,38 @@ impl JjBackend {
         })
     }
 
+    /// Evaluate a revset and return up to `limit` commit ids, newest first.
+    fn resolve_commit_ids(
+        &self,
+        revset_str: &str,
+        limit: usize,
+    ) -> Result<Vec<CommitId>, VcsError> {
+        let repo = self.repo.as_ref();
+
+        self.with_revset_context(|context| {
+            let mut diagnostics = RevsetDiagnostics::new();
+            let expression = jj_lib::revset::parse(&mut diagnostics, revset_str, context)
+                .map_err(|e| format_ref_error(revset_str, &format!("parse error: {}", e)))?;
+
+            let symbol_resolver =
+                SymbolResolver::new(repo, &([] as [&Box<dyn SymbolResolverExtension>; 0]));
+
+            let resolved = expression
+                .resolve_user_expression(repo, &symbol_resolver)
+                .map_err(|e| format_ref_error(revset_str, &format!("resolution error: {}", e)))?;
+
+            let revset = resolved
+                .evaluate(repo)
+                .map_err(|e| VcsError::Other(format!("evaluation error: {}", e)))?;
+
+            let mut ids = Vec::new();
+            for id in revset.iter().take(limit) {
+                ids.push(id.map_err(|e| VcsError::Other(format!("iterator error: {}", e)))?);
+            }
+            Ok(ids)
+        })
+    }
+
     /// Generate a unified diff for a commit (comparing to its first parent).
     fn generate_diff(&self, commit: &Commit) -> Result<String, VcsError> {
         let repo = self.repo.as_ref();
@@ -567,13 +608,7 @@ impl VcsBackend for JjBackend {
         let commit_id = commit.id().hex();
         let change_id = commit.change_id().hex();
         let message = commit.description().to_string();
-        let author_sig = commit.author();
-        let author = format!("{} <{}>", author_sig.name, author_sig.email);
-
-        // Format date from author timestamp
-        let date = chrono::DateTime::from_timestamp_millis(author_sig.timestamp.timestamp.0)
-            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-            .unwrap_or_default();
+        let (author, date) = format_signature(commit.author());
 
         // Generate diff
         let diff = self.generate_diff(&commit)?;
@@ -588,6 +623,31 @@ impl VcsBackend for JjBackend {
         })
     }
 
+    fn get_commit_metadata(&self, reference: &str) -> Result<CommitMetadata, VcsError> {
+        let commit = self.resolve_single_commit(reference.trim())?;
+        let (author, author_date) = format_signature(commit.author());
+        let (committer, committer_date) = format_signature(commit.committer());
+
+        // Presence of a signature only; verifying it needs the configured signing backend
+        let signature = if commit.is_signed() {
+            "signed (not verified)"
+        } else {
+            "unsigned"
+        };
+
+        Ok(CommitMetadata {
+            commit_id: commit.id().hex(),
+            change_id: Some(commit.change_id().hex()),
+            author,
+            author_date,
+            committer: Some(committer),
+            committer_date: Some(committer_date),
+            parents: commit.parent_ids().iter().map(|id| id.hex()).collect(),
+            message: commit.description().trim_end_matches('\n').to_string(),
+            signature: Some(signature.to_string()),
+        })
+    }
+
     fn get_working_tree_diff(&self, _staged: bool) -> Result<String, VcsError> {
         // For jj, working tree changes are part of @ commit
         // Get diff of @ vs @-
@@ -739,6 +799,40 @@ impl VcsBackend for JjBackend {
         Ok(commit.id().hex())
     }
 
+    fn resolve_revset_range(&self, reference: &str) -> Result<Option<(String, String)>, VcsError> {
+        let reference = reference.trim();
+        // Unresolvable references are left for the single-commit path to report
+        match self.resolve_commit_ids(reference, 2) {
+            Ok(ids) if ids.len() >= 2 => {}
+            _ => return Ok(None),
+        }
+
+        // A range diff needs one entry and one exit point
+        let heads = self.resolve_commit_ids(&format!("heads({})", reference), 2)?;
+        let roots = self.resolve_commit_ids(&format!("roots({})", reference), 2)?;
+        let (head, root) = match (heads.as_slice(), roots.as_slice()) {
+            ([head], [root]) => (head, root),
+            _ => {
+                return Err(format_ref_error(
+                    reference,
+                    "revset must have a single head and a single root to be shown as a diff",
+                ))
+            }
+        };
+
+        let root_commit = self
+            .repo
+            .store()
+            .get_commit(root)
+            .map_err(|e| VcsError::Other(format!("failed to load commit: {}", e)))?;
+        let from = match root_commit.parent_ids().first() {
+            Some(parent) => parent.hex(),
+            None => "root()".to_string(),
+        };
+
+        Ok(Some((from, head.hex())))
+    }
+
     fn get_commit_log_for_fzf(&self) -> Result<String, VcsError> {
         // Get visible commits using "all()" revset, limited to 100 for fzf performance
         let repo = self.repo.as_ref();
@@ -840,6 +934,14 @@ impl VcsBackend for JjBackend {
         "@-"
     }
 
+    fn get_working_tree_changed_files_against(
+        &self,
+        reference: &str,
+    ) -> Result<Vec<String>, VcsError> {
+        // The working copy is itself a commit in jj
+        self.get_range_changed_files(reference, "@")
+    }
+
     fn get_range_changed_files(&self, from: &str, to: &str) -> Result<Vec<String>, VcsError> {
         let from_commit = self.resolve_single_commit(from)?;
         let to_commit = self.resolve_single_commit(to)?;
@@ -961,6 +1063,7 @@ impl VcsBackend for JjBackend {
                         .next()
                         .unwrap_or("")
                         .to_string(),
+                    history: None,
                 });
             }
 
@@ -973,6 +1076,14 @@ impl VcsBackend for JjBackend {
     fn name(&self) -> &'static str {
         "jj"
     }
+
+    fn workdir(&self) -> PathBuf {
+        self.workspace.workspace_root().to_path_buf()
+    }
+
+    fn state_dir(&self) -> PathBuf {
+        self.workspace.workspace_root().join(".jj").join("lumen")
+    }
 }
 
 #[cfg(test)]
@@ -1399,6 +1510,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_commit_metadata_reads_committer_and_parents() {
+        let Some(repo) = JjRepoGuard::new() else {
+            eprintln!("Skipping test: jj not available");
+            return;
+        };
+
+        let backend = JjBackend::new(&repo.dir).expect("should load backend");
+        let metadata = backend
+            .get_commit_metadata("@")
+            .expect("should get metadata");
+        let commit = backend.get_commit("@").expect("should get commit");
+
+        assert_eq!(metadata.commit_id, commit.commit_id);
+        assert_eq!(metadata.author, commit.author);
+        let committer = metadata.committer.expect("jj commits have a committer");
+        assert!(
+            committer.contains('<') && committer.contains('>'),
+            "committer should be 'Name <email>' format, got: {}",
+            committer
+        );
+        assert_eq!(metadata.committer_date.map(|date| date.len()), Some(19));
+        assert_eq!(metadata.parents.len(), 1, "@ should have one parent");
+        assert_eq!(metadata.signature.as_deref(), Some("unsigned"));
+    }
+
     #[test]
     fn test_get_range_diff_identical_commits() {
         let Some(repo) = JjRepoGuard::new() else {
@@ -1,18 +1,23 @@
 //! VCS backend abstraction module.
 //!
-//! Provides a unified interface for working with git and jj repositories.
+//! Provides a unified interface for working with git, jj and hg repositories.
 
 mod backend;
 mod detection;
 mod git;
+mod hg;
 #[cfg(feature = "jj")]
 mod jj;
 #[cfg(test)]
 pub mod test_utils;
 
-pub use backend::{CommitInfo, StackedCommitInfo, VcsBackend, VcsError};
+pub use backend::{
+    BlameLine, CommitInfo, CommitMetadata, FileRevision, InProgressOperation, LineOrigin,
+    StackedCommitInfo, VcsBackend, VcsError,
+};
 pub use detection::{detect_vcs_type, VcsType};
-pub use git::GitBackend;
+pub use git::{format_epoch_secs, GitBackend, EMPTY_TREE_SHA};
+pub use hg::HgBackend;
 #[cfg(feature = "jj")]
 pub use jj::JjBackend;
 
@@ -25,6 +30,7 @@ use crate::config::cli::VcsOverride;
 pub enum VcsBackendType {
     Git,
     Jj,
+    Hg,
 }
 
 impl From<VcsOverride> for VcsBackendType {
@@ -32,6 +38,7 @@ impl From<VcsOverride> for VcsBackendType {
         match vcs {
             VcsOverride::Git => VcsBackendType::Git,
             VcsOverride::Jj => VcsBackendType::Jj,
+            VcsOverride::Hg => VcsBackendType::Hg,
         }
     }
 }
@@ -39,7 +46,8 @@ impl From<VcsOverride> for VcsBackendType {
 /// Get the appropriate VCS backend for the current directory.
 ///
 /// If `override_type` is provided, uses that backend type explicitly.
-/// Otherwise auto-detects jj vs git repositories. Prefers jj when both are present (colocated).
+/// Otherwise auto-detects jj, git and hg repositories. Prefers jj when both jj and git are
+/// present (colocated).
 pub fn get_backend(
     path: &Path,
     override_type: Option<VcsBackendType>,
@@ -49,6 +57,7 @@ pub fn get_backend(
         |ot| match ot {
             VcsBackendType::Git => VcsType::Git,
             VcsBackendType::Jj => VcsType::Jj,
+            VcsBackendType::Hg => VcsType::Hg,
         },
     );
 
@@ -66,6 +75,7 @@ pub fn get_backend(
                 GitBackend::new(path).map(|b| Box::new(b) as Box<dyn VcsBackend>)
             }
         }
+        VcsType::Hg => HgBackend::new(path).map(|b| Box::new(b) as Box<dyn VcsBackend>),
         VcsType::None => Err(VcsError::NotARepository),
     }
 }
@@ -129,5 +139,6 @@ mod tests {
     fn test_vcs_override_to_backend_type_conversion() {
         assert_eq!(VcsBackendType::from(VcsOverride::Git), VcsBackendType::Git);
         assert_eq!(VcsBackendType::from(VcsOverride::Jj), VcsBackendType::Jj);
+        assert_eq!(VcsBackendType::from(VcsOverride::Hg), VcsBackendType::Hg);
     }
 }
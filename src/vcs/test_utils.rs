@@ -1,6 +1,6 @@
 //! Shared test utilities for VCS tests.
 //!
-//! Provides RepoGuard and JjRepoGuard for creating temporary test repositories.
+//! Provides RepoGuard, JjRepoGuard and HgRepoGuard for creating temporary test repositories.
 
 use std::env;
 use std::fs;
@@ -267,3 +267,53 @@ impl Drop for JjRepoGuard {
         let _ = fs::remove_dir_all(&self.dir);
     }
 }
+
+/// Run an hg command in a directory, returning whether it succeeded.
+pub fn hg(dir: &Path, args: &[&str]) -> bool {
+    Command::new("hg")
+        .current_dir(dir)
+        .args(args)
+        .env("HGPLAIN", "1")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// RAII guard for a temporary, empty Mercurial repository.
+/// Returns None if hg is not available.
+pub struct HgRepoGuard {
+    pub dir: PathBuf,
+}
+
+impl HgRepoGuard {
+    pub fn new() -> Option<Self> {
+        let dir = make_temp_dir("lumen-hg-test");
+        if !hg(&dir, &["init"]) {
+            let _ = fs::remove_dir_all(&dir);
+            return None;
+        }
+        Some(Self { dir })
+    }
+
+    /// Commit every change in the working directory, adding new files.
+    pub fn commit(&self, message: &str) {
+        let committed = hg(
+            &self.dir,
+            &[
+                "commit",
+                "--addremove",
+                "-u",
+                "Test User <test@example.com>",
+                "-m",
+                message,
+            ],
+        );
+        assert!(committed, "hg commit failed");
+    }
+}
+
+impl Drop for HgRepoGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}